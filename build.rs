@@ -0,0 +1,15 @@
+//! Generates the gRPC service of `edifact serve --grpc` from
+//! `proto/edifact.proto`. The schema is compiled with protox, so no
+//! protoc needs to be installed.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/edifact.proto");
+        let files = protox::compile(["edifact.proto"], ["proto"])
+            .expect("proto/edifact.proto should be valid");
+        tonic_build::configure()
+            .compile_fds(files)
+            .expect("the gRPC service should be generated");
+    }
+}
@@ -0,0 +1,34 @@
+//! Decodes arbitrary input with the bundled APERAK and CONTRL
+//! descriptions, which parses the input and matches the segments.
+#![no_main]
+
+use std::sync::OnceLock;
+
+use edifact::mig::description::Interchange;
+use edifact::mig::validate::ids;
+use edifact::mig::{self, CompiledMig, DecodeOptions, ParseMode};
+use libfuzzer_sys::fuzz_target;
+
+fn descriptions() -> &'static [CompiledMig] {
+    static DESCRIPTIONS: OnceLock<Vec<CompiledMig>> = OnceLock::new();
+    DESCRIPTIONS.get_or_init(|| {
+        [include_str!("../../APERAK.json"), include_str!("../../CONTRL.json")]
+            .iter()
+            .map(|json| serde_json::from_str::<Interchange>(json).unwrap())
+            .map(CompiledMig::from)
+            .collect()
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    for description in descriptions() {
+        let salvage = DecodeOptions {
+            parse_mode: ParseMode::Salvage,
+            ..DecodeOptions::lenient()
+        };
+        for mut options in [DecodeOptions::strict(), salvage] {
+            options.identifiers = ids::Kind::all();
+            let _ = mig::decode(description, &mut &data[..], &options);
+        }
+    }
+});
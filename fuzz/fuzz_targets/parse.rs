@@ -0,0 +1,8 @@
+//! Parses arbitrary input into segments, without a description.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = edifact::mig::parse(&mut &data[..]);
+});
@@ -1,16 +1,60 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::path::PathBuf;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+#[cfg(feature = "server")]
+use std::sync::Arc;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser, ValueEnum};
+use clap_complete::Shell;
+use serde::Deserialize;
 
-use crate::mig::spec;
+use edifact::mig;
+use edifact::mig::compact;
+use edifact::mig::conversation::Graph;
+use edifact::mig::coverage::Coverage;
+use edifact::mig::decode;
+use edifact::mig::decode::value;
+use edifact::mig::diff;
+use edifact::mig::directory::{self, Directory};
+use edifact::mig::either::Either;
+use edifact::mig::lint;
+use edifact::mig::metrics::{self, Counters};
+use edifact::mig::report;
+use edifact::mig::report::html;
+use edifact::mig::signature::{self, HmacSha256, SignatureError, Signer};
+use edifact::mig::spec;
+use edifact::mig::stats::Stats;
+use edifact::mig::table::Table;
+use edifact::usecase::description::Manual;
+
+use crate::config::{self, Config};
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::index;
+#[cfg(feature = "tui")]
+use crate::inspect::Inspector;
+#[cfg(feature = "server")]
+use crate::server;
+use crate::suite;
+use crate::watch;
 
 #[derive(Debug, Parser)]
 #[command(
     name = "edifact",
+    version,
     about = "An EDIFACT tool for the edi@energy subset"
 )]
 pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        help = "A configuration file instead of the edifact.toml found."
+    )]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Command,
 }
@@ -19,6 +63,508 @@ pub struct Cli {
 enum Command {
     #[command(subcommand)]
     Mig(Mig),
+    #[command(subcommand)]
+    Ahb(Ahb),
+    #[command(subcommand)]
+    Index(Index),
+    #[cfg(feature = "tui")]
+    #[command(about = "Browse an interchange in the terminal.")]
+    Inspect {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+        #[arg(long, help = "A MIG description in JSON to decode the file.")]
+        mig: Option<PathBuf>,
+    },
+    #[command(
+        about = "Extract values from EDIFACT files, e.g. all NAD+MS IDs."
+    )]
+    Grep {
+        #[arg(
+            help = "The values to extract, e.g. NAD+MS, RFF+Z13 or UNH/2/1."
+        )]
+        path: mig::Path,
+        #[arg(required = true, help = "EDIFACT files or directories.")]
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum, help = "The format, by default csv.")]
+        output: Option<Output>,
+    },
+    #[command(
+        about = "Print an EDIFACT file as JSON by tag and position, e.g. \
+                 for a message type without a MIG."
+    )]
+    Structure {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+    },
+    #[command(
+        about = "Export the repetitions of a segment group as a table, e.g. \
+                 the quantities in SG10 of MSCONS."
+    )]
+    Export {
+        #[arg(required = true, help = "EDIFACT files or directories.")]
+        files: Vec<PathBuf>,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(long, help = "The label of the segment group, e.g. SG10.")]
+        table: String,
+        #[arg(
+            long,
+            required = true,
+            value_delimiter = ',',
+            help = "The values of the columns, e.g. DTM+163,QTY+220."
+        )]
+        columns: Vec<mig::Path>,
+        #[arg(long, value_enum, help = "The format, by default csv.")]
+        output: Option<Output>,
+    },
+    #[command(
+        about = "Extract values from EDIFACT files as JSON objects with \
+                 rules, e.g. marktlokation: SG5/LOC[3227=172]/C517.3225."
+    )]
+    Map {
+        #[arg(long, help = "The rules in YAML, mapping keys to selectors.")]
+        rules: PathBuf,
+        #[arg(required = true, help = "EDIFACT files or directories.")]
+        files: Vec<PathBuf>,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+    },
+    #[command(
+        about = "Build an EDIFACT file from a JSON object of values with the \
+                 rules of edifact map."
+    )]
+    Build {
+        #[arg(help = "A JSON object of the values by key.")]
+        file: PathBuf,
+        #[arg(long, help = "The rules in YAML, mapping keys to selectors.")]
+        rules: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON used as template, e.g. one \
+                    specialized with a use case."
+        )]
+        mig: PathBuf,
+    },
+    #[cfg(feature = "template")]
+    #[command(
+        about = "Render an EDIFACT file from a Tera template with JSON \
+                 values, which is validated against the MIG before it is \
+                 printed."
+    )]
+    Render {
+        #[arg(long, help = "The template, e.g. BGM+313+{{ document }}'.")]
+        template: PathBuf,
+        #[arg(long, help = "The values in JSON.")]
+        data: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+    },
+    #[command(about = "Validate many EDIFACT files against a MIG.")]
+    Validate {
+        #[arg(
+            required_unless_present = "glob",
+            help = "EDIFACT files or directories."
+        )]
+        files: Vec<PathBuf>,
+        #[arg(long, help = "A pattern of files to validate, e.g. '*.edi'.")]
+        glob: Vec<String>,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(long, help = "Print how often each error code occurred.")]
+        summary: bool,
+        #[arg(
+            long,
+            default_value_t = 0,
+            help = "The number of errors tolerated before failing."
+        )]
+        threshold: usize,
+        #[arg(long, help = "Tolerate deviations and skip unknown segments.")]
+        lenient: bool,
+        #[arg(
+            long,
+            help = "Validate without reading whole files into memory, e.g. \
+                    for archives of several gigabytes."
+        )]
+        bounded: bool,
+        #[arg(
+            long,
+            conflicts_with = "bounded",
+            help = "Write an HTML report with the errors highlighted."
+        )]
+        report: Option<PathBuf>,
+    },
+    #[command(
+        about = "Replace names, addresses and meter numbers with pseudonyms."
+    )]
+    Anonymize {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(
+            long = "label",
+            help = "The label of a personal data element, e.g. 3036. \
+                    Replaces the default labels."
+        )]
+        labels: Vec<String>,
+        #[arg(long, help = "A secret mixed into every pseudonym.")]
+        key: Option<String>,
+    },
+    #[command(about = "Remove segments and segment groups from a file.")]
+    Filter {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "The segments or groups to remove, e.g. FTX, LOC+172 or \
+                    SG4."
+        )]
+        strip: Vec<Selector>,
+        #[arg(
+            long,
+            value_parser = parse_limit,
+            help = "The number of segments or groups to keep, e.g. LOC=1."
+        )]
+        limit: Vec<(Selector, usize)>,
+    },
+    #[command(
+        about = "Print a hash or signature of the segments of a file, \
+                 which is kept apart from it."
+    )]
+    Hash {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(long, help = "Tolerate deviations and skip unknown segments.")]
+        lenient: bool,
+        #[arg(
+            long,
+            help = "A file with a secret shared with the partner, which \
+                    signs the content with HMAC-SHA256 instead of hashing \
+                    it with SHA-256."
+        )]
+        key_file: Option<PathBuf>,
+    },
+    #[command(
+        about = "Verify the hash or signature of a file printed by hash."
+    )]
+    Verify {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+        #[arg(help = "A file with the hash or signature.")]
+        signature: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(long, help = "Tolerate deviations and skip unknown segments.")]
+        lenient: bool,
+        #[arg(
+            long,
+            help = "A file with the secret, the content has been signed with."
+        )]
+        key_file: Option<PathBuf>,
+    },
+    #[command(
+        about = "Migrate a file to another version of its MIG, e.g. during \
+                 a format change."
+    )]
+    Migrate {
+        #[arg(help = "An EDIFACT file.")]
+        file: PathBuf,
+        #[arg(long, help = "The version the file follows, e.g. 5.2d.")]
+        from: String,
+        #[arg(long, help = "The version to migrate to, e.g. 5.2e.")]
+        to: String,
+        #[arg(
+            long,
+            help = "A mapping in JSON with the renamed qualifiers and moved \
+                    elements."
+        )]
+        mapping: Option<PathBuf>,
+    },
+    #[command(about = "Explain the errors reported in a CONTRL or APERAK.")]
+    Report {
+        #[arg(help = "A received CONTRL or APERAK.")]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "The interchange originally sent to name the segments \
+                    and data elements at the reported positions."
+        )]
+        sent: Option<PathBuf>,
+    },
+    #[command(about = "Compare two interchanges segment by segment.")]
+    Diff {
+        #[arg(help = "The EDIFACT file compared against.")]
+        left: PathBuf,
+        #[arg(help = "The EDIFACT file compared.")]
+        right: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "The label of a data element, whose changes are \
+                    ignored, e.g. 0020."
+        )]
+        ignore: Vec<String>,
+    },
+    #[command(
+        about = "Report the segments, groups and codes of a MIG seen in \
+                 many EDIFACT files."
+    )]
+    Coverage {
+        #[arg(
+            required_unless_present = "glob",
+            help = "EDIFACT files or directories."
+        )]
+        files: Vec<PathBuf>,
+        #[arg(long, help = "A pattern of files, e.g. 'archive/*.edi'.")]
+        glob: Vec<String>,
+        #[arg(long, help = "A MIG description in JSON.")]
+        mig: PathBuf,
+        #[arg(long, help = "Print only the items never seen.")]
+        unseen: bool,
+    },
+    #[command(
+        about = "Print the message types, versions, partners and sizes of \
+                 many EDIFACT files, e.g. of an archive."
+    )]
+    Stats {
+        #[arg(
+            required_unless_present = "glob",
+            help = "EDIFACT files or directories."
+        )]
+        files: Vec<PathBuf>,
+        #[arg(long, help = "A pattern of files, e.g. 'archive/*.edi'.")]
+        glob: Vec<String>,
+        #[arg(long, value_enum, help = "The format, by default json.")]
+        output: Option<Output>,
+    },
+    #[command(
+        about = "Validate the EDIFACT files arriving in a directory and move \
+                 them according to the result."
+    )]
+    Watch {
+        #[arg(help = "The directory to watch.")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            num_args = 2,
+            value_names = ["ACTION", "DIR"],
+            help = "What to do with valid files, mv-to or cp-to a directory."
+        )]
+        on_valid: Vec<String>,
+        #[arg(
+            long,
+            num_args = 2,
+            value_names = ["ACTION", "DIR"],
+            help = "What to do with invalid files, mv-to or cp-to a \
+                    directory."
+        )]
+        on_invalid: Vec<String>,
+        #[arg(
+            long,
+            help = "A directory to write an HTML report of every file to."
+        )]
+        report: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(long, help = "Tolerate deviations and skip unknown segments.")]
+        lenient: bool,
+        #[arg(
+            long,
+            default_value_t = 2,
+            help = "The number of seconds to look for new files after at \
+                    the latest, if no change of the directory is reported."
+        )]
+        interval: u64,
+        #[arg(
+            long,
+            help = "Process the files in the directory and exit instead of \
+                    watching it."
+        )]
+        once: bool,
+        #[arg(
+            long,
+            help = "A file to write the metrics to in the OpenMetrics text \
+                    format after looking for new files, e.g. for the \
+                    textfile collector of the Prometheus node exporter."
+        )]
+        metrics: Option<PathBuf>,
+    },
+    #[cfg(feature = "server")]
+    #[command(
+        about = "Serve an HTTP API to validate, decode and encode EDIFACT \
+                 files: POST /validate, POST /decode and POST /encode."
+    )]
+    Serve {
+        #[arg(
+            long,
+            default_value = "127.0.0.1:8080",
+            help = "The address to listen on."
+        )]
+        addr: String,
+        #[arg(
+            long,
+            help = "A directory with MIG descriptions, by default the \
+                    configured ones."
+        )]
+        mig_dir: Vec<PathBuf>,
+        #[arg(long, help = "Tolerate deviations and skip unknown segments.")]
+        lenient: bool,
+        #[cfg(feature = "grpc")]
+        #[arg(
+            long,
+            help = "An address to serve the gRPC service of \
+                    proto/edifact.proto on as well."
+        )]
+        grpc: Option<String>,
+    },
+    #[command(about = "Run a conformance test suite against a MIG.")]
+    Test {
+        #[arg(help = "A directory of EDIFACT files, each with its expected \
+                    outcome in a TOML or JSON file of the same name.")]
+        suite: PathBuf,
+        #[arg(
+            long,
+            help = "A MIG description in JSON, by default the one for the \
+                    message type in the configured MIG directories."
+        )]
+        mig: Option<PathBuf>,
+        #[arg(long, help = "Tolerate deviations and skip unknown segments.")]
+        lenient: bool,
+        #[arg(long, help = "A file to write the results to as JUnit XML.")]
+        junit: Option<PathBuf>,
+    },
+    #[cfg(feature = "testing")]
+    #[command(about = "Generate example interchanges conforming to a MIG.")]
+    Generate {
+        #[arg(long, help = "A MIG description in JSON.")]
+        mig: PathBuf,
+        #[arg(
+            long,
+            default_value_t = 1,
+            help = "The number of interchanges, printed one per line."
+        )]
+        count: usize,
+        #[arg(long, help = "A seed to generate the same interchanges again.")]
+        seed: Option<u64>,
+    },
+    #[command(about = "Print the completions for a shell.")]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    #[command(about = "Print the manual page in roff.")]
+    Man,
+}
+
+/// A `Selector` selects segments by path, e.g. `LOC+172`, or segment
+/// groups by label, e.g. `SG4`.
+#[derive(Debug, Clone)]
+enum Selector {
+    Group(String),
+    Segment(mig::Path),
+}
+
+impl FromStr for Selector {
+    type Err = mig::decode::path::InvalidPath;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_group = s.strip_prefix("SG").is_some_and(|number| {
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        });
+        if is_group {
+            Ok(Selector::Group(s.to_string()))
+        } else {
+            s.parse().map(Selector::Segment)
+        }
+    }
+}
+
+impl Selector {
+    fn matches(
+        &self,
+        value: Either<&value::Segmentgroup, &value::Segment>,
+    ) -> bool {
+        match (self, value) {
+            (Selector::Group(label), Either::Left(group)) => {
+                group.label() == label
+            }
+            (Selector::Segment(path), Either::Right(segment)) => {
+                path.matches(segment)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parses a selector with the number of values to keep, e.g. `LOC=1`.
+fn parse_limit(s: &str) -> Result<(Selector, usize), String> {
+    let (selector, limit) = s.split_once('=').ok_or_else(|| {
+        format!("'{}' is not a limit, expected e.g. LOC=1", s)
+    })?;
+    let selector = selector.parse().map_err(|e| format!("{}", e))?;
+    let limit = limit.parse().map_err(|e| format!("{}", e))?;
+    Ok((selector, limit))
+}
+
+/// The formats `grep`, `index search` and `stats` print values in.
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    /// Comma separated values with a header.
+    Csv,
+    /// One JSON object per line.
+    Json,
 }
 
 #[derive(Debug, Parser)]
@@ -28,16 +574,216 @@ enum Mig {
         #[arg(help = "A PDF file.")]
         file: PathBuf,
     },
+    #[command(
+        name = "changelog",
+        about = "Print the changes listed in the message integration guide."
+    )]
+    Changelog {
+        #[arg(help = "A PDF file or its text converted by pdftotext.")]
+        file: PathBuf,
+    },
+    #[command(
+        name = "to-yaml",
+        about = "Print a MIG description in the compact YAML format."
+    )]
+    ToYaml {
+        #[arg(help = "A MIG description in JSON.")]
+        file: PathBuf,
+    },
+    #[command(
+        name = "to-json",
+        about = "Print a MIG description in the compact YAML format as JSON."
+    )]
+    ToJson {
+        #[arg(help = "A MIG description in YAML.")]
+        file: PathBuf,
+    },
+    #[command(
+        name = "lint",
+        about = "Check MIG descriptions for issues, which make decoding fail."
+    )]
+    Lint {
+        #[arg(required = true, help = "MIG descriptions in JSON or YAML.")]
+        files: Vec<PathBuf>,
+    },
+    #[command(
+        name = "check",
+        about = "Check a MIG description against a UN/EDIFACT directory."
+    )]
+    Check {
+        #[arg(help = "A MIG description in JSON or YAML.")]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "A directory file, otherwise the embedded D.07B is used."
+        )]
+        directory: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum Ahb {
+    #[command(
+        name = "list",
+        about = "List the Pruefidentifikatoren of the use cases of an AHB."
+    )]
+    List {
+        #[arg(help = "An AHB description in JSON.")]
+        file: PathBuf,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum Index {
+    #[command(about = "Index the EDIFACT files in the given directories.")]
+    Build {
+        #[arg(required = true, help = "EDIFACT files or directories.")]
+        files: Vec<PathBuf>,
+        #[arg(long, default_value = INDEX, help = "The index to write.")]
+        index: PathBuf,
+    },
+    #[command(about = "Search the index for interchanges.")]
+    Search {
+        #[arg(long, default_value = INDEX, help = "The index to search.")]
+        index: PathBuf,
+        #[arg(
+            long = "ref",
+            help = "An interchange reference or a document or \
+                    transaction number."
+        )]
+        reference: Option<String>,
+        #[arg(long, help = "The ID of the sender.")]
+        sender: Option<String>,
+        #[arg(long, help = "The ID of the receiver.")]
+        receiver: Option<String>,
+        #[arg(long = "type", help = "A message type, e.g. UTILMD.")]
+        message_type: Option<String>,
+        #[arg(long, help = "The earliest creation date, e.g. 2020-03-07.")]
+        from: Option<String>,
+        #[arg(long, help = "The latest creation date, e.g. 2020-03-07.")]
+        until: Option<String>,
+        #[arg(long, value_enum, help = "The format, by default csv.")]
+        output: Option<Output>,
+    },
+    #[command(about = "Show the messages referring to each other, e.g. the \
+                 APERAK for a UTILMD.")]
+    Conversation {
+        #[arg(long, default_value = INDEX, help = "The index to search.")]
+        index: PathBuf,
+        #[arg(
+            long = "ref",
+            help = "An interchange reference or a document or \
+                    transaction number."
+        )]
+        reference: String,
+        #[arg(long, value_enum, help = "The format, by default csv.")]
+        output: Option<Output>,
+    },
 }
 
+/// The index file used, if none is given.
+const INDEX: &str = "edifact-index.jsonl";
+
 #[derive(Debug)]
 pub enum Error {
-    NoPdf(),
+    Spec(spec::Error),
+    Io(std::io::Error),
+    Config(config::Error),
+    InvalidMig(serde_json::Error),
+    InvalidYaml(compact::Error),
+    InvalidGlob(glob::PatternError),
+    Decode(decode::Error),
+    ThresholdExceeded { errors: usize, threshold: usize },
+    Linted { issues: usize },
+    InvalidDirectory(directory::Error),
+    Deviated { deviations: usize },
+    Different { differences: usize },
+    NoReport,
+    NotReferenced,
+    Index(index::Error),
+    Suite(suite::Error),
+    Failed { failures: usize, cases: usize },
+    UnknownVersion(String),
+    InvalidMapping(serde_json::Error),
+    Migrate(mig::migrate::MigrateError),
+    InvalidRules(mig::extract::ExtractError),
+    InvalidValues(serde_json::Error),
+    InvalidManual(serde_json::Error),
+    #[cfg(feature = "template")]
+    Template(mig::template::TemplateError),
+    InvalidAction(String),
+    Signature(SignatureError),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "An error occurred")
+        match self {
+            Error::Spec(error) => error.fmt(f),
+            Error::Io(error) => error.fmt(f),
+            Error::Config(error) => error.fmt(f),
+            Error::InvalidMig(error) => {
+                write!(f, "The MIG description is invalid: {}", error)
+            }
+            Error::InvalidYaml(error) => {
+                write!(f, "The MIG description is invalid: {}", error)
+            }
+            Error::InvalidGlob(error) => {
+                write!(f, "The pattern is invalid: {}", error)
+            }
+            Error::Decode(error) => {
+                write!(f, "The file could not be parsed: {}", error)
+            }
+            Error::ThresholdExceeded { errors, threshold } => write!(
+                f,
+                "{} errors found, but only {} are tolerated",
+                errors, threshold
+            ),
+            Error::Linted { issues } => {
+                write!(f, "{} issues found in the MIG descriptions", issues)
+            }
+            Error::InvalidDirectory(error) => {
+                write!(f, "The directory is invalid: {}", error)
+            }
+            Error::Deviated { deviations } => {
+                write!(f, "{} deviations from the directory found", deviations)
+            }
+            Error::Different { differences } => {
+                write!(f, "{} differences found", differences)
+            }
+            Error::NoReport => {
+                write!(f, "The file contains neither a CONTRL nor an APERAK")
+            }
+            Error::NotReferenced => {
+                write!(f, "The sent interchange is not the one reported on")
+            }
+            Error::Index(error) => {
+                write!(f, "The index could not be used: {}", error)
+            }
+            Error::Suite(error) => {
+                write!(f, "The suite could not be read: {}", error)
+            }
+            Error::Failed { failures, cases } => {
+                write!(f, "{} of {} cases failed", failures, cases)
+            }
+            Error::UnknownVersion(version) => {
+                write!(f, "No MIG of the version {} found", version)
+            }
+            Error::InvalidMapping(error) => {
+                write!(f, "The mapping is invalid: {}", error)
+            }
+            Error::Migrate(error) => error.fmt(f),
+            Error::InvalidRules(error) => error.fmt(f),
+            Error::InvalidValues(error) => {
+                write!(f, "The values are invalid: {}", error)
+            }
+            Error::InvalidManual(error) => {
+                write!(f, "The AHB description is invalid: {}", error)
+            }
+            #[cfg(feature = "template")]
+            Error::Template(error) => error.fmt(f),
+            Error::InvalidAction(message) => message.fmt(f),
+            Error::Signature(error) => error.fmt(f),
+        }
     }
 }
 
@@ -46,10 +792,1385 @@ pub fn parse() -> Cli {
 }
 
 pub fn run(cli: Cli) -> Result<(), Error> {
+    let config = Config::load(cli.config.as_deref()).map_err(Error::Config)?;
     match cli.command {
         Command::Mig(Mig::Parse { file }) => {
-            println!("{:?}", spec::parse(file));
+            let mut interchange = spec::parse(file).map_err(Error::Spec)?;
+            Directory::d07b().enrich(&mut interchange);
+            let json = serde_json::to_string_pretty(&interchange)
+                .map_err(Error::InvalidMig)?;
+            println!("{}", json);
+        }
+        Command::Mig(Mig::Changelog { file }) => {
+            for change in spec::parse_changelog(file).map_err(Error::Spec)? {
+                println!("{}", change);
+            }
+        }
+        Command::Mig(Mig::ToYaml { file }) => {
+            let desc = read_mig(&file)?;
+            let yaml = compact::to_yaml(&desc).map_err(Error::InvalidYaml)?;
+            print!("{}", yaml);
+        }
+        Command::Mig(Mig::Lint { files }) => {
+            let mut issues = 0;
+            for file in files {
+                for lint in lint::lint(&read_mig(&file)?) {
+                    println!("{}: {}", file.display(), lint);
+                    issues += 1;
+                }
+            }
+            if issues > 0 {
+                return Err(Error::Linted { issues });
+            }
+        }
+        Command::Mig(Mig::Check { file, directory }) => {
+            let directory = match directory {
+                Some(path) => {
+                    let input = fs::read_to_string(path).map_err(Error::Io)?;
+                    Directory::parse(&input)
+                        .map_err(Error::InvalidDirectory)?
+                }
+                None => Directory::d07b(),
+            };
+            let deviations = directory.check(&read_mig(&file)?);
+            for deviation in &deviations {
+                println!("{}", deviation);
+            }
+            if !deviations.is_empty() {
+                let deviations = deviations.len();
+                return Err(Error::Deviated { deviations });
+            }
+        }
+        Command::Mig(Mig::ToJson { file }) => {
+            let desc = read_mig(&file)?;
+            let json = serde_json::to_string_pretty(&desc)
+                .map_err(Error::InvalidMig)?;
+            println!("{}", json);
+        }
+        Command::Ahb(Ahb::List { file }) => {
+            let input = fs::read_to_string(file).map_err(Error::Io)?;
+            let manual: Manual =
+                serde_json::from_str(&input).map_err(Error::InvalidManual)?;
+            for use_case in &manual.use_cases {
+                match &use_case.ident {
+                    Some(ident) => println!("{}", ident.value),
+                    None => println!("-"),
+                }
+            }
+        }
+        Command::Index(Index::Build { files, index }) => {
+            let mut all = vec![];
+            for file in files {
+                collect_files(&file, &mut all).map_err(Error::Io)?;
+            }
+            let entries = build_index(&all);
+            index::write(&index, &entries).map_err(Error::Index)?;
+            eprintln!("{} interchanges indexed", entries.len());
+        }
+        Command::Index(Index::Search {
+            index,
+            reference,
+            sender,
+            receiver,
+            message_type,
+            from,
+            until,
+            output,
+        }) => {
+            let query = index::Query {
+                reference,
+                sender,
+                receiver,
+                message_type,
+                from,
+                until,
+            };
+            let output = output.or(config.output).unwrap_or(Output::Csv);
+            let entries = index::read(&index).map_err(Error::Index)?;
+            search(&query, &entries, output);
+        }
+        Command::Index(Index::Conversation { index, reference, output }) => {
+            let output = output.or(config.output).unwrap_or(Output::Csv);
+            let entries = index::read(&index).map_err(Error::Index)?;
+            conversation(&reference, &entries, output);
+        }
+        #[cfg(feature = "tui")]
+        Command::Inspect { file, mig } => {
+            let input = fs::read(file).map_err(Error::Io)?;
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            // Without a description, the interchange is browsed as parsed.
+            let description = migs
+                .lookup(&input)
+                .ok()
+                .and_then(|known| known.first())
+                .map(|mig| mig.description().clone());
+            let options = decode_options(&config, false);
+            let input = decode::charset::read(&mut input.as_slice(), &options)
+                .map_err(Error::Decode)?;
+            let inspector =
+                Inspector::new(&input, description).map_err(Error::Decode)?;
+
+            let mut terminal = ratatui::init();
+            let result = inspector.run(&mut terminal);
+            ratatui::restore();
+            result.map_err(Error::Io)?;
+        }
+        Command::Grep { path, files, output } => {
+            let output = output.or(config.output).unwrap_or(Output::Csv);
+            grep(&path, &files, output)?
+        }
+        Command::Structure { file } => {
+            let input = fs::read(&file).map_err(Error::Io)?;
+            let interchange =
+                mig::parse(&mut input.as_slice()).map_err(Error::Decode)?;
+            // One segment per line keeps large interchanges readable.
+            let json = interchange.to_structural_json();
+            let segments: Vec<String> = json
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|segment| format!("  {}", segment))
+                .collect();
+            println!("[\n{}\n]", segments.join(",\n"));
+        }
+        Command::Export { files, mig, table, columns, output } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let output = output.or(config.output).unwrap_or(Output::Csv);
+            export(&migs, &files, &table, &columns, output)?
+        }
+        Command::Map { rules, files, mig } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let rules = fs::read_to_string(rules).map_err(Error::Io)?;
+            let extractor = mig::extract::Extractor::from_yaml(&rules)
+                .map_err(Error::InvalidRules)?;
+            map(&migs, &files, &extractor)?
+        }
+        Command::Build { file, rules, mig } => {
+            let desc = read_mig(&mig)?;
+            let rules = fs::read_to_string(rules).map_err(Error::Io)?;
+            let extractor = mig::extract::Extractor::from_yaml(&rules)
+                .map_err(Error::InvalidRules)?;
+            let input = fs::read_to_string(file).map_err(Error::Io)?;
+            let values: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&input).map_err(Error::InvalidValues)?;
+            let interchange =
+                extractor.build(&desc, &values).map_err(Error::Decode)?;
+            print_encoded(&interchange, &config.encode.options());
+        }
+        #[cfg(feature = "template")]
+        Command::Render { template, data, mig } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let template = fs::read_to_string(template).map_err(Error::Io)?;
+            let template: mig::template::Template =
+                template.parse().map_err(Error::Template)?;
+            let input = fs::read_to_string(data).map_err(Error::Io)?;
+            let values: serde_json::Value =
+                serde_json::from_str(&input).map_err(Error::InvalidValues)?;
+            let output = template.render(&values).map_err(Error::Template)?;
+            let known = migs.lookup(output.as_bytes()).map_err(Error::Decode)?;
+            let options = decode_options(&config, false);
+            let interchange =
+                mig::decode(known, &mut output.as_bytes(), &options)
+                    .map_err(Error::Decode)?;
+            print_encoded(&interchange, &config.encode.options());
+        }
+        Command::Validate {
+            files,
+            glob,
+            mig,
+            summary,
+            threshold,
+            lenient,
+            bounded,
+            report,
+        } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let options = decode_options(&config, lenient);
+            let all = collect_paths(&files, &glob)?;
+            let mut document = report.as_ref().map(|_| html::Document::new());
+            let result = validate(
+                &migs,
+                &all,
+                &options,
+                bounded,
+                document.as_mut(),
+                config.language,
+            );
+            if let (Some(path), Some(document)) = (report, document) {
+                fs::write(path, document.to_string()).map_err(Error::Io)?;
+            }
+            if summary {
+                print!("{}", result);
+            }
+            if result.errors() > threshold {
+                return Err(Error::ThresholdExceeded {
+                    errors: result.errors(),
+                    threshold,
+                });
+            }
+        }
+        Command::Anonymize { file, mig, labels, key } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let mut interchange = decode_lenient(&migs, &file)?;
+            let encode = config.encode.options();
+
+            let mut options = mig::transform::AnonymizeOptions::default();
+            let config = config.anonymize;
+            if !labels.is_empty() {
+                options.labels = labels;
+            } else if let Some(labels) = config.labels {
+                options.labels = labels;
+            }
+            if let Some(names) = config.names {
+                options.names = names;
+            }
+            if let Some(key) = key.or(config.key) {
+                options.key = key;
+            }
+            mig::transform::anonymize(&mut interchange, &options);
+            print_encoded(&interchange, &encode);
+        }
+        Command::Filter { file, mig, strip, limit } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let interchange = decode_lenient(&migs, &file)?;
+            let mut counts = vec![0; limit.len()];
+            let filtered = mig::transform::filter(&interchange, |value| {
+                if strip.iter().any(|selector| selector.matches(value)) {
+                    return false;
+                }
+                let limits = limit.iter().zip(&mut counts);
+                for ((selector, limit), count) in limits {
+                    if selector.matches(value) {
+                        *count += 1;
+                        if *count > *limit {
+                            return false;
+                        }
+                    }
+                }
+                true
+            });
+            print_encoded(&filtered, &config.encode.options());
+        }
+        Command::Hash { file, mig, lenient, key_file } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let options = decode_options(&config, lenient);
+            let interchange = signed_file(&migs, &file, &options)?;
+            let signer = signer(key_file.as_deref())?;
+            println!("{}", signature::sign(&interchange, signer.as_ref()));
+        }
+        Command::Verify { file, signature, mig, lenient, key_file } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let options = decode_options(&config, lenient);
+            let interchange = signed_file(&migs, &file, &options)?;
+            let signer = signer(key_file.as_deref())?;
+            let signature = fs::read_to_string(signature)
+                .map_err(Error::Io)?
+                .parse()
+                .map_err(Error::Signature)?;
+            signature::verify(&interchange, signer.as_ref(), &signature)
+                .map_err(Error::Signature)?;
+            println!("{} verified", file.display());
+        }
+        Command::Migrate { file, from, to, mapping } => {
+            let migs = Migs::new(None, &config)?;
+            let input = fs::read(&file).map_err(Error::Io)?;
+            let routing =
+                mig::routing(&mut input.as_slice()).map_err(Error::Decode)?;
+            let from = migs.version(&routing, &from)?;
+            let to = migs.version(&routing, &to)?;
+            let mapping = match mapping {
+                Some(path) => {
+                    let json = fs::read_to_string(path).map_err(Error::Io)?;
+                    serde_json::from_str(&json)
+                        .map_err(Error::InvalidMapping)?
+                }
+                None => mig::migrate::Mapping::default(),
+            };
+            let interchange = mig::migrate::migrate(
+                from,
+                to,
+                &mapping,
+                &mut input.as_slice(),
+                &decode_options(&config, false),
+            )
+            .map_err(Error::Migrate)?;
+            print_encoded(&interchange, &config.encode.options());
+        }
+        Command::Report { file, mig, sent } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let interchange = decode_lenient(&migs, &file)?;
+            let mut report =
+                report::report(&interchange).ok_or(Error::NoReport)?;
+            if let Some(sent) = sent {
+                // The sent interchange is of another message type.
+                let migs = Migs::new(None, &config)?;
+                if !report.resolve(&decode_lenient(&migs, &sent)?) {
+                    return Err(Error::NotReferenced);
+                }
+            }
+            print!("{}", report);
+        }
+        Command::Diff { left, right, mig, ignore } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let left = decode_lenient(&migs, &left)?;
+            let right = decode_lenient(&migs, &right)?;
+            let differences: Vec<diff::Difference> = diff::diff(&left, &right)
+                .into_iter()
+                .filter(|difference| {
+                    !difference.label().is_some_and(|label| {
+                        ignore.iter().any(|ignored| ignored == label)
+                    })
+                })
+                .collect();
+            for difference in &differences {
+                println!("{}", difference);
+            }
+            if !differences.is_empty() {
+                return Err(Error::Different {
+                    differences: differences.len(),
+                });
+            }
+        }
+        Command::Coverage { files, glob, mig, unseen } => {
+            let desc = read_mig(&mig)?;
+            let all = collect_paths(&files, &glob)?;
+            let coverage = coverage(&desc, &all);
+            if unseen {
+                for item in coverage.unseen() {
+                    println!("{} {}", item.kind, item.path);
+                }
+            } else {
+                print!("{}", coverage);
+            }
+        }
+        Command::Stats { files, glob, output } => {
+            let all = collect_paths(&files, &glob)?;
+            let stats = stats(&all);
+            match output.or(config.output).unwrap_or(Output::Json) {
+                Output::Csv => {
+                    println!("kind,key,value");
+                    for row in stats.rows() {
+                        let row: Vec<String> =
+                            row.iter().map(|value| csv(value)).collect();
+                        println!("{}", row.join(","));
+                    }
+                }
+                Output::Json => {
+                    let json = serde_json::to_string_pretty(&stats)
+                        .expect("Statistics can be serialized");
+                    println!("{}", json);
+                }
+            }
+        }
+        Command::Watch {
+            dir,
+            on_valid,
+            on_invalid,
+            report,
+            mig,
+            lenient,
+            interval,
+            once,
+            metrics,
+        } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let options = decode_options(&config, lenient);
+            let on_valid =
+                watch::Action::parse(&on_valid).map_err(Error::InvalidAction)?;
+            let on_invalid = watch::Action::parse(&on_invalid)
+                .map_err(Error::InvalidAction)?;
+            let mut watcher = watch::Watcher::new(&dir, !once)
+                .map_err(|error| Error::Io(io::Error::other(error)))?;
+            let counters = migs.counters();
+            loop {
+                for file in watcher.poll().map_err(Error::Io)? {
+                    let report = report.as_deref();
+                    let valid =
+                        intake(&migs, &file, &options, report, &counters);
+                    let action = if valid { &on_valid } else { &on_invalid };
+                    let Some(action) = action else {
+                        continue;
+                    };
+                    match action.apply(&file) {
+                        Ok(to) => {
+                            println!("{} -> {}", file.display(), to.display())
+                        }
+                        Err(error) => {
+                            eprintln!("{}: {}", file.display(), error)
+                        }
+                    }
+                }
+                if let Some(path) = &metrics {
+                    fs::write(path, counters.render()).map_err(Error::Io)?;
+                }
+                if once {
+                    break;
+                }
+                watcher.wait(Duration::from_secs(interval));
+            }
+        }
+        #[cfg(feature = "server")]
+        Command::Serve {
+            addr,
+            mig_dir,
+            lenient,
+            #[cfg(feature = "grpc")]
+            grpc,
+        } => {
+            let options = decode_options(&config, lenient);
+            let encode = config.encode.options();
+            let mig_dirs =
+                if mig_dir.is_empty() { config.mig_dirs } else { mig_dir };
+            let migs = Migs::new(None, &Config { mig_dirs, ..config })?;
+            let listener =
+                std::net::TcpListener::bind(&addr).map_err(Error::Io)?;
+            let decoder = Arc::new(Decoder::new(migs, options));
+            #[cfg(feature = "grpc")]
+            if let Some(grpc) = grpc {
+                let listener =
+                    std::net::TcpListener::bind(&grpc).map_err(Error::Io)?;
+                eprintln!("Serving gRPC on {}", grpc);
+                let decoder = Arc::clone(&decoder);
+                std::thread::spawn(move || {
+                    let decode = move |input: &[u8]| decoder.decode(input);
+                    if let Err(error) = grpc::serve(listener, decode) {
+                        eprintln!("error: {}", error);
+                        std::process::exit(1);
+                    }
+                });
+            }
+            eprintln!("Listening on {}", addr);
+            let handler = move |request: &server::Request| {
+                respond(&decoder, &encode, request)
+            };
+            server::serve(listener, handler).map_err(Error::Io)?;
+        }
+        Command::Test { suite, mig, lenient, junit } => {
+            let migs = Migs::new(mig.as_deref(), &config)?;
+            let options = decode_options(&config, lenient);
+            let cases = suite::read(&suite).map_err(Error::Suite)?;
+            let results: Vec<suite::CaseResult> = cases
+                .iter()
+                .map(|case| {
+                    suite::run(case, |input| {
+                        let known = migs.lookup(input)?;
+                        mig::decode(known, &mut &input[..], &options)
+                    })
+                })
+                .collect();
+            for result in &results {
+                match &result.failure {
+                    Some(failure) => {
+                        println!("FAILED {}: {}", result.name, failure)
+                    }
+                    None => println!("ok {}", result.name),
+                }
+            }
+            if let Some(junit) = junit {
+                let name = suite.file_name().unwrap_or_default();
+                let xml = suite::junit(&name.to_string_lossy(), &results);
+                fs::write(junit, xml).map_err(Error::Io)?;
+            }
+            let failures =
+                results.iter().filter(|r| r.failure.is_some()).count();
+            if failures > 0 {
+                return Err(Error::Failed { failures, cases: results.len() });
+            }
+        }
+        #[cfg(feature = "testing")]
+        Command::Generate { mig, count, seed } => {
+            let desc = read_mig(&mig)?;
+            for interchange in generate(&desc, count, seed) {
+                println!("{}", interchange);
+            }
+        }
+        Command::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            // Generating directly to stdout panics on a closed pipe.
+            let mut buffer = vec![];
+            clap_complete::generate(shell, &mut command, name, &mut buffer);
+            std::io::stdout().write_all(&buffer).map_err(Error::Io)?;
+        }
+        Command::Man => {
+            let man = clap_mangen::Man::new(Cli::command());
+            man.render(&mut std::io::stdout()).map_err(Error::Io)?;
+        }
+    }
+    Ok(())
+}
+
+/// The `Migs` are the compiled descriptions files are decoded with:
+/// either the one given with `--mig` or the ones in the configured MIG
+/// directories by message type. Of several descriptions for a message
+/// type, the first one, which applies at the creation date of a file,
+/// is used.
+struct Migs {
+    given: Option<mig::CompiledMig>,
+    by_type: HashMap<String, Vec<mig::CompiledMig>>,
+}
+
+impl Migs {
+    fn new(mig: Option<&Path>, config: &Config) -> Result<Migs, Error> {
+        let mut migs = Migs { given: None, by_type: HashMap::new() };
+        if let Some(mig) = mig {
+            migs.given = Some(read_mig(mig)?.into());
+            return Ok(migs);
+        }
+
+        for dir in &config.mig_dirs {
+            for entry in fs::read_dir(dir).map_err(Error::Io)? {
+                let path = entry.map_err(Error::Io)?.path();
+                let is_mig = path
+                    .extension()
+                    .is_some_and(|e| e == "json" || e == "yaml" || e == "yml");
+                let Some(stem) = path.file_stem().filter(|_| is_mig) else {
+                    continue;
+                };
+                // Earlier directories take precedence.
+                let desc = read_mig(&path)?;
+                let message_type = desc
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.message_type.clone())
+                    .unwrap_or_else(|| stem.to_string_lossy().to_uppercase());
+                migs.by_type
+                    .entry(message_type)
+                    .or_default()
+                    .push(desc.into());
+            }
+        }
+        // The bundled descriptions are used for message types without any
+        // description in the directories.
+        for desc in mig::bundled::all() {
+            let Some(message_type) = desc
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.message_type.clone())
+            else {
+                continue;
+            };
+            migs.by_type
+                .entry(message_type)
+                .or_insert_with(|| vec![desc.into()]);
+        }
+        Ok(migs)
+    }
+
+    /// Returns the known descriptions for the given input, which are
+    /// empty, if there is none for its message type.
+    fn lookup(
+        &self,
+        input: &[u8],
+    ) -> Result<&[mig::CompiledMig], decode::Error> {
+        if self.given.is_some() {
+            return Ok(self.given.as_slice());
+        }
+        let routing = mig::routing(&mut &input[..])?;
+        Ok(self.lookup_routing(&routing))
+    }
+
+    /// Returns the known descriptions for the given routing metadata like
+    /// [Migs::lookup].
+    fn lookup_routing(&self, routing: &mig::Routing) -> &[mig::CompiledMig] {
+        if self.given.is_some() {
+            return self.given.as_slice();
+        }
+        let date = routing.created.map(|created| {
+            format!(
+                "{:04}-{:02}-{:02}",
+                created.year, created.month, created.day
+            )
+        });
+        let applies = |mig: &&mig::CompiledMig| match (
+            &mig.description().metadata,
+            &date,
+        ) {
+            (Some(metadata), Some(date)) => metadata.is_valid_at(date),
+            _ => true,
+        };
+        routing
+            .message_type
+            .as_ref()
+            .and_then(|message_type| self.by_type.get(message_type))
+            .and_then(|migs| migs.iter().find(applies))
+            .map(std::slice::from_ref)
+            .unwrap_or_default()
+    }
+
+    /// Returns the description of the given version, e.g. `5.2e`, for
+    /// the message type of the given routing metadata.
+    fn version(
+        &self,
+        routing: &mig::Routing,
+        version: &str,
+    ) -> Result<&mig::CompiledMig, Error> {
+        routing
+            .message_type
+            .as_ref()
+            .and_then(|message_type| self.by_type.get(message_type))
+            .and_then(|migs| {
+                migs.iter().find(|mig| {
+                    let identifier = mig.description().message_identifier();
+                    identifier.association_code.as_deref() == Some(version)
+                })
+            })
+            .ok_or_else(|| Error::UnknownVersion(version.to_string()))
+    }
+
+    /// Returns the counters labelling the message types of these
+    /// descriptions.
+    fn counters(&self) -> Counters {
+        let given = self.given.iter().filter_map(|mig| {
+            mig.description().message_identifier().message_type
+        });
+        Counters::new(given.chain(self.by_type.keys().cloned()))
+    }
+}
+
+/// Prints the given interchange encoded with the given options, ending
+/// with exactly one newline.
+fn print_encoded(
+    interchange: &value::Interchange,
+    options: &mig::EncodeOptions,
+) {
+    let output = mig::encode_with(interchange, options);
+    if output.ends_with('\n') {
+        print!("{}", output);
+    } else {
+        println!("{}", output);
+    }
+}
+
+/// Reads a MIG description from the given file, which is in the compact
+/// YAML format, if it ends with `.yaml` or `.yml`, and in JSON otherwise.
+fn read_mig(path: &Path) -> Result<mig::description::Interchange, Error> {
+    let input = fs::read_to_string(path).map_err(Error::Io)?;
+    if path.extension().is_some_and(|e| e == "yaml" || e == "yml") {
+        compact::from_yaml(&input).map_err(Error::InvalidYaml)
+    } else {
+        serde_json::from_str(&input).map_err(Error::InvalidMig)
+    }
+}
+
+/// Returns the options to decode with, which are lenient, if given or
+/// configured.
+fn decode_options(config: &Config, lenient: bool) -> mig::DecodeOptions {
+    let mut options = if lenient || config.decode.lenient {
+        mig::DecodeOptions::lenient()
+    } else {
+        mig::DecodeOptions::strict()
+    };
+    options.treat_warnings_as_errors = config.decode.treat_warnings_as_errors;
+    options.max_errors = config.decode.max_errors;
+    options.max_identical_errors = config.decode.max_identical_errors;
+    options.sender = config.identity.sender.clone();
+    options.receiver = config.identity.receiver.clone();
+    options
+}
+
+/// Decodes the given file leniently, so files with deviations can still
+/// be transformed.
+fn decode_lenient(
+    migs: &Migs,
+    file: &Path,
+) -> Result<value::Interchange, Error> {
+    decode_file(migs, file, &mig::DecodeOptions::lenient())
+}
+
+fn decode_file(
+    migs: &Migs,
+    file: &Path,
+    options: &mig::DecodeOptions,
+) -> Result<value::Interchange, Error> {
+    let input = fs::read(file).map_err(Error::Io)?;
+    let known = migs.lookup(&input).map_err(Error::Decode)?;
+    mig::decode(known, &mut input.as_slice(), options).map_err(Error::Decode)
+}
+
+/// Returns the segments of the given file, which are signed, after making
+/// sure, that it can be decoded.
+fn signed_file(
+    migs: &Migs,
+    file: &Path,
+    options: &mig::DecodeOptions,
+) -> Result<decode::parser::value::Interchange, Error> {
+    decode_file(migs, file, options)?;
+    let input = fs::read(file).map_err(Error::Io)?;
+    mig::parse(&mut input.as_slice()).map_err(Error::Decode)
+}
+
+/// Returns the signer with the secret in the given file, if any, and the
+/// SHA-256 hash otherwise.
+fn signer(key_file: Option<&Path>) -> Result<Box<dyn Signer>, Error> {
+    match key_file {
+        Some(path) => {
+            let secret = fs::read(path).map_err(Error::Io)?;
+            // A line break at the end of the file is not part of it.
+            Ok(Box::new(HmacSha256::new(secret.trim_ascii_end())))
+        }
+        None => Ok(Box::new(signature::Sha256)),
+    }
+}
+
+/// Validates all given files with their descriptions and prints the
+/// ones, which are invalid. Decoded files are added to the report, if
+/// one is given.
+fn validate(
+    migs: &Migs,
+    files: &[PathBuf],
+    options: &mig::DecodeOptions,
+    bounded: bool,
+    mut report: Option<&mut html::Document>,
+    language: mig::error::Language,
+) -> mig::ValidationSummary {
+    let mut summary = mig::ValidationSummary::with_language(language);
+    for file in files {
+        let result = if bounded {
+            validate_bounded(migs, file, options)
+        } else {
+            validate_file(migs, file, options, report.as_deref_mut())
+        };
+        print_result(file, &result);
+        summary.add(&result);
+    }
+    summary
+}
+
+/// Prints the errors of an invalid file.
+fn print_result(file: &Path, result: &Result<(), decode::Error>) {
+    match result {
+        Ok(_) => {}
+        Err(decode::Error::Mig(error)) => {
+            let codes: Vec<String> = error
+                .syntax_errors()
+                .iter()
+                .map(|error| error.get_code().to_string())
+                .collect();
+            println!("{}: invalid ({})", file.display(), codes.join(", "));
+            let repeated =
+                error.message_errors.iter().flat_map(|e| &e.repeated);
+            for repetition in repeated {
+                println!("  {}", repetition);
+            }
+        }
+        Err(error) => println!("{}: {}", file.display(), error),
+    }
+}
+
+/// The `Decoder` of `edifact serve`, which decodes the inputs of the HTTP
+/// API and the gRPC service with the known descriptions and observes
+/// them with its counters.
+#[cfg(feature = "server")]
+struct Decoder {
+    migs: Migs,
+    options: mig::DecodeOptions,
+    counters: Counters,
+}
+
+#[cfg(feature = "server")]
+impl Decoder {
+    fn new(migs: Migs, options: mig::DecodeOptions) -> Self {
+        let counters = migs.counters();
+        Decoder { migs, options, counters }
+    }
+
+    fn decode(
+        &self,
+        input: &[u8],
+    ) -> Result<value::Interchange, decode::Error> {
+        metrics::measure(&self.counters, input, || {
+            let known = self.migs.lookup(input)?;
+            mig::decode(known, &mut &input[..], &self.options)
+        })
+    }
+}
+
+/// Returns the response of the HTTP API to the given request, see
+/// [server].
+#[cfg(feature = "server")]
+fn respond(
+    decoder: &Decoder,
+    encode: &mig::EncodeOptions,
+    request: &server::Request,
+) -> server::Response {
+    use server::Response;
+
+    let decode = |input: &[u8]| decoder.decode(input);
+    let counters = &decoder.counters;
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => Response {
+            status: 200,
+            content_type: "application/openmetrics-text; version=1.0.0; \
+                           charset=utf-8",
+            body: counters.render(),
+        },
+        ("POST", "/validate") => match decode(&request.body) {
+            Ok(_) => Response::json(200, &serde_json::json!({"valid": true})),
+            Err(decode::Error::Mig(error)) => Response::json(
+                200,
+                &serde_json::json!({"valid": false, "errors": error}),
+            ),
+            Err(error) => Response::error(400, &error.to_string()),
+        },
+        ("POST", "/decode") => match decode(&request.body) {
+            Ok(interchange) => {
+                Response::json(200, &serde_json::json!(interchange))
+            }
+            Err(decode::Error::Mig(error)) => {
+                Response::json(422, &serde_json::json!({"errors": error}))
+            }
+            Err(error) => Response::error(400, &error.to_string()),
+        },
+        ("POST", "/encode") => {
+            match serde_json::from_slice::<value::Interchange>(&request.body) {
+                Ok(interchange) => {
+                    Response::text(200, mig::encode_with(&interchange, encode))
+                }
+                Err(error) => Response::error(400, &error.to_string()),
+            }
+        }
+        (_, "/validate" | "/decode" | "/encode") => {
+            Response::error(405, "Only POST is supported")
+        }
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+/// Validates a file arriving in a watched directory, observes it with the
+/// given counters and writes its report into the given directory, if
+/// any. Returns, whether the file is valid.
+fn intake(
+    migs: &Migs,
+    file: &Path,
+    options: &mig::DecodeOptions,
+    report: Option<&Path>,
+    counters: &Counters,
+) -> bool {
+    let mut document = report.map(|_| html::Document::new());
+    let result = fs::read(file)
+        .map_err(decode::Error::Io)
+        .and_then(|input| {
+            metrics::measure(counters, &input, || {
+                validate_input(migs, file, &input, options, document.as_mut())
+            })
+        });
+    match &result {
+        Ok(_) => println!("{}: valid", file.display()),
+        Err(_) => print_result(file, &result),
+    }
+    if let (Some(dir), Some(document)) = (report, document) {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let path = dir.join(format!("{}.html", name));
+        let written = fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, document.to_string()));
+        if let Err(error) = written {
+            eprintln!("{}: {}", path.display(), error);
+        }
+    }
+    result.is_ok()
+}
+
+/// Validates the given file and adds it to the report, if one is given.
+fn validate_file(
+    migs: &Migs,
+    file: &Path,
+    options: &mig::DecodeOptions,
+    report: Option<&mut html::Document>,
+) -> Result<(), decode::Error> {
+    let input = fs::read(file).map_err(decode::Error::Io)?;
+    validate_input(migs, file, &input, options, report)
+}
+
+/// Validates the given input of a file and adds it to the report, if
+/// one is given.
+fn validate_input(
+    migs: &Migs,
+    file: &Path,
+    input: &[u8],
+    options: &mig::DecodeOptions,
+    report: Option<&mut html::Document>,
+) -> Result<(), decode::Error> {
+    let decode = |options: &mig::DecodeOptions| {
+        let known = migs.lookup(input)?;
+        mig::decode(known, &mut &input[..], options)
+    };
+    let result = decode(options);
+    // The report shows the text in the character set of the interchange.
+    let text = decode::charset::read(&mut &input[..], options);
+    if let (Some(report), Ok(input)) = (report, text.as_deref()) {
+        let title = file.display().to_string();
+        match &result {
+            Ok(interchange) => {
+                report.add(&title, input, Some(interchange), None)
+            }
+            Err(decode::Error::Mig(error)) => {
+                // An invalid interchange may still be decoded leniently,
+                // which shows the names of the MIG.
+                let lenient = decode(&mig::DecodeOptions::lenient()).ok();
+                report.add(&title, input, lenient.as_ref(), Some(error));
+            }
+            Err(_) => {}
+        }
+    }
+    result.map(|_| ())
+}
+
+/// Validates the given file with [mig::validate::bounded::validate],
+/// reading it twice instead of into memory.
+fn validate_bounded(
+    migs: &Migs,
+    file: &Path,
+    options: &mig::DecodeOptions,
+) -> Result<(), decode::Error> {
+    let open = || fs::File::open(file).map(std::io::BufReader::new);
+    let routing = mig::routing(&mut open().map_err(decode::Error::Io)?)?;
+    let mig = migs
+        .lookup_routing(&routing)
+        .first()
+        .ok_or(decode::Error::NoDescription)?;
+    let input = open().map_err(decode::Error::Io)?;
+    mig::validate::bounded::validate(mig, input, options)
+}
+
+/// Returns the coverage of the description by the given files. Files,
+/// which cannot be decoded, are reported and skipped.
+fn coverage(
+    desc: &mig::description::Interchange,
+    files: &[PathBuf],
+) -> Coverage {
+    let mut coverage = Coverage::new(desc);
+    let compiled = mig::CompiledMig::from(desc.clone());
+    let options = mig::DecodeOptions::lenient();
+    for file in files {
+        let interchange =
+            fs::read(file).map_err(decode::Error::Io).and_then(|input| {
+                mig::decode(&compiled, &mut input.as_slice(), &options)
+            });
+        match interchange {
+            Ok(interchange) => coverage.add(&interchange),
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), Error::Decode(error))
+            }
+        }
+    }
+    coverage
+}
+
+/// Generates the given number of example interchanges conforming to the
+/// description. The same seed generates the same interchanges.
+#[cfg(feature = "testing")]
+fn generate(
+    desc: &mig::description::Interchange,
+    count: usize,
+    seed: Option<u64>,
+) -> Vec<String> {
+    use proptest::strategy::{Strategy, ValueTree};
+    use proptest::test_runner::{self, RngAlgorithm, TestRng, TestRunner};
+
+    let mut runner = match seed {
+        Some(seed) => {
+            let mut bytes = [0; 32];
+            bytes[..8].copy_from_slice(&seed.to_le_bytes());
+            let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &bytes);
+            TestRunner::new_with_rng(test_runner::Config::default(), rng)
+        }
+        None => TestRunner::default(),
+    };
+    let strategy = mig::testing::example(desc);
+    (0..count)
+        .map(|_| {
+            // The strategies do not filter values, so they never reject.
+            let tree = strategy.new_tree(&mut runner).unwrap();
+            tree.current()
+        })
+        .collect()
+}
+
+/// Prints the values selected by the given path in all given files and
+/// the files in the given directories. Files, which cannot be parsed, are
+/// reported and skipped.
+fn grep(
+    path: &mig::Path,
+    files: &[PathBuf],
+    output: Output,
+) -> Result<(), Error> {
+    let mut all = vec![];
+    for file in files {
+        collect_files(file, &mut all).map_err(Error::Io)?;
+    }
+
+    if let Output::Csv = output {
+        println!("file,segment,value");
+    }
+    for file in all {
+        let interchange = fs::read(&file)
+            .map_err(decode::Error::Io)
+            .and_then(|input| mig::parse(&mut input.as_slice()));
+        let interchange = match interchange {
+            Ok(interchange) => interchange,
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), Error::Decode(error));
+                continue;
+            }
+        };
+
+        let name = file.display().to_string();
+        for (segment, value) in interchange.select(path) {
+            match output {
+                Output::Csv => {
+                    println!("{},{},{}", csv(&name), segment, csv(&value))
+                }
+                Output::Json => println!(
+                    "{}",
+                    serde_json::json!({
+                        "file": name,
+                        "segment": segment,
+                        "value": value,
+                    })
+                ),
+            }
         }
     }
     Ok(())
 }
+
+/// Prints the rows of the segment group with the given label in all
+/// given files and the files in the given directories. Files, which
+/// cannot be decoded, are reported and skipped.
+fn export(
+    migs: &Migs,
+    files: &[PathBuf],
+    label: &str,
+    columns: &[mig::Path],
+    output: Output,
+) -> Result<(), Error> {
+    let mut all = vec![];
+    for file in files {
+        collect_files(file, &mut all).map_err(Error::Io)?;
+    }
+
+    if let Output::Csv = output {
+        let header: Vec<String> =
+            columns.iter().map(|column| csv(&column.to_string())).collect();
+        println!("file,{}", header.join(","));
+    }
+    for file in all {
+        let interchange = match decode_lenient(migs, &file) {
+            Ok(interchange) => interchange,
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), error);
+                continue;
+            }
+        };
+
+        let name = file.display().to_string();
+        let table = Table::new(&interchange, label, columns);
+        for row in table.rows {
+            match output {
+                Output::Csv => {
+                    let values: Vec<String> = row
+                        .iter()
+                        .map(|value| csv(value.as_deref().unwrap_or_default()))
+                        .collect();
+                    println!("{},{}", csv(&name), values.join(","))
+                }
+                Output::Json => {
+                    let mut object = serde_json::Map::new();
+                    object.insert("file".to_string(), name.clone().into());
+                    for (column, value) in columns.iter().zip(row) {
+                        object.insert(column.to_string(), value.into());
+                    }
+                    println!("{}", serde_json::Value::Object(object))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the statistics of the given files. Files, which cannot be
+/// parsed, are reported and counted as unreadable.
+fn stats(files: &[PathBuf]) -> Stats {
+    let mut stats = Stats::new();
+    for file in files {
+        let interchange = fs::read(file)
+            .map_err(decode::Error::Io)
+            .and_then(|input| mig::parse(&mut input.as_slice()));
+        match interchange {
+            Ok(interchange) => stats.add(&interchange),
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), Error::Decode(error));
+                stats.add_unreadable();
+            }
+        }
+    }
+    stats
+}
+
+/// Prints the values extracted from every file as a JSON object per
+/// line. Files, which cannot be decoded, are reported and skipped.
+fn map(
+    migs: &Migs,
+    files: &[PathBuf],
+    extractor: &mig::extract::Extractor,
+) -> Result<(), Error> {
+    let mut all = vec![];
+    for file in files {
+        collect_files(file, &mut all).map_err(Error::Io)?;
+    }
+    for file in all {
+        let interchange = match decode_lenient(migs, &file) {
+            Ok(interchange) => interchange,
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), error);
+                continue;
+            }
+        };
+        let mut object = serde_json::Map::new();
+        object.insert("file".to_string(), file.display().to_string().into());
+        object.extend(extractor.extract(&interchange));
+        println!("{}", serde_json::Value::Object(object))
+    }
+    Ok(())
+}
+
+/// Returns the index entries of the given files. Files, which cannot be
+/// parsed, are reported and skipped.
+fn build_index(files: &[PathBuf]) -> Vec<index::Entry> {
+    let mut entries = vec![];
+    for file in files {
+        let interchange = fs::read(file)
+            .map_err(decode::Error::Io)
+            .and_then(|input| mig::parse(&mut input.as_slice()));
+        match interchange {
+            Ok(interchange) => {
+                entries.push(index::Entry::new(file, &interchange))
+            }
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), Error::Decode(error))
+            }
+        }
+    }
+    entries
+}
+
+/// Prints the entries of the index matching the given query.
+fn search(query: &index::Query, entries: &[index::Entry], output: Output) {
+    if let Output::Csv = output {
+        println!("file,sender,receiver,reference,types,created");
+    }
+    for entry in entries.iter().filter(|entry| query.matches(entry)) {
+        match output {
+            Output::Csv => {
+                let types: Vec<&str> = entry
+                    .messages
+                    .iter()
+                    .map(|message| message.message_type.as_str())
+                    .collect();
+                let field = |value: &Option<String>| {
+                    csv(value.as_deref().unwrap_or_default())
+                };
+                println!(
+                    "{},{},{},{},{},{}",
+                    csv(&entry.path.display().to_string()),
+                    field(&entry.sender),
+                    field(&entry.receiver),
+                    field(&entry.reference),
+                    csv(&types.join(" ")),
+                    field(&entry.created),
+                )
+            }
+            // Entries consist of strings only, so they always serialize.
+            Output::Json => {
+                println!("{}", serde_json::to_string(entry).unwrap())
+            }
+        }
+    }
+}
+
+/// Prints the messages linked with the ones with the given reference in
+/// the order of the index.
+fn conversation(reference: &str, entries: &[index::Entry], output: Output) {
+    let mut paths = HashMap::new();
+    let mut messages = vec![];
+    for entry in entries {
+        for message in &entry.messages {
+            let key = (message.interchange.clone(), message.reference.clone());
+            paths.entry(key).or_insert(&entry.path);
+            messages.push(message.clone());
+        }
+    }
+    let graph = Graph::new(messages);
+    let mut linked: Vec<usize> = graph
+        .find(reference)
+        .into_iter()
+        .flat_map(|message| graph.conversation(message))
+        .collect();
+    linked.sort();
+    linked.dedup();
+
+    if let Output::Csv = output {
+        println!("file,interchange,message,type,ids");
+    }
+    for message in linked.into_iter().map(|i| &graph.messages[i]) {
+        let key = (message.interchange.clone(), message.reference.clone());
+        let path = paths[&key].display().to_string();
+        match output {
+            Output::Csv => println!(
+                "{},{},{},{},{}",
+                csv(&path),
+                csv(message.interchange.as_deref().unwrap_or_default()),
+                csv(&message.reference),
+                csv(&message.message_type),
+                csv(&message.ids.join(" ")),
+            ),
+            Output::Json => println!(
+                "{}",
+                serde_json::json!({ "file": path, "message": message })
+            ),
+        }
+    }
+}
+
+/// Collects the given files, the files in the given directories and the
+/// files matching the given patterns.
+fn collect_paths(
+    files: &[PathBuf],
+    patterns: &[String],
+) -> Result<Vec<PathBuf>, Error> {
+    let mut all = vec![];
+    for file in files {
+        collect_files(file, &mut all).map_err(Error::Io)?;
+    }
+    for pattern in patterns {
+        let paths = glob::glob(pattern).map_err(Error::InvalidGlob)?;
+        // Paths, which cannot be read, are reported when reading them.
+        all.extend(paths.map(|path| match path {
+            Ok(path) => path,
+            Err(error) => error.path().to_path_buf(),
+        }));
+    }
+    Ok(all)
+}
+
+/// Collects the given file or all files in the given directory and its
+/// subdirectories in a stable order.
+fn collect_files(
+    path: &Path,
+    result: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    if !path.is_dir() {
+        result.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        collect_files(&entry, result)?;
+    }
+    Ok(())
+}
+
+/// Quotes the given value, if it contains characters with a special
+/// meaning in CSV.
+fn csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::CommandFactory;
+
+    use edifact::mig;
+    use edifact::mig::description::{Interchange, Metadata};
+
+    use crate::cli::{validate_file, Cli, Migs};
+    use crate::config::Config;
+
+    #[test]
+    fn test_command() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_lookup_by_date() {
+        let dir = std::env::temp_dir().join("edifact-test-lookup-by-date");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut desc: Interchange =
+            serde_json::from_str(include_str!("../APERAK.json")).unwrap();
+        for (file, from, until) in [
+            ("old.json", "2019-10-01", "2020-03-31"),
+            ("new.json", "2020-04-01", "2020-09-30"),
+        ] {
+            desc.metadata = Some(Metadata {
+                message_type: Some("APERAK".to_string()),
+                version: Some(file.to_string()),
+                valid_from: Some(from.to_string()),
+                valid_until: Some(until.to_string()),
+                revision: None,
+            });
+            let json = serde_json::to_string(&desc).unwrap();
+            std::fs::write(dir.join(file), json).unwrap();
+        }
+
+        let config = Config { mig_dirs: vec![dir], ..Config::default() };
+        let migs = Migs::new(None, &config).unwrap();
+        let version = |input: &str| {
+            let known = migs.lookup(input.as_bytes()).unwrap();
+            let metadata = known[0].description().metadata.clone();
+            metadata.and_then(|metadata| metadata.version)
+        };
+        let input = "UNB+UNOC:3+9900467000000:500+9904590000002:500\
+            +DATE:0705+C3AAAAAAAAHKLC'UNH+1+APERAK:D:07B:UN:2.1d'";
+        assert_eq!(
+            version(&input.replace("DATE", "200307")).as_deref(),
+            Some("old.json")
+        );
+        assert_eq!(
+            version(&input.replace("DATE", "200401")).as_deref(),
+            Some("new.json")
+        );
+    }
+
+    #[test]
+    fn test_validate_latin1() {
+        let dir = std::env::temp_dir().join("edifact-test-validate-latin1");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("latin1.edi");
+        // M\xfcller is Müller in ISO-8859-1, which is not valid UTF-8.
+        let input = b"UNA:+.? 'UNB+UNOC:3+9900467000000:500\
+            +9904590000002:500+200307:0705+C3AAAAAAAAHKLC'\
+            UNH+1+APERAK:D:07B:UN:2.1d'BGM+313+M\xfcller'\
+            DTM+137:202003070705:203'NAD+MS+9900467000000::293'\
+            NAD+MR+9904590000002::293'ERC+Z29'FTX+Z02+++10'UNT+8+1'\
+            UNZ+1+C3AAAAAAAAHKLC'";
+        std::fs::write(&file, input).unwrap();
+
+        let mig = concat!(env!("CARGO_MANIFEST_DIR"), "/APERAK.json");
+        let migs = Migs::new(Some(Path::new(mig)), &Config::default());
+        let options = mig::DecodeOptions::default();
+        let result = validate_file(&migs.unwrap(), &file, &options, None);
+        assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_generate_seeded() {
+        let desc =
+            serde_json::from_str(include_str!("../APERAK.json")).unwrap();
+        let interchanges = crate::cli::generate(&desc, 2, Some(7));
+        assert_eq!(interchanges, crate::cli::generate(&desc, 2, Some(7)));
+        assert_ne!(interchanges[0], interchanges[1]);
+        assert!(interchanges[0].starts_with("UNA:+.? 'UNB+UNOC:3+"));
+    }
+}
@@ -0,0 +1,208 @@
+//! The configuration of the command line tool in `edifact.toml`, so
+//! options used for every call need not be repeated as flags.
+//!
+//! The configuration is looked up in the working directory first and in
+//! `$XDG_CONFIG_HOME/edifact/edifact.toml` second, e.g.
+//!
+//! ```toml
+//! mig-dirs = ["migs"]
+//! output = "json"
+//! language = "english"
+//!
+//! [decode]
+//! lenient = true
+//! max-errors = 100
+//! max-identical-errors = 3
+//!
+//! [identity]
+//! receiver = "9900467000000"
+//!
+//! [encode]
+//! newline-after-segment = true
+//!
+//! [anonymize]
+//! labels = ["3036", "3042"]
+//! ```
+//!
+//! Flags given on the command line take precedence.
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use edifact::mig::error::Language;
+use edifact::mig::{EncodeOptions, UnaPolicy};
+
+use crate::cli::Output;
+
+const FILE_NAME: &str = "edifact.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Directories with MIG descriptions in JSON or YAML named after
+    /// their message type, e.g. `APERAK.json`, unless their metadata give
+    /// it. Relative directories are relative to the configuration file.
+    pub mig_dirs: Vec<PathBuf>,
+    /// The format `grep` prints values in.
+    pub output: Option<Output>,
+    /// The language of the names of syntax errors, German by default.
+    pub language: Language,
+    pub decode: Decode,
+    pub encode: Encode,
+    pub identity: Identity,
+    pub anonymize: Anonymize,
+}
+
+/// The options used to decode interchanges.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Decode {
+    pub lenient: bool,
+    pub treat_warnings_as_errors: bool,
+    pub max_errors: Option<usize>,
+    /// The number of identical errors of a message reported before they
+    /// are only counted.
+    pub max_identical_errors: Option<usize>,
+}
+
+/// The layout of interchanges written by `anonymize` and `filter`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Encode {
+    pub newline_after_segment: bool,
+    pub max_line_length: Option<usize>,
+    /// Whether the UNA is omitted, which some receivers require.
+    pub omit_una: bool,
+}
+
+impl Encode {
+    pub fn options(&self) -> EncodeOptions {
+        EncodeOptions {
+            newline_after_segment: self.newline_after_segment,
+            max_line_length: self.max_line_length,
+            una_policy: if self.omit_una {
+                UnaPolicy::Omit
+            } else {
+                UnaPolicy::Always
+            },
+            una: None,
+        }
+    }
+}
+
+/// The IDs of the market partners expected in UNB. Interchanges sent by
+/// or to others are rejected.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Identity {
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+}
+
+/// The data elements replaced by `anonymize`, which replace the default
+/// ones, if given.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Anonymize {
+    pub labels: Option<Vec<String>>,
+    pub names: Option<Vec<String>>,
+    pub key: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Invalid(PathBuf, toml::de::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, error) => {
+                write!(f, "{} could not be read: {}", path.display(), error)
+            }
+            Error::Invalid(path, error) => {
+                write!(
+                    f,
+                    "{} is not a valid configuration: {}",
+                    path.display(),
+                    error
+                )
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the given file or, if there is none,
+    /// from the first one found. Without any, the default is returned.
+    pub fn load(path: Option<&Path>) -> Result<Config, Error> {
+        match path.map(Path::to_path_buf).or_else(discover) {
+            Some(path) => Config::read(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Config, Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| Error::Invalid(path.to_path_buf(), e))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for dir in &mut config.mig_dirs {
+            *dir = base.join(&*dir);
+        }
+        Ok(config)
+    }
+}
+
+/// Returns the configuration file in the working directory or the one
+/// in the configuration directory of the user, if any exists.
+fn discover() -> Option<PathBuf> {
+    let config_home =
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| {
+            env::var_os("HOME").map(|h| Path::new(&h).join(".config"))
+        });
+    let candidates = [
+        Some(PathBuf::from(FILE_NAME)),
+        config_home.map(|dir| dir.join("edifact").join(FILE_NAME)),
+    ];
+    candidates.into_iter().flatten().find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use edifact::mig::UnaPolicy;
+
+    use crate::cli::Output;
+    use crate::config::Config;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            "mig-dirs = [\"migs\"]\n\
+             output = \"json\"\n\
+             [decode]\n\
+             lenient = true\n\
+             [identity]\n\
+             receiver = \"9900467000000\"\n\
+             [encode]\n\
+             omit-una = true\n\
+             [anonymize]\n\
+             labels = [\"3036\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.mig_dirs.len(), 1);
+        assert!(matches!(config.output, Some(Output::Json)));
+        assert!(config.decode.lenient);
+        assert_eq!(config.identity.receiver.as_deref(), Some("9900467000000"));
+        assert_eq!(config.encode.options().una_policy, UnaPolicy::Omit);
+        assert_eq!(config.anonymize.labels, Some(vec!["3036".to_string()]));
+        assert!(toml::from_str::<Config>("strict = true").is_err());
+    }
+}
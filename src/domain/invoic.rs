@@ -0,0 +1,266 @@
+//! A model of INVOIC messages, which transmit invoices, e.g. for the use
+//! of the grid or for metering services.
+//!
+//! An INVOIC message consists of a header with the invoice number, dates,
+//! references, sender and receiver, followed by line items. Each line
+//! item starts with a LIN segment and may contain its quantity (QTY),
+//! price (PRI), amount (MOA+203) and tax rate (TAX). The summary section
+//! starts with UNS+S and contains the total amounts (MOA) and the taxes
+//! (TAX), each followed by the tax amount (MOA+124) and the taxable
+//! amount (MOA+125).
+//!
+//! Amounts, prices and quantities are kept as given in the message, so
+//! no precision is lost.
+use std::convert::TryFrom;
+
+use crate::domain::{self, Error, Referenz};
+use crate::mig::decode::value::Interchange;
+use crate::mig::validate::dtm::Timestamp;
+
+/// A `Rechnung` is an INVOIC message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rechnung {
+    /// The document name code from BGM, e.g. `380` for an invoice.
+    pub document_name: Option<String>,
+    /// The invoice number from BGM.
+    pub document_number: String,
+    /// The date of the invoice from DTM+137.
+    pub created: Option<Timestamp>,
+    /// The start of the billing period from DTM+155.
+    pub start: Option<Timestamp>,
+    /// The end of the billing period from DTM+156.
+    pub end: Option<Timestamp>,
+    /// The due date from DTM+265.
+    pub due_date: Option<Timestamp>,
+    /// The MP-ID of the sender from NAD+MS.
+    pub sender: Option<String>,
+    /// The MP-ID of the receiver from NAD+MR.
+    pub receiver: Option<String>,
+    /// The references from the RFF segments of the header, e.g. to a
+    /// cancelled invoice.
+    pub references: Vec<Referenz>,
+    pub items: Vec<Rechnungsposition>,
+    pub taxes: Vec<Steuer>,
+    /// The total amount of the invoice from MOA+77.
+    pub total: Option<String>,
+    /// The amount due from MOA+9.
+    pub amount_due: Option<String>,
+}
+
+/// A `Rechnungsposition` is a single line item of an invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Rechnungsposition {
+    /// The number of the line item from LIN.
+    pub number: String,
+    /// The article ID from LIN.
+    pub article: Option<String>,
+    /// The billed quantity from QTY.
+    pub quantity: Option<String>,
+    /// The unit of the quantity from QTY, e.g. `KWH`.
+    pub unit: Option<String>,
+    /// The price per unit from PRI.
+    pub price: Option<String>,
+    /// The amount of the line item from MOA+203.
+    pub amount: Option<String>,
+    /// The tax rate in percent from TAX.
+    pub tax_rate: Option<String>,
+}
+
+/// A `Steuer` is a tax of the summary section of an invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Steuer {
+    /// The type of the tax from TAX, e.g. `VAT`.
+    pub kind: Option<String>,
+    /// The tax rate in percent from TAX.
+    pub rate: Option<String>,
+    /// The tax category from TAX, e.g. `S` for the standard rate.
+    pub category: Option<String>,
+    /// The tax amount from MOA+124.
+    pub amount: Option<String>,
+    /// The taxable amount from MOA+125.
+    pub taxable_amount: Option<String>,
+}
+
+/// The sections of an INVOIC message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Header,
+    Items,
+    Summary,
+}
+
+impl TryFrom<&Interchange> for Rechnung {
+    type Error = Error;
+
+    fn try_from(interchange: &Interchange) -> Result<Self, Self::Error> {
+        domain::expect_message_type(interchange, "INVOIC")?;
+
+        let mut section = Section::Header;
+        let mut rechnung = Rechnung {
+            document_name: None,
+            document_number: String::new(),
+            created: None,
+            start: None,
+            end: None,
+            due_date: None,
+            sender: None,
+            receiver: None,
+            references: vec![],
+            items: vec![],
+            taxes: vec![],
+            total: None,
+            amount_due: None,
+        };
+        let mut document_number = None;
+
+        for segment in interchange.all_segments() {
+            let qualifier = domain::qualifier(segment);
+            let text = |label| segment.text_of(label).map(|v| v.to_string());
+            match (section, segment.tag.as_str(), qualifier) {
+                (_, "LIN", _) => {
+                    section = Section::Items;
+                    rechnung.items.push(Rechnungsposition {
+                        number: text("1082").unwrap_or_default(),
+                        article: text("7140"),
+                        ..Rechnungsposition::default()
+                    });
+                }
+                (_, "UNS", Some("S")) => section = Section::Summary,
+                (Section::Header, "BGM", _) => {
+                    rechnung.document_name = text("1001");
+                    document_number = text("1004");
+                }
+                (Section::Header, "DTM", Some(qualifier)) => {
+                    let target = match qualifier {
+                        "137" => &mut rechnung.created,
+                        "155" => &mut rechnung.start,
+                        "156" => &mut rechnung.end,
+                        "265" => &mut rechnung.due_date,
+                        _ => continue,
+                    };
+                    *target = Some(domain::timestamp(segment)?);
+                }
+                (Section::Header, "RFF", _) => {
+                    rechnung.references.push(domain::reference(segment))
+                }
+                (Section::Header, "NAD", Some("MS")) => {
+                    rechnung.sender = text("3039")
+                }
+                (Section::Header, "NAD", Some("MR")) => {
+                    rechnung.receiver = text("3039")
+                }
+                (Section::Items, tag, qualifier) => {
+                    let item = match rechnung.items.last_mut() {
+                        Some(item) => item,
+                        None => continue,
+                    };
+                    match (tag, qualifier) {
+                        ("QTY", _) => {
+                            item.quantity = text("6060");
+                            item.unit = text("6411");
+                        }
+                        ("PRI", _) => item.price = text("5118"),
+                        ("MOA", Some("203")) => item.amount = text("5004"),
+                        ("TAX", _) => item.tax_rate = text("5278"),
+                        _ => {}
+                    }
+                }
+                (Section::Summary, "TAX", _) => rechnung.taxes.push(Steuer {
+                    kind: text("5153"),
+                    rate: text("5278"),
+                    category: text("5305"),
+                    ..Steuer::default()
+                }),
+                (Section::Summary, "MOA", Some(qualifier)) => {
+                    let amount = text("5004");
+                    let tax = rechnung.taxes.last_mut();
+                    match (qualifier, tax) {
+                        ("77", _) => rechnung.total = amount,
+                        ("9", _) => rechnung.amount_due = amount,
+                        ("124", Some(tax)) => tax.amount = amount,
+                        ("125", Some(tax)) => tax.taxable_amount = amount,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        rechnung.document_number =
+            document_number.ok_or(Error::MissingSegment("BGM"))?;
+        Ok(rechnung)
+    }
+}
+
+impl TryFrom<Interchange> for Rechnung {
+    type Error = Error;
+
+    fn try_from(interchange: Interchange) -> Result<Self, Self::Error> {
+        Rechnung::try_from(&interchange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::domain::invoic::{Rechnung, Rechnungsposition, Steuer};
+    use crate::domain::Referenz;
+    use crate::fixtures;
+    use crate::mig::validate::dtm::Timestamp;
+    use crate::mig::{self, DecodeOptions};
+
+    #[test]
+    fn test_try_from() {
+        let input = fixtures::interchange(&[fixtures::INVOIC]);
+        let options = DecodeOptions::default();
+        let known = vec![fixtures::invoic()];
+        let interchange =
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap();
+        let at = |year, month, day, hour| Timestamp {
+            year,
+            month,
+            day,
+            hour,
+            minute: 0,
+        };
+        let owned = |value: &str| Some(value.to_string());
+
+        assert_eq!(
+            Rechnung::try_from(&interchange).unwrap(),
+            Rechnung {
+                document_name: owned("380"),
+                document_number: "INV1".to_string(),
+                created: Some(Timestamp { minute: 5, ..at(2020, 3, 7, 6) }),
+                // Local times and dates are in German civil time.
+                start: Some(at(2019, 12, 31, 23)),
+                end: Some(at(2020, 6, 30, 22)),
+                due_date: Some(at(2020, 3, 14, 23)),
+                sender: owned("9900467000000"),
+                receiver: owned("9904590000002"),
+                references: vec![Referenz {
+                    qualifier: "Z13".to_string(),
+                    value: "31002".to_string(),
+                }],
+                items: vec![Rechnungsposition {
+                    number: "1".to_string(),
+                    article: owned("9990001000798"),
+                    quantity: owned("1000"),
+                    unit: owned("KWH"),
+                    price: owned("0.25"),
+                    amount: owned("250.00"),
+                    tax_rate: owned("19"),
+                }],
+                taxes: vec![Steuer {
+                    kind: owned("VAT"),
+                    rate: owned("19"),
+                    category: owned("S"),
+                    amount: owned("47.50"),
+                    taxable_amount: owned("250.00"),
+                }],
+                total: owned("297.50"),
+                amount_due: owned("297.50"),
+            }
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! High-level models of the most common edi@energy messages.
+//!
+//! A decoded interchange is a generic tree of segment groups, segments
+//! and data elements. The models in this module map such a tree into
+//! types describing the business content of a message, so application
+//! developers do not have to traverse the tree themselves.
+//!
+//! The mapping relies on segment tags, qualifiers and data element
+//! labels only, so it works with any version of the respective MIG.
+//!
+//! All timestamps of the models are in UTC. Dates and timestamps without
+//! an offset in a message are taken as German civil time and converted.
+//!
+//! A model describes a single message, so an interchange with several
+//! messages is rejected, unless the model maps each of them, like
+//! [utilmd::Marktnachricht::all].
+
+pub mod invoic;
+pub mod mscons;
+pub mod orders;
+pub mod ordrsp;
+pub mod remadv;
+pub mod utilmd;
+
+use std::fmt;
+
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::decode::value::{Interchange, Message, Segment};
+use crate::mig::validate::dtm::{self, TimeZone, Timestamp};
+
+/// An `Error`, which may happen while mapping a decoded interchange to a
+/// domain model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The interchange contains a message of another type.
+    WrongMessageType { expected: &'static str, actual: Option<String> },
+    /// A segment required by the model is missing.
+    MissingSegment(&'static str),
+    /// The interchange contains the given number of messages, but the
+    /// model describes a single one.
+    SeveralMessages(usize),
+    /// A value could not be interpreted, e.g. a date.
+    InvalidValue { pos: usize, tag: String, value: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WrongMessageType { expected, actual } => write!(
+                f,
+                "Expected a {} message, but got {}",
+                expected,
+                actual.as_deref().unwrap_or("an unknown one")
+            ),
+            Error::MissingSegment(segment) => {
+                write!(f, "The segment {} is missing", segment)
+            }
+            Error::SeveralMessages(count) => write!(
+                f,
+                "Expected a single message, but the interchange contains {}",
+                count
+            ),
+            Error::InvalidValue { pos, tag, value } => {
+                write!(f, "{}: Invalid value '{}' in {}", pos, value, tag)
+            }
+        }
+    }
+}
+
+/// An `Address` of a customer or location, as given in a NAD segment.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Address {
+    pub street: Option<String>,
+    pub house_number: Option<String>,
+    pub postal_code: Option<String>,
+    pub city: Option<String>,
+    pub country: Option<String>,
+}
+
+/// A `Referenz` is a reference to another document or process, as given
+/// in an RFF segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Referenz {
+    /// The qualifier, e.g. `Z13` for a Pruefidentifikator.
+    pub qualifier: String,
+    pub value: String,
+}
+
+/// Returns the message type (UNH 0065) of the given interchange.
+pub(crate) fn message_type(interchange: &Interchange) -> Option<&str> {
+    interchange
+        .all_segments()
+        .into_iter()
+        .find(|segment| segment.tag == "UNH")
+        .and_then(|unh| unh.text_of("0065"))
+}
+
+/// Returns an error, if the given interchange does not contain exactly
+/// one message of the expected type.
+pub(crate) fn expect_message_type(
+    interchange: &Interchange,
+    expected: &'static str,
+) -> Result<(), Error> {
+    let count = interchange.messages().len();
+    if count > 1 {
+        return Err(Error::SeveralMessages(count));
+    }
+    expect_type(message_type(interchange), expected)
+}
+
+/// Returns the only message of the given interchange, if it is of the
+/// expected type.
+pub(crate) fn single_message(
+    interchange: &Interchange,
+    expected: &'static str,
+) -> Result<Message, Error> {
+    expect_message_type(interchange, expected)?;
+    interchange.messages().pop().ok_or(Error::MissingSegment("UNT"))
+}
+
+/// Returns an error, if the given message is not of the expected type.
+pub(crate) fn expect_message_type_of(
+    message: &Message,
+    expected: &'static str,
+) -> Result<(), Error> {
+    expect_type(message.unh().text_of("0065"), expected)
+}
+
+fn expect_type(
+    actual: Option<&str>,
+    expected: &'static str,
+) -> Result<(), Error> {
+    match actual {
+        Some(actual) if actual == expected => Ok(()),
+        actual => Err(Error::WrongMessageType {
+            expected,
+            actual: actual.map(|a| a.to_string()),
+        }),
+    }
+}
+
+/// Returns the qualifier of the given segment, which is the first data
+/// element of it.
+pub(crate) fn qualifier(segment: &Segment) -> Option<&str> {
+    segment.data_elements().first().and_then(|element| element.text())
+}
+
+/// Returns the timestamp of the given DTM segment in UTC. Timestamps
+/// without an offset (`203`) are in German civil time and a date (`102`)
+/// is the start of that day in German civil time, e.g. `20201231` is
+/// 2020-12-30 23:00 UTC.
+pub(crate) fn timestamp(segment: &Segment) -> Result<Timestamp, Error> {
+    let value = segment.text_of("2380").unwrap_or("");
+    let german = |local: Timestamp| TimeZone::German.from_local(&local).utc();
+    let result = match segment.text_of("2379") {
+        Some("303") => dtm::parse_timestamp_with_offset(value, true),
+        Some("102") => dtm::parse_date(value).map(|(year, month, day)| {
+            german(Timestamp { year, month, day, hour: 0, minute: 0 })
+        }),
+        _ => dtm::parse_timestamp(value, true).map(german),
+    };
+    result.map_err(|_| invalid_value(segment, value))
+}
+
+/// Returns the reference of the given RFF segment.
+pub(crate) fn reference(rff: &Segment) -> Referenz {
+    Referenz {
+        qualifier: rff.text_of("1153").unwrap_or_default().to_string(),
+        value: rff.text_of("1154").unwrap_or_default().to_string(),
+    }
+}
+
+/// Returns the non-empty name parts (3036) of the given NAD segment.
+pub(crate) fn name(nad: &Segment) -> Vec<String> {
+    nad.texts_of("3036")
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_string())
+        .collect()
+}
+
+/// Returns the address of the given NAD segment, if it contains one.
+pub(crate) fn address(nad: &Segment) -> Option<Address> {
+    let owned = |value: Option<&str>| value.map(|v| v.to_string());
+    let streets = nad.texts_of("3042");
+    let address = Address {
+        street: owned(streets.first().copied()),
+        house_number: owned(streets.get(1..).and_then(|s| s.last().copied())),
+        postal_code: owned(nad.text_of("3251")),
+        city: owned(nad.text_of("3164")),
+        country: owned(nad.text_of("3207")),
+    };
+    if address == Address::default() {
+        None
+    } else {
+        Some(address)
+    }
+}
+
+pub(crate) fn invalid_value(segment: &Segment, value: &str) -> Error {
+    Error::InvalidValue {
+        pos: segment.index,
+        tag: segment.tag.clone(),
+        value: value.to_string(),
+    }
+}
+
+// RENDERING
+
+/// Renders a segment with the given tag and elements using the default
+/// separators. Each element is a list of its components, which will be
+/// escaped.
+pub(crate) fn render_segment(tag: &str, elements: &[Vec<&str>]) -> String {
+    let una = UNA::default();
+    let mut result = tag.to_string();
+    for element in elements {
+        result.push('+');
+        // Trailing empty components are omitted.
+        let len =
+            element.iter().rposition(|c| !c.is_empty()).map_or(0, |i| i + 1);
+        let components: Vec<String> =
+            element[..len].iter().map(|c| una.escape_value(c)).collect();
+        result.push_str(&components.join(":"));
+    }
+    result.push('\'');
+    result
+}
+
+/// Returns the given UTC timestamp in the format CCYYMMDDHHMMZZZ (303).
+pub(crate) fn format_timestamp(timestamp: &Timestamp) -> String {
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}+00",
+        timestamp.year,
+        timestamp.month,
+        timestamp.day,
+        timestamp.hour,
+        timestamp.minute
+    )
+}
+
+/// Returns the code lists of the organization, which issued the given
+/// market partner ID, for the UNB and NAD segments.
+pub(crate) fn code_lists(
+    market_partner_id: &str,
+) -> (&'static str, &'static str) {
+    if market_partner_id.starts_with("99") {
+        ("500", "293")
+    } else if market_partner_id.starts_with("98") {
+        ("502", "332")
+    } else {
+        ("14", "9")
+    }
+}
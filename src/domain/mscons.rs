@@ -0,0 +1,350 @@
+//! A model of MSCONS messages, which transmit metering data, e.g. the
+//! load profile of a metering location.
+//!
+//! An MSCONS message consists of a header with the message date, sender
+//! and receiver, followed by metering points. Each metering point starts
+//! with a LOC+172 segment and may declare the transmitted period with
+//! DTM+163 and DTM+164. It contains time series, each starting with a
+//! LIN segment followed by a PIA+5 segment containing the OBIS code. The
+//! values of a series are given in QTY segments, each followed by the
+//! DTM+163 and DTM+164 segments of its interval.
+//!
+//! A model can be converted back into a decoded interchange with
+//! [Messwerte::to_interchange], e.g. to encode it afterwards.
+use std::convert::TryFrom;
+
+use crate::domain::{self, Error};
+use crate::mig;
+use crate::mig::decode::value::Interchange;
+use crate::mig::description;
+use crate::mig::validate::dtm::Timestamp;
+use crate::mig::{decode, DecodeOptions};
+
+/// `Messwerte` are the metering data of an MSCONS message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Messwerte {
+    /// The reference of the interchange from UNB.
+    pub reference: String,
+    /// The version of the MIG from UNH, e.g. `2.4c`.
+    pub version: Option<String>,
+    /// The document name code from BGM, e.g. `7` for a
+    /// Prozessdatenbericht.
+    pub document_name: Option<String>,
+    /// The document number from BGM.
+    pub document_number: String,
+    /// The date of the message from DTM+137.
+    pub created: Timestamp,
+    /// The MP-ID of the sender from NAD+MS.
+    pub sender: String,
+    /// The MP-ID of the receiver from NAD+MR.
+    pub receiver: String,
+    pub metering_points: Vec<Zaehlpunkt>,
+}
+
+/// A `Zaehlpunkt` is a metering point, e.g. a Messlokation.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Zaehlpunkt {
+    /// The ID of the metering point from LOC+172.
+    pub id: String,
+    /// The start of the transmitted period from DTM+163.
+    pub start: Option<Timestamp>,
+    /// The end of the transmitted period from DTM+164.
+    pub end: Option<Timestamp>,
+    pub series: Vec<Zeitreihe>,
+}
+
+/// A `Zeitreihe` is a time series of values for one OBIS code.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Zeitreihe {
+    /// The OBIS code from PIA+5.
+    pub obis: Option<String>,
+    pub values: Vec<Messwert>,
+}
+
+/// A `Messwert` is a single measured quantity of a time series.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Messwert {
+    /// The quantity from QTY, as given in the message.
+    pub value: String,
+    /// The status of the quantity from the QTY qualifier, e.g. `220` for
+    /// a true value or `67` for a substitute value.
+    pub status: Option<String>,
+    /// The start of the interval from DTM+163.
+    pub start: Option<Timestamp>,
+    /// The end of the interval from DTM+164.
+    pub end: Option<Timestamp>,
+}
+
+impl TryFrom<&Interchange> for Messwerte {
+    type Error = Error;
+
+    fn try_from(interchange: &Interchange) -> Result<Self, Self::Error> {
+        domain::expect_message_type(interchange, "MSCONS")?;
+
+        let mut reference = None;
+        let mut version = None;
+        let mut document_name = None;
+        let mut document_number = None;
+        let mut created = None;
+        let mut sender = None;
+        let mut receiver = None;
+        let mut metering_points: Vec<Zaehlpunkt> = vec![];
+
+        for segment in interchange.all_segments() {
+            let qualifier = domain::qualifier(segment);
+            let text = |label| segment.text_of(label).map(|v| v.to_string());
+            let point = metering_points.last_mut();
+            let series = point.as_ref().and_then(|p| p.series.last());
+            let has_values = series.is_some_and(|s| !s.values.is_empty());
+            match (segment.tag.as_str(), qualifier, point) {
+                ("UNB", _, _) => reference = text("0020"),
+                ("UNH", _, _) => version = text("0057"),
+                ("BGM", _, _) => {
+                    document_name = text("1001");
+                    document_number = text("1004");
+                }
+                ("DTM", Some("137"), None) => {
+                    created = Some(domain::timestamp(segment)?)
+                }
+                ("NAD", Some("MS"), None) => sender = text("3039"),
+                ("NAD", Some("MR"), None) => receiver = text("3039"),
+                ("LOC", Some("172"), _) => metering_points.push(Zaehlpunkt {
+                    id: text("3225").unwrap_or_default(),
+                    ..Zaehlpunkt::default()
+                }),
+                ("LIN", _, Some(point)) => {
+                    point.series.push(Zeitreihe::default())
+                }
+                ("PIA", Some("5"), Some(point)) => {
+                    if let Some(series) = point.series.last_mut() {
+                        series.obis = text("7140");
+                    }
+                }
+                ("QTY", Some(status), Some(point)) => {
+                    if let Some(series) = point.series.last_mut() {
+                        series.values.push(Messwert {
+                            value: text("6060").unwrap_or_default(),
+                            status: Some(status.to_string()),
+                            ..Messwert::default()
+                        });
+                    }
+                }
+                ("DTM", Some(qualifier @ ("163" | "164")), Some(point)) => {
+                    let timestamp = Some(domain::timestamp(segment)?);
+                    let value = point
+                        .series
+                        .last_mut()
+                        .and_then(|series| series.values.last_mut());
+                    match (value, qualifier) {
+                        (Some(value), "163") if has_values => {
+                            value.start = timestamp
+                        }
+                        (Some(value), _) if has_values => {
+                            value.end = timestamp
+                        }
+                        (_, "163") => point.start = timestamp,
+                        _ => point.end = timestamp,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Messwerte {
+            reference: reference.ok_or(Error::MissingSegment("UNB"))?,
+            version,
+            document_name,
+            document_number: document_number
+                .ok_or(Error::MissingSegment("BGM"))?,
+            created: created.ok_or(Error::MissingSegment("DTM+137"))?,
+            sender: sender.ok_or(Error::MissingSegment("NAD+MS"))?,
+            receiver: receiver.ok_or(Error::MissingSegment("NAD+MR"))?,
+            metering_points,
+        })
+    }
+}
+
+impl TryFrom<Interchange> for Messwerte {
+    type Error = Error;
+
+    fn try_from(interchange: Interchange) -> Result<Self, Self::Error> {
+        Messwerte::try_from(&interchange)
+    }
+}
+
+impl Messwerte {
+    /// Converts these metering data into an interchange decoded with one
+    /// of the `known` descriptions, which can be encoded afterwards.
+    pub fn to_interchange(
+        &self,
+        known: Vec<description::Interchange>,
+        options: &DecodeOptions,
+    ) -> Result<Interchange, decode::Error> {
+        mig::decode(known, &mut self.to_edifact().as_bytes(), options)
+    }
+
+    /// Returns these metering data as an EDIFACT interchange with the
+    /// default separators. All timestamps are given in UTC.
+    pub fn to_edifact(&self) -> String {
+        let mut segments = vec![];
+        let created = domain::format_timestamp(&self.created);
+        let dtm = |qualifier, timestamp: &Option<Timestamp>| {
+            timestamp.as_ref().map(|timestamp| {
+                let value = domain::format_timestamp(timestamp);
+                domain::render_segment(
+                    "DTM",
+                    &[vec![qualifier, value.as_str(), "303"]],
+                )
+            })
+        };
+
+        let (unb_sender, nad_sender) = domain::code_lists(&self.sender);
+        let (unb_receiver, nad_receiver) = domain::code_lists(&self.receiver);
+        let message_identifier = vec![
+            "MSCONS",
+            "D",
+            "04B",
+            "UN",
+            self.version.as_deref().unwrap_or(""),
+        ];
+        segments.push(domain::render_segment(
+            "UNH",
+            &[vec!["1"], message_identifier],
+        ));
+        segments.push(domain::render_segment(
+            "BGM",
+            &[
+                vec![self.document_name.as_deref().unwrap_or("")],
+                vec![self.document_number.as_str()],
+                vec!["9"],
+            ],
+        ));
+        segments.extend(dtm("137", &Some(self.created)));
+        segments.push(domain::render_segment(
+            "NAD",
+            &[vec!["MS"], vec![self.sender.as_str(), "", nad_sender]],
+        ));
+        segments.push(domain::render_segment(
+            "NAD",
+            &[vec!["MR"], vec![self.receiver.as_str(), "", nad_receiver]],
+        ));
+        segments.push(domain::render_segment("UNS", &[vec!["D"]]));
+
+        for point in &self.metering_points {
+            segments.push(domain::render_segment(
+                "LOC",
+                &[vec!["172"], vec![point.id.as_str()]],
+            ));
+            segments.extend(dtm("163", &point.start));
+            segments.extend(dtm("164", &point.end));
+            for (i, series) in point.series.iter().enumerate() {
+                let number = (i + 1).to_string();
+                segments.push(domain::render_segment(
+                    "LIN",
+                    &[vec![number.as_str()]],
+                ));
+                if let Some(obis) = &series.obis {
+                    segments.push(domain::render_segment(
+                        "PIA",
+                        &[vec!["5"], vec![obis.as_str(), "SRW"]],
+                    ));
+                }
+                for value in &series.values {
+                    let status = value.status.as_deref().unwrap_or("220");
+                    segments.push(domain::render_segment(
+                        "QTY",
+                        &[vec![status, value.value.as_str()]],
+                    ));
+                    segments.extend(dtm("163", &value.start));
+                    segments.extend(dtm("164", &value.end));
+                }
+            }
+        }
+
+        // The number of segments includes UNH and UNT.
+        let count = (segments.len() + 1).to_string();
+        segments.push(domain::render_segment(
+            "UNT",
+            &[vec![count.as_str()], vec!["1"]],
+        ));
+
+        let date = &created[2..8];
+        let time = &created[8..12];
+        let unb = domain::render_segment(
+            "UNB",
+            &[
+                vec!["UNOC", "3"],
+                vec![self.sender.as_str(), unb_sender],
+                vec![self.receiver.as_str(), unb_receiver],
+                vec![date, time],
+                vec![self.reference.as_str()],
+            ],
+        );
+        let unz = domain::render_segment(
+            "UNZ",
+            &[vec!["1"], vec![self.reference.as_str()]],
+        );
+
+        format!("UNA:+.? '{}{}{}", unb, segments.concat(), unz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::mscons::{Messwert, Messwerte, Zaehlpunkt, Zeitreihe};
+    use crate::mig::validate::dtm::Timestamp;
+
+    #[test]
+    fn test_to_edifact() {
+        let at = |hour, minute| Timestamp {
+            year: 2020,
+            month: 3,
+            day: 7,
+            hour,
+            minute,
+        };
+        let messwerte = Messwerte {
+            reference: "REF1".to_string(),
+            version: Some("2.4c".to_string()),
+            document_name: Some("7".to_string()),
+            document_number: "DOC1".to_string(),
+            created: at(7, 5),
+            sender: "9900467000000".to_string(),
+            receiver: "4078901000029".to_string(),
+            metering_points: vec![Zaehlpunkt {
+                id: "DE00056266802AO6G56M11SN51G21M24S".to_string(),
+                start: None,
+                end: None,
+                series: vec![Zeitreihe {
+                    obis: Some("1-1:1.29.0".to_string()),
+                    values: vec![Messwert {
+                        value: "1.5".to_string(),
+                        status: Some("220".to_string()),
+                        start: Some(at(0, 0)),
+                        end: Some(at(0, 15)),
+                    }],
+                }],
+            }],
+        };
+
+        assert_eq!(
+            messwerte.to_edifact(),
+            "UNA:+.? '\
+             UNB+UNOC:3+9900467000000:500+4078901000029:14+200307:0705+REF1'\
+             UNH+1+MSCONS:D:04B:UN:2.4c'\
+             BGM+7+DOC1+9'\
+             DTM+137:202003070705?+00:303'\
+             NAD+MS+9900467000000::293'\
+             NAD+MR+4078901000029::9'\
+             UNS+D'\
+             LOC+172+DE00056266802AO6G56M11SN51G21M24S'\
+             LIN+1'\
+             PIA+5+1-1?:1.29.0:SRW'\
+             QTY+220:1.5'\
+             DTM+163:202003070000?+00:303'\
+             DTM+164:202003070015?+00:303'\
+             UNT+13+1'\
+             UNZ+1+REF1'"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! A model of ORDERS messages, which request data or services from
+//! another market partner, e.g. in the Geschäftsdatenanfrage or in the
+//! redispatch processes.
+//!
+//! An ORDERS message consists of a header with the order number, sender
+//! and receiver and the Pruefidentifikator (RFF+Z13) of the use case,
+//! followed by line items. Each line item starts with a LIN segment and
+//! refers to a location (LOC), optionally restricted to a requested
+//! period (DTM+163 and DTM+164) and a measured value (PIA+5).
+//!
+//! The response to an ORDERS message is an ORDRSP message, see
+//! [crate::domain::ordrsp].
+//!
+//! A description of ORDERS to decode these messages with is bundled with
+//! the crate, see [crate::mig::bundled].
+use std::convert::TryFrom;
+
+use crate::domain::{self, Error, Referenz};
+use crate::mig::decode::value::Interchange;
+use crate::mig::validate::dtm::Timestamp;
+
+/// The qualifiers of LOC segments, which identify the requested location.
+pub const LOCATION_QUALIFIERS: [&str; 4] = ["172", "Z16", "Z17", "Z18"];
+
+/// An `Anfrage` is an ORDERS message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anfrage {
+    /// The document name code from BGM, identifying the kind of request.
+    pub document_name: Option<String>,
+    /// The order number from BGM, which is referenced by the response.
+    pub document_number: String,
+    /// The date of the message from DTM+137.
+    pub created: Option<Timestamp>,
+    /// The MP-ID of the sender from NAD+MS.
+    pub sender: Option<String>,
+    /// The MP-ID of the receiver from NAD+MR.
+    pub receiver: Option<String>,
+    /// The Pruefidentifikator from RFF+Z13, identifying the use case.
+    pub pruefidentifikator: Option<String>,
+    /// The other references from the RFF segments of the header.
+    pub references: Vec<Referenz>,
+    pub items: Vec<Anfrageposition>,
+}
+
+/// An `Anfrageposition` is a single line item of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Anfrageposition {
+    /// The number of the line item from LIN.
+    pub number: String,
+    /// The qualifier of the location from LOC, e.g. `Z16` for a
+    /// Marktlokation.
+    pub location_qualifier: Option<String>,
+    /// The ID of the location from LOC.
+    pub location: Option<String>,
+    /// The OBIS code of the requested value from PIA+5.
+    pub obis: Option<String>,
+    /// The start of the requested period from DTM+163.
+    pub start: Option<Timestamp>,
+    /// The end of the requested period from DTM+164.
+    pub end: Option<Timestamp>,
+    /// The references from the RFF segments of the line item.
+    pub references: Vec<Referenz>,
+}
+
+impl TryFrom<&Interchange> for Anfrage {
+    type Error = Error;
+
+    fn try_from(interchange: &Interchange) -> Result<Self, Self::Error> {
+        domain::expect_message_type(interchange, "ORDERS")?;
+
+        let mut document_name = None;
+        let mut document_number = None;
+        let mut created = None;
+        let mut sender = None;
+        let mut receiver = None;
+        let mut pruefidentifikator = None;
+        let mut references = vec![];
+        let mut items: Vec<Anfrageposition> = vec![];
+
+        for segment in interchange.all_segments() {
+            let qualifier = domain::qualifier(segment);
+            let text = |label| segment.text_of(label).map(|v| v.to_string());
+            match (segment.tag.as_str(), qualifier, items.last_mut()) {
+                ("BGM", _, _) => {
+                    document_name = text("1001");
+                    document_number = text("1004");
+                }
+                ("DTM", Some("137"), None) => {
+                    created = Some(domain::timestamp(segment)?)
+                }
+                ("NAD", Some("MS"), None) => sender = text("3039"),
+                ("NAD", Some("MR"), None) => receiver = text("3039"),
+                ("RFF", Some("Z13"), None) => {
+                    pruefidentifikator = text("1154")
+                }
+                ("RFF", _, None) => {
+                    references.push(domain::reference(segment))
+                }
+                ("LIN", _, _) => items.push(Anfrageposition {
+                    number: text("1082").unwrap_or_default(),
+                    ..Anfrageposition::default()
+                }),
+                ("LOC", Some(qualifier), Some(item))
+                    if LOCATION_QUALIFIERS.contains(&qualifier) =>
+                {
+                    item.location_qualifier = Some(qualifier.to_string());
+                    item.location = text("3225");
+                }
+                ("PIA", Some("5"), Some(item)) => item.obis = text("7140"),
+                ("DTM", Some("163"), Some(item)) => {
+                    item.start = Some(domain::timestamp(segment)?)
+                }
+                ("DTM", Some("164"), Some(item)) => {
+                    item.end = Some(domain::timestamp(segment)?)
+                }
+                ("RFF", _, Some(item)) => {
+                    item.references.push(domain::reference(segment))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Anfrage {
+            document_name,
+            document_number: document_number
+                .ok_or(Error::MissingSegment("BGM"))?,
+            created,
+            sender,
+            receiver,
+            pruefidentifikator,
+            references,
+            items,
+        })
+    }
+}
+
+impl TryFrom<Interchange> for Anfrage {
+    type Error = Error;
+
+    fn try_from(interchange: Interchange) -> Result<Self, Self::Error> {
+        Anfrage::try_from(&interchange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::domain::orders::{Anfrage, Anfrageposition};
+    use crate::domain::Referenz;
+    use crate::fixtures;
+    use crate::mig::validate::dtm::Timestamp;
+    use crate::mig::{self, bundled, DecodeOptions};
+
+    #[test]
+    fn test_try_from() {
+        let input = fixtures::interchange(&[fixtures::ORDERS]);
+        let options = DecodeOptions::default();
+        let known = vec![bundled::orders()];
+        let interchange =
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap();
+        let at = |month, day, hour, minute| Timestamp {
+            year: 2020,
+            month,
+            day,
+            hour,
+            minute,
+        };
+
+        assert_eq!(
+            Anfrage::try_from(&interchange).unwrap(),
+            Anfrage {
+                document_name: Some("Z10".to_string()),
+                document_number: "ORD1".to_string(),
+                created: Some(at(3, 7, 7, 5)),
+                sender: Some("9900467000000".to_string()),
+                receiver: Some("9904590000002".to_string()),
+                pruefidentifikator: Some("17101".to_string()),
+                references: vec![],
+                items: vec![
+                    Anfrageposition {
+                        number: "1".to_string(),
+                        location_qualifier: Some("Z16".to_string()),
+                        location: Some("51238696781".to_string()),
+                        obis: Some("1-1:1.29.0".to_string()),
+                        start: Some(at(2, 29, 23, 0)),
+                        end: Some(at(3, 31, 22, 0)),
+                        references: vec![],
+                    },
+                    Anfrageposition {
+                        number: "2".to_string(),
+                        location_qualifier: Some("Z17".to_string()),
+                        location: Some(
+                            "DE00014545768S0000000000000003054".to_string()
+                        ),
+                        references: vec![Referenz {
+                            qualifier: "AVE".to_string(),
+                            value: "V1".to_string(),
+                        }],
+                        ..Anfrageposition::default()
+                    },
+                ],
+            }
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! A model of ORDRSP messages, which respond to an ORDERS message.
+//!
+//! An ORDRSP message consists of a header with the document number,
+//! sender and receiver, the Pruefidentifikator (RFF+Z13) of the use case
+//! and the order number of the answered request (RFF+ON), followed by
+//! line items. Each line item starts with a LIN segment and may contain
+//! the answered location (LOC) and the reason, why the request could not
+//! be fulfilled (AJT).
+//!
+//! A description of ORDRSP to decode these messages with is bundled with
+//! the crate, see [crate::mig::bundled].
+use std::convert::TryFrom;
+
+use crate::domain::orders::{Anfrage, LOCATION_QUALIFIERS};
+use crate::domain::{self, Error, Referenz};
+use crate::mig::decode::value::Interchange;
+use crate::mig::validate::dtm::Timestamp;
+
+/// An `Antwort` is an ORDRSP message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Antwort {
+    /// The document name code from BGM, identifying the kind of response.
+    pub document_name: Option<String>,
+    /// The document number from BGM.
+    pub document_number: String,
+    /// The date of the message from DTM+137.
+    pub created: Option<Timestamp>,
+    /// The MP-ID of the sender from NAD+MS.
+    pub sender: Option<String>,
+    /// The MP-ID of the receiver from NAD+MR.
+    pub receiver: Option<String>,
+    /// The Pruefidentifikator from RFF+Z13, identifying the use case.
+    pub pruefidentifikator: Option<String>,
+    /// The order number of the answered ORDERS message from RFF+ON.
+    pub order_number: Option<String>,
+    /// The other references from the RFF segments of the header.
+    pub references: Vec<Referenz>,
+    pub items: Vec<Antwortposition>,
+}
+
+/// An `Antwortposition` is the answer to a single line item of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Antwortposition {
+    /// The number of the line item from LIN.
+    pub number: String,
+    /// The qualifier of the location from LOC.
+    pub location_qualifier: Option<String>,
+    /// The ID of the location from LOC.
+    pub location: Option<String>,
+    /// The reason from AJT, why the line item could not be fulfilled.
+    pub rejection_reason: Option<String>,
+    /// The references from the RFF segments of the line item.
+    pub references: Vec<Referenz>,
+}
+
+impl Antwort {
+    /// Returns, whether this is the response to the given request.
+    pub fn answers(&self, anfrage: &Anfrage) -> bool {
+        self.order_number.as_deref() == Some(anfrage.document_number.as_str())
+    }
+
+    /// Returns, whether all line items of the request could be fulfilled.
+    pub fn is_accepted(&self) -> bool {
+        self.items.iter().all(|item| item.rejection_reason.is_none())
+    }
+}
+
+impl TryFrom<&Interchange> for Antwort {
+    type Error = Error;
+
+    fn try_from(interchange: &Interchange) -> Result<Self, Self::Error> {
+        domain::expect_message_type(interchange, "ORDRSP")?;
+
+        let mut document_name = None;
+        let mut document_number = None;
+        let mut created = None;
+        let mut sender = None;
+        let mut receiver = None;
+        let mut pruefidentifikator = None;
+        let mut order_number = None;
+        let mut references = vec![];
+        let mut items: Vec<Antwortposition> = vec![];
+
+        for segment in interchange.all_segments() {
+            let qualifier = domain::qualifier(segment);
+            let text = |label| segment.text_of(label).map(|v| v.to_string());
+            match (segment.tag.as_str(), qualifier, items.last_mut()) {
+                ("BGM", _, _) => {
+                    document_name = text("1001");
+                    document_number = text("1004");
+                }
+                ("DTM", Some("137"), None) => {
+                    created = Some(domain::timestamp(segment)?)
+                }
+                ("NAD", Some("MS"), None) => sender = text("3039"),
+                ("NAD", Some("MR"), None) => receiver = text("3039"),
+                ("RFF", Some("Z13"), None) => {
+                    pruefidentifikator = text("1154")
+                }
+                ("RFF", Some("ON"), None) => order_number = text("1154"),
+                ("RFF", _, None) => {
+                    references.push(domain::reference(segment))
+                }
+                ("LIN", _, _) => items.push(Antwortposition {
+                    number: text("1082").unwrap_or_default(),
+                    ..Antwortposition::default()
+                }),
+                ("LOC", Some(qualifier), Some(item))
+                    if LOCATION_QUALIFIERS.contains(&qualifier) =>
+                {
+                    item.location_qualifier = Some(qualifier.to_string());
+                    item.location = text("3225");
+                }
+                ("AJT", _, Some(item)) => item.rejection_reason = text("4465"),
+                ("RFF", _, Some(item)) => {
+                    item.references.push(domain::reference(segment))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Antwort {
+            document_name,
+            document_number: document_number
+                .ok_or(Error::MissingSegment("BGM"))?,
+            created,
+            sender,
+            receiver,
+            pruefidentifikator,
+            order_number,
+            references,
+            items,
+        })
+    }
+}
+
+impl TryFrom<Interchange> for Antwort {
+    type Error = Error;
+
+    fn try_from(interchange: Interchange) -> Result<Self, Self::Error> {
+        Antwort::try_from(&interchange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::domain::orders::Anfrage;
+    use crate::domain::ordrsp::{Antwort, Antwortposition};
+    use crate::fixtures;
+    use crate::mig::validate::dtm::Timestamp;
+    use crate::mig::{self, bundled, DecodeOptions};
+
+    const MESSAGE: &str = "UNH+1+ORDRSP:D:10A:UN:1.4a'\
+        BGM+Z11+RSP1'\
+        DTM+137:202003080900?+00:303'\
+        RFF+Z13:19101'\
+        RFF+ON:ORD1'\
+        NAD+MS+9904590000002::293'\
+        NAD+MR+9900467000000::293'\
+        LIN+1'\
+        LOC+Z16+51238696781'\
+        LIN+2'\
+        LOC+Z17+DE00014545768S0000000000000003054'\
+        AJT+Z29'\
+        UNT+13+1'";
+
+    #[test]
+    fn test_try_from() {
+        let options = DecodeOptions::default();
+        let decode = |message| {
+            let input = fixtures::interchange(&[message]);
+            let known = vec![bundled::orders(), bundled::ordrsp()];
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap()
+        };
+
+        let antwort = Antwort::try_from(&decode(MESSAGE)).unwrap();
+        assert_eq!(
+            antwort,
+            Antwort {
+                document_name: Some("Z11".to_string()),
+                document_number: "RSP1".to_string(),
+                created: Some(Timestamp {
+                    year: 2020,
+                    month: 3,
+                    day: 8,
+                    hour: 9,
+                    minute: 0,
+                }),
+                sender: Some("9904590000002".to_string()),
+                receiver: Some("9900467000000".to_string()),
+                pruefidentifikator: Some("19101".to_string()),
+                order_number: Some("ORD1".to_string()),
+                references: vec![],
+                items: vec![
+                    Antwortposition {
+                        number: "1".to_string(),
+                        location_qualifier: Some("Z16".to_string()),
+                        location: Some("51238696781".to_string()),
+                        ..Antwortposition::default()
+                    },
+                    Antwortposition {
+                        number: "2".to_string(),
+                        location_qualifier: Some("Z17".to_string()),
+                        location: Some(
+                            "DE00014545768S0000000000000003054".to_string()
+                        ),
+                        rejection_reason: Some("Z29".to_string()),
+                        ..Antwortposition::default()
+                    },
+                ],
+            }
+        );
+        assert!(!antwort.is_accepted());
+
+        let anfrage = Anfrage::try_from(&decode(fixtures::ORDERS)).unwrap();
+        assert!(antwort.answers(&anfrage));
+    }
+}
@@ -0,0 +1,199 @@
+//! A model of REMADV messages, which advise the payment of invoices or
+//! reject them.
+//!
+//! A REMADV message consists of a header with the document number, the
+//! date of the payment, sender and receiver, followed by the advised
+//! invoices. Each invoice starts with a DOC segment containing the
+//! invoice number and may contain the date of the invoice (DTM+137), the
+//! paid amount (MOA+9), references (RFF) and the reason of a rejection
+//! (AJT).
+use std::convert::TryFrom;
+
+use crate::domain::{self, Error, Referenz};
+use crate::mig::decode::value::Interchange;
+use crate::mig::validate::dtm::Timestamp;
+
+/// A `Zahlungsavis` is a REMADV message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zahlungsavis {
+    /// The document number from BGM.
+    pub document_number: String,
+    /// The date of the message from DTM+137.
+    pub created: Option<Timestamp>,
+    /// The date of the payment from DTM+203.
+    pub payment_date: Option<Timestamp>,
+    /// The MP-ID of the sender from NAD+MS.
+    pub sender: Option<String>,
+    /// The MP-ID of the receiver from NAD+MR.
+    pub receiver: Option<String>,
+    pub payments: Vec<Zahlung>,
+}
+
+/// A `Zahlung` is the payment or rejection of a single invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Zahlung {
+    /// The document name code of the invoice from DOC.
+    pub document_name: Option<String>,
+    /// The invoice number from DOC.
+    pub invoice_number: String,
+    /// The date of the invoice from DTM+137.
+    pub invoice_date: Option<Timestamp>,
+    /// The paid amount from MOA+9.
+    pub amount: Option<String>,
+    /// The references from RFF, e.g. the payment reference.
+    pub references: Vec<Referenz>,
+    /// The reason of a rejection from AJT.
+    pub rejection_reason: Option<String>,
+}
+
+impl Zahlung {
+    /// Returns, whether this invoice has been rejected.
+    pub fn is_rejected(&self) -> bool {
+        self.rejection_reason.is_some()
+    }
+}
+
+impl TryFrom<&Interchange> for Zahlungsavis {
+    type Error = Error;
+
+    fn try_from(interchange: &Interchange) -> Result<Self, Self::Error> {
+        domain::expect_message_type(interchange, "REMADV")?;
+
+        let mut document_number = None;
+        let mut created = None;
+        let mut payment_date = None;
+        let mut sender = None;
+        let mut receiver = None;
+        let mut payments: Vec<Zahlung> = vec![];
+
+        for segment in interchange.all_segments() {
+            let qualifier = domain::qualifier(segment);
+            let text = |label| segment.text_of(label).map(|v| v.to_string());
+            match (segment.tag.as_str(), qualifier, payments.last_mut()) {
+                ("BGM", _, _) => document_number = text("1004"),
+                ("DTM", Some("137"), None) => {
+                    created = Some(domain::timestamp(segment)?)
+                }
+                ("DTM", Some("203"), None) => {
+                    payment_date = Some(domain::timestamp(segment)?)
+                }
+                ("NAD", Some("MS"), None) => sender = text("3039"),
+                ("NAD", Some("MR"), None) => receiver = text("3039"),
+                ("DOC", _, _) => payments.push(Zahlung {
+                    document_name: text("1001"),
+                    invoice_number: text("1004").unwrap_or_default(),
+                    ..Zahlung::default()
+                }),
+                ("DTM", Some("137"), Some(payment)) => {
+                    payment.invoice_date = Some(domain::timestamp(segment)?)
+                }
+                ("MOA", Some("9"), Some(payment)) => {
+                    payment.amount = text("5004")
+                }
+                ("RFF", _, Some(payment)) => {
+                    payment.references.push(domain::reference(segment))
+                }
+                ("AJT", _, Some(payment)) => {
+                    payment.rejection_reason = text("4465")
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Zahlungsavis {
+            document_number: document_number
+                .ok_or(Error::MissingSegment("BGM"))?,
+            created,
+            payment_date,
+            sender,
+            receiver,
+            payments,
+        })
+    }
+}
+
+impl TryFrom<Interchange> for Zahlungsavis {
+    type Error = Error;
+
+    fn try_from(interchange: Interchange) -> Result<Self, Self::Error> {
+        Zahlungsavis::try_from(&interchange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::domain::remadv::{Zahlung, Zahlungsavis};
+    use crate::domain::Referenz;
+    use crate::fixtures;
+    use crate::mig::validate::dtm::Timestamp;
+    use crate::mig::{self, DecodeOptions};
+
+    const MESSAGE: &str = "UNH+1+REMADV:D:05A:UN:2.9b'\
+        BGM+481+AVIS1'\
+        DTM+137:202003070705?+00:303'\
+        DTM+203:20200310:102'\
+        NAD+MS+9900467000000::293'\
+        NAD+MR+9904590000002::293'\
+        DOC+380+INV1'\
+        MOA+9:297.50'\
+        DTM+137:20200307:102'\
+        RFF+AFL:PAY1'\
+        DOC+380+INV2'\
+        MOA+9:0'\
+        DTM+137:20200308:102'\
+        AJT+Z31'\
+        UNT+15+1'";
+
+    #[test]
+    fn test_try_from() {
+        let input = fixtures::interchange(&[MESSAGE]);
+        let options = DecodeOptions::default();
+        let known = vec![fixtures::remadv()];
+        let interchange =
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap();
+        let at = |day, hour, minute| Timestamp {
+            year: 2020,
+            month: 3,
+            day,
+            hour,
+            minute,
+        };
+
+        let avis = Zahlungsavis::try_from(&interchange).unwrap();
+        assert_eq!(
+            avis,
+            Zahlungsavis {
+                document_number: "AVIS1".to_string(),
+                created: Some(at(7, 7, 5)),
+                payment_date: Some(at(9, 23, 0)),
+                sender: Some("9900467000000".to_string()),
+                receiver: Some("9904590000002".to_string()),
+                payments: vec![
+                    Zahlung {
+                        document_name: Some("380".to_string()),
+                        invoice_number: "INV1".to_string(),
+                        invoice_date: Some(at(6, 23, 0)),
+                        amount: Some("297.50".to_string()),
+                        references: vec![Referenz {
+                            qualifier: "AFL".to_string(),
+                            value: "PAY1".to_string(),
+                        }],
+                        rejection_reason: None,
+                    },
+                    Zahlung {
+                        document_name: Some("380".to_string()),
+                        invoice_number: "INV2".to_string(),
+                        invoice_date: Some(at(7, 23, 0)),
+                        amount: Some("0".to_string()),
+                        references: vec![],
+                        rejection_reason: Some("Z31".to_string()),
+                    },
+                ],
+            }
+        );
+        assert!(!avis.payments[0].is_rejected());
+        assert!(avis.payments[1].is_rejected());
+    }
+}
@@ -0,0 +1,244 @@
+//! A model of UTILMD messages, which exchange master data of market
+//! locations, e.g. when a customer switches their supplier.
+//!
+//! A UTILMD message consists of a header with the message date, sender
+//! and receiver, followed by transactions (Vorgänge). Each transaction
+//! starts with an IDE+24 segment containing its ID.
+//!
+//! An interchange may contain several UTILMD messages, which are mapped
+//! with [Marktnachricht::all].
+use std::convert::TryFrom;
+
+use crate::domain::{self, Address, Error};
+use crate::mig::decode::value::{Interchange, Message};
+use crate::mig::validate::dtm::Timestamp;
+
+/// A `Marktnachricht` is a UTILMD message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marktnachricht {
+    /// The document number from BGM.
+    pub document_number: String,
+    /// The date of the message from DTM+137.
+    pub created: Option<Timestamp>,
+    /// The MP-ID of the sender from NAD+MS.
+    pub sender: Option<String>,
+    /// The MP-ID of the receiver from NAD+MR.
+    pub receiver: Option<String>,
+    pub transactions: Vec<Vorgang>,
+}
+
+/// A `Vorgang` is a single transaction of a UTILMD message, e.g. the
+/// registration of a supply for one market location.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Vorgang {
+    /// The transaction ID from IDE+24.
+    pub id: String,
+    /// The Pruefidentifikator from RFF+Z13, identifying the use case.
+    pub pruefidentifikator: Option<String>,
+    /// The reason of the transaction from STS+7, e.g. E01 for a move-in.
+    pub reason: Option<String>,
+    /// The ID of the market location from LOC+Z16.
+    pub marktlokation: Option<String>,
+    /// The customer from NAD+Z09.
+    pub customer: Option<Customer>,
+    /// The start of the supply from DTM+92.
+    pub start: Option<Timestamp>,
+    /// The end of the supply from DTM+93.
+    pub end: Option<Timestamp>,
+}
+
+/// A `Customer` of a supplier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Customer {
+    /// The name parts, usually the last name followed by the first name.
+    pub name: Vec<String>,
+    pub address: Option<Address>,
+}
+
+impl Marktnachricht {
+    /// Returns the messages of the given interchange from their UNH to
+    /// their UNT in order.
+    pub fn all(interchange: &Interchange) -> Result<Vec<Self>, Error> {
+        interchange.messages().iter().map(Marktnachricht::try_from).collect()
+    }
+}
+
+impl TryFrom<&Message> for Marktnachricht {
+    type Error = Error;
+
+    fn try_from(message: &Message) -> Result<Self, Self::Error> {
+        domain::expect_message_type_of(message, "UTILMD")?;
+
+        let mut document_number = None;
+        let mut created = None;
+        let mut sender = None;
+        let mut receiver = None;
+        let mut transactions: Vec<Vorgang> = vec![];
+
+        for segment in message.all_segments() {
+            let qualifier = domain::qualifier(segment);
+            let text = |label| segment.text_of(label).map(|v| v.to_string());
+            match (segment.tag.as_str(), qualifier, transactions.last_mut()) {
+                ("BGM", _, _) => document_number = text("1004"),
+                ("DTM", Some("137"), None) => {
+                    created = Some(domain::timestamp(segment)?)
+                }
+                ("NAD", Some("MS"), None) => sender = text("3039"),
+                ("NAD", Some("MR"), None) => receiver = text("3039"),
+                ("IDE", Some("24"), _) => transactions.push(Vorgang {
+                    id: text("7402").unwrap_or_default(),
+                    ..Vorgang::default()
+                }),
+                ("DTM", Some("92"), Some(transaction)) => {
+                    transaction.start = Some(domain::timestamp(segment)?)
+                }
+                ("DTM", Some("93"), Some(transaction)) => {
+                    transaction.end = Some(domain::timestamp(segment)?)
+                }
+                ("STS", Some("7"), Some(transaction)) => {
+                    transaction.reason = text("9013")
+                }
+                ("LOC", Some("Z16"), Some(transaction)) => {
+                    transaction.marktlokation = text("3225")
+                }
+                ("RFF", Some("Z13"), Some(transaction)) => {
+                    transaction.pruefidentifikator = text("1154")
+                }
+                ("NAD", Some("Z09"), Some(transaction)) => {
+                    transaction.customer = Some(Customer {
+                        name: domain::name(segment),
+                        address: domain::address(segment),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Marktnachricht {
+            document_number: document_number
+                .ok_or(Error::MissingSegment("BGM"))?,
+            created,
+            sender,
+            receiver,
+            transactions,
+        })
+    }
+}
+
+/// Maps the only message of an interchange, which must not contain
+/// several ones.
+impl TryFrom<&Interchange> for Marktnachricht {
+    type Error = Error;
+
+    fn try_from(interchange: &Interchange) -> Result<Self, Self::Error> {
+        let message = domain::single_message(interchange, "UTILMD")?;
+        Marktnachricht::try_from(&message)
+    }
+}
+
+impl TryFrom<Interchange> for Marktnachricht {
+    type Error = Error;
+
+    fn try_from(interchange: Interchange) -> Result<Self, Self::Error> {
+        Marktnachricht::try_from(&interchange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::domain::utilmd::{Customer, Marktnachricht, Vorgang};
+    use crate::domain::{Address, Error};
+    use crate::fixtures;
+    use crate::mig::decode::value::Interchange;
+    use crate::mig::validate::dtm::Timestamp;
+    use crate::mig::{self, DecodeOptions};
+
+    const MESSAGE: &str = "UNH+1+UTILMD:D:11A:UN:5.2a'\
+        BGM+E01+DOC1'\
+        DTM+137:202003070705?+00:303'\
+        NAD+MS+9900467000000::293'\
+        NAD+MR+9904590000002::293'\
+        IDE+24+TX1'\
+        DTM+92:202004010000?+00:303'\
+        DTM+93:20201231:102'\
+        STS+7++E01'\
+        LOC+Z16+51238696781'\
+        RFF+Z13:11001'\
+        NAD+Z09+++Mustermann:Erika+Musterstraße:12+Berlin++10115+DE'\
+        UNT+13+1'";
+
+    fn decode(input: &str) -> Interchange {
+        let options = DecodeOptions::default();
+        let known = vec![fixtures::utilmd()];
+        mig::decode(known, &mut input.as_bytes(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_try_from() {
+        let at = |month, day, hour, minute| Timestamp {
+            year: 2020,
+            month,
+            day,
+            hour,
+            minute,
+        };
+        let interchange = decode(&fixtures::interchange(&[MESSAGE]));
+        let message = Marktnachricht::try_from(&interchange).unwrap();
+        assert_eq!(
+            message,
+            Marktnachricht {
+                document_number: "DOC1".to_string(),
+                created: Some(at(3, 7, 7, 5)),
+                sender: Some("9900467000000".to_string()),
+                receiver: Some("9904590000002".to_string()),
+                transactions: vec![Vorgang {
+                    id: "TX1".to_string(),
+                    pruefidentifikator: Some("11001".to_string()),
+                    reason: Some("E01".to_string()),
+                    marktlokation: Some("51238696781".to_string()),
+                    customer: Some(Customer {
+                        name: vec!["Mustermann".into(), "Erika".into()],
+                        address: Some(Address {
+                            street: Some("Musterstraße".to_string()),
+                            house_number: Some("12".to_string()),
+                            postal_code: Some("10115".to_string()),
+                            city: Some("Berlin".to_string()),
+                            country: Some("DE".to_string()),
+                        }),
+                    }),
+                    start: Some(at(4, 1, 0, 0)),
+                    // The date is the start of the day in Germany.
+                    end: Some(at(12, 30, 23, 0)),
+                }],
+            }
+        );
+
+        // The model is the same after encoding the interchange again.
+        let encoded = decode(&mig::encode(&interchange));
+        assert_eq!(Marktnachricht::try_from(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn test_several_messages() {
+        let second = MESSAGE
+            .replace("UNH+1", "UNH+2")
+            .replace("DOC1", "DOC2")
+            .replace("TX1", "TX2")
+            .replace("UNT+13+1", "UNT+13+2");
+        let interchange = decode(&fixtures::interchange(&[MESSAGE, &second]));
+
+        let messages = Marktnachricht::all(&interchange).unwrap();
+        let numbers: Vec<&str> = messages
+            .iter()
+            .map(|message| message.document_number.as_str())
+            .collect();
+        assert_eq!(numbers, vec!["DOC1", "DOC2"]);
+        assert_eq!(messages[1].transactions[0].id, "TX2");
+        assert_eq!(
+            Marktnachricht::try_from(&interchange),
+            Err(Error::SeveralMessages(2))
+        );
+    }
+}
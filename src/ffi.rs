@@ -0,0 +1,107 @@
+//! A C interface to decode messages from other languages, e.g. Java or
+//! .NET.
+//!
+//! This module is only available with the `ffi` feature. All functions
+//! take and return null-terminated UTF-8 strings. Strings returned by
+//! this library are owned by the caller and have to be released with
+//! [edifact_string_free].
+//!
+//! The results are JSON objects. On success, the object contains `ok`
+//! set to `true` and the decoded `interchange`. Otherwise `ok` is `false`
+//! and `error` contains either the errors of the interchange or a
+//! message.
+use std::ffi::{c_char, CStr, CString};
+use std::slice;
+
+use serde_json::{json, Value};
+
+use crate::mig;
+use crate::mig::decode::Error;
+use crate::mig::{description, DecodeOptions};
+
+/// Decodes the `len` bytes at `input` with the given MIG description in
+/// JSON and returns the result as JSON.
+///
+/// # Safety
+///
+/// `mig_json` has to be a valid, null-terminated string and `input` has
+/// to point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn edifact_decode(
+    mig_json: *const c_char,
+    input: *const u8,
+    len: usize,
+) -> *mut c_char {
+    let result = if mig_json.is_null() || input.is_null() {
+        failure("The description and the input must not be null".into())
+    } else {
+        let mig_json = CStr::from_ptr(mig_json);
+        let input = slice::from_raw_parts(input, len);
+        decode(mig_json, input)
+    };
+    into_raw(result)
+}
+
+/// Releases a string returned by this library. Passing null does
+/// nothing.
+///
+/// # Safety
+///
+/// `value` has to be a string returned by this library, which has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn edifact_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+fn decode(mig_json: &CStr, mut input: &[u8]) -> Value {
+    let mig_json = match mig_json.to_str() {
+        Ok(mig_json) => mig_json,
+        Err(error) => return failure(error.to_string().into()),
+    };
+    let description: description::Interchange =
+        match serde_json::from_str(mig_json) {
+            Ok(description) => description,
+            Err(error) => return failure(error.to_string().into()),
+        };
+
+    let options = DecodeOptions::default();
+    match mig::decode(vec![description], &mut input, &options) {
+        Ok(interchange) => json!({ "ok": true, "interchange": interchange }),
+        Err(Error::Mig(error)) => failure(json!(error)),
+        Err(error) => failure(error.to_string().into()),
+    }
+}
+
+fn failure(error: Value) -> Value {
+    json!({ "ok": false, "error": error })
+}
+
+fn into_raw(value: Value) -> *mut c_char {
+    // JSON escapes control characters, so there is no null byte.
+    CString::new(value.to_string()).unwrap_or_default().into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use crate::ffi::{edifact_decode, edifact_string_free};
+
+    #[test]
+    fn test_edifact_decode() {
+        let mig_json = CString::new("{}").unwrap();
+        let input = b"UNA:+.? 'UNB+UNOC:3'";
+        unsafe {
+            let result =
+                edifact_decode(mig_json.as_ptr(), input.as_ptr(), input.len());
+            let json: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(result).to_str().unwrap())
+                    .unwrap();
+            assert_eq!(json["ok"], false);
+            edifact_string_free(result);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Fixtures shared by the tests of all modules: the descriptions of
+//! APERAK and CONTRL in the repository root and interchanges matching
+//! them, and shortened descriptions of other messages for the domain
+//! models.
+use crate::mig::compact;
+use crate::mig::description::Interchange;
+
+/// The description of APERAK 2.1d as JSON.
+pub(crate) const APERAK_JSON: &str = include_str!("../APERAK.json");
+
+/// The description of CONTRL 2.0a as JSON.
+pub(crate) const CONTRL_JSON: &str = include_str!("../CONTRL.json");
+
+/// The reference of the interchanges below.
+pub(crate) const REFERENCE: &str = "C3AAAAAAAAHKLC";
+
+/// A short APERAK rejecting a message with the error Z29.
+pub(crate) const APERAK: &str = "UNA:+.? '\
+    UNB+UNOC:3+9900467000000:500+9904590000002:500\
+    +200307:0705+C3AAAAAAAAHKLC'\
+    UNH+1+APERAK:D:07B:UN:2.1d'\
+    BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+    DTM+137:202003070705:203'\
+    NAD+MS+9900467000000::293'\
+    NAD+MR+9904590000002::293'\
+    ERC+Z29'\
+    FTX+Z02+++10'\
+    UNT+8+1'\
+    UNZ+1+C3AAAAAAAAHKLC'";
+
+/// The [APERAK] with an escaped apostrophe in its last free text.
+pub(crate) const APERAK_ESCAPED: &str = "UNA:+.? '\
+    UNB+UNOC:3+9900467000000:500+9904590000002:500\
+    +200307:0705+C3AAAAAAAAHKLC'\
+    UNH+1+APERAK:D:07B:UN:2.1d'\
+    BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+    DTM+137:202003070705:203'\
+    NAD+MS+9900467000000::293'\
+    NAD+MR+9904590000002::293'\
+    ERC+Z29'\
+    FTX+Z02+++1?'0'\
+    UNT+8+1'\
+    UNZ+1+C3AAAAAAAAHKLC'";
+
+/// An APERAK with the references and free texts of the error, which uses
+/// most of the segments of the description.
+pub(crate) const APERAK_FULL: &str = "UNA:+.? '\
+    UNB+UNOC:3+9900467000000:500+9904590000002:500\
+    +200307:0705+C3AAAAAAAAHKLC'\
+    UNH+1+APERAK:D:07B:UN:2.1d'\
+    BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+    DTM+137:202003070705:203'\
+    RFF+ACE:O1583553607732'\
+    DTM+171:202003070500:203'\
+    NAD+MS+9900467000000::293'\
+    NAD+MR+9904590000002::293'\
+    ERC+Z29'\
+    FTX+ABO+++LOC17251283352734'\
+    RFF+ACW:V1583553607732'\
+    RFF+AGO:9904590000002ORD1583553607706'\
+    FTX+AAO+++LOC Datenelement 3225 ungültiger Wert'\
+    FTX+Z02+++10'\
+    UNT+14+1'\
+    UNZ+1+C3AAAAAAAAHKLC'";
+
+/// An ORDERS message requesting values of a Marktlokation and the master
+/// data of a Messlokation.
+pub(crate) const ORDERS: &str = "UNH+1+ORDERS:D:09B:UN:1.4a'\
+    BGM+Z10+ORD1'\
+    DTM+137:202003070705?+00:303'\
+    RFF+Z13:17101'\
+    NAD+MS+9900467000000::293'\
+    NAD+MR+9904590000002::293'\
+    LIN+1'\
+    PIA+5+1-1?:1.29.0:SRW'\
+    LOC+Z16+51238696781'\
+    DTM+163:202002292300?+00:303'\
+    DTM+164:202003312200?+00:303'\
+    LIN+2'\
+    LOC+Z17+DE00014545768S0000000000000003054'\
+    RFF+AVE:V1'\
+    UNT+15+1'";
+
+/// An INVOIC message of a network usage invoice with one line item.
+pub(crate) const INVOIC: &str = "UNH+1+INVOIC:D:06A:UN:2.8c'\
+    BGM+380+INV1'\
+    DTM+137:202003070705?+01:303'\
+    DTM+155:202001010000:203'\
+    DTM+156:202007010000:203'\
+    DTM+265:20200315:102'\
+    RFF+Z13:31002'\
+    NAD+MS+9900467000000::293'\
+    NAD+MR+9904590000002::293'\
+    LIN+1++9990001000798:Z01'\
+    QTY+47:1000:KWH'\
+    MOA+203:250.00'\
+    PRI+CAL:0.25'\
+    TAX+7+VAT+++:::19+S'\
+    UNS+S'\
+    MOA+77:297.50'\
+    MOA+9:297.50'\
+    TAX+7+VAT+++:::19+S'\
+    MOA+124:47.50'\
+    MOA+125:250.00'\
+    UNT+21+1'";
+
+/// Returns the description of APERAK 2.1d.
+pub(crate) fn aperak() -> Interchange {
+    serde_json::from_str(APERAK_JSON).unwrap()
+}
+
+/// Returns the description of CONTRL 2.0a.
+pub(crate) fn contrl() -> Interchange {
+    serde_json::from_str(CONTRL_JSON).unwrap()
+}
+
+/// Returns a shortened description of UTILMD 5.2a.
+pub(crate) fn utilmd() -> Interchange {
+    compact::from_yaml(include_str!("fixtures/UTILMD.yaml")).unwrap()
+}
+
+/// Returns a shortened description of INVOIC 2.8c.
+pub(crate) fn invoic() -> Interchange {
+    compact::from_yaml(include_str!("fixtures/INVOIC.yaml")).unwrap()
+}
+
+/// Returns a shortened description of REMADV 2.9b.
+pub(crate) fn remadv() -> Interchange {
+    compact::from_yaml(include_str!("fixtures/REMADV.yaml")).unwrap()
+}
+
+/// Returns an interchange from 9900467000000 to 9904590000002 with the
+/// given messages from their UNH to their UNT.
+pub(crate) fn interchange(messages: &[&str]) -> String {
+    format!(
+        "UNA:+.? '\
+         UNB+UNOC:3+9900467000000:500+9904590000002:500\
+         +200307:0705+REF1'\
+         {}\
+         UNZ+{}+REF1'",
+        messages.concat(),
+        messages.len()
+    )
+}
+
+/// Returns an APERAK interchange in the envelope of [APERAK] with the
+/// given segments between UNH and UNT, which are counted in the UNT.
+pub(crate) fn aperak_with(segments: &str) -> String {
+    // A terminator follows an even number of escape characters.
+    let mut count = 0;
+    let mut escaped = false;
+    for c in segments.chars() {
+        count += usize::from(c == '\'' && !escaped);
+        escaped = c == '?' && !escaped;
+    }
+    format!(
+        "UNA:+.? '\
+         UNB+UNOC:3+9900467000000:500+9904590000002:500\
+         +200307:0705+{reference}'\
+         UNH+1+APERAK:D:07B:UN:2.1d'\
+         {segments}\
+         UNT+{count}+1'\
+         UNZ+1+{reference}'",
+        reference = REFERENCE,
+        segments = segments,
+        count = count + 2,
+    )
+}
@@ -0,0 +1,393 @@
+//! The gRPC service of `edifact serve --grpc`, which decodes and
+//! validates interchanges like `POST /decode` and `POST /validate` of the
+//! [HTTP server](crate::server), but with the typed messages of
+//! `proto/edifact.proto` instead of JSON.
+//!
+//! Requests are limited in size and time like those of the HTTP server.
+use std::io;
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+use edifact::mig::decode::{self, value};
+use edifact::mig::either::Either;
+use edifact::mig::error;
+
+use crate::server::{MAX_BODY, TIMEOUT};
+
+/// The messages and the service generated from `proto/edifact.proto`.
+pub mod proto {
+    tonic::include_proto!("edifact");
+}
+
+use proto::edifact_server::{Edifact, EdifactServer};
+
+/// Serves the gRPC service on the given listener, decoding the inputs
+/// with the given function.
+pub fn serve<F>(listener: TcpListener, decode: F) -> io::Result<()>
+where
+    F: Fn(&[u8]) -> Result<value::Interchange, decode::Error>
+        + Send
+        + Sync
+        + 'static,
+{
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .map_err(io::Error::other)?;
+        let service = Service { decode: Arc::new(decode) };
+        tonic::transport::Server::builder()
+            .timeout(TIMEOUT)
+            .add_service(
+                EdifactServer::new(service)
+                    .max_decoding_message_size(MAX_BODY)
+                    .max_encoding_message_size(usize::MAX),
+            )
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(io::Error::other)
+    })
+}
+
+struct Service<F> {
+    decode: Arc<F>,
+}
+
+impl<F> Service<F>
+where
+    F: Fn(&[u8]) -> Result<value::Interchange, decode::Error>
+        + Send
+        + Sync
+        + 'static,
+{
+    /// Decodes the interchange of the given request on a thread, which may
+    /// block. An input, which cannot be read at all, is an invalid
+    /// argument.
+    async fn run(
+        &self,
+        request: Request<proto::DecodeRequest>,
+    ) -> Result<Result<value::Interchange, error::InterchangeError>, Status>
+    {
+        let decode = Arc::clone(&self.decode);
+        let input = request.into_inner().interchange;
+        let result = tokio::task::spawn_blocking(move || decode(&input))
+            .await
+            .map_err(|error| Status::internal(error.to_string()))?;
+        match result {
+            Ok(interchange) => Ok(Ok(interchange)),
+            Err(decode::Error::Mig(error)) => Ok(Err(error)),
+            Err(error) => Err(Status::invalid_argument(error.to_string())),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<F> Edifact for Service<F>
+where
+    F: Fn(&[u8]) -> Result<value::Interchange, decode::Error>
+        + Send
+        + Sync
+        + 'static,
+{
+    async fn decode(
+        &self,
+        request: Request<proto::DecodeRequest>,
+    ) -> Result<Response<proto::DecodeResponse>, Status> {
+        use proto::decode_response::Result as Decoded;
+
+        let result = match self.run(request).await? {
+            Ok(value) => Decoded::Interchange(interchange(&value)),
+            Err(error) => Decoded::Errors(interchange_error(&error)),
+        };
+        Ok(Response::new(proto::DecodeResponse { result: Some(result) }))
+    }
+
+    async fn validate(
+        &self,
+        request: Request<proto::DecodeRequest>,
+    ) -> Result<Response<proto::ValidateResponse>, Status> {
+        let errors = self.run(request).await?.err();
+        Ok(Response::new(proto::ValidateResponse {
+            valid: errors.is_none(),
+            errors: errors.as_ref().map(interchange_error),
+        }))
+    }
+}
+
+fn interchange(value: &value::Interchange) -> proto::Interchange {
+    proto::Interchange {
+        segments: nodes(&value.segments),
+        test: value.test,
+        warnings: value.warnings.iter().map(segment_error).collect(),
+    }
+}
+
+fn nodes(
+    nodes: &[Either<value::Segmentgroup, value::Segment>],
+) -> Vec<proto::Node> {
+    use proto::node::Node;
+
+    let node = |node: &Either<_, _>| proto::Node {
+        node: Some(match node {
+            Either::Left(group) => Node::Segmentgroup(segmentgroup(group)),
+            Either::Right(value) => Node::Segment(segment(value)),
+        }),
+    };
+    nodes.iter().map(node).collect()
+}
+
+fn segmentgroup(group: &value::Segmentgroup) -> proto::Segmentgroup {
+    proto::Segmentgroup {
+        counter: group.counter().to_string(),
+        label: group.label().to_string(),
+        name: group.name().to_string(),
+        st: format!("{:?}", group.st()),
+        comment: group.comment().map(str::to_string),
+        segments: nodes(group.segments()),
+    }
+}
+
+fn segment(segment: &value::Segment) -> proto::Segment {
+    use proto::element::Element;
+
+    let element = |element: &Either<_, _>| proto::Element {
+        element: Some(match element {
+            Either::Left(value) => Element::Composite(composite(value)),
+            Either::Right(value) => Element::DataElement(data_element(value)),
+        }),
+    };
+    proto::Segment {
+        index: segment.index() as u64,
+        counter: segment.counter().to_string(),
+        number: segment.number(),
+        tag: segment.tag().to_string(),
+        name: segment.name().to_string(),
+        st: format!("{:?}", segment.st()),
+        comment: segment.comment().map(str::to_string),
+        elements: segment.elements().iter().map(element).collect(),
+        raw: segment.raw().to_string(),
+        span: Some(span(segment.span())),
+    }
+}
+
+fn composite(composite: &value::Composite) -> proto::Composite {
+    proto::Composite {
+        index: composite.index() as u64,
+        label: composite.label().to_string(),
+        name: composite.name().to_string(),
+        st: format!("{:?}", composite.st()),
+        elements: composite.elements().iter().map(data_element).collect(),
+        span: Some(span(composite.span())),
+    }
+}
+
+fn data_element(data_element: &value::DataElement) -> proto::DataElement {
+    use proto::data_element::Value;
+
+    let value = data_element.value().map(|value| match value {
+        value::Matched::Text(text) => Value::Text(text.clone()),
+        value::Matched::Int(int) => Value::Int(*int),
+        value::Matched::Decimal(decimal) => Value::Decimal(*decimal),
+    });
+    proto::DataElement {
+        index: data_element.index() as u64,
+        label: data_element.label().to_string(),
+        name: data_element.name().to_string(),
+        st: format!("{:?}", data_element.description().st),
+        value,
+        span: Some(span(data_element.span())),
+    }
+}
+
+fn span(span: value::Span) -> proto::Span {
+    proto::Span { start: span.start as u64, end: span.end as u64 }
+}
+
+fn interchange_error(
+    error: &error::InterchangeError,
+) -> proto::InterchangeError {
+    proto::InterchangeError {
+        pos: error.pos as u64,
+        service_segment_error: error
+            .service_segment_error
+            .as_ref()
+            .map(service_segment_error),
+        message_errors: error
+            .message_errors
+            .iter()
+            .map(message_error)
+            .collect(),
+    }
+}
+
+fn message_error(error: &error::MessageError) -> proto::MessageError {
+    proto::MessageError {
+        pos: error.pos as u64,
+        service_segment_error: error
+            .service_segment_error
+            .as_ref()
+            .map(service_segment_error),
+        segment_errors: error
+            .segment_errors
+            .iter()
+            .map(segment_error)
+            .collect(),
+        repeated: error.repeated.iter().map(repeated).collect(),
+    }
+}
+
+fn repeated(repeated: &error::Repeated) -> proto::Repeated {
+    proto::Repeated {
+        first: repeated.first as u64,
+        syntax_errors: repeated
+            .syntax_errors
+            .iter()
+            .map(syntax_error)
+            .collect(),
+        count: repeated.count as u64,
+        omitted: repeated.omitted as u64,
+    }
+}
+
+fn service_segment_error(
+    error: &error::ServiceSegmentError,
+) -> proto::ServiceSegmentError {
+    proto::ServiceSegmentError {
+        tag: error.tag.clone(),
+        error: Some(element_error(&error.error)),
+    }
+}
+
+fn segment_error(error: &error::SegmentError) -> proto::SegmentError {
+    let severity = match error.severity {
+        error::Severity::Error => proto::Severity::Error,
+        error::Severity::Warning => proto::Severity::Warning,
+    };
+    proto::SegmentError {
+        pos: error.pos as u64,
+        severity: severity.into(),
+        syntax_error: error.syntax_error.as_ref().map(syntax_error),
+        errors: error.errors.iter().map(element_error).collect(),
+        raw: error.raw.clone(),
+    }
+}
+
+fn element_error(
+    error: &Either<error::CompositeError, error::DataElementError>,
+) -> proto::ElementError {
+    use proto::element_error::Error;
+
+    proto::ElementError {
+        error: Some(match error {
+            Either::Left(error) => Error::Composite(composite_error(error)),
+            Either::Right(error) => {
+                Error::DataElement(data_element_error(error))
+            }
+        }),
+    }
+}
+
+fn composite_error(error: &error::CompositeError) -> proto::CompositeError {
+    proto::CompositeError {
+        pos: error.pos as u64,
+        syntax_error: error.syntax_error.as_ref().map(syntax_error),
+        errors: error.errors.iter().map(data_element_error).collect(),
+    }
+}
+
+fn data_element_error(
+    error: &error::DataElementError,
+) -> proto::DataElementError {
+    proto::DataElementError {
+        pos: error.pos as u64,
+        syntax_errors: error.syntax_errors.iter().map(syntax_error).collect(),
+    }
+}
+
+fn syntax_error(error: &error::SyntaxError) -> proto::SyntaxError {
+    proto::SyntaxError {
+        code: error.get_code(),
+        name: error.get_name().to_string(),
+        message: error.get_message().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use edifact::mig::{self, description::Interchange, DecodeOptions};
+
+    use crate::grpc::proto::edifact_client::EdifactClient;
+    use crate::grpc::proto::{decode_response, node, DecodeRequest};
+    use crate::grpc::serve;
+
+    const APERAK: &str = "UNA:+.? '\
+        UNB+UNOC:3+9900467000000:500+9904590000002:500\
+        +200307:0705+C3AAAAAAAAHKLC'\
+        UNH+1+APERAK:D:07B:UN:2.1d'\
+        BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+        DTM+137:202003070705:203'\
+        NAD+MS+9900467000000::293'\
+        NAD+MR+9904590000002::293'\
+        ERC+Z29'\
+        FTX+Z02+++10'\
+        UNT+8+1'\
+        UNZ+1+C3AAAAAAAAHKLC'";
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let desc: Interchange =
+            serde_json::from_str(include_str!("../APERAK.json")).unwrap();
+        thread::spawn(move || {
+            serve(listener, move |input| {
+                let options = DecodeOptions::default();
+                mig::decode(vec![desc.clone()], &mut &input[..], &options)
+            })
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let url = format!("http://{}", addr);
+            let mut client = EdifactClient::connect(url).await.unwrap();
+            let request = |input: &str| DecodeRequest {
+                interchange: input.as_bytes().to_vec(),
+            };
+
+            let response = client.decode(request(APERAK)).await.unwrap();
+            let Some(decode_response::Result::Interchange(interchange)) =
+                response.into_inner().result
+            else {
+                panic!("the interchange should be decoded");
+            };
+            let tags: Vec<&str> = interchange
+                .segments
+                .iter()
+                .filter_map(|node| match &node.node {
+                    Some(node::Node::Segment(segment)) => Some(&segment.tag),
+                    _ => None,
+                })
+                .map(String::as_str)
+                .collect();
+            assert_eq!(tags.first(), Some(&"UNB"));
+
+            let invalid = APERAK.replace("ERC+Z29", "ERC+Z29+X");
+            let response = client.validate(request(&invalid)).await.unwrap();
+            let response = response.into_inner();
+            assert!(!response.valid);
+            let errors = response.errors.unwrap();
+            assert_eq!(errors.message_errors.len(), 1);
+
+            let status =
+                client.validate(request("garbage")).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        });
+    }
+}
@@ -0,0 +1,199 @@
+//! An index of archived interchanges, so they can be found by their
+//! references, partners and dates without parsing every file again.
+//!
+//! The index is a file with one JSON object per line and interchange,
+//! e.g.
+//!
+//! ```json
+//! {"path":"in/aperak.edi","sender":"9900467000000","receiver":"9904590000002","reference":"C3AAAAAAAAHKLC","messages":[{"interchange":"C3AAAAAAAAHKLC","reference":"1","messageType":"APERAK","ids":["53ff5de4caab4ea18abafab5e6036991"],"references":[{"qualifier":"ACE","value":"O1583553607732"}]}],"created":"2020-03-07T07:05","test":false}
+//! ```
+//!
+//! The messages are linked by their references with
+//! [Graph](edifact::mig::conversation::Graph).
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use edifact::mig::conversation::{self, Message};
+use edifact::mig::decode::parser::value::Interchange;
+
+/// An `Entry` of the index describes one interchange.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub path: PathBuf,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    /// The interchange reference from UNB 0020.
+    pub reference: Option<String>,
+    /// The messages in order with their references to other messages.
+    pub messages: Vec<Message>,
+    /// The creation date from UNB in ISO 8601, e.g. `2020-03-07T07:05`.
+    pub created: Option<String>,
+    pub test: bool,
+}
+
+impl Entry {
+    pub fn new(path: &Path, interchange: &Interchange) -> Entry {
+        let routing = interchange.routing();
+        Entry {
+            path: path.to_path_buf(),
+            sender: routing.sender,
+            receiver: routing.receiver,
+            reference: routing.reference,
+            messages: conversation::messages(interchange),
+            created: routing.created.map(|created| {
+                format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}",
+                    created.year,
+                    created.month,
+                    created.day,
+                    created.hour,
+                    created.minute
+                )
+            }),
+            test: routing.test,
+        }
+    }
+}
+
+/// A `Query` selects the entries, which match all of its fields given.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// An interchange reference or a document or transaction number.
+    pub reference: Option<String>,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    pub message_type: Option<String>,
+    /// The earliest creation date, e.g. `2020-03-07`.
+    pub from: Option<String>,
+    /// The latest creation date, e.g. `2020-03-07`, which includes the
+    /// whole day.
+    pub until: Option<String>,
+}
+
+impl Query {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let is = |expected: &Option<String>, actual: &Option<String>| {
+            expected.is_none() || expected == actual
+        };
+        let reference = self.reference.as_ref().is_none_or(|reference| {
+            entry.reference.as_ref() == Some(reference)
+                || entry
+                    .messages
+                    .iter()
+                    .any(|message| message.ids.contains(reference))
+        });
+        let message_type =
+            self.message_type.as_ref().is_none_or(|message_type| {
+                entry
+                    .messages
+                    .iter()
+                    .any(|message| &message.message_type == message_type)
+            });
+        // ISO dates compare like strings, so a shorter date compares with
+        // the same prefix of the creation date.
+        let created = entry.created.as_deref().unwrap_or_default();
+        let from = self
+            .from
+            .as_deref()
+            .is_none_or(|from| entry.created.is_some() && created >= from);
+        let until = self.until.as_deref().is_none_or(|until| {
+            entry.created.is_some()
+                && created[..until.len().min(created.len())] <= *until
+        });
+        reference
+            && is(&self.sender, &entry.sender)
+            && is(&self.receiver, &entry.receiver)
+            && message_type
+            && from
+            && until
+    }
+}
+
+/// The error returned, if an index cannot be read.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Invalid { line: usize, error: serde_json::Error },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => error.fmt(f),
+            Error::Invalid { line, error } => {
+                write!(f, "line {} of the index is invalid: {}", line, error)
+            }
+        }
+    }
+}
+
+/// Writes the given entries to the index at the given path, replacing it.
+pub fn write(path: &Path, entries: &[Entry]) -> Result<(), Error> {
+    let mut file =
+        io::BufWriter::new(fs::File::create(path).map_err(Error::Io)?);
+    for entry in entries {
+        // Entries consist of strings only, so they always serialize.
+        let line = serde_json::to_string(entry).unwrap();
+        writeln!(file, "{}", line).map_err(Error::Io)?;
+    }
+    file.flush().map_err(Error::Io)
+}
+
+/// Reads all entries of the index at the given path.
+pub fn read(path: &Path) -> Result<Vec<Entry>, Error> {
+    let file = fs::File::open(path).map_err(Error::Io)?;
+    let mut entries = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(Error::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|error| Error::Invalid { line: i + 1, error })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use edifact::mig;
+
+    use crate::index::{Entry, Query};
+
+    const INPUT: &str = "UNA:+.? '\
+        UNB+UNOC:3+9900467000000:500+9904590000002:500\
+        +200307:0705+C3AAAAAAAAHKLC'\
+        UNH+1+APERAK:D:07B:UN:2.1d'\
+        BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+        UNT+3+1'\
+        UNZ+1+C3AAAAAAAAHKLC'";
+
+    #[test]
+    fn test_query() {
+        let interchange = mig::parse(&mut INPUT.as_bytes()).unwrap();
+        let entry = Entry::new(Path::new("aperak.edi"), &interchange);
+        assert_eq!(entry.messages[0].message_type, "APERAK");
+        assert_eq!(entry.created.as_deref(), Some("2020-03-07T07:05"));
+
+        let query = |reference: &str, from: &str, until: &str| Query {
+            reference: Some(reference.to_string()),
+            from: Some(from.to_string()),
+            until: Some(until.to_string()),
+            ..Query::default()
+        };
+        assert!(query("C3AAAAAAAAHKLC", "2020-03-07", "2020-03-07")
+            .matches(&entry));
+        assert!(query("53ff5de4caab4ea18abafab5e6036991", "2020", "2020")
+            .matches(&entry));
+        assert!(!query("1", "2020-03-08", "2020-12").matches(&entry));
+        assert!(!query("2", "2020", "2020").matches(&entry));
+    }
+}
@@ -0,0 +1,529 @@
+//! An interactive browser for interchanges in the terminal.
+//!
+//! The browser shows the segments of an interchange as a tree, the
+//! composites and data elements of the selected segment and the errors
+//! found while decoding. Names and statuses of the elements are taken
+//! from the description, if there is one.
+use std::collections::HashSet;
+use std::io;
+
+use edifact::mig::decode::parser::value as parsed;
+use edifact::mig::decode::{self, value};
+use edifact::mig::description as desc;
+use edifact::mig::either::Either;
+use edifact::mig::error::{
+    CompositeError, DataElementError, InterchangeError, SegmentError,
+    SyntaxError,
+};
+use edifact::mig::{self, DecodeOptions};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{
+    Block, List, ListItem, ListState, Paragraph, Row, Table,
+};
+use ratatui::{DefaultTerminal, Frame};
+
+const HELP: &str = " ↑/↓ navigate · Tab switch to errors · Enter jump to \
+                    segment · n next error · q quit";
+
+/// An `Entry` is a segment or segment group in the tree.
+struct Entry {
+    depth: usize,
+    title: String,
+    /// The segment as it has been received, if this row is a segment.
+    raw: Option<String>,
+    fields: Vec<Field>,
+}
+
+/// A `Field` is a composite or data element of a segment.
+struct Field {
+    label: String,
+    name: String,
+    st: String,
+    value: String,
+}
+
+/// An `Issue` is an error found while decoding, which points to the row
+/// of its segment, if it could be found.
+struct Issue {
+    row: Option<usize>,
+    message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Segments,
+    Issues,
+}
+
+/// The `Inspector` contains the state of the browser.
+pub struct Inspector {
+    rows: Vec<Entry>,
+    issues: Vec<Issue>,
+    segments: ListState,
+    errors: ListState,
+    focus: Focus,
+}
+
+impl Inspector {
+    /// Create an inspector of the given input, which is decoded with the
+    /// given description, if there is one.
+    ///
+    /// If the input does not match the description, its segments are
+    /// shown as they have been parsed, next to the errors.
+    pub fn new(
+        input: &str,
+        description: Option<desc::Interchange>,
+    ) -> Result<Self, decode::Error> {
+        let (rows, issues) = match description {
+            None => (
+                parsed_rows(&mig::parse(&mut input.as_bytes())?, None),
+                vec![],
+            ),
+            Some(description) => {
+                let known = vec![description.clone()];
+                let options = DecodeOptions::default();
+                match mig::decode(known, &mut input.as_bytes(), &options) {
+                    Ok(interchange) => {
+                        let mut rows = vec![];
+                        decoded_rows(&interchange.segments, 0, &mut rows);
+                        (rows, vec![])
+                    }
+                    Err(decode::Error::Mig(error)) => {
+                        let interchange = mig::parse(&mut input.as_bytes())?;
+                        let rows =
+                            parsed_rows(&interchange, Some(&description));
+                        let issues = issues(&error, &rows);
+                        (rows, issues)
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        };
+
+        let mut segments = ListState::default();
+        segments.select_first();
+        let mut errors = ListState::default();
+        errors.select_first();
+        Ok(Inspector {
+            rows,
+            issues,
+            segments,
+            errors,
+            focus: Focus::Segments,
+        })
+    }
+
+    /// Runs the browser until the user quits.
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let state = match self.focus {
+                Focus::Segments => &mut self.segments,
+                Focus::Issues => &mut self.errors,
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+                KeyCode::Home | KeyCode::Char('g') => state.select_first(),
+                KeyCode::End | KeyCode::Char('G') => state.select_last(),
+                KeyCode::Tab if !self.issues.is_empty() => {
+                    self.focus = match self.focus {
+                        Focus::Segments => Focus::Issues,
+                        Focus::Issues => Focus::Segments,
+                    }
+                }
+                KeyCode::Enter if self.focus == Focus::Issues => self.jump(),
+                KeyCode::Char('n') if !self.issues.is_empty() => {
+                    let next = self.errors.selected().map_or(0, |i| i + 1);
+                    self.errors.select(Some(next % self.issues.len()));
+                    self.jump();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Selects the segment of the selected issue.
+    fn jump(&mut self) {
+        let issue = self.errors.selected().and_then(|i| self.issues.get(i));
+        if let Some(row) = issue.and_then(|issue| issue.row) {
+            self.segments.select(Some(row));
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let issues_height = match self.issues.len() {
+            0 => 0,
+            n => (n as u16 + 2).min(10),
+        };
+        let [main, bottom, help] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(issues_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ])
+        .areas(main);
+
+        let with_issue: HashSet<usize> =
+            self.issues.iter().filter_map(|issue| issue.row).collect();
+        let items = self.rows.iter().enumerate().map(|(i, row)| {
+            let item = ListItem::new(format!(
+                "{}{}",
+                "  ".repeat(row.depth),
+                row.title
+            ));
+            if with_issue.contains(&i) {
+                item.style(Style::new().fg(Color::Red))
+            } else {
+                item
+            }
+        });
+        let tree = List::new(items)
+            .block(Block::bordered().title(" Segments "))
+            .highlight_style(highlight(self.focus == Focus::Segments));
+        frame.render_stateful_widget(tree, left, &mut self.segments);
+
+        let row = self.segments.selected().and_then(|i| self.rows.get(i));
+        let fields = row.map_or(&[][..], |row| &row.fields);
+        let table = Table::new(
+            fields.iter().map(|field| {
+                Row::new([
+                    field.label.as_str(),
+                    field.name.as_str(),
+                    field.st.as_str(),
+                    field.value.as_str(),
+                ])
+            }),
+            [
+                Constraint::Length(8),
+                Constraint::Percentage(50),
+                Constraint::Length(3),
+                Constraint::Percentage(50),
+            ],
+        )
+        .header(
+            ["Label", "Name", "St", "Value"]
+                .into_iter()
+                .collect::<Row>()
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Elements ").title_bottom(
+            row.and_then(|row| row.raw.clone()).unwrap_or_default(),
+        ));
+        frame.render_widget(table, right);
+
+        if !self.issues.is_empty() {
+            let items = self.issues.iter().map(|issue| issue.message.as_str());
+            let list = List::new(items)
+                .block(Block::bordered().title(" Errors "))
+                .highlight_style(highlight(self.focus == Focus::Issues));
+            frame.render_stateful_widget(list, bottom, &mut self.errors);
+        }
+
+        frame.render_widget(Paragraph::new(HELP), help);
+    }
+}
+
+fn highlight(focused: bool) -> Style {
+    if focused {
+        Style::new().add_modifier(Modifier::REVERSED)
+    } else {
+        Style::new().add_modifier(Modifier::BOLD)
+    }
+}
+
+// DECODED
+
+fn decoded_rows(
+    values: &[Either<value::Segmentgroup, value::Segment>],
+    depth: usize,
+    rows: &mut Vec<Entry>,
+) {
+    for value in values {
+        match value {
+            Either::Left(group) => {
+                rows.push(Entry {
+                    depth,
+                    title: format!("{} {}", group.label(), group.name()),
+                    raw: None,
+                    fields: vec![],
+                });
+                decoded_rows(group.segments(), depth + 1, rows);
+            }
+            Either::Right(segment) => {
+                let mut fields = vec![];
+                for element in segment.elements() {
+                    match element {
+                        Either::Left(composite) => {
+                            fields.push(Field {
+                                label: composite.label().to_string(),
+                                name: composite.name().to_string(),
+                                st: format!("{:?}", composite.st()),
+                                value: String::new(),
+                            });
+                            fields.extend(
+                                composite
+                                    .elements()
+                                    .iter()
+                                    .map(|e| decoded_field(e, "  ")),
+                            );
+                        }
+                        Either::Right(data_element) => {
+                            fields.push(decoded_field(data_element, ""))
+                        }
+                    }
+                }
+                rows.push(Entry {
+                    depth,
+                    title: format!("{} {}", segment.tag(), segment.name()),
+                    raw: Some(segment.raw().to_string()),
+                    fields,
+                });
+            }
+        }
+    }
+}
+
+fn decoded_field(data_element: &value::DataElement, indent: &str) -> Field {
+    let description = data_element.description();
+    let value = match data_element.value() {
+        Some(value::Matched::Text(text)) => text.clone(),
+        Some(value::Matched::Int(int)) => int.to_string(),
+        Some(value::Matched::Decimal(decimal)) => decimal.to_string(),
+        None => String::new(),
+    };
+    Field {
+        label: format!("{}{}", indent, description.label),
+        name: description.name.clone(),
+        st: format!("{:?}", description.st),
+        value,
+    }
+}
+
+// PARSED
+
+/// Returns the rows of the parsed segments. The descriptions of the
+/// segments are looked up by their tag and qualifier.
+fn parsed_rows(
+    interchange: &parsed::Interchange,
+    description: Option<&desc::Interchange>,
+) -> Vec<Entry> {
+    let descs = description.map(segment_descriptions).unwrap_or_default();
+    let find = |segment: &parsed::Segment| {
+        descs
+            .iter()
+            .find(|desc| value::matches_segment(desc, true, segment))
+            .or_else(|| {
+                descs.iter().find(|desc| desc.tag == segment.tag.value)
+            })
+            .copied()
+    };
+
+    interchange
+        .segments
+        .iter()
+        .map(|segment| {
+            let desc = find(segment);
+            let mut fields = vec![];
+            for (i, element) in segment.elements.iter().enumerate() {
+                let element_desc = desc.and_then(|desc| desc.elements.get(i));
+                let label = format!("{:02}", i + 1);
+                match element {
+                    Either::Left(composite) => {
+                        let desc = match element_desc {
+                            Some(Either::Left(desc)) => Some(desc),
+                            _ => None,
+                        };
+                        fields.push(Field {
+                            label: desc.map_or(label, |d| d.label.clone()),
+                            name: desc
+                                .map(|d| d.name.clone())
+                                .unwrap_or_default(),
+                            st: desc
+                                .map(|d| format!("{:?}", d.st))
+                                .unwrap_or_default(),
+                            value: String::new(),
+                        });
+                        for (j, data_element) in
+                            composite.elements.iter().enumerate()
+                        {
+                            let desc = desc.and_then(|d| d.elements.get(j));
+                            fields.push(parsed_field(
+                                format!("  {:02}", j + 1),
+                                desc,
+                                data_element,
+                            ));
+                        }
+                    }
+                    Either::Right(data_element) => {
+                        let desc = match element_desc {
+                            Some(Either::Right(desc)) => Some(desc),
+                            Some(Either::Left(desc)) => desc.elements.first(),
+                            None => None,
+                        };
+                        fields.push(parsed_field(label, desc, data_element));
+                    }
+                }
+            }
+
+            let name = desc.map(|desc| desc.name.as_str()).unwrap_or_default();
+            Entry {
+                depth: 0,
+                title: format!("{} {}", segment.tag.value, name),
+                raw: Some(segment.raw.clone()),
+                fields,
+            }
+        })
+        .collect()
+}
+
+fn parsed_field(
+    label: String,
+    desc: Option<&desc::DataElement>,
+    data_element: &parsed::DataElement,
+) -> Field {
+    Field {
+        label: desc.map_or(label, |desc| desc.label.clone()),
+        name: desc.map(|desc| desc.name.clone()).unwrap_or_default(),
+        st: desc.map(|desc| format!("{:?}", desc.st)).unwrap_or_default(),
+        value: data_element.value.clone(),
+    }
+}
+
+/// Returns the descriptions of all segments of the given interchange.
+fn segment_descriptions(
+    description: &desc::Interchange,
+) -> Vec<&desc::Segment> {
+    fn collect<'a>(
+        values: &'a [Either<desc::Segmentgroup, desc::Segment>],
+        result: &mut Vec<&'a desc::Segment>,
+    ) {
+        for value in values {
+            match value {
+                Either::Left(group) => collect(&group.segments, result),
+                Either::Right(segment) => result.push(segment),
+            }
+        }
+    }
+
+    let mut result = vec![&description.unb, &description.message.unh];
+    collect(&description.message.segments, &mut result);
+    result.push(&description.message.unt);
+    result.push(&description.unz);
+    result
+}
+
+// ISSUES
+
+fn issues(error: &InterchangeError, rows: &[Entry]) -> Vec<Issue> {
+    let mut issues = vec![];
+    if let Some(error) = &error.service_segment_error {
+        let prefix = format!("{} ", error.tag);
+        issues.push(Issue {
+            row: rows.iter().position(|row| row.title.starts_with(&prefix)),
+            message: format!("{}: {}", error.tag, describe(&error.error)),
+        });
+    }
+
+    for error in error.message_errors.iter().flat_map(|e| &e.segment_errors) {
+        issues.push(Issue {
+            row: find_row(error, rows),
+            message: describe_segment(error),
+        });
+    }
+    issues
+}
+
+/// Returns the row of the segment of the given error. If the segment
+/// occurs multiple times, the one closest to the position of the error
+/// is used.
+fn find_row(error: &SegmentError, rows: &[Entry]) -> Option<usize> {
+    let raw = error.raw.as_deref()?;
+    (0..rows.len())
+        .filter(|&i| rows[i].raw.as_deref() == Some(raw))
+        .min_by_key(|&i| i.abs_diff(error.pos))
+}
+
+fn describe_segment(error: &SegmentError) -> String {
+    let mut parts = vec![];
+    if let Some(syntax_error) = &error.syntax_error {
+        parts.push(syntax(syntax_error));
+    }
+    parts.extend(error.errors.iter().map(describe));
+
+    let segment = match &error.raw {
+        Some(raw) => raw.clone(),
+        None => format!("Segment {}", error.pos),
+    };
+    format!("{}: {}", segment, parts.join(", "))
+}
+
+fn describe(error: &Either<CompositeError, DataElementError>) -> String {
+    let data_element = |prefix: String, error: &DataElementError| {
+        error
+            .syntax_errors
+            .iter()
+            .map(|e| format!("{}{}: {}", prefix, error.pos + 1, syntax(e)))
+            .collect::<Vec<_>>()
+    };
+
+    let parts = match error {
+        Either::Left(error) => {
+            let mut parts = vec![];
+            if let Some(syntax_error) = &error.syntax_error {
+                parts.push(format!(
+                    "{}: {}",
+                    error.pos + 1,
+                    syntax(syntax_error)
+                ));
+            }
+            for e in &error.errors {
+                parts.extend(data_element(format!("{}:", error.pos + 1), e));
+            }
+            parts
+        }
+        Either::Right(error) => data_element(String::new(), error),
+    };
+    parts.join(", ")
+}
+
+fn syntax(error: &SyntaxError) -> String {
+    format!("{} {}", error.get_code(), error.get_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspect::Inspector;
+
+    #[test]
+    fn test_issues() {
+        let desc =
+            serde_json::from_str(include_str!("../APERAK.json")).unwrap();
+        let input = "UNA:+.? 'UNB+UNOC:3+9900467000000:500+9904590000002:500\
+                     +200307:0705+C3AAAAAAAAHKLC'\
+                     UNH+1+APERAK:D:07B:UN:2.1d'\
+                     BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+                     XYZ+1'\
+                     UNT+14+1'\
+                     UNZ+1+C3AAAAAAAAHKLC'";
+        let inspector = Inspector::new(input, Some(desc)).unwrap();
+
+        assert_eq!(inspector.rows.len(), 6);
+        let row = inspector.issues.iter().find_map(|issue| issue.row);
+        assert_eq!(row, Some(3));
+    }
+}
@@ -0,0 +1,45 @@
+//! A library to decode and validate EDIFACT messages of the edi@energy
+//! subset, which is used in the German energy market.
+//!
+//! Messages are decoded against a description of their Message
+//! Integration Guide (MIG), see [mig]. The descriptions can be generated
+//! from the MIG PDF files with the `spec` feature, which requires the
+//! command line program `pdftotext`.
+//!
+//! Without the `spec` feature, the crate does not access the file system
+//! or spawn processes, so it compiles to `wasm32-unknown-unknown`. The
+//! `wasm` feature provides JavaScript bindings for this target.
+//!
+//! The `ffi` feature provides a C interface, see [ffi], and the `python`
+//! feature a Python module.
+//!
+//! The `cli` feature builds the command line program `edifact` with its
+//! dependencies. Services, which only decode and encode interchanges,
+//! can disable the default features to leave it and the `spec` feature
+//! out.
+//!
+//! The `testing` feature provides generators of random interchanges
+//! conforming to a description for property-based tests.
+//!
+//! The `transport` feature provides the unwrapping of interchanges from
+//! their transport containers in [transport].
+//!
+//! The public API consists of [mig] with the descriptions, decoding,
+//! encoding, validation and their errors, the models of common messages
+//! in [domain], the use cases in [usecase] and the unwrapping of
+//! transport containers. The command line program
+//! `edifact` is built on this API only.
+
+pub mod domain;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod mig;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "transport")]
+pub mod transport;
+pub mod usecase;
+#[cfg(feature = "wasm")]
+pub mod wasm;
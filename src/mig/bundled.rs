@@ -0,0 +1,75 @@
+//! Descriptions bundled with the crate for the messages, which have no
+//! MIG in the repository to generate them from with `edifact mig parse`.
+//!
+//! The bundled descriptions cover the segments and qualifiers of ORDERS
+//! and ORDRSP as used in the Geschäftsdatenanfrage and the redispatch
+//! processes, which are mapped by [crate::domain::orders] and
+//! [crate::domain::ordrsp]. The one of AUTACK covers the acknowledgement
+//! of secured interchanges, see [crate::mig::security]. Descriptions in
+//! the directories of the configuration take precedence over them.
+use crate::mig::compact;
+use crate::mig::description::Interchange;
+
+/// The description of AUTACK in the compact YAML representation.
+pub const AUTACK: &str = include_str!("../../descriptions/AUTACK.yaml");
+
+/// The description of ORDERS in the compact YAML representation.
+pub const ORDERS: &str = include_str!("../../descriptions/ORDERS.yaml");
+
+/// The description of ORDRSP in the compact YAML representation.
+pub const ORDRSP: &str = include_str!("../../descriptions/ORDRSP.yaml");
+
+/// Returns the bundled description of AUTACK.
+pub fn autack() -> Interchange {
+    compact::from_yaml(AUTACK).expect("The bundled AUTACK is valid")
+}
+
+/// Returns the bundled description of ORDERS.
+pub fn orders() -> Interchange {
+    compact::from_yaml(ORDERS).expect("The bundled ORDERS is valid")
+}
+
+/// Returns the bundled description of ORDRSP.
+pub fn ordrsp() -> Interchange {
+    compact::from_yaml(ORDRSP).expect("The bundled ORDRSP is valid")
+}
+
+/// Returns all bundled descriptions.
+pub fn all() -> Vec<Interchange> {
+    vec![autack(), orders(), ordrsp()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::bundled;
+    use crate::mig::encode::encode;
+    use crate::mig::{self, DecodeOptions};
+
+    #[test]
+    fn test_all() {
+        let message_types: Vec<_> = bundled::all()
+            .into_iter()
+            .filter_map(|desc| desc.metadata?.message_type)
+            .collect();
+        assert_eq!(message_types, ["AUTACK", "ORDERS", "ORDRSP"]);
+    }
+
+    #[test]
+    fn test_autack() {
+        let input = fixtures::interchange(&["UNH+1+AUTACK:4:1:UN'\
+            USH+7+1+3+1+2+1'\
+            USB+1+1:20200307:0705'\
+            USX+C3AAAAAAAAHKLC+9904590000002:500+9900467000000:500'\
+            USY+1+1:3045022100E1'\
+            UST+1+5'\
+            UNT+7+1'"]);
+        let options = DecodeOptions::default();
+        let descs = bundled::all();
+        let interchange =
+            mig::decode(descs, &mut input.as_bytes(), &options).unwrap();
+        assert!(interchange.security.is_empty());
+
+        assert_eq!(encode(&interchange), input);
+    }
+}
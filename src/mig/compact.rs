@@ -0,0 +1,555 @@
+//! A compact representation of descriptions in YAML, which is easier to
+//! read and patch by hand than the JSON generated from the MIGs.
+//!
+//! Segment groups and composites are given by their label and data
+//! elements by their label, status and format in the notation of the
+//! MIGs, e.g.
+//!
+//! ```yaml
+//! - tag: BGM
+//!   counter: '0020'
+//!   number: 3
+//!   st: M
+//!   max-reps: 1
+//!   level: 0
+//!   name: Beginn der Nachricht
+//!   elements:
+//!   - composite: C002
+//!     name: Dokumenten-/Nachrichtenname
+//!     st: R
+//!     elements:
+//!     - element: '1001'
+//!       name: Dokumentenname, Code
+//!       st: R
+//!       format: an..3
+//!       choices:
+//!       - 313 Antwort auf eine Nachricht
+//! ```
+//!
+//! A data element contains text, unless it has a `type` of `integer` or
+//! `decimal`, a static `value` or `choices`. Choices without a comment
+//! are written as their code followed by their meaning.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mig::description as desc;
+use crate::mig::description::{Format, Size, St, Usage};
+use crate::mig::either::Either;
+
+#[derive(Debug)]
+pub enum Error {
+    Yaml(serde_yaml::Error),
+    /// The data element with the given label is invalid.
+    Invalid {
+        label: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Yaml(error) => error.fmt(f),
+            Error::Invalid { label, reason } => {
+                write!(f, "The data element {} is invalid: {}", label, reason)
+            }
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::Yaml(error)
+    }
+}
+
+/// Returns the given description in the compact representation.
+pub fn to_yaml(desc: &desc::Interchange) -> Result<String, Error> {
+    Ok(serde_yaml::to_string(&Interchange::from(desc))?)
+}
+
+/// Reads a description in the compact representation.
+pub fn from_yaml(input: &str) -> Result<desc::Interchange, Error> {
+    let interchange: Interchange = serde_yaml::from_str(input)?;
+    interchange.try_into()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Interchange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unb: Option<Segment>,
+    message: Message,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unz: Option<Segment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    valid_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    valid_until: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Message {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unh: Option<Segment>,
+    segments: Vec<Either<Segmentgroup, Segment>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unt: Option<Segment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Segmentgroup {
+    group: String,
+    counter: String,
+    st: St,
+    max_reps: u64,
+    level: u64,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    segments: Vec<Either<Segmentgroup, Segment>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Segment {
+    tag: String,
+    counter: String,
+    number: u64,
+    st: St,
+    max_reps: u64,
+    level: u64,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    condition: Option<u16>,
+    elements: Vec<Either<Composite, DataElement>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Composite {
+    composite: String,
+    name: String,
+    st: St,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_occurs: Option<usize>,
+    elements: Vec<DataElement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct DataElement {
+    element: String,
+    name: String,
+    st: St,
+    /// The format and length, e.g. `an..35`.
+    format: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    discriminator: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    condition: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_occurs: Option<usize>,
+    #[serde(
+        default,
+        rename = "type",
+        skip_serializing_if = "Option::is_none"
+    )]
+    kind: Option<Kind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<Choice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    choices: Option<Vec<Choice>>,
+    /// The comment of the usage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Integer,
+    Decimal,
+}
+
+/// A `Choice` is either written as its code followed by its meaning or,
+/// if it has a comment, in full.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Choice {
+    Short(String),
+    Full(desc::Choice),
+}
+
+// FROM DESCRIPTIONS
+
+impl From<&desc::Interchange> for Interchange {
+    fn from(desc: &desc::Interchange) -> Self {
+        Interchange {
+            metadata: desc.metadata.clone().map(|metadata| Metadata {
+                message_type: metadata.message_type,
+                version: metadata.version,
+                valid_from: metadata.valid_from,
+                valid_until: metadata.valid_until,
+                revision: metadata.revision,
+            }),
+            unb: Some((&desc.unb).into()),
+            message: Message {
+                unh: Some((&desc.message.unh).into()),
+                segments: segments(&desc.message.segments),
+                unt: Some((&desc.message.unt).into()),
+            },
+            unz: Some((&desc.unz).into()),
+        }
+    }
+}
+
+fn segments(
+    descs: &[Either<desc::Segmentgroup, desc::Segment>],
+) -> Vec<Either<Segmentgroup, Segment>> {
+    descs
+        .iter()
+        .map(|desc| match desc {
+            Either::Left(group) => Either::Left(Segmentgroup {
+                group: group.label.clone(),
+                counter: group.counter.clone(),
+                st: group.st,
+                max_reps: group.max_reps,
+                level: group.level,
+                name: group.name.clone(),
+                comment: group.comment.clone(),
+                segments: segments(&group.segments),
+            }),
+            Either::Right(segment) => Either::Right(segment.into()),
+        })
+        .collect()
+}
+
+impl From<&desc::Segment> for Segment {
+    fn from(desc: &desc::Segment) -> Self {
+        Segment {
+            tag: desc.tag.clone(),
+            counter: desc.counter.clone(),
+            number: desc.number,
+            st: desc.st,
+            max_reps: desc.max_reps,
+            level: desc.level,
+            name: desc.name.clone(),
+            comment: desc.comment.clone(),
+            condition: desc.condition,
+            elements: desc
+                .elements
+                .iter()
+                .map(|element| match element {
+                    Either::Left(composite) => Either::Left(Composite {
+                        composite: composite.label.clone(),
+                        name: composite.name.clone(),
+                        st: composite.st,
+                        max_occurs: composite.max_occurs,
+                        elements: composite
+                            .elements
+                            .iter()
+                            .map(DataElement::from)
+                            .collect(),
+                    }),
+                    Either::Right(data_element) => {
+                        Either::Right(data_element.into())
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&desc::DataElement> for DataElement {
+    fn from(desc: &desc::DataElement) -> Self {
+        let mut result = DataElement {
+            element: desc.label.clone(),
+            name: desc.name.clone(),
+            st: desc.st,
+            format: format(desc.format, desc.length),
+            discriminator: desc.discriminator,
+            condition: desc.condition,
+            max_occurs: desc.max_occurs,
+            kind: None,
+            value: None,
+            choices: None,
+            comment: None,
+        };
+        match &desc.usage {
+            Usage::Text { comment } => result.comment = comment.clone(),
+            Usage::Integer { comment } => {
+                result.kind = Some(Kind::Integer);
+                result.comment = comment.clone();
+            }
+            Usage::Decimal { comment } => {
+                result.kind = Some(Kind::Decimal);
+                result.comment = comment.clone();
+            }
+            Usage::OneOf { choices, comment } => {
+                result.choices =
+                    Some(choices.iter().map(Choice::from).collect());
+                result.comment = comment.clone();
+            }
+            Usage::Static { value, comment } => {
+                result.value = Some(value.into());
+                result.comment = comment.clone();
+            }
+        }
+        result
+    }
+}
+
+impl From<&desc::Choice> for Choice {
+    fn from(choice: &desc::Choice) -> Self {
+        let is_code = !choice.value.is_empty()
+            && !choice.value.contains(char::is_whitespace);
+        let is_meaning = |semantics: &str| {
+            !semantics.is_empty() && semantics.trim() == semantics
+        };
+        match (&choice.semantics, &choice.comment) {
+            (None, None) if is_code => Choice::Short(choice.value.clone()),
+            (Some(semantics), None) if is_code && is_meaning(semantics) => {
+                Choice::Short(format!("{} {}", choice.value, semantics))
+            }
+            _ => Choice::Full(choice.clone()),
+        }
+    }
+}
+
+/// Returns the format and length in the notation of the MIGs.
+pub(crate) fn format(format: Format, length: usize) -> String {
+    let format = match format {
+        Format::Alphanumeric(Size::Exactly) => "an",
+        Format::Alphanumeric(Size::AtMost) => "an..",
+        Format::Alpha(Size::Exactly) => "a",
+        Format::Alpha(Size::AtMost) => "a..",
+        Format::Numeric(Size::Exactly) => "n",
+        Format::Numeric(Size::AtMost) => "n..",
+    };
+    format!("{}{}", format, length)
+}
+
+// INTO DESCRIPTIONS
+
+impl TryFrom<Interchange> for desc::Interchange {
+    type Error = Error;
+
+    fn try_from(value: Interchange) -> Result<Self, Self::Error> {
+        Ok(desc::Interchange {
+            unb: service(value.unb, desc::service::unb)?,
+            message: desc::Message {
+                unh: service(value.message.unh, desc::service::unh)?,
+                segments: descs(value.message.segments)?,
+                unt: service(value.message.unt, desc::service::unt)?,
+            },
+            unz: service(value.unz, desc::service::unz)?,
+            metadata: value.metadata.map(|metadata| desc::Metadata {
+                message_type: metadata.message_type,
+                version: metadata.version,
+                valid_from: metadata.valid_from,
+                valid_until: metadata.valid_until,
+                revision: metadata.revision,
+            }),
+        })
+    }
+}
+
+/// Returns the given service segment or, if it has been left out, the
+/// one of the syntax standard.
+fn service(
+    segment: Option<Segment>,
+    default: fn() -> desc::Segment,
+) -> Result<desc::Segment, Error> {
+    segment.map_or_else(|| Ok(default()), desc::Segment::try_from)
+}
+
+fn descs(
+    values: Vec<Either<Segmentgroup, Segment>>,
+) -> Result<Vec<Either<desc::Segmentgroup, desc::Segment>>, Error> {
+    values
+        .into_iter()
+        .map(|value| match value {
+            Either::Left(group) => Ok(Either::Left(desc::Segmentgroup {
+                counter: group.counter,
+                label: group.group,
+                st: group.st,
+                max_reps: group.max_reps,
+                level: group.level,
+                name: group.name,
+                comment: group.comment,
+                segments: descs(group.segments)?,
+            })),
+            Either::Right(segment) => Ok(Either::Right(segment.try_into()?)),
+        })
+        .collect()
+}
+
+impl TryFrom<Segment> for desc::Segment {
+    type Error = Error;
+
+    fn try_from(value: Segment) -> Result<Self, Self::Error> {
+        let elements = value
+            .elements
+            .into_iter()
+            .map(|element| match element {
+                Either::Left(composite) => Ok(Either::Left(desc::Composite {
+                    label: composite.composite,
+                    name: composite.name,
+                    st: composite.st,
+                    elements: composite
+                        .elements
+                        .into_iter()
+                        .map(desc::DataElement::try_from)
+                        .collect::<Result<_, _>>()?,
+                    max_occurs: composite.max_occurs,
+                })),
+                Either::Right(data_element) => {
+                    Ok(Either::Right(data_element.try_into()?))
+                }
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(desc::Segment {
+            counter: value.counter,
+            number: value.number,
+            tag: value.tag,
+            st: value.st,
+            max_reps: value.max_reps,
+            level: value.level,
+            name: value.name,
+            comment: value.comment,
+            elements,
+            condition: value.condition,
+        })
+    }
+}
+
+impl TryFrom<DataElement> for desc::DataElement {
+    type Error = Error;
+
+    fn try_from(value: DataElement) -> Result<Self, Self::Error> {
+        let invalid = |reason: &str| Error::Invalid {
+            label: value.element.clone(),
+            reason: reason.to_string(),
+        };
+        let (format, length) = parse_format(&value.format)
+            .ok_or_else(|| invalid("the format is not like an..35"))?;
+        let comment = value.comment.clone();
+        let usage = match (value.kind, &value.value, &value.choices) {
+            (None, None, None) => Usage::Text { comment },
+            (Some(Kind::Integer), None, None) => Usage::Integer { comment },
+            (Some(Kind::Decimal), None, None) => Usage::Decimal { comment },
+            (None, Some(choice), None) => {
+                Usage::Static { value: choice.into(), comment }
+            }
+            (None, None, Some(choices)) => Usage::OneOf {
+                choices: choices.iter().map(desc::Choice::from).collect(),
+                comment,
+            },
+            _ => {
+                return Err(invalid(
+                    "only one of type, value and choices may be given",
+                ))
+            }
+        };
+
+        Ok(desc::DataElement {
+            label: value.element,
+            name: value.name,
+            st: value.st,
+            format,
+            length,
+            usage,
+            discriminator: value.discriminator,
+            condition: value.condition,
+            max_occurs: value.max_occurs,
+        })
+    }
+}
+
+impl From<&Choice> for desc::Choice {
+    fn from(choice: &Choice) -> Self {
+        match choice {
+            Choice::Short(short) => {
+                let (value, semantics) = match short.split_once(' ') {
+                    Some((value, semantics)) => {
+                        (value, Some(semantics.to_string()))
+                    }
+                    None => (short.as_str(), None),
+                };
+                desc::Choice {
+                    value: value.to_string(),
+                    semantics,
+                    comment: None,
+                }
+            }
+            Choice::Full(choice) => choice.clone(),
+        }
+    }
+}
+
+/// Parses a format and length in the notation of the MIGs, e.g. `an..35`.
+pub(crate) fn parse_format(input: &str) -> Option<(Format, usize)> {
+    let digits = input.find(|c: char| c.is_ascii_digit())?;
+    let (format, length) = input.split_at(digits);
+    let format = match format {
+        "an" => Format::Alphanumeric(Size::Exactly),
+        "an.." => Format::Alphanumeric(Size::AtMost),
+        "a" => Format::Alpha(Size::Exactly),
+        "a.." => Format::Alpha(Size::AtMost),
+        "n" => Format::Numeric(Size::Exactly),
+        "n.." => Format::Numeric(Size::AtMost),
+        _ => return None,
+    };
+    Some((format, length.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::compact::{from_yaml, to_yaml};
+
+    #[test]
+    fn test_roundtrip() {
+        for desc in [fixtures::aperak(), fixtures::contrl()] {
+            let yaml = to_yaml(&desc).unwrap();
+
+            let parsed = from_yaml(&yaml).unwrap();
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                serde_json::to_value(&desc).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_data_element() {
+        let desc = fixtures::aperak();
+        let yaml =
+            to_yaml(&desc).unwrap().replacen("format: a4", "format: x4", 1);
+        let error = from_yaml(&yaml).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The data element 0001 is invalid: the format is not like an..35"
+        );
+    }
+}
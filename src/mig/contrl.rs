@@ -0,0 +1,204 @@
+//! Mapping of the errors found while decoding an interchange to the
+//! segments of a CONTRL reporting them, for users who build the CONTRL
+//! themselves.
+//!
+//! An [InterchangeError] is mapped to a UCI, followed by a UCM for every
+//! erroneous message, a UCS for every erroneous segment of that message
+//! and a UCD for every erroneous data element or composite of that
+//! segment:
+//!
+//! - The code 0085 is the code of the first syntax error reported for
+//!   the interchange, message, segment or data element, if any.
+//! - UCS 0096 counts the segments of the message, where UNH is 1.
+//! - The element position 0098 counts the elements of the segment, where
+//!   the tag is 1, and the component position 0104 counts the components
+//!   of a composite starting with 1.
+//! - An error of a service segment is reported in the UCI for UNB and UNZ
+//!   and in the UCM for UNH and UNT with its tag in 0013.
+//!
+//! Received CONTRL messages are interpreted the other way around by
+//! [report](crate::mig::report).
+use crate::mig::either::Either;
+use crate::mig::error::{
+    CompositeError, DataElementError, InterchangeError, MessageError,
+    SegmentError, ServiceSegmentError, SyntaxError,
+};
+
+/// The action code 0083 of a rejected interchange or message.
+pub const REJECTED: &str = "4";
+/// The action code 0083 of an interchange, which has been acknowledged,
+/// because only some of its messages are rejected.
+pub const ACKNOWLEDGED: &str = "7";
+
+/// The values of a segment of a CONTRL, see the [module](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContrlSegment {
+    /// Either UCI, UCM, UCS or UCD.
+    pub tag: &'static str,
+    /// The action code 0083 of a UCI or UCM.
+    pub action: Option<&'static str>,
+    /// The syntax error code 0085.
+    pub code: Option<u64>,
+    /// The tag of the erroneous service segment 0013 of a UCI or UCM.
+    pub service_segment: Option<String>,
+    /// The position of the erroneous segment 0096 of a UCS.
+    pub segment: Option<usize>,
+    /// The position of the erroneous element 0098.
+    pub element: Option<usize>,
+    /// The position of the erroneous component 0104.
+    pub component: Option<usize>,
+}
+
+impl ContrlSegment {
+    fn new(tag: &'static str) -> Self {
+        ContrlSegment {
+            tag,
+            action: None,
+            code: None,
+            service_segment: None,
+            segment: None,
+            element: None,
+            component: None,
+        }
+    }
+}
+
+/// Returns the segments of a CONTRL reporting the given error in order,
+/// see the [module](self).
+pub fn segments(error: &InterchangeError) -> Vec<ContrlSegment> {
+    let mut uci = ContrlSegment::new("UCI");
+    uci.action = Some(match error.service_segment_error {
+        Some(_) => REJECTED,
+        None if error.message_errors.is_empty() => REJECTED,
+        None => ACKNOWLEDGED,
+    });
+    if let Some(error) = &error.service_segment_error {
+        service_segment(error, &mut uci);
+    }
+    let mut result = vec![uci];
+    for error in &error.message_errors {
+        message(error, &mut result);
+    }
+    result
+}
+
+fn message(error: &MessageError, result: &mut Vec<ContrlSegment>) {
+    let mut ucm = ContrlSegment::new("UCM");
+    ucm.action = Some(REJECTED);
+    if let Some(error) = &error.service_segment_error {
+        service_segment(error, &mut ucm);
+    }
+    result.push(ucm);
+    for segment_error in &error.segment_errors {
+        segment(segment_error, error.pos, result);
+    }
+}
+
+fn service_segment(error: &ServiceSegmentError, result: &mut ContrlSegment) {
+    let ucd = element(&error.error);
+    result.service_segment = Some(error.tag.clone());
+    result.code = ucd.code;
+    result.element = ucd.element;
+    result.component = ucd.component;
+}
+
+fn segment(error: &SegmentError, unh: usize, result: &mut Vec<ContrlSegment>) {
+    let mut ucs = ContrlSegment::new("UCS");
+    // The position counts the UNH of the message as 1.
+    ucs.segment = Some((error.pos + 1).saturating_sub(unh));
+    ucs.code = error.syntax_error.as_ref().map(SyntaxError::get_code);
+    result.push(ucs);
+    result.extend(error.errors.iter().map(element));
+}
+
+fn element(error: &Either<CompositeError, DataElementError>) -> ContrlSegment {
+    let mut ucd = ContrlSegment::new("UCD");
+    match error {
+        Either::Left(composite) => {
+            ucd.element = Some(composite.pos + 2);
+            match (&composite.syntax_error, composite.errors.first()) {
+                (Some(error), _) => ucd.code = Some(error.get_code()),
+                (None, Some(error)) => {
+                    ucd.code = first_code(error);
+                    ucd.component = Some(error.pos + 1);
+                }
+                (None, None) => {}
+            }
+        }
+        Either::Right(error) => {
+            ucd.element = Some(error.pos + 2);
+            ucd.code = first_code(error);
+        }
+    }
+    ucd
+}
+
+fn first_code(error: &DataElementError) -> Option<u64> {
+    error.syntax_errors.first().map(SyntaxError::get_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::contrl::{segments, ContrlSegment, ACKNOWLEDGED};
+    use crate::mig::decode::Error;
+    use crate::mig::{self, DecodeOptions};
+
+    #[test]
+    fn test_segments() {
+        let desc = fixtures::aperak();
+        let input = &fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705'\
+             NAD+MS+9900467000000::293+X'\
+             NAD+MR+9904590000002::293'\
+             ERC+Z29'",
+        );
+        let options = DecodeOptions::default();
+        let decode = |input: &str| {
+            let desc = desc.clone();
+            match mig::decode(vec![desc], &mut input.as_bytes(), &options) {
+                Err(Error::Mig(error)) => error,
+                result => panic!("Expected errors, got {:?}", result),
+            }
+        };
+        let error = decode(input);
+
+        let at = |tag, segment, element, component, code| ContrlSegment {
+            tag,
+            action: None,
+            code,
+            service_segment: None,
+            segment,
+            element,
+            component,
+        };
+        let uci = ContrlSegment {
+            action: Some(ACKNOWLEDGED),
+            ..ContrlSegment::new("UCI")
+        };
+        let ucm =
+            ContrlSegment { action: Some("4"), ..ContrlSegment::new("UCM") };
+        assert_eq!(
+            segments(&error),
+            vec![
+                uci,
+                ucm,
+                at("UCS", Some(3), None, None, None),
+                at("UCD", None, Some(2), Some(3), Some(13)),
+                at("UCS", Some(4), None, None, Some(16)),
+            ]
+        );
+
+        // Security segments are counted like any other segment.
+        let secured = input
+            .replace("'BGM", "'USH+7+1+3+1+2+1++++1:20200307:070500'USA+1'BGM")
+            .replace("'UNT+7", "'UST+1+2'USR+1:30450221'UNT+11");
+        let positions: Vec<_> = segments(&decode(&secured))
+            .into_iter()
+            .filter(|segment| segment.tag == "UCS")
+            .map(|segment| segment.segment)
+            .collect();
+        assert_eq!(positions, vec![Some(5), Some(6)]);
+    }
+}
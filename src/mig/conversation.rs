@@ -0,0 +1,240 @@
+//! Linking of messages, which refer to each other, e.g. an APERAK
+//! rejecting a UTILMD or a CONTRL acknowledging an interchange.
+//!
+//! Messages are identified by their document number in BGM 1004 and the
+//! transaction numbers in IDE 7402. Responses refer to them by the RFF
+//! qualifiers in [QUALIFIERS]. A CONTRL refers to a whole interchange by
+//! the reference in UCI 0020.
+//!
+//! ```
+//! # use edifact::mig;
+//! # use edifact::mig::conversation::{self, Graph};
+//! let utilmd = "UNA:+.? 'UNB+UNOC:3+9900467000000:500+9904590000002:500\
+//!               +200307:0705+REF1'UNH+1+UTILMD:D:11A:UN:5.2a'\
+//!               BGM+E01+DOC1'IDE+24+TX1'UNT+4+1'UNZ+1+REF1'";
+//! let aperak = "UNA:+.? 'UNB+UNOC:3+9904590000002:500+9900467000000:500\
+//!               +200307:0710+REF2'UNH+1+APERAK:D:07B:UN:2.1d'\
+//!               BGM+313+DOC2'RFF+ACE:DOC1'UNT+4+1'UNZ+1+REF2'";
+//! let mut messages = vec![];
+//! for input in [utilmd, aperak] {
+//!     let interchange = mig::parse(&mut input.as_bytes()).unwrap();
+//!     messages.extend(conversation::messages(&interchange));
+//! }
+//! let graph = Graph::new(messages);
+//! let responses = graph.responses(graph.find("TX1")[0]);
+//! assert_eq!(graph.messages[responses[0]].message_type, "APERAK");
+//! ```
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mig::decode::parser::value::Interchange;
+use crate::mig::decode::routing::component;
+
+/// The RFF qualifiers, which refer to other messages: the document
+/// number of a related message (ACE), the reference of a previous
+/// message (ACW), the original reference of the sender (AGO) and the
+/// transaction reference (TN).
+pub const QUALIFIERS: &[&str] = &["ACE", "ACW", "AGO", "TN"];
+
+/// The qualifier of a reference of a CONTRL to an interchange.
+pub const UCI: &str = "UCI";
+
+/// A `Message` with the identifiers other messages refer to it by and
+/// the references to other messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    /// The reference of the interchange from UNB 0020.
+    pub interchange: Option<String>,
+    /// The message reference from UNH 0062.
+    pub reference: String,
+    /// The message type from UNH 0065, e.g. `UTILMD`.
+    pub message_type: String,
+    /// The document number and the transaction numbers.
+    pub ids: Vec<String>,
+    pub references: Vec<Reference>,
+}
+
+/// A `Reference` to another message, e.g. `RFF+ACE:DOC1`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reference {
+    pub qualifier: String,
+    pub value: String,
+}
+
+/// Returns the messages of the given interchange in order.
+pub fn messages(interchange: &Interchange) -> Vec<Message> {
+    let reference = interchange.routing().reference;
+    let mut messages: Vec<Message> = vec![];
+    for segment in &interchange.segments {
+        let value = |element, at| component(segment, element, at);
+        if segment.tag.value == "UNH" {
+            messages.push(Message {
+                interchange: reference.clone(),
+                reference: value(0, 0).unwrap_or_default(),
+                message_type: value(1, 0).unwrap_or_default(),
+                ids: vec![],
+                references: vec![],
+            });
+            continue;
+        }
+        let Some(message) = messages.last_mut() else {
+            continue;
+        };
+        match segment.tag.value.as_str() {
+            "BGM" | "IDE" => message.ids.extend(value(1, 0)),
+            "RFF" => {
+                if let (Some(qualifier), Some(value)) =
+                    (value(0, 0), value(0, 1))
+                {
+                    if QUALIFIERS.contains(&qualifier.as_str()) {
+                        message
+                            .references
+                            .push(Reference { qualifier, value });
+                    }
+                }
+            }
+            "UCI" => {
+                message.references.extend(value(0, 0).map(|value| Reference {
+                    qualifier: UCI.to_string(),
+                    value,
+                }))
+            }
+            _ => {}
+        }
+    }
+    messages
+}
+
+/// A `Link` from a message to the one it refers to by their positions
+/// in [Graph::messages].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub from: usize,
+    pub to: usize,
+    pub qualifier: String,
+}
+
+/// A `Graph` of messages linked by their references.
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    pub messages: Vec<Message>,
+    pub links: Vec<Link>,
+}
+
+impl Graph {
+    /// Links the given messages. Messages with the same interchange and
+    /// message reference, e.g. an interchange archived twice, are only
+    /// added once.
+    pub fn new(messages: impl IntoIterator<Item = Message>) -> Graph {
+        let mut graph = Graph::default();
+        let mut seen = HashSet::new();
+        for message in messages {
+            let key = (message.interchange.clone(), message.reference.clone());
+            if seen.insert(key) {
+                graph.messages.push(message);
+            }
+        }
+
+        for (from, message) in graph.messages.iter().enumerate() {
+            for reference in &message.references {
+                let targets =
+                    graph.messages.iter().enumerate().filter(|(to, other)| {
+                        *to != from
+                            && if reference.qualifier == UCI {
+                                other.interchange.as_ref()
+                                    == Some(&reference.value)
+                            } else {
+                                other.ids.contains(&reference.value)
+                            }
+                    });
+                graph.links.extend(targets.map(|(to, _)| Link {
+                    from,
+                    to,
+                    qualifier: reference.qualifier.clone(),
+                }));
+            }
+        }
+        graph
+    }
+
+    /// Returns the positions of the messages with the given document or
+    /// transaction number or interchange reference.
+    pub fn find(&self, id: &str) -> Vec<usize> {
+        let matches = |message: &Message| {
+            message.interchange.as_deref() == Some(id)
+                || message.ids.iter().any(|other| other == id)
+        };
+        (0..self.messages.len())
+            .filter(|i| matches(&self.messages[*i]))
+            .collect()
+    }
+
+    /// Returns the positions of the messages referring to the given one.
+    pub fn responses(&self, message: usize) -> Vec<usize> {
+        let links = self.links.iter().filter(|link| link.to == message);
+        links.map(|link| link.from).collect()
+    }
+
+    /// Returns the positions of all messages directly or indirectly
+    /// linked with the given one in order, including itself.
+    pub fn conversation(&self, message: usize) -> Vec<usize> {
+        let mut result = HashSet::from([message]);
+        let mut pending = vec![message];
+        while let Some(current) = pending.pop() {
+            for link in &self.links {
+                let other = match (link.from == current, link.to == current) {
+                    (true, _) => link.to,
+                    (_, true) => link.from,
+                    _ => continue,
+                };
+                if result.insert(other) {
+                    pending.push(other);
+                }
+            }
+        }
+        let mut result: Vec<usize> = result.into_iter().collect();
+        result.sort();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::conversation::{messages, Graph};
+    use crate::mig::decode::parser;
+
+    const UTILMD: &str = "UNA:+.? '\
+        UNB+UNOC:3+9900467000000:500+9904590000002:500+200307:0705+REF1'\
+        UNH+1+UTILMD:D:11A:UN:5.2a'BGM+E01+DOC1'IDE+24+TX1'UNT+4+1'\
+        UNZ+1+REF1'";
+
+    const APERAK: &str = "UNA:+.? '\
+        UNB+UNOC:3+9904590000002:500+9900467000000:500+200307:0710+REF2'\
+        UNH+1+APERAK:D:07B:UN:2.1d'BGM+313+DOC2'RFF+ACE:DOC1'\
+        RFF+Z13:55001'UNT+5+1'UNZ+1+REF2'";
+
+    const CONTRL: &str = "UNA:+.? '\
+        UNB+UNOC:3+9900467000000:500+9904590000002:500+200307:0715+REF3'\
+        UNH+1+CONTRL:D:3:UN:2.0a'\
+        UCI+REF2+9904590000002:500+9900467000000:500+7'UNT+3+1'\
+        UNZ+1+REF3'";
+
+    #[test]
+    fn test_conversation() {
+        let mut all = vec![];
+        for input in [UTILMD, APERAK, UTILMD, CONTRL] {
+            let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+            all.extend(messages(&interchange));
+        }
+        assert_eq!(all[1].references.len(), 1);
+
+        let graph = Graph::new(all);
+        assert_eq!(graph.messages.len(), 3);
+        assert_eq!(graph.find("DOC1"), vec![0]);
+        assert_eq!(graph.responses(0), vec![1]);
+        assert_eq!(graph.responses(1), vec![2]);
+        assert_eq!(graph.conversation(2), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Coverage of a MIG by a corpus of interchanges, e.g. to build a test
+//! suite or to spot unused branches before a format change.
+//!
+//! A [Coverage] counts, how often each segment, segment group and code
+//! of a description has been seen in the interchanges added. Segments
+//! are identified by their number in the MIG and segment groups by their
+//! first segment, so variants of a group with the same label, e.g. the
+//! SG3 for sender and receiver, are counted separately. Only codes of
+//! data elements with a choice of values are counted.
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::mig::decode::value;
+use crate::mig::description::{self as desc, Usage};
+use crate::mig::either::Either;
+
+/// The `Kind` of an [Item] of a coverage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Segment,
+    Group,
+    Code,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Segment => write!(f, "segment"),
+            Kind::Group => write!(f, "group"),
+            Kind::Code => write!(f, "code"),
+        }
+    }
+}
+
+/// An `Item` of a description and how often it has been seen. The path
+/// names it by its segment groups, tag and qualifier, e.g.
+/// `SG4/SG5/RFF+Z13`. The path of a code is followed by the position of
+/// its element and component starting at 1, its label and value, e.g.
+/// `SG4/ERC 1:1 9321=Z29`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub kind: Kind,
+    pub path: String,
+    pub count: usize,
+}
+
+/// A segment is identified by its tag and number in the MIG, as the
+/// service segments are numbered separately.
+type Id = (String, u64);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Segment(Id),
+    Group(Id),
+    /// A code by the segment, the position of its element and component
+    /// and its value.
+    Code(Id, usize, Option<usize>, String),
+}
+
+/// The `Coverage` of a description, see the [module](self).
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    /// The items in the order of the description.
+    items: Vec<Item>,
+    keys: HashMap<Key, usize>,
+    interchanges: usize,
+}
+
+impl Coverage {
+    /// Creates a coverage of the given description, in which nothing has
+    /// been seen yet.
+    pub fn new(desc: &desc::Interchange) -> Coverage {
+        let mut coverage =
+            Coverage { items: vec![], keys: HashMap::new(), interchanges: 0 };
+        let message = &desc.message;
+        coverage.insert_segment(&desc.unb, "");
+        coverage.insert_segment(&message.unh, "");
+        coverage.insert_values(&message.segments, "");
+        coverage.insert_segment(&message.unt, "");
+        coverage.insert_segment(&desc.unz, "");
+        coverage
+    }
+
+    /// Counts the segments, segment groups and codes of the given
+    /// interchange, which has been decoded with the description.
+    pub fn add(&mut self, interchange: &value::Interchange) {
+        self.interchanges += 1;
+        self.add_values(&interchange.segments);
+    }
+
+    /// Returns the number of interchanges added.
+    pub fn interchanges(&self) -> usize {
+        self.interchanges
+    }
+
+    /// Returns all items in the order of the description.
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// Returns the items, which have never been seen.
+    pub fn unseen(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|item| item.count == 0)
+    }
+
+    fn insert(&mut self, key: Key, kind: Kind, path: String) {
+        // Items described twice are only listed once.
+        if !self.keys.contains_key(&key) {
+            self.keys.insert(key, self.items.len());
+            self.items.push(Item { kind, path, count: 0 });
+        }
+    }
+
+    fn insert_values(
+        &mut self,
+        values: &[Either<desc::Segmentgroup, desc::Segment>],
+        path: &str,
+    ) {
+        for value in values {
+            match value {
+                Either::Left(group) => {
+                    let path = join(path, &group.label);
+                    if let Some(first) = first_segment(&group.segments) {
+                        let id = (first.tag.clone(), first.number);
+                        self.insert(Key::Group(id), Kind::Group, path.clone());
+                    }
+                    self.insert_values(&group.segments, &path);
+                }
+                Either::Right(segment) => self.insert_segment(segment, path),
+            }
+        }
+    }
+
+    fn insert_segment(&mut self, segment: &desc::Segment, path: &str) {
+        let id = (segment.tag.clone(), segment.number);
+        let path = join(path, &name(segment));
+        self.insert(Key::Segment(id.clone()), Kind::Segment, path.clone());
+        for (i, element) in segment.elements.iter().enumerate() {
+            let data_elements: Vec<(Option<usize>, &desc::DataElement)> =
+                match element {
+                    Either::Left(composite) => composite
+                        .elements
+                        .iter()
+                        .enumerate()
+                        .map(|(j, data_element)| (Some(j), data_element))
+                        .collect(),
+                    Either::Right(data_element) => vec![(None, data_element)],
+                };
+            for (j, data_element) in data_elements {
+                let Usage::OneOf { choices, .. } = &data_element.usage else {
+                    continue;
+                };
+                for choice in choices {
+                    let key =
+                        Key::Code(id.clone(), i, j, choice.value.clone());
+                    let position = match j {
+                        Some(j) => format!("{}:{}", i + 1, j + 1),
+                        None => (i + 1).to_string(),
+                    };
+                    let path = format!(
+                        "{} {} {}={}",
+                        path, position, data_element.label, choice.value
+                    );
+                    self.insert(key, Kind::Code, path);
+                }
+            }
+        }
+    }
+
+    fn count(&mut self, key: &Key) {
+        if let Some(i) = self.keys.get(key) {
+            self.items[*i].count += 1;
+        }
+    }
+
+    fn add_values(
+        &mut self,
+        values: &[Either<value::Segmentgroup, value::Segment>],
+    ) {
+        for value in values {
+            match value {
+                Either::Left(group) => {
+                    if let Some(first) = first_segment(&group.segments) {
+                        let id = (first.tag.clone(), first.number);
+                        self.count(&Key::Group(id));
+                    }
+                    self.add_values(&group.segments);
+                }
+                Either::Right(segment) => self.add_segment(segment),
+            }
+        }
+    }
+
+    fn add_segment(&mut self, segment: &value::Segment) {
+        let id = (segment.tag.clone(), segment.number);
+        self.count(&Key::Segment(id.clone()));
+        for element in &segment.elements {
+            let data_elements = match element {
+                Either::Left(composite) => composite
+                    .elements
+                    .iter()
+                    .map(|data_element| {
+                        (
+                            composite.index,
+                            Some(data_element.index),
+                            data_element,
+                        )
+                    })
+                    .collect(),
+                Either::Right(data_element) => {
+                    vec![(data_element.index, None, data_element)]
+                }
+            };
+            for (i, j, data_element) in data_elements {
+                if let Some(text) = data_element.text() {
+                    let key = Key::Code(id.clone(), i, j, text.to_string());
+                    self.count(&key);
+                }
+            }
+        }
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for kind in [Kind::Segment, Kind::Group, Kind::Code] {
+            let items = self.items.iter().filter(|item| item.kind == kind);
+            let (seen, all) = items.fold((0, 0), |(seen, all), item| {
+                (seen + usize::from(item.count > 0), all + 1)
+            });
+            writeln!(f, "{}s: {} of {} seen", kind, seen, all)?;
+        }
+        for item in &self.items {
+            writeln!(f, "{:>8} {} {}", item.count, item.kind, item.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the first segment of a segment group, which identifies it.
+fn first_segment<G, S>(values: &[Either<G, S>]) -> Option<&S> {
+    match values.first()? {
+        Either::Right(segment) => Some(segment),
+        Either::Left(_) => None,
+    }
+}
+
+/// Returns the tag of the segment followed by its qualifier, if its
+/// first data element has a single value, e.g. `NAD+MS`.
+fn name(segment: &desc::Segment) -> String {
+    let first = segment.elements.first().and_then(|element| match element {
+        Either::Left(composite) => composite.elements.first(),
+        Either::Right(data_element) => Some(data_element),
+    });
+    match first.map(|data_element| &data_element.usage) {
+        Some(Usage::Static { value, .. }) => {
+            format!("{}+{}", segment.tag, value.value)
+        }
+        _ => segment.tag.clone(),
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", path, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::coverage::{Coverage, Kind};
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_coverage() {
+        let desc = fixtures::aperak();
+        let mut coverage = Coverage::new(&desc);
+        let options = DecodeOptions::default();
+        let interchange = mig::decode(
+            vec![desc],
+            &mut fixtures::APERAK.as_bytes(),
+            &options,
+        )
+        .unwrap();
+        coverage.add(&interchange);
+        coverage.add(&interchange);
+
+        let count = |kind: Kind, path: &str| {
+            let item = coverage
+                .items()
+                .iter()
+                .find(|item| item.kind == kind && item.path == path);
+            item.map(|item| item.count)
+        };
+        assert_eq!(coverage.interchanges(), 2);
+        assert_eq!(count(Kind::Segment, "BGM+313"), Some(2));
+        assert_eq!(count(Kind::Group, "SG4"), Some(2));
+        assert_eq!(count(Kind::Segment, "SG2/RFF+ACE"), Some(0));
+        assert_eq!(count(Kind::Code, "SG4/ERC 1:1 9321=Z29"), Some(2));
+        assert!(coverage.unseen().any(|item| item.path == "SG2"));
+    }
+}
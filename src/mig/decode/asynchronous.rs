@@ -0,0 +1,145 @@
+//! Asynchronous decoding of interchanges, e.g. inside an AS4 or SMTP
+//! receiver, which must not block its worker threads on reading.
+//!
+//! This module is only available with the `tokio` feature.
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt};
+
+use crate::mig::decode::options::DecodeOptions;
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::decode::{self, value, Error, Known};
+
+/// Decodes the given asynchronous input with one of the `known`
+/// descriptions, like [decode::decode].
+pub async fn decode_async<'a, R: AsyncRead + Unpin>(
+    known: impl Into<Known<'a>>,
+    input: &mut R,
+    options: &DecodeOptions,
+) -> Result<value::Interchange, Error> {
+    let mut contents = vec![];
+    input.read_to_end(&mut contents).await?;
+    decode::decode(known, &mut contents.as_slice(), options)
+}
+
+/// A `SegmentReader` reads the segments of an interchange one at a time,
+/// without reading the whole input into memory.
+///
+/// The separators are taken from the UNA segment, if the input starts
+/// with one, otherwise the default ones are used.
+pub struct SegmentReader<R> {
+    input: R,
+    una: UNA,
+    started: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> SegmentReader<R> {
+    /// Create a new reader of the given input.
+    pub fn new(input: R) -> Self {
+        SegmentReader { input, una: UNA::default(), started: false }
+    }
+
+    /// Returns the next segment without its terminator, or `None` at the
+    /// end of the input. The UNA segment is not returned.
+    ///
+    /// Escape characters are kept, so the segment can be parsed like any
+    /// other input.
+    pub async fn next_segment(&mut self) -> io::Result<Option<String>> {
+        if !self.started {
+            self.started = true;
+            self.read_una().await?;
+        }
+
+        let terminator = ascii(self.una.segment_sep)?;
+        let escape = ascii(self.una.escape)?;
+        let mut segment = vec![];
+        loop {
+            let read = self.input.read_until(terminator, &mut segment).await?;
+            if read == 0 {
+                break;
+            }
+
+            // The terminator is escaped, if it is preceded by an odd
+            // number of escape characters.
+            let content = &segment[..segment.len() - 1];
+            let escapes =
+                content.iter().rev().take_while(|b| **b == escape).count();
+            if segment.last() == Some(&terminator) && escapes % 2 == 0 {
+                segment.pop();
+                break;
+            }
+        }
+
+        let segment = String::from_utf8(segment)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let segment = segment.trim_start();
+        if segment.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(segment.to_string()))
+        }
+    }
+
+    async fn read_una(&mut self) -> io::Result<()> {
+        if !self.input.fill_buf().await?.starts_with(b"UNA") {
+            return Ok(());
+        }
+
+        let mut una = [0u8; 9];
+        self.input.read_exact(&mut una).await?;
+        let chars: Vec<char> = std::str::from_utf8(&una[3..])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .chars()
+            .collect();
+        if chars.len() != 6 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The UNA segment contains non-ASCII characters",
+            ));
+        }
+        let una = UNA::new(
+            chars[0], chars[1], chars[2], chars[3], chars[4], chars[5],
+        );
+        if let Some(pos) = una.find_invalid() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("The service character at {} of UNA is invalid", pos),
+            ));
+        }
+        self.una = una;
+        Ok(())
+    }
+}
+
+/// Returns the given separator as a byte. Only ASCII separators are
+/// supported by the reader.
+fn ascii(c: char) -> io::Result<u8> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("The separator '{}' is not an ASCII character", c),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::decode::asynchronous::SegmentReader;
+
+    #[tokio::test]
+    async fn test_next_segment() {
+        let input = "UNA:+.? #UNB+UNOC:3#\nFTX+AAO+++Preis?#?? 5#UNZ+1#";
+        let mut reader = SegmentReader::new(input.as_bytes());
+
+        let mut segments = vec![];
+        while let Some(segment) = reader.next_segment().await.unwrap() {
+            segments.push(segment);
+        }
+        assert_eq!(
+            segments,
+            vec!["UNB+UNOC:3", "FTX+AAO+++Preis?#?? 5", "UNZ+1"]
+        );
+    }
+}
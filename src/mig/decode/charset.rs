@@ -0,0 +1,191 @@
+//! Decoding of the bytes of an input into text with its character set.
+//!
+//! By default, the input is read as UTF-8, if it is valid UTF-8, which
+//! covers ASCII as well. Otherwise, the character set is taken from the
+//! syntax identifier in UNB 0001, e.g. ISO-8859-1 for UNOC, which many
+//! partners send as is. The [Encoding] of [DecodeOptions] overrides this.
+//!
+//! [DecodeOptions]: crate::mig::DecodeOptions
+use std::io::Read;
+
+use encoding::all::{
+    ASCII, ISO_8859_1, ISO_8859_2, ISO_8859_5, ISO_8859_7, WINDOWS_1252,
+};
+use encoding::EncodingRef;
+
+use crate::mig::decode::options::{DecodeOptions, Encoding};
+use crate::mig::decode::{Error, Limit};
+
+/// The byte order mark of UTF-8.
+pub(crate) const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The `Charset` of an input, see the [module](self).
+#[derive(Clone, Copy)]
+pub(crate) struct Charset {
+    /// The character set used, if the input is not valid UTF-8, or
+    /// `None` for UTF-8.
+    fallback: Option<EncodingRef>,
+    /// Whether valid UTF-8 is accepted, regardless of the fallback.
+    detect: bool,
+}
+
+impl Charset {
+    /// Returns the charset of the given encoding. If it is detected, the
+    /// syntax identifier is taken from the given UNB segment, which is
+    /// separated by the given component and element separators.
+    pub(crate) fn new(
+        encoding: Encoding,
+        unb: &[u8],
+        separators: (u8, u8),
+    ) -> Charset {
+        let fallback = match encoding {
+            Encoding::Detect => {
+                let identifier = syntax_identifier(unb, separators);
+                return Charset {
+                    fallback: identifier.and_then(of_syntax),
+                    detect: true,
+                };
+            }
+            Encoding::Utf8 => None,
+            Encoding::Latin1 => Some(ISO_8859_1 as EncodingRef),
+            Encoding::Windows1252 => Some(WINDOWS_1252 as EncodingRef),
+        };
+        Charset { fallback, detect: false }
+    }
+
+    /// Decodes the given bytes, which start at the given offset of the
+    /// input. An invalid byte is reported with its offset.
+    pub(crate) fn decode(
+        &self,
+        bytes: &[u8],
+        offset: usize,
+    ) -> Result<String, Error> {
+        let utf8 = match std::str::from_utf8(bytes) {
+            Ok(text) if self.detect || self.fallback.is_none() => {
+                return Ok(text.to_string())
+            }
+            Ok(_) => None,
+            Err(error) => Some(error.valid_up_to()),
+        };
+        let Some(encoding) = self.fallback else {
+            return Err(Error::InvalidByte {
+                offset: offset + utf8.unwrap_or_default(),
+                encoding: "utf-8",
+            });
+        };
+
+        let mut decoder = encoding.raw_decoder();
+        let mut text = String::with_capacity(bytes.len());
+        let (processed, error) = decoder.raw_feed(bytes, &mut text);
+        match error.or_else(|| decoder.raw_finish(&mut text)) {
+            None => Ok(text),
+            Some(_) => Err(Error::InvalidByte {
+                offset: offset + processed,
+                encoding: encoding.name(),
+            }),
+        }
+    }
+}
+
+/// Reads the whole input, which may not exceed the maximum number of
+/// bytes of the options, and decodes it with their encoding.
+pub fn read<R: Read>(
+    input: &mut R,
+    options: &DecodeOptions,
+) -> Result<String, Error> {
+    let mut bytes = vec![];
+    match options.max_input_bytes {
+        Some(max) => {
+            input.take(max as u64 + 1).read_to_end(&mut bytes)?;
+            if bytes.len() > max {
+                return Err(Error::Limit(Limit::InputBytes(max)));
+            }
+        }
+        None => {
+            input.read_to_end(&mut bytes)?;
+        }
+    }
+
+    // UNA is followed by the component and the element separator.
+    let head = bytes.strip_prefix(BOM).unwrap_or(&bytes).trim_ascii_start();
+    let (unb, separators) = match head.strip_prefix(b"UNA") {
+        Some(rest) if rest.len() >= 6 => (&rest[6..], (rest[0], rest[1])),
+        _ => (head, (b':', b'+')),
+    };
+    Charset::new(options.encoding, unb, separators).decode(&bytes, 0)
+}
+
+/// Returns the syntax identifier in UNB 0001 at the start of the given
+/// bytes, e.g. `UNOC`.
+fn syntax_identifier(
+    unb: &[u8],
+    (component, element): (u8, u8),
+) -> Option<&[u8]> {
+    let rest = unb.trim_ascii_start().strip_prefix(b"UNB")?;
+    let rest = rest.strip_prefix(&[element])?;
+    let end = rest
+        .iter()
+        .position(|b| *b == component || *b == element)
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+/// Returns the character set of the given syntax identifier, or `None`
+/// for UTF-8 and unknown ones.
+fn of_syntax(identifier: &[u8]) -> Option<EncodingRef> {
+    match identifier {
+        b"UNOA" | b"UNOB" => Some(ASCII),
+        b"UNOC" => Some(ISO_8859_1),
+        b"UNOD" => Some(ISO_8859_2),
+        b"UNOE" => Some(ISO_8859_5),
+        b"UNOF" => Some(ISO_8859_7),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::decode::charset;
+    use crate::mig::decode::options::{DecodeOptions, Encoding};
+    use crate::mig::decode::{Error, Limit};
+
+    fn read(input: &[u8], encoding: Encoding) -> Result<String, Error> {
+        let options = DecodeOptions { encoding, ..DecodeOptions::default() };
+        charset::read(&mut &input[..], &options)
+    }
+
+    #[test]
+    fn test_read() {
+        let latin1 = b"UNA:+.? 'UNB+UNOC:3+S+R'FTX+Z02+++Stra\xdfe'";
+        let text = read(latin1, Encoding::Detect).unwrap();
+        assert!(text.ends_with("Straße'"));
+
+        let utf8 = "UNB+UNOC:3+S+R'FTX+Z02+++Straße'";
+        let text = read(utf8.as_bytes(), Encoding::Detect).unwrap();
+        assert_eq!(text, utf8);
+
+        let windows = b"UNB+UNOC:3+S+R'FTX+Z02+++10 \x80'";
+        let text = read(windows, Encoding::Windows1252).unwrap();
+        assert!(text.ends_with("10 €'"));
+
+        let result = read(latin1, Encoding::Utf8);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidByte { offset: 38, encoding: "utf-8" })
+        ));
+
+        let ascii = b"UNB+UNOA:3+S+R'FTX+Z02+++Stra\xdfe'";
+        let result = read(ascii, Encoding::Detect);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidByte { offset: 29, encoding: "ascii" })
+        ));
+
+        let options = DecodeOptions {
+            max_input_bytes: Some(utf8.len() - 1),
+            ..DecodeOptions::default()
+        };
+        let result = charset::read(&mut utf8.as_bytes(), &options);
+        assert!(matches!(result, Err(Error::Limit(Limit::InputBytes(_)))));
+    }
+}
@@ -0,0 +1,201 @@
+//! Comparison of decoded interchanges, which ignores the details that
+//! depend on the input rather than its content, e.g. to assert on a
+//! decoded structure in a test:
+//!
+//! ```
+//! # use edifact::mig::{self, Comparer, DecodeOptions};
+//! # use edifact::mig::description::Interchange;
+//! # let desc: Interchange =
+//! #     serde_json::from_str(include_str!("../../../APERAK.json")).unwrap();
+//! let input = "UNA:+.? '\
+//!              UNB+UNOC:3+9900467000000:500+9904590000002:500\
+//!              +200307:0705+C3AAAAAAAAHKLC'\
+//!              UNH+1+APERAK:D:07B:UN:2.1d'\
+//!              BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+//!              DTM+137:202003070705:203'\
+//!              UNT+4+1'\
+//!              UNZ+1+C3AAAAAAAAHKLC'";
+//! let options = DecodeOptions::default();
+//! let decode = |input: &str| {
+//!     mig::decode(vec![desc.clone()], &mut input.as_bytes(), &options)
+//! };
+//! let expected = decode(input).unwrap();
+//! let actual = decode(&input.replace('\'', "'\n")).unwrap();
+//! assert_ne!(actual, expected);
+//!
+//! let comparer = Comparer { ignore_positions: true, ..Comparer::default() };
+//! assert!(comparer.eq(&actual, &expected));
+//! ```
+//!
+//! Decoded values implement `PartialEq` as well, which compares all of
+//! their details like the default `Comparer`.
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::decode::value::{
+    Composite, DataElement, Interchange, Segment, Segmentgroup, Span,
+};
+use crate::mig::description::Usage;
+use crate::mig::either::Either;
+
+/// A `Comparer` of decoded interchanges, which ignores the given details,
+/// see the [module](self). By default, nothing is ignored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Comparer {
+    /// Whether the spans, the raw text of segments and the service
+    /// characters are ignored, which depend on the layout of the input.
+    pub ignore_positions: bool,
+    /// Whether the positions of segments among all segments and of
+    /// values within their segment or composite are ignored.
+    pub ignore_indices: bool,
+    /// Whether the comments of the descriptions are ignored, e.g. to
+    /// compare interchanges decoded with different revisions of a MIG.
+    pub ignore_comments: bool,
+}
+
+impl Comparer {
+    /// Returns, whether the given interchanges are equal, except for the
+    /// ignored details.
+    pub fn eq(&self, left: &Interchange, right: &Interchange) -> bool {
+        self.normalize(left) == self.normalize(right)
+    }
+
+    /// Returns a copy of the given interchange, where the ignored details
+    /// are reset, so two of them can be compared with `assert_eq!`, which
+    /// shows the differences.
+    pub fn normalize(&self, interchange: &Interchange) -> Interchange {
+        let mut result = interchange.clone();
+        if self.ignore_positions {
+            result.una = UNA::default();
+            for raw in &mut result.raw_segments {
+                raw.start = Default::default();
+            }
+            for warning in &mut result.warnings {
+                warning.raw = None;
+            }
+        }
+        if self.ignore_indices {
+            for raw in &mut result.raw_segments {
+                raw.pos = 0;
+            }
+            for warning in &mut result.warnings {
+                warning.pos = 0;
+            }
+        }
+        self.normalize_segments(&mut result.segments);
+        result
+    }
+
+    fn normalize_segments(
+        &self,
+        values: &mut [Either<Segmentgroup, Segment>],
+    ) {
+        for value in values {
+            match value {
+                Either::Left(group) => {
+                    if self.ignore_comments {
+                        group.comment = None;
+                    }
+                    self.normalize_segments(&mut group.segments);
+                }
+                Either::Right(segment) => self.normalize_segment(segment),
+            }
+        }
+    }
+
+    fn normalize_segment(&self, segment: &mut Segment) {
+        if self.ignore_positions {
+            segment.raw.clear();
+            segment.span = Span::default();
+        }
+        if self.ignore_indices {
+            segment.index = 0;
+        }
+        if self.ignore_comments {
+            segment.comment = None;
+        }
+        for element in &mut segment.elements {
+            match element {
+                Either::Left(composite) => self.normalize_composite(composite),
+                Either::Right(data_element) => {
+                    self.normalize_data_element(data_element)
+                }
+            }
+        }
+    }
+
+    fn normalize_composite(&self, composite: &mut Composite) {
+        if self.ignore_positions {
+            composite.span = Span::default();
+        }
+        if self.ignore_indices {
+            composite.index = 0;
+        }
+        for data_element in &mut composite.elements {
+            self.normalize_data_element(data_element);
+        }
+    }
+
+    fn normalize_data_element(&self, data_element: &mut DataElement) {
+        if self.ignore_positions {
+            data_element.span = Span::default();
+        }
+        if self.ignore_indices {
+            data_element.index = 0;
+        }
+        if self.ignore_comments {
+            remove_comments(&mut data_element.description.usage);
+        }
+    }
+}
+
+fn remove_comments(usage: &mut Usage) {
+    match usage {
+        Usage::Text { comment }
+        | Usage::Integer { comment }
+        | Usage::Decimal { comment } => *comment = None,
+        Usage::OneOf { choices, comment } => {
+            *comment = None;
+            for choice in choices {
+                choice.comment = None;
+            }
+        }
+        Usage::Static { value, comment } => {
+            *comment = None;
+            value.comment = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::description::Interchange;
+    use crate::mig::{self, Comparer, DecodeOptions};
+
+    #[test]
+    fn test_compare() {
+        let desc = fixtures::aperak();
+        let json = fixtures::APERAK_JSON
+            .replace("\"comment\":null", "\"comment\":\"Neu\"");
+        let revised: Interchange = serde_json::from_str(&json).unwrap();
+        let input = &fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'",
+        );
+        let other = input.replace('\'', "'\r\n").replace("UNA:+.? '", "");
+        let options = DecodeOptions::default();
+        let decode = |desc: &Interchange, input: &str| {
+            let known = vec![desc.clone()];
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap()
+        };
+        let expected = decode(&desc, input);
+        assert_eq!(decode(&desc, input), expected);
+
+        let actual = decode(&revised, &other);
+        let positions =
+            Comparer { ignore_positions: true, ..Comparer::default() };
+        assert!(!Comparer::default().eq(&actual, &expected));
+        assert!(!positions.eq(&actual, &expected));
+        let comparer = Comparer { ignore_comments: true, ..positions };
+        assert_eq!(comparer.normalize(&actual), comparer.normalize(&expected));
+    }
+}
@@ -0,0 +1,171 @@
+//! A description compiled ahead of time, so the lookups needed while
+//! matching are not computed anew for every decoded interchange.
+//!
+//! Services decoding many interchanges with the same description should
+//! compile it once with [CompiledMig::compile] and pass the compiled
+//! description to [decode](crate::mig::decode()).
+use std::collections::HashSet;
+
+use crate::mig::description as desc;
+use crate::mig::description::Usage;
+use crate::mig::either::Either;
+
+/// A `CompiledMig` contains a description together with the tags of all
+/// its segments and the qualifiers discriminating each segment.
+#[derive(Debug, Clone)]
+pub struct CompiledMig {
+    description: desc::Interchange,
+    tags: HashSet<String>,
+    pub(crate) unb: Node,
+    pub(crate) unh: Node,
+    pub(crate) segments: Vec<Node>,
+    pub(crate) unt: Node,
+    pub(crate) unz: Node,
+}
+
+/// A `Node` mirrors a segment group or segment of the description.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Group(Vec<Node>),
+    /// The qualifiers discriminating a segment, if it has any.
+    Segment(Vec<Qualifier>),
+}
+
+/// A `Qualifier` is a data element discriminating a segment, given by
+/// the position of its element and component, starting at 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Qualifier {
+    pub element: usize,
+    pub component: usize,
+    /// The values allowed in the data element.
+    pub values: HashSet<String>,
+}
+
+impl CompiledMig {
+    /// Compiles the given description.
+    pub fn compile(description: &desc::Interchange) -> Self {
+        CompiledMig::from(description.clone())
+    }
+
+    pub fn description(&self) -> &desc::Interchange {
+        &self.description
+    }
+
+    /// Returns, whether a segment with the given tag occurs anywhere in
+    /// the description.
+    pub fn knows_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+}
+
+impl From<desc::Interchange> for CompiledMig {
+    fn from(description: desc::Interchange) -> Self {
+        let mut tags = HashSet::new();
+        let mut segment = |desc: &desc::Segment| {
+            tags.insert(desc.tag.clone());
+            Node::Segment(qualifiers(desc))
+        };
+        let unb = segment(&description.unb);
+        let unh = segment(&description.message.unh);
+        let unt = segment(&description.message.unt);
+        let unz = segment(&description.unz);
+        let segments = nodes(&description.message.segments, &mut tags);
+
+        CompiledMig { description, tags, unb, unh, segments, unt, unz }
+    }
+}
+
+fn nodes(
+    descs: &[Either<desc::Segmentgroup, desc::Segment>],
+    tags: &mut HashSet<String>,
+) -> Vec<Node> {
+    descs
+        .iter()
+        .map(|desc| match desc {
+            Either::Left(group) => Node::Group(nodes(&group.segments, tags)),
+            Either::Right(segment) => {
+                tags.insert(segment.tag.clone());
+                Node::Segment(qualifiers(segment))
+            }
+        })
+        .collect()
+}
+
+/// Returns the qualifiers of the given segment, which are the data
+/// elements flagged as discriminators or, without any, the first data
+/// element, if it is a qualifier. Only data elements with a fixed set of
+/// values can discriminate segments.
+pub(crate) fn qualifiers(desc: &desc::Segment) -> Vec<Qualifier> {
+    let data_elements = desc.elements.iter().enumerate().flat_map(
+        |(element, desc)| match desc {
+            Either::Left(composite) => composite
+                .elements
+                .iter()
+                .enumerate()
+                .map(|(component, desc)| (element, component, desc))
+                .collect(),
+            Either::Right(desc) => vec![(element, 0, desc)],
+        },
+    );
+
+    let mut result: Vec<Qualifier> = data_elements
+        .filter(|(_, _, desc)| desc.discriminator)
+        .filter_map(|(element, component, desc)| {
+            qualifier(element, component, desc)
+        })
+        .collect();
+    if result.is_empty() {
+        let first = match desc.elements.first() {
+            Some(Either::Left(composite)) => composite.elements.first(),
+            Some(Either::Right(data_element)) => Some(data_element),
+            None => None,
+        };
+        result.extend(
+            first
+                .filter(|desc| desc.is_qualifier())
+                .and_then(|desc| qualifier(0, 0, desc)),
+        );
+    }
+    result
+}
+
+fn qualifier(
+    element: usize,
+    component: usize,
+    desc: &desc::DataElement,
+) -> Option<Qualifier> {
+    let values = match &desc.usage {
+        Usage::OneOf { choices, comment: _ } => {
+            choices.iter().map(|c| c.value.clone()).collect()
+        }
+        Usage::Static { value, comment: _ } => {
+            HashSet::from([value.value.clone()])
+        }
+        _ => return None,
+    };
+    Some(Qualifier { element, component, values })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::decode::compiled::{CompiledMig, Node};
+
+    #[test]
+    fn test_compile() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::compile(&desc);
+
+        assert!(mig.knows_tag("UNB"));
+        assert!(mig.knows_tag("FTX"));
+        assert!(!mig.knows_tag("XYZ"));
+        // BGM starts with a code, DTM with a qualifier.
+        assert!(matches!(&mig.segments[0], Node::Segment(q) if q.is_empty()));
+        match &mig.segments[1] {
+            Node::Segment(qualifiers) => {
+                assert!(qualifiers[0].values.contains("137"))
+            }
+            _ => panic!("Expected the qualifiers of DTM"),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! The checks of the counters and references in UNT and UNZ, which are
+//! shared by [decode](crate::mig::decode()) and
+//! [bounded::validate](crate::mig::validate::bounded::validate).
+use crate::mig::either::Either;
+use crate::mig::error::{DataElementError, ServiceSegmentError, SyntaxError};
+
+/// The `Counters` of an interchange, which are fed with its service
+/// segments in the order of the input.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    /// The interchange reference from UNB.
+    reference: Option<String>,
+    /// The message reference of the current message from UNH.
+    message: Option<String>,
+    /// The position of the UNH of the current message. Skipped segments,
+    /// e.g. security segments, are counted, too.
+    start: Option<usize>,
+    /// The number of messages so far.
+    messages: usize,
+}
+
+impl Counters {
+    pub(crate) fn unb(&mut self, reference: Option<String>) {
+        self.reference = reference;
+    }
+
+    pub(crate) fn unh(&mut self, pos: usize, reference: Option<String>) {
+        self.start = Some(pos);
+        self.message = reference;
+        self.messages += 1;
+    }
+
+    /// Returns the error of the UNT at the given position, if its number
+    /// of segments or its reference does not match its message.
+    pub(crate) fn unt(
+        &mut self,
+        pos: usize,
+        count: Option<String>,
+        reference: Option<String>,
+    ) -> Option<ServiceSegmentError> {
+        let segments = self.start.take().map(|start| pos - start + 1);
+        if count.and_then(|c| c.parse().ok()) != segments {
+            Some(error_at("UNT", 0, SyntaxError::counter_not_equal()))
+        } else if reference != self.message {
+            Some(error_at("UNT", 1, SyntaxError::references_not_equal()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the error of the UNZ, if its number of messages or its
+    /// reference does not match the interchange.
+    pub(crate) fn unz(
+        &self,
+        count: Option<String>,
+        reference: Option<String>,
+    ) -> Option<ServiceSegmentError> {
+        if count.and_then(|c| c.parse().ok()) != Some(self.messages) {
+            Some(error_at("UNZ", 0, SyntaxError::counter_not_equal()))
+        } else if reference != self.reference {
+            Some(error_at("UNZ", 1, SyntaxError::references_not_equal()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns an error of the data element at the given position of a
+/// service segment.
+fn error_at(tag: &str, pos: usize, error: SyntaxError) -> ServiceSegmentError {
+    ServiceSegmentError {
+        tag: tag.to_string(),
+        error: Either::Right(DataElementError::new(pos, error)),
+    }
+}
@@ -0,0 +1,647 @@
+//! Incremental decoding of interchanges into a sequence of events, so
+//! large interchanges, e.g. MSCONS with load profiles, can be processed
+//! without building the whole tree of values.
+//!
+//! The input is read one segment at a time and every segment is matched
+//! as soon as it has been read. The events are the same a
+//! [decode](crate::mig::decode()) would produce, only flattened:
+//!
+//! ```
+//! # use edifact::mig::{self, CompiledMig, DecodeOptions, Event};
+//! # use edifact::mig::description::Interchange;
+//! # let desc: Interchange =
+//! #     serde_json::from_str(include_str!("../../../APERAK.json")).unwrap();
+//! let mig = CompiledMig::from(desc);
+//! let input = "UNA:+.? '\
+//!              UNB+UNOC:3+9900467000000:500+9904590000002:500\
+//!              +200307:0705+C3AAAAAAAAHKLC'\
+//!              UNH+1+APERAK:D:07B:UN:2.1d'\
+//!              BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+//!              DTM+137:202003070705:203'\
+//!              UNT+4+1'\
+//!              UNZ+1+C3AAAAAAAAHKLC'";
+//! let options = DecodeOptions::lenient();
+//! let mut tags = vec![];
+//! for event in mig::decode_events(&mig, input.as_bytes(), &options) {
+//!     if let Event::Segment(segment) = event.unwrap() {
+//!         tags.push(segment.tag().to_string());
+//!     }
+//! }
+//! assert_eq!(tags, vec!["UNB", "UNH", "BGM", "DTM", "UNT", "UNZ"]);
+//! ```
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
+
+use combine::stream::position::Positioner;
+
+use crate::mig::decode::charset::{Charset, BOM};
+use crate::mig::decode::compiled::CompiledMig;
+use crate::mig::decode::options::{
+    DecodeOptions, Encoding, ParseMode, UnknownSegments,
+};
+use crate::mig::decode::parser::value::{Position, RawSegment, UNA};
+use crate::mig::decode::parser::{self, parse_una};
+use crate::mig::decode::routing;
+use crate::mig::decode::value::{
+    check_routing, children, get_counter, interchange_descs, match_segment,
+    matches_compiled, matches_segmentgroup, tolerates_leftover, Context, Desc,
+    Segment,
+};
+use crate::mig::decode::{Error, Limit};
+use crate::mig::description as desc;
+use crate::mig::either::Either;
+use crate::mig::error::{SegmentError, Severity, SyntaxError};
+use crate::mig::security::{self, SecuritySegment};
+
+/// An `Event` while decoding an interchange.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The next segment is the UNH starting a message.
+    StartMessage,
+    /// The previous segment has been the UNT ending a message.
+    EndMessage,
+    /// The next segments belong to the given segment group until the
+    /// matching [Event::EndGroup].
+    StartGroup(&'a desc::Segmentgroup),
+    EndGroup,
+    /// A segment has been matched with its description.
+    Segment(Segment),
+    /// A segment does not conform to its description or is not supported
+    /// at its position. Decoding continues with the next segment.
+    Error(SegmentError),
+    /// A deviation of a segment has been tolerated, see
+    /// [Severity::Warning].
+    Warning(SegmentError),
+    /// A segment could not be parsed and has been skipped, see
+    /// [ParseMode::Salvage].
+    Raw(RawSegment),
+    /// A security segment unknown to the description has been received,
+    /// see [security](crate::mig::security).
+    Security(SecuritySegment),
+}
+
+/// The `DecodeEvents` of an input, see the [module](self).
+///
+/// Errors, which prevent decoding the rest of the input, e.g. a segment,
+/// which cannot be parsed, end the iteration.
+pub struct DecodeEvents<'a, R> {
+    mig: &'a CompiledMig,
+    options: &'a DecodeOptions,
+    source: Source<R>,
+    /// The descriptions of the unfinished segment groups, starting with
+    /// the whole interchange.
+    frames: Vec<Frame<'a>>,
+    pending: VecDeque<Event<'a>>,
+    errors: usize,
+    /// Whether the UNZ has been matched, so the interchange is complete.
+    complete: bool,
+    done: bool,
+}
+
+/// A `Frame` contains the descriptions of a segment group, which are
+/// matched one counter at a time like in [decode](crate::mig::decode()).
+struct Frame<'a> {
+    descs: Vec<Desc<'a>>,
+    /// The position of the first description not matched yet.
+    next: usize,
+    /// The descriptions sharing the current counter, which may still be
+    /// matched.
+    current: Vec<Desc<'a>>,
+    check_qualifier: bool,
+    group: bool,
+}
+
+impl<'a> Frame<'a> {
+    fn new(descs: Vec<Desc<'a>>, group: bool) -> Self {
+        Frame {
+            descs,
+            next: 0,
+            current: vec![],
+            check_qualifier: false,
+            group,
+        }
+    }
+
+    /// Continues with the descriptions of the next counter and returns
+    /// false, if there are none.
+    fn advance(&mut self, options: &DecodeOptions) -> bool {
+        let Some(first) = self.descs.get(self.next) else {
+            return false;
+        };
+        let counter = get_counter(first);
+        let count = self.descs[self.next..]
+            .iter()
+            .take_while(|desc| get_counter(desc) == counter)
+            .count();
+        self.current = self.descs[self.next..self.next + count].to_vec();
+        self.next += count;
+        self.check_qualifier = options.check_qualifiers && count > 1;
+        true
+    }
+}
+
+impl<'a, R: BufRead> DecodeEvents<'a, R> {
+    /// Creates the events of the given input decoded with the given
+    /// description.
+    pub fn new(
+        mig: &'a CompiledMig,
+        input: R,
+        options: &'a DecodeOptions,
+    ) -> Self {
+        DecodeEvents {
+            mig,
+            options,
+            source: Source::new(input, options),
+            frames: vec![Frame::new(interchange_descs(mig).collect(), false)],
+            pending: VecDeque::new(),
+            errors: 0,
+            complete: false,
+            done: false,
+        }
+    }
+
+    /// Matches the next segment or finishes the current segment group or
+    /// counter, which adds zero or more pending events.
+    fn step(&mut self) -> Result<(), Error> {
+        if self.options.reached_max_errors(self.errors) {
+            self.done = true;
+            return Ok(());
+        }
+        let mig = self.mig;
+        let options = self.options;
+        let root = self.frames.len() == 1;
+        let Some(frame) = self.frames.last_mut() else {
+            return self.leftover();
+        };
+        if frame.current.is_empty() && !frame.advance(options) {
+            if frame.group {
+                self.pending.push_back(Event::EndGroup);
+            }
+            self.frames.pop();
+            return Ok(());
+        }
+
+        let events = &mut self.pending;
+        let Some(value) = self.source.peek(mig, options, events)? else {
+            frame.current.clear();
+            return Ok(());
+        };
+        let check_qualifier = frame.check_qualifier;
+        let next_match = frame.current.iter().position(|(d, node)| match d {
+            Either::Left(desc) => {
+                matches_segmentgroup(desc, node, check_qualifier, value)
+            }
+            Either::Right(desc) => {
+                matches_compiled(desc, node, check_qualifier, value)
+            }
+        });
+        let Some(i) = next_match else {
+            frame.current.clear();
+            // Another message follows, which is matched with the
+            // descriptions from UNH on again.
+            if root && frame.next > 2 && value.tag.value == "UNH" {
+                frame.next = 1;
+            }
+            return Ok(());
+        };
+
+        match frame.current[i] {
+            (Either::Right(desc), _) => {
+                if desc.max_reps == 1 {
+                    frame.current.remove(i);
+                }
+                // The value has just been peeked.
+                let value = self.source.take().unwrap();
+                let ctx = Context {
+                    options,
+                    una: self.source.una,
+                    hook: None,
+                    too_deep: Cell::new(false),
+                    warnings: RefCell::new(vec![]),
+                };
+                if desc.tag == "UNH" {
+                    self.pending.push_back(Event::StartMessage);
+                }
+                match match_segment(value.pos, desc, &value, &ctx) {
+                    Ok(matched) => {
+                        let warnings = ctx.warnings.take();
+                        self.pending
+                            .extend(warnings.into_iter().map(Event::Warning));
+                        self.pending.push_back(Event::Segment(matched))
+                    }
+                    Err(error) => {
+                        self.errors += 1;
+                        self.pending.push_back(Event::Error(error));
+                    }
+                }
+                if desc.tag == "UNT" {
+                    self.pending.push_back(Event::EndMessage);
+                }
+                self.complete |= desc.tag == "UNZ";
+            }
+            (Either::Left(desc), node) => {
+                // The value starts the group, so it is matched by the
+                // first description of the group next.
+                let depth = self.frames.len();
+                if let Some(max) =
+                    options.max_group_depth.filter(|max| depth > *max)
+                {
+                    return Err(Error::Limit(Limit::GroupDepth(max)));
+                }
+                self.pending.push_back(Event::StartGroup(desc));
+                let descs = children(desc, node).collect();
+                self.frames.push(Frame::new(descs, true));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reports the segments left over after matching the interchange,
+    /// which are not supported at their position.
+    fn leftover(&mut self) -> Result<(), Error> {
+        let events = &mut self.pending;
+        if self.source.peek(self.mig, self.options, events)?.is_none() {
+            self.done = true;
+            return Ok(());
+        }
+        let value = self.source.take().unwrap();
+        let tolerated =
+            tolerates_leftover(&value, self.complete, self.options);
+        let error = SegmentError {
+            pos: value.pos,
+            severity: Severity::Warning,
+            syntax_error: Some(SyntaxError::not_supported_at_this_position()),
+            errors: vec![],
+            raw: Some(value.raw),
+        };
+        if tolerated {
+            self.pending.push_back(Event::Warning(error));
+        } else {
+            self.errors += 1;
+            let severity = Severity::Error;
+            self.pending
+                .push_back(Event::Error(SegmentError { severity, ..error }));
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: BufRead> Iterator for DecodeEvents<'a, R> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            } else if self.done {
+                return None;
+            } else if let Err(error) = self.step() {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+/// Reads and parses the segments of the given input up to the first UNH,
+/// e.g. to route it without reading the rest.
+pub(super) fn read_head<R: BufRead>(
+    input: R,
+) -> Result<parser::value::Interchange, Error> {
+    let mut source = Source::new(input, &DecodeOptions::default());
+    let mut segments = vec![];
+    while let Some((start, text)) = source.read_segment()? {
+        let pos = segments.len();
+        let segment = parser::parse_segment(&source.una, pos, start, &text)?;
+        let is_unh = segment.tag.value == "UNH";
+        segments.push(segment);
+        if is_unh {
+            break;
+        }
+    }
+    Ok(parser::value::Interchange {
+        una: source.una,
+        segments,
+        raw_segments: vec![],
+    })
+}
+
+/// The `Source` of the segments, which are read and parsed one at a time.
+struct Source<R> {
+    input: R,
+    una: UNA,
+    started: bool,
+    position: Position,
+    encoding: Encoding,
+    skip_preamble: bool,
+    max_input_bytes: Option<usize>,
+    max_segments: Option<usize>,
+    /// The number of bytes consumed from the input.
+    consumed: usize,
+    /// The charset, once the first segment has been read.
+    charset: Option<Charset>,
+    peeked: Option<parser::value::Segment>,
+    /// The number of segments read, not counting UNA.
+    read: usize,
+}
+
+impl<R: BufRead> Source<R> {
+    fn new(input: R, options: &DecodeOptions) -> Self {
+        Source {
+            input,
+            una: UNA::default(),
+            started: false,
+            position: Position::default(),
+            encoding: options.encoding,
+            skip_preamble: options.skip_preamble,
+            max_input_bytes: options.max_input_bytes,
+            max_segments: options.max_segments,
+            consumed: 0,
+            charset: None,
+            peeked: None,
+            read: 0,
+        }
+    }
+
+    /// Returns the next segment known to the description. Unknown
+    /// segments are skipped and reported, unless they are ignored.
+    fn peek<'a>(
+        &mut self,
+        mig: &CompiledMig,
+        options: &DecodeOptions,
+        events: &mut VecDeque<Event<'a>>,
+    ) -> Result<Option<&parser::value::Segment>, Error> {
+        while self.peeked.is_none() {
+            let Some((start, text)) = self.read_segment()? else {
+                return Ok(None);
+            };
+            let pos = self.read;
+            self.read += 1;
+            if let Some(max) = self.max_segments.filter(|max| pos >= *max) {
+                return Err(Error::Limit(Limit::Segments(max)));
+            }
+            let parsed = parser::parse_segment(&self.una, pos, start, &text);
+            let mut segment = match parsed {
+                Ok(segment) => segment,
+                Err(_) if options.parse_mode == ParseMode::Salvage => {
+                    events.push_back(Event::Raw(RawSegment {
+                        pos,
+                        start,
+                        value: text,
+                    }));
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            // The syntax version of UNB decides on the repetition
+            // separator.
+            let una = self.una.for_unb(&segment);
+            if pos == 0 && una != self.una {
+                segment = parser::parse_segment(&una, pos, start, &text)?;
+                self.una = una;
+            }
+            if pos == 0 && segment.tag.value == "UNB" {
+                let interchange = parser::value::Interchange {
+                    una: self.una,
+                    segments: vec![segment.clone()],
+                    raw_segments: vec![],
+                };
+                check_routing(&interchange.routing(), options)?;
+            }
+            if segment.tag.value == "UNH" {
+                let expected = mig.description().message_identifier();
+                let actual = routing::message_identifier(&segment);
+                if !expected.matches(&actual) {
+                    return Err(Error::MessageMismatch { expected, actual });
+                }
+            }
+            if mig.knows_tag(&segment.tag.value) {
+                self.peeked = Some(segment);
+            } else if security::is_security_segment(&segment.tag.value) {
+                let segment = SecuritySegment::new(segment);
+                events.push_back(Event::Security(segment));
+            } else if options.unknown_segments == UnknownSegments::Reject {
+                events.push_back(Event::Error(SegmentError {
+                    pos,
+                    severity: Severity::Error,
+                    syntax_error: Some(
+                        SyntaxError::not_supported_at_this_position(),
+                    ),
+                    errors: vec![],
+                    raw: Some(segment.raw),
+                }));
+            }
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn take(&mut self) -> Option<parser::value::Segment> {
+        self.peeked.take()
+    }
+
+    /// Reads the next segment including its terminator together with its
+    /// start position. The whitespace between segments is skipped.
+    fn read_segment(&mut self) -> Result<Option<(Position, String)>, Error> {
+        if !self.started {
+            self.started = true;
+            if self.skip_preamble {
+                self.skip_preamble()?;
+            }
+            self.read_una()?;
+        }
+
+        let terminator = ascii(self.una.segment_sep)?;
+        let escape = ascii(self.una.escape)?;
+        let mut segment = vec![];
+        loop {
+            // At most one byte more than allowed is read.
+            let limit = self.max_input_bytes.map_or(u64::MAX, |max| {
+                (max + 1).saturating_sub(self.consumed) as u64
+            });
+            let mut input = (&mut self.input).take(limit);
+            let read = input.read_until(terminator, &mut segment)?;
+            self.consume(read)?;
+            if read == 0 {
+                break;
+            }
+
+            // The terminator is escaped, if it is preceded by an odd
+            // number of escape characters.
+            let content = &segment[..segment.len() - 1];
+            let escapes =
+                content.iter().rev().take_while(|b| **b == escape).count();
+            if segment.last() == Some(&terminator) && escapes % 2 == 0 {
+                break;
+            }
+        }
+
+        let separators =
+            (ascii(self.una.component_sep)?, ascii(self.una.element_sep)?);
+        let charset = *self.charset.get_or_insert_with(|| {
+            Charset::new(self.encoding, &segment, separators)
+        });
+        let segment = charset.decode(&segment, self.position.offset)?;
+        let text = segment.trim_start();
+        advance(&mut self.position, &segment[..segment.len() - text.len()]);
+        let start = self.position;
+        advance(&mut self.position, text);
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((start, text.to_string())))
+        }
+    }
+
+    /// Counts the given number of bytes as consumed, which may not exceed
+    /// the maximum of the input.
+    fn consume(&mut self, count: usize) -> Result<(), Error> {
+        self.consumed += count;
+        match self.max_input_bytes {
+            Some(max) if self.consumed > max => {
+                Err(Error::Limit(Limit::InputBytes(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Skips a byte order mark and the whitespace at the start of the
+    /// input, which must be followed by UNA or UNB, see
+    /// [DecodeOptions::skip_preamble].
+    fn skip_preamble(&mut self) -> Result<(), Error> {
+        if self.input.fill_buf()?.starts_with(BOM) {
+            self.input.consume(BOM.len());
+            self.consume(BOM.len())?;
+            self.position.update(&'\u{feff}');
+        }
+        loop {
+            let buffer = self.input.fill_buf()?;
+            let count =
+                buffer.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            for b in &buffer[..count] {
+                self.position.update(&char::from(*b));
+            }
+            let done = count < buffer.len() || buffer.is_empty();
+            self.input.consume(count);
+            self.consume(count)?;
+            if done {
+                break;
+            }
+        }
+        let rest = self.input.fill_buf()?;
+        let skipped = self.position.offset > 0;
+        if skipped && !(rest.starts_with(b"UNA") || rest.starts_with(b"UNB")) {
+            return Err(Error::InvalidStart(self.position));
+        }
+        Ok(())
+    }
+
+    fn read_una(&mut self) -> Result<(), Error> {
+        if !self.input.fill_buf()?.starts_with(b"UNA") {
+            return Ok(());
+        }
+        let mut una = [0u8; 9];
+        self.input.read_exact(&mut una)?;
+        self.consume(una.len())?;
+        let una = String::from_utf8_lossy(&una);
+        let (una, rest) = parse_una(&una, self.position)?;
+        self.una = una;
+        self.position = rest.positioner;
+        Ok(())
+    }
+}
+
+fn advance(position: &mut Position, text: &str) {
+    for c in text.chars() {
+        position.update(&c);
+    }
+}
+
+/// Returns the given separator as a byte. Only ASCII separators are
+/// supported while reading incrementally.
+fn ascii(c: char) -> io::Result<u8> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("The separator '{}' is not an ASCII character", c),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::events::Event;
+    use crate::mig::{CompiledMig, DecodeOptions, UnknownSegments};
+
+    const INPUT: &str = "UNA:+.? '\
+        UNB+UNOC:3+9900467000000:500+9904590000002:500\
+        +200307:0705+C3AAAAAAAAHKLC'\n\
+        UNH+1+APERAK:D:07B:UN:2.1d'\n\
+        BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+        DTM+137:202003070705:203'\
+        XYZ+1'\
+        NAD+MS+9900467000000::293'\
+        NAD+MR+9904590000002::293'\
+        ERC+Z29'\
+        FTX+Z02+++1?'0'\
+        UNT+9+1'\
+        UNZ+1+C3AAAAAAAAHKLC'";
+
+    #[test]
+    fn test_events() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let mut options = DecodeOptions {
+            unknown_segments: UnknownSegments::Skip,
+            ..DecodeOptions::default()
+        };
+        let expected =
+            mig::decode(&mig, &mut INPUT.as_bytes(), &options).unwrap();
+
+        let mut events = vec![];
+        let mut segments = vec![];
+        for event in mig::decode_events(&mig, INPUT.as_bytes(), &options) {
+            match event.unwrap() {
+                Event::Segment(segment) => {
+                    events.push(segment.tag().to_string());
+                    segments.push(segment);
+                }
+                Event::StartGroup(group) => events.push(group.label.clone()),
+                Event::EndGroup => events.push("/".to_string()),
+                Event::StartMessage => events.push("<".to_string()),
+                Event::EndMessage => events.push(">".to_string()),
+                Event::Error(error) | Event::Warning(error) => {
+                    panic!("Unexpected {:?}", error)
+                }
+                Event::Raw(raw) => panic!("Unexpected {:?}", raw),
+                Event::Security(segment) => panic!("Unexpected {:?}", segment),
+            }
+        }
+        assert_eq!(
+            events.join(" "),
+            "UNB < UNH BGM DTM SG3 NAD / SG3 NAD / SG4 ERC FTX / UNT > UNZ"
+        );
+        let expected = expected.all_segments();
+        assert_eq!(segments.len(), expected.len());
+        for (segment, expected) in segments.iter().zip(expected) {
+            assert_eq!(segment.index, expected.index);
+            assert_eq!(segment.raw, expected.raw);
+            assert_eq!(segment.span, expected.span);
+        }
+
+        options.unknown_segments = UnknownSegments::Reject;
+        let errors: Vec<usize> =
+            mig::decode_events(&mig, INPUT.as_bytes(), &options)
+                .filter_map(|event| match event.unwrap() {
+                    Event::Error(error) => Some(error.pos),
+                    _ => None,
+                })
+                .collect();
+        assert_eq!(errors, vec![4]);
+    }
+}
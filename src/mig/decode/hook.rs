@@ -0,0 +1,124 @@
+//! Hooks, which are called with every segment as soon as it has been
+//! matched, e.g. to collect metrics or extract values while decoding a
+//! large interchange and to stop early, once everything needed has been
+//! seen.
+//!
+//! ```
+//! # use std::ops::ControlFlow;
+//! # use edifact::mig::{self, DecodeOptions};
+//! # use edifact::mig::decode::{value::Segment, Error};
+//! # use edifact::mig::description::Interchange;
+//! # let desc: Interchange =
+//! #     serde_json::from_str(include_str!("../../../APERAK.json")).unwrap();
+//! let input = "UNA:+.? '\
+//!              UNB+UNOC:3+9900467000000:500+9904590000002:500\
+//!              +200307:0705+C3AAAAAAAAHKLC'\
+//!              UNH+1+APERAK:D:07B:UN:2.1d'\
+//!              BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+//!              DTM+137:202003070705:203'\
+//!              UNT+4+1'\
+//!              UNZ+1+C3AAAAAAAAHKLC'";
+//! let mut document = None;
+//! let mut hook = |segment: &Segment| {
+//!     if segment.tag() == "BGM" {
+//!         document = segment.text_of("1004").map(String::from);
+//!         return ControlFlow::Break(());
+//!     }
+//!     ControlFlow::Continue(())
+//! };
+//! let options = DecodeOptions::default();
+//! let mut input = input.as_bytes();
+//! let result = mig::decode_with_hook(vec![desc], &mut input, &options, &mut hook);
+//! assert!(matches!(result, Err(Error::Aborted)));
+//! assert_eq!(document.as_deref(), Some("53ff5de4caab4ea18abafab5e6036991"));
+//! ```
+use std::cell::{Cell, RefCell};
+use std::ops::ControlFlow;
+
+use crate::mig::decode::value::Segment;
+
+/// A `SegmentHook` is called with every segment matched in order. If it
+/// breaks, decoding stops with [Error::Aborted](super::Error::Aborted).
+///
+/// Segments, which do not conform to their description, are not passed.
+pub trait SegmentHook {
+    fn segment(&mut self, segment: &Segment) -> ControlFlow<()>;
+}
+
+impl<F> SegmentHook for F
+where
+    F: FnMut(&Segment) -> ControlFlow<()>,
+{
+    fn segment(&mut self, segment: &Segment) -> ControlFlow<()> {
+        self(segment)
+    }
+}
+
+/// A `Hook` shared by all of matching, which remembers whether the hook
+/// broke.
+pub(super) struct Hook<'a> {
+    hook: RefCell<&'a mut dyn SegmentHook>,
+    aborted: Cell<bool>,
+}
+
+impl<'a> Hook<'a> {
+    pub(super) fn new(hook: &'a mut dyn SegmentHook) -> Self {
+        Hook { hook: RefCell::new(hook), aborted: Cell::new(false) }
+    }
+}
+
+/// An `Observer` of matching, which hides the lifetime of the hook, so
+/// it can be shared like the rest of the context.
+pub(super) trait Observer {
+    fn call(&self, segment: &Segment);
+    fn aborted(&self) -> bool;
+}
+
+impl Observer for Hook<'_> {
+    fn call(&self, segment: &Segment) {
+        if self.hook.borrow_mut().segment(segment).is_break() {
+            self.aborted.set(true);
+        }
+    }
+
+    fn aborted(&self) -> bool {
+        self.aborted.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::value::Segment;
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_hook() {
+        let desc = fixtures::aperak();
+        let input = &fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'\
+             NAD+MS+9900467000000::293'\
+             NAD+MR+9904590000002::293'",
+        );
+        let mut tags = vec![];
+        let mut hook = |segment: &Segment| {
+            tags.push(segment.tag().to_string());
+            ControlFlow::Continue(())
+        };
+        let options = DecodeOptions::default();
+        let interchange = mig::decode_with_hook(
+            vec![desc],
+            &mut input.as_bytes(),
+            &options,
+            &mut hook,
+        )
+        .unwrap();
+        let expected: Vec<&str> =
+            interchange.all_segments().iter().map(|s| s.tag()).collect();
+        assert_eq!(tags, expected);
+    }
+}
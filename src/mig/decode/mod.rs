@@ -3,11 +3,27 @@ use combine::easy;
 use combine::stream::position::SourcePosition;
 use core::fmt;
 use std::io::Read;
-use crate::mig::description;
+use crate::mig::description::{self, MessageIdentifier};
+use crate::mig::decode::compiled::CompiledMig;
 use crate::mig::error::InterchangeError;
+use crate::mig::decode::hook::{Hook, Observer, SegmentHook};
+use crate::mig::decode::options::{DecodeOptions, ParseMode};
+use crate::mig::decode::routing::Routing;
 
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+pub mod charset;
+pub mod compare;
+pub mod compiled;
+pub(crate) mod counters;
+pub mod events;
+pub mod hook;
+pub mod options;
+pub mod path;
+pub mod routing;
+mod tree;
 pub mod value;
-mod parser;
+pub mod parser;
 
 // type ParseError = easy::Errors<char, String, SourcePosition>;
 
@@ -15,7 +31,50 @@ mod parser;
 pub enum Error {
     Io(io::Error),
     Parse(easy::Errors<char, String, SourcePosition>),
-    Mig(InterchangeError)
+    Mig(InterchangeError),
+    /// No description has been given to decode the input with.
+    NoDescription,
+    /// None of the descriptions given describes the message type in UNH.
+    UnknownMessageType(String),
+    /// The message identifier in UNH differs from the one described by
+    /// the description of its message type, e.g. in its version.
+    MessageMismatch {
+        expected: MessageIdentifier,
+        actual: MessageIdentifier,
+    },
+    /// Decoding has been stopped by a [SegmentHook].
+    Aborted,
+    /// The byte at the given offset of the input is not valid in the
+    /// character set of the input, see [charset].
+    InvalidByte { offset: usize, encoding: &'static str },
+    /// The input does not start with UNA or UNB after the byte order
+    /// mark or whitespace at the given position.
+    InvalidStart(parser::value::Position),
+    /// The input exceeds a limit of the [DecodeOptions].
+    Limit(Limit),
+}
+
+/// A `Limit` of the [DecodeOptions], which an input may not exceed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// See [DecodeOptions::max_input_bytes].
+    InputBytes(usize),
+    /// See [DecodeOptions::max_segments].
+    Segments(usize),
+    /// See [DecodeOptions::max_group_depth].
+    GroupDepth(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::InputBytes(max) => write!(f, "{} bytes", max),
+            Limit::Segments(max) => write!(f, "{} segments", max),
+            Limit::GroupDepth(max) => {
+                write!(f, "{} nested segment groups", max)
+            }
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -23,7 +82,30 @@ impl fmt::Display for Error {
         match self {
             Error::Io(error) => error.fmt(f),
             Error::Parse(error) => error.fmt(f),
-            Error::Mig(_) => Ok(())
+            Error::Mig(_) => Ok(()),
+            Error::NoDescription => {
+                write!(f, "No description to decode the input with")
+            }
+            Error::UnknownMessageType(message_type) => {
+                write!(f, "No description for message type {}", message_type)
+            }
+            Error::MessageMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Expected the message {}, but got {}",
+                    expected, actual
+                )
+            }
+            Error::Aborted => write!(f, "Decoding has been aborted"),
+            Error::InvalidByte { offset, encoding } => {
+                write!(f, "The byte at {} is not valid {}", offset, encoding)
+            }
+            Error::InvalidStart(position) => {
+                write!(f, "Expected UNA or UNB at {}", position)
+            }
+            Error::Limit(limit) => {
+                write!(f, "The input exceeds the limit of {}", limit)
+            }
         }
     }
 }
@@ -46,9 +128,381 @@ impl From<easy::Errors<char, String, SourcePosition>> for Error {
     }
 }
 
+/// The `Known` descriptions to decode an input with, either as they have
+/// been deserialized or compiled ahead of time.
+pub enum Known<'a> {
+    Descriptions(Vec<description::Interchange>),
+    Compiled(&'a [CompiledMig]),
+}
+
+impl From<Vec<description::Interchange>> for Known<'_> {
+    fn from(descriptions: Vec<description::Interchange>) -> Self {
+        Known::Descriptions(descriptions)
+    }
+}
+
+impl<'a> From<&'a [CompiledMig]> for Known<'a> {
+    fn from(migs: &'a [CompiledMig]) -> Self {
+        Known::Compiled(migs)
+    }
+}
+
+impl<'a> From<&'a CompiledMig> for Known<'a> {
+    fn from(mig: &'a CompiledMig) -> Self {
+        Known::Compiled(std::slice::from_ref(mig))
+    }
+}
+
+/// Decodes the given input with the description of its message type.
+/// The messages of an interchange are matched one after another, see
+/// [Interchange::messages](value::Interchange::messages), and their
+/// errors are reported separately.
+pub fn decode<'a, R: Read>(
+    known: impl Into<Known<'a>>,
+    input: &mut R,
+    options: &DecodeOptions,
+) -> Result<value::Interchange, Error> {
+    decode_help(known, input, options, None)
+}
+
+/// Decodes the input like [decode] and calls the hook with every matched
+/// segment, see [SegmentHook].
+pub fn decode_with_hook<'a, R: Read>(
+    known: impl Into<Known<'a>>,
+    input: &mut R,
+    options: &DecodeOptions,
+    hook: &mut dyn SegmentHook,
+) -> Result<value::Interchange, Error> {
+    let hook = Hook::new(hook);
+    let result = decode_help(known, input, options, Some(&hook));
+    if hook.aborted() {
+        Err(Error::Aborted)
+    } else {
+        result
+    }
+}
+
+fn decode_help<'a, R: Read>(
+    known: impl Into<Known<'a>>,
+    input: &mut R,
+    options: &DecodeOptions,
+    hook: Option<&dyn Observer>,
+) -> Result<value::Interchange, Error> {
+    let known = known.into();
+    let empty = match &known {
+        Known::Descriptions(descriptions) => descriptions.is_empty(),
+        Known::Compiled(migs) => migs.is_empty(),
+    };
+    if empty {
+        return Err(Error::NoDescription);
+    }
+    let contents = charset::read(input, options)?;
+    let interchange = match options.parse_mode {
+        ParseMode::Strict => {
+            parser::parse_text(&contents, options.skip_preamble)?
+        }
+        ParseMode::Salvage => {
+            parser::parse_salvaged_text(&contents, options.skip_preamble)?
+        }
+    };
+    let segments = interchange.segments.len() + interchange.raw_segments.len();
+    if let Some(max) = options.max_segments.filter(|max| segments > *max) {
+        return Err(Error::Limit(Limit::Segments(max)));
+    }
 
-pub fn decode<R: Read>(known: Vec<description::Interchange>, input: &mut R) -> Result<value::Interchange, Error> {
-    let interchange = parser::parse(input)?;
-    let result = value::match_interchange(&known[0], interchange)?;
+    let unh = interchange.segments.iter().find(|s| s.tag.value == "UNH");
+    let actual = unh.map(routing::message_identifier);
+    let compiled;
+    let mig = match known {
+        Known::Descriptions(mut descriptions) => {
+            let i = select(descriptions.iter(), actual.as_ref())?;
+            compiled = CompiledMig::from(descriptions.swap_remove(i));
+            &compiled
+        }
+        Known::Compiled(migs) => {
+            let descriptions = migs.iter().map(|mig| mig.description());
+            &migs[select(descriptions, actual.as_ref())?]
+        }
+    };
+    let result =
+        value::match_interchange_with(mig, interchange, options, hook)?;
     Ok(result)
 }
+
+/// Returns the position of the first description, which matches the
+/// given message identifier of UNH. Descriptions of an unknown message
+/// type apply to any interchange.
+fn select<'d>(
+    descriptions: impl Iterator<Item = &'d description::Interchange>,
+    actual: Option<&MessageIdentifier>,
+) -> Result<usize, Error> {
+    let Some(actual) = actual else {
+        return Ok(0);
+    };
+    let mut mismatch = None;
+    for (i, desc) in descriptions.enumerate() {
+        let expected = desc.message_identifier();
+        if expected.matches(actual) {
+            return Ok(i);
+        }
+        let same_type = match (&expected.message_type, &actual.message_type) {
+            (Some(expected), Some(actual)) => expected == actual,
+            _ => true,
+        };
+        if same_type && mismatch.is_none() {
+            mismatch = Some(expected);
+        }
+    }
+    Err(match mismatch {
+        Some(expected) => Error::MessageMismatch {
+            expected,
+            actual: actual.clone(),
+        },
+        None => Error::UnknownMessageType(
+            actual.message_type.clone().unwrap_or_default(),
+        ),
+    })
+}
+
+/// Returns the routing metadata of the given input, without matching it
+/// against a description. Only the segments up to the first UNH are read.
+pub fn routing<R: Read>(input: &mut R) -> Result<Routing, Error> {
+    let interchange = events::read_head(io::BufReader::new(input))?;
+    Ok(interchange.routing())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::decode::{decode, Error, Limit};
+    use crate::mig::description::Interchange;
+    use crate::mig::either::Either;
+    use crate::mig::error::{Severity, SyntaxError};
+    use crate::mig::decode::parser::value::UNA;
+    use crate::mig::encode::encode_with;
+    use crate::mig::encode::options::EncodeOptions;
+    use crate::mig::{decode_events, CompiledMig, DecodeOptions, Event};
+
+    #[test]
+    fn test_decode_without_description() {
+        let input = "UNA:+.? 'UNB+UNOC:3'";
+        let result =
+            decode(vec![], &mut input.as_bytes(), &DecodeOptions::default());
+        assert!(matches!(result, Err(Error::NoDescription)));
+    }
+
+    #[test]
+    fn test_decode_unknown_message_type() {
+        let desc = fixtures::aperak();
+        let input = fixtures::interchange(&["UNH+1+UTILMD:D:11A:UN:5.2a'\
+            BGM+E01+DOC1'UNT+3+1'"]);
+        let options = DecodeOptions::default();
+        let result = decode(vec![desc], &mut input.as_bytes(), &options);
+        assert!(matches!(
+            result,
+            Err(Error::UnknownMessageType(message_type))
+                if message_type == "UTILMD"
+        ));
+    }
+
+    #[test]
+    fn test_decode_message_mismatch() {
+        let desc = fixtures::aperak();
+        let input = fixtures::interchange(&["UNH+1+APERAK:D:07B:UN:2.1e'\
+            BGM+313+DOC1'UNT+3+1'"]);
+        let options = DecodeOptions::default();
+        let result = decode(vec![desc], &mut input.as_bytes(), &options);
+        let Err(Error::MessageMismatch { expected, actual }) = result else {
+            panic!("Expected a mismatch of the message identifier");
+        };
+        assert_eq!(expected.to_string(), "APERAK:D:07B:UN:2.1d");
+        assert_eq!(actual.association_code.as_deref(), Some("2.1e"));
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let desc = fixtures::aperak();
+        let input = fixtures::interchange(&["UNH+1+APERAK:D:07B:UN:2.1d'\
+            BGM+313+DOC1'NAD+MS+9900467000000::293'UNT+4+1'"]);
+        let limit = |options: DecodeOptions| {
+            let desc = desc.clone();
+            match decode(vec![desc], &mut input.as_bytes(), &options) {
+                Err(Error::Limit(limit)) => Some(limit),
+                _ => None,
+            }
+        };
+        let options = DecodeOptions {
+            max_input_bytes: Some(input.len()),
+            max_segments: Some(6),
+            max_group_depth: Some(1),
+            ..DecodeOptions::lenient()
+        };
+        assert_eq!(limit(options.clone()), None);
+        assert_eq!(
+            limit(DecodeOptions {
+                max_input_bytes: Some(100),
+                ..options.clone()
+            }),
+            Some(Limit::InputBytes(100))
+        );
+        assert_eq!(
+            limit(DecodeOptions { max_segments: Some(5), ..options.clone() }),
+            Some(Limit::Segments(5))
+        );
+        assert_eq!(
+            limit(DecodeOptions { max_group_depth: Some(0), ..options }),
+            Some(Limit::GroupDepth(0))
+        );
+    }
+
+    #[test]
+    fn test_decode_warnings() {
+        let desc = fixtures::aperak();
+        let input = fixtures::interchange(&["UNH+1+APERAK:D:07B:UN:2.1d'\
+            BGM+313+DOC1'NAD+MS+9900467000000:X:293'UNT+4+1++'"]);
+        let decode = |options: &DecodeOptions| {
+            decode(vec![desc.clone()], &mut input.as_bytes(), options)
+        };
+
+        let interchange = decode(&DecodeOptions::lenient()).unwrap();
+        let warnings = interchange.warnings;
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+        // The code list of NAD is ignored, since it is not used.
+        assert_eq!(warnings[0].pos, 3);
+        let Either::Left(composite) = &warnings[0].errors[0] else {
+            panic!("Expected a warning about the composite C082");
+        };
+        assert_eq!((composite.pos, composite.errors[0].pos), (1, 1));
+        assert_eq!(warnings[1].pos, 4);
+        assert_eq!(
+            warnings[1].syntax_error,
+            Some(SyntaxError::too_many_parts())
+        );
+
+        let options = DecodeOptions {
+            treat_warnings_as_errors: true,
+            ..DecodeOptions::lenient()
+        };
+        let Err(Error::Mig(error)) = decode(&options) else {
+            panic!("Expected the warnings to be errors");
+        };
+        let errors = &error.message_errors[0].segment_errors;
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_decode_lenient_leftover() {
+        let desc = fixtures::aperak();
+        let input = fixtures::APERAK_FULL.replace("NAD+MS", "NAD+XX");
+        let options = DecodeOptions::lenient();
+        let result =
+            decode(vec![desc.clone()], &mut input.as_bytes(), &options);
+        let Err(Error::Mig(error)) = result else {
+            panic!("Expected the segments from NAD+XX on to be errors");
+        };
+        let errors = &error.message_errors[0].segment_errors;
+        let positions: Vec<usize> = errors.iter().map(|e| e.pos).collect();
+        assert_eq!(positions, (6..=15).collect::<Vec<_>>());
+        assert!(errors.iter().all(|e| e.severity == Severity::Error));
+
+        let mig = CompiledMig::from(desc.clone());
+        let events = decode_events(&mig, input.as_bytes(), &options);
+        let errors = events
+            .filter(|event| matches!(event, Ok(Event::Error(_))))
+            .count();
+        assert_eq!(errors, 10);
+
+        // A segment after the UNZ of a complete interchange is tolerated.
+        let input = format!("{}FTX+Z02+++10'", fixtures::APERAK);
+        let result = decode(vec![desc], &mut input.as_bytes(), &options);
+        let warnings = result.unwrap().warnings;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pos, 10);
+    }
+
+    #[test]
+    fn test_decode_unknown_segments() {
+        let desc = fixtures::aperak();
+        let input = fixtures::interchange(&["UNH+1+APERAK:D:07B:UN:2.1d'\
+            XYZ+1'BGM+313+DOC1+1+2+3+4'UNT+4+1'"]);
+        let options = DecodeOptions::default();
+        let result = decode(vec![desc], &mut input.as_bytes(), &options);
+        let Err(Error::Mig(error)) = result else {
+            panic!("Expected the unknown segment to be an error");
+        };
+        let errors = &error.message_errors[0].segment_errors;
+        let positions: Vec<usize> = errors.iter().map(|e| e.pos).collect();
+        assert_eq!(positions, vec![2, 3]);
+        assert_eq!(errors[0].raw.as_deref(), Some("XYZ+1'"));
+    }
+
+    #[test]
+    fn test_decode_identical_errors() {
+        let desc = fixtures::aperak();
+        let input = fixtures::interchange(&["UNH+1+APERAK:D:07B:UN:2.1d'\
+            BGM+313+DOC1'XYZ+1'XYZ+2'XYZ+3'XYZ+4'XYZ+5'UNT+8+1'"]);
+        let options = DecodeOptions {
+            max_identical_errors: Some(2),
+            ..DecodeOptions::default()
+        };
+        let result = decode(vec![desc], &mut input.as_bytes(), &options);
+        let Err(Error::Mig(error)) = result else {
+            panic!("Expected the unknown segments to be errors");
+        };
+        let error = &error.message_errors[0];
+        assert_eq!(error.segment_errors.len(), 2);
+        assert_eq!(error.repeated.len(), 1);
+        assert_eq!(
+            error.repeated[0].to_string(),
+            "The error 15 occurred 5 times, first at segment 3"
+        );
+        assert_eq!(error.repeated[0].omitted, 3);
+    }
+
+    #[test]
+    fn test_decode_repetitions() {
+        let mut desc = fixtures::aperak();
+        let input = "UNA:+.?*'UNB+UNOC:4+9900467000000:500\
+                     +9904590000002:500+200307:0705+REF1'\
+                     UNH+1+APERAK:D:07B:UN:2.1d'BGM+313+DOC1*DOC2'\
+                     UNT+3+1'UNZ+1+REF1'";
+        let decode = |desc: &Interchange, input: &str| {
+            let options = DecodeOptions::default();
+            decode(vec![desc.clone()], &mut input.as_bytes(), &options)
+        };
+        let codes = |result: Result<_, Error>| match result {
+            Err(Error::Mig(error)) => error
+                .syntax_errors()
+                .iter()
+                .map(|error| error.get_code())
+                .collect(),
+            _ => vec![],
+        };
+        assert_eq!(codes(decode(&desc, input)), vec![35]);
+
+        let Either::Right(bgm) = &mut desc.message.segments[0] else {
+            panic!("Expected BGM");
+        };
+        match &mut bgm.elements[1] {
+            Either::Left(composite) => composite.max_occurs = Some(2),
+            Either::Right(data_element) => data_element.max_occurs = Some(2),
+        }
+        let interchange = decode(&desc, input).unwrap();
+        let bgm = interchange.all_segments()[2];
+        assert_eq!(bgm.texts_of("1004"), vec!["DOC1", "DOC2"]);
+        assert_eq!(crate::mig::encode(&interchange), input);
+        // The repetition separator is declared, if the UNA has none.
+        let options = EncodeOptions {
+            una: Some(UNA::default()),
+            ..EncodeOptions::default()
+        };
+        let encoded = encode_with(&interchange, &options);
+        assert!(encoded.starts_with("UNA:+.?*'"));
+        assert!(decode(&desc, &encoded).is_ok());
+
+        let input = input.replace("DOC2", "DOC2*DOC3");
+        assert_eq!(codes(decode(&desc, &input)), vec![35]);
+    }
+}
@@ -0,0 +1,182 @@
+//! This module contains the options, which control how tolerant a
+//! message is decoded against its description.
+use crate::mig::validate::element::Validators;
+use crate::mig::validate::ids;
+use crate::usecase::conditions::Conditions;
+
+/// The `Strictness` defines, how deviations from a description are
+/// treated, which do not change the meaning of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strictness {
+    /// Every deviation from the description is reported as an error.
+    Strict,
+    /// Deviations, which do not change the meaning of a message, are
+    /// tolerated. These are superfluous empty data elements at the end
+    /// of a segment or composite and segments after the UNZ, which are
+    /// left over after the whole interchange has been matched. Segments,
+    /// which could not be matched before, are always errors.
+    Lenient,
+}
+
+/// Defines, what happens with segments, whose tag does not occur
+/// anywhere in the description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownSegments {
+    /// Report an unknown segment as not supported at its position.
+    Reject,
+    /// Drop an unknown segment before matching.
+    Skip,
+}
+
+/// Defines, what happens with interchanges flagged as a test
+/// transmission in UNB 0035.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestIndicator {
+    /// Decode test interchanges and tag the result as a test.
+    Accept,
+    /// Reject test interchanges with error 25 (test not supported), e.g.
+    /// in productive pipelines.
+    Reject,
+}
+
+/// Defines, how the input is split into segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail, if any segment cannot be parsed.
+    Strict,
+    /// Split the input at every segment terminator and keep segments,
+    /// which cannot be parsed, e.g. because of broken escaping, as raw
+    /// segments. Matching continues with the rest of the segments.
+    Salvage,
+}
+
+/// Defines, how the bytes of the input are decoded into characters, see
+/// [charset](crate::mig::decode::charset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Use UTF-8, if the input is valid UTF-8, and the character set of
+    /// the syntax identifier in UNB otherwise, e.g. ISO-8859-1 for UNOC.
+    Detect,
+    Utf8,
+    /// ISO-8859-1, which is the character set of UNOC.
+    Latin1,
+    /// Windows-1252, the superset of ISO-8859-1 used by many Windows
+    /// systems, e.g. for `€`.
+    Windows1252,
+}
+
+/// `DecodeOptions` are passed to [decode](crate::mig::decode()) and
+/// define how tolerant decoding is.
+///
+/// The default options are strict, check qualifiers and reject unknown
+/// segments.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    pub strictness: Strictness,
+    /// Deviations tolerated by [Strictness::Lenient] are considered
+    /// warnings and reported with the decoded interchange, see
+    /// [Severity](crate::mig::error::Severity). If this is set, they are
+    /// reported as errors anyway.
+    pub treat_warnings_as_errors: bool,
+    /// The maximum number of segment errors collected before decoding
+    /// stops. `None` means, that all errors are collected.
+    pub max_errors: Option<usize>,
+    /// The maximum number of identical segment errors of a message, e.g.
+    /// of the repetitions of a malformed segment group. Further ones are
+    /// only counted in the [MessageError](crate::mig::error::MessageError).
+    /// `None` means, that all of them are kept.
+    pub max_identical_errors: Option<usize>,
+    pub unknown_segments: UnknownSegments,
+    /// Whether qualifiers are used to decide between descriptions with
+    /// the same counter. If not, the first description with a matching
+    /// tag is used.
+    pub check_qualifiers: bool,
+    /// The kinds of identifiers, which are checked for validity while
+    /// matching, e.g. the check digit of a Marktlokations-ID.
+    pub identifiers: Vec<ids::Kind>,
+    /// Custom checks of values, e.g. of IBANs, which are run after their
+    /// format has been checked.
+    pub validators: Validators,
+    pub test_indicator: TestIndicator,
+    pub parse_mode: ParseMode,
+    pub encoding: Encoding,
+    /// Whether a byte order mark and whitespace before UNA or UNB are
+    /// skipped, which some systems export.
+    pub skip_preamble: bool,
+    /// Whether the decimal character of numeric values is replaced by
+    /// `.`, if the UNA defines another one, so `3,14` decodes to `3.14`.
+    pub normalize_decimals: bool,
+    /// The expected sender ID in UNB 0004. Interchanges from other
+    /// senders are rejected with error 23 (unknown sender).
+    pub sender: Option<String>,
+    /// The expected receiver ID in UNB 0010, usually one's own. Other
+    /// interchanges are rejected with error 7 (not the actual receiver).
+    pub receiver: Option<String>,
+    /// The maximum number of bytes of the input. Decoding larger inputs
+    /// is aborted with a [Limit](crate::mig::decode::Limit), e.g. to
+    /// protect a service accepting interchanges from untrusted partners
+    /// against memory exhaustion.
+    pub max_input_bytes: Option<usize>,
+    /// The maximum number of segments of the input.
+    pub max_segments: Option<usize>,
+    /// The maximum number of nested segment groups.
+    pub max_group_depth: Option<usize>,
+    /// The conditions of the use case evaluated for the input, which
+    /// decide whether data elements with the status D or C are required.
+    pub conditions: Conditions,
+}
+
+impl DecodeOptions {
+    /// Create strict options, which are the same as the default ones.
+    pub fn strict() -> Self {
+        DecodeOptions::default()
+    }
+
+    /// Create options, which tolerate deviations not changing the
+    /// meaning of a message and skip unknown segments.
+    pub fn lenient() -> Self {
+        DecodeOptions {
+            strictness: Strictness::Lenient,
+            unknown_segments: UnknownSegments::Skip,
+            ..DecodeOptions::default()
+        }
+    }
+
+    /// Returns, whether a deviation, which does not change the meaning of
+    /// a message should be tolerated.
+    pub fn tolerates_warnings(&self) -> bool {
+        self.strictness == Strictness::Lenient
+            && !self.treat_warnings_as_errors
+    }
+
+    /// Returns, whether the given number of errors reached the maximum.
+    pub fn reached_max_errors(&self, count: usize) -> bool {
+        self.max_errors.is_some_and(|max| count >= max)
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            strictness: Strictness::Strict,
+            treat_warnings_as_errors: false,
+            max_errors: None,
+            max_identical_errors: None,
+            unknown_segments: UnknownSegments::Reject,
+            check_qualifiers: true,
+            identifiers: vec![],
+            validators: Validators::default(),
+            test_indicator: TestIndicator::Accept,
+            parse_mode: ParseMode::Strict,
+            encoding: Encoding::Detect,
+            skip_preamble: true,
+            normalize_decimals: true,
+            sender: None,
+            receiver: None,
+            max_input_bytes: None,
+            max_segments: None,
+            max_group_depth: None,
+            conditions: Conditions::default(),
+        }
+    }
+}
@@ -1,20 +1,327 @@
+//! This module parses interchanges into segments, without matching them
+//! against a description.
+mod structure;
 pub mod value;
 
-use combine::stream::position::Stream;
-use combine::EasyParser;
+use combine::stream::position::{Positioner, SourcePosition, Stream};
+use combine::easy;
+use combine::{EasyParser, Parser};
 use std::io::Read;
+use crate::mig::decode::charset;
+use crate::mig::decode::options::DecodeOptions;
 use crate::mig::decode::Error;
+use crate::mig::either::Either;
+use crate::mig::error::{
+    DataElementError, InterchangeError, ServiceSegmentError, SyntaxError,
+};
 
 
+/// Parses the given input, whose character set is detected, see
+/// [charset](crate::mig::decode::charset).
 pub fn parse<R: Read>(input: &mut R) -> Result<value::Interchange, Error> {
-    let mut contents = String::new();
-    input.read_to_string(&mut contents)?;
-    let i = &*contents;
+    parse_text(&charset::read(input, &DecodeOptions::default())?, true)
+}
+
+pub(crate) fn parse_text(
+    i: &str,
+    skip_preamble: bool,
+) -> Result<value::Interchange, Error> {
+    let (start, i) = preamble(i, skip_preamble)?;
+    parse_una(i, start)?;
 
     let mut parser = value::Interchange::parser();
     let (interchange, _) = parser
-        .easy_parse(Stream::new(i))
-        .map_err(|e| Error::Parse(e.map_range(|s| s.to_string())))?;
+        .easy_parse(Stream::with_positioner(i, start))
+        .map_err(parse_error)?;
+
+    Ok(interchange)
+}
+
+/// Parses a single segment including its terminator, which is the one at
+/// the given position of the interchange and starts at the given position
+/// of the input.
+pub(super) fn parse_segment(
+    una: &value::UNA,
+    pos: usize,
+    start: value::Position,
+    text: &str,
+) -> Result<value::Segment, Error> {
+    let stream = Stream::with_positioner(text, start);
+    let (segment, _) = value::Segment::parser(una)
+        .easy_parse(stream)
+        .map_err(parse_error)?;
+    Ok(value::Segment { pos, ..segment })
+}
+
+fn parse_error(
+    error: easy::Errors<char, &str, value::Position>,
+) -> Error {
+    Error::Parse(error.map_range(|s| s.to_string()).map_position(|p| {
+        SourcePosition { line: p.line, column: p.column }
+    }))
+}
+
+/// Parses the given input segment by segment, so a segment, which cannot
+/// be parsed, does not fail the whole interchange. It is kept as a raw
+/// segment instead.
+pub fn parse_salvaged<R: Read>(
+    input: &mut R,
+) -> Result<value::Interchange, Error> {
+    let options = DecodeOptions::default();
+    parse_salvaged_text(&charset::read(input, &options)?, true)
+}
+
+pub(crate) fn parse_salvaged_text(
+    contents: &str,
+    skip_preamble: bool,
+) -> Result<value::Interchange, Error> {
+    let (start, contents) = preamble(contents, skip_preamble)?;
+    let (mut una, rest) = parse_una(contents, start)?;
+    let pieces = split_segments(rest.input, &una, rest.positioner);
+    // The UNB is read ahead for its syntax version.
+    if let Some((start, text)) = pieces.first() {
+        let stream = Stream::with_positioner(*text, *start);
+        if let Ok((unb, _)) = value::Segment::parser(&una).parse(stream) {
+            una = una.for_unb(&unb);
+        }
+    }
+    let mut interchange = value::Interchange {
+        una,
+        segments: vec![],
+        raw_segments: vec![],
+    };
+    for (pos, (start, text)) in pieces.into_iter().enumerate() {
+        let stream = Stream::with_positioner(text, start);
+        match value::Segment::parser(&una).parse(stream) {
+            Ok((segment, rest)) if rest.input.is_empty() => {
+                interchange.segments.push(value::Segment { pos, ..segment })
+            }
+            _ => interchange.raw_segments.push(value::RawSegment {
+                pos,
+                start,
+                value: text.to_string(),
+            }),
+        }
+    }
 
     Ok(interchange)
 }
+
+/// Returns the position after the preamble of the given input and the
+/// rest of it, if the preamble is skipped. The preamble is a byte order
+/// mark and whitespace before UNA or UNB, which some systems export. Any
+/// other start after a preamble is rejected.
+pub(super) fn preamble(
+    input: &str,
+    skip: bool,
+) -> Result<(value::Position, &str), Error> {
+    let mut start = value::Position::default();
+    let rest = input.strip_prefix('\u{feff}').unwrap_or(input).trim_start();
+    if !skip || rest.len() == input.len() {
+        return Ok((start, input));
+    }
+    for c in input[..input.len() - rest.len()].chars() {
+        start.update(&c);
+    }
+    if rest.starts_with("UNA") || rest.starts_with("UNB") {
+        Ok((start, rest))
+    } else {
+        Err(Error::InvalidStart(start))
+    }
+}
+
+/// Parses the UNA segment at the start of the given input, or returns the
+/// default one, and checks its service characters. Invalid ones are
+/// reported with error 20 at their position.
+pub(super) fn parse_una(
+    input: &str,
+    start: value::Position,
+) -> Result<(value::UNA, Stream<&str, value::Position>), Error> {
+    // The UNA parser always succeeds, since it falls back to the default.
+    let stream = Stream::with_positioner(input, start);
+    let (una, rest) = match value::UNA::parser().parse(stream.clone()) {
+        Ok(result) => result,
+        Err(_) => (value::UNA::default(), stream),
+    };
+
+    match una.find_invalid() {
+        Some(pos) => Err(Error::Mig(InterchangeError {
+            pos: 0,
+            service_segment_error: Some(ServiceSegmentError {
+                tag: "UNA".to_string(),
+                error: Either::Right(DataElementError::new(
+                    pos,
+                    SyntaxError::invalid_service_chars(),
+                )),
+            }),
+            message_errors: vec![],
+            una: Some(una),
+        })),
+        None => Ok((una, rest)),
+    }
+}
+
+/// Splits the given input after every segment terminator, which is not
+/// escaped, and returns the segments with their start positions. The
+/// whitespace between segments is skipped.
+fn split_segments<'a>(
+    input: &'a str,
+    una: &value::UNA,
+    mut position: value::Position,
+) -> Vec<(value::Position, &'a str)> {
+    let mut result = vec![];
+    let mut start: Option<(usize, value::Position)> = None;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        let from = match start {
+            Some((from, _)) => from,
+            None if c.is_whitespace() => {
+                position.update(&c);
+                continue;
+            }
+            None => {
+                start = Some((i, position));
+                i
+            }
+        };
+        position.update(&c);
+
+        if escaped {
+            escaped = false;
+        } else if c == una.escape {
+            escaped = true;
+        } else if c == una.segment_sep {
+            if let Some((_, at)) = start.take() {
+                result.push((at, &input[from..i + c.len_utf8()]));
+            }
+        }
+    }
+
+    if let Some((from, at)) = start {
+        result.push((at, &input[from..]));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::decode::parser::{parse, parse_salvaged, parse_text};
+    use crate::mig::decode::Error;
+    use crate::mig::either::Either;
+
+    /// Returns the position of the invalid service character reported
+    /// for the given input.
+    fn invalid_service_char(input: &str) -> Option<usize> {
+        match parse(&mut input.as_bytes()) {
+            Err(Error::Mig(error)) => {
+                let Either::Right(error) = error.service_segment_error?.error
+                else {
+                    return None;
+                };
+                assert_eq!(error.syntax_errors[0].get_code(), 20);
+                Some(error.pos)
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_una() {
+        assert_eq!(invalid_service_char("UNA:+.? 'UNB+UNOC:3'"), None);
+        assert_eq!(invalid_service_char("UNA:+,?*'UNB+UNOC:4'"), None);
+        assert_eq!(invalid_service_char("UNA:+.: 'UNB+UNOC:3'"), Some(3));
+        assert_eq!(invalid_service_char("UNA:+;? 'UNB+UNOC:3'"), Some(2));
+        assert_eq!(invalid_service_char("UNA1+.? 'UNB+UNOC:3'"), Some(0));
+        assert_eq!(invalid_service_char("UNA:+.?  UNB+UNOC:3 "), Some(5));
+        assert_eq!(invalid_service_char("UNA:+.? ä"), Some(5));
+    }
+
+    #[test]
+    fn test_unescape() {
+        let input = "UNA:+.? 'FTX+AAO+++Wert?+X?:Y???''";
+        let interchange = parse(&mut input.as_bytes()).unwrap();
+        let segment = &interchange.segments[0];
+
+        match &segment.elements[3] {
+            Either::Right(element) => assert_eq!(element.value, "Wert+X:Y?'"),
+            Either::Left(_) => panic!("Expected a data element"),
+        }
+        assert_eq!(segment.raw, "FTX+AAO+++Wert?+X?:Y???''");
+
+        // An escape character, which releases nothing, is kept.
+        let input = "FTX+AAO+++LOC 3225 ung?'ltiger Wert 10?%?''";
+        let interchange = parse(&mut input.as_bytes()).unwrap();
+        match &interchange.segments[0].elements[3] {
+            Either::Right(element) => {
+                assert_eq!(element.value, "LOC 3225 ung'ltiger Wert 10?%'")
+            }
+            Either::Left(_) => panic!("Expected a data element"),
+        }
+    }
+
+    #[test]
+    fn test_parse_repetitions() {
+        let input = "UNA:+.?*'FII+A*B:1+C?*D'";
+        let interchange = parse(&mut input.as_bytes()).unwrap();
+        let segment = &interchange.segments[0];
+        assert_eq!(segment.elements.len(), 2);
+        assert_eq!(segment.repetitions.len(), 1);
+        assert!(matches!(
+            &segment.repetitions[0],
+            (0, Either::Left(composite)) if composite.elements[0].value == "B"
+        ));
+        match &segment.elements[1] {
+            Either::Right(element) => assert_eq!(element.value, "C*D"),
+            Either::Left(_) => panic!("Expected a data element"),
+        }
+
+        // Without a repetition separator, the character is kept.
+        let input = "FII+A*B'";
+        let interchange = parse(&mut input.as_bytes()).unwrap();
+        assert!(interchange.segments[0].repetitions.is_empty());
+
+        // Before syntax version 4, the reserved character is a space.
+        let input = "UNA:+.?*'UNB+UNOC:3'FII+A*B'";
+        for interchange in [
+            parse(&mut input.as_bytes()).unwrap(),
+            parse_salvaged(&mut input.as_bytes()).unwrap(),
+        ] {
+            assert_eq!(interchange.una.repetition_sep(), None);
+            assert!(interchange.segments[1].repetitions.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_salvaged() {
+        let input = "UNA:+.? 'UNB+UNOC:3'\nBROKEN'FTX+AAO+++Preis?'s'UNZ+1";
+        assert!(parse(&mut input.as_bytes()).is_err());
+
+        let interchange = parse_salvaged(&mut input.as_bytes()).unwrap();
+        let tags: Vec<&str> = interchange
+            .segments
+            .iter()
+            .map(|s| s.tag.value.as_str())
+            .collect();
+        assert_eq!(tags, vec!["UNB", "FTX"]);
+
+        let raw: Vec<(usize, i32, &str)> = interchange
+            .raw_segments
+            .iter()
+            .map(|s| (s.pos, s.start.line, s.value.as_str()))
+            .collect();
+        assert_eq!(raw, vec![(1, 2, "BROKEN'"), (3, 2, "UNZ+1")]);
+    }
+
+    #[test]
+    fn test_preamble() {
+        let input = "\u{feff}\r\nUNA:+.? 'UNB+UNOC:3'\nUNZ+0+1'";
+        let interchange = parse(&mut input.as_bytes()).unwrap();
+        let unb = &interchange.segments[0];
+        assert_eq!((unb.start.line, unb.start.offset), (2, 14));
+        assert_eq!(interchange.segments.len(), 2);
+
+        assert!(parse_text(input, false).is_err());
+        let result = parse(&mut "\u{feff}\nxUNB+UNOC:3'".as_bytes());
+        assert!(matches!(result, Err(Error::InvalidStart(p)) if p.line == 2));
+    }
+}
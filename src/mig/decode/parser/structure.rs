@@ -0,0 +1,67 @@
+//! Conversion of parsed interchanges into JSON, which only keeps their
+//! structure, e.g. for message types without a description:
+//!
+//! ```json
+//! [
+//!   {"UNB": [["UNOC", "3"], ["9900467000000", "500"], ...]},
+//!   {"BGM": ["313", "53ff5de4caab4ea18abafab5e6036991"]},
+//!   ...
+//! ]
+//! ```
+//!
+//! Every segment is an object with its tag as the only key, so the order
+//! and repetitions of segments are kept. Its elements are the positional
+//! values, where a composite is an array of its components. Empty values
+//! are kept as empty strings, so the positions do not shift.
+use serde_json::{json, Map, Value};
+
+use crate::mig::decode::parser::value::{Interchange, Segment};
+use crate::mig::either::Either;
+
+impl Interchange {
+    /// Returns the structural JSON of this interchange, see the
+    /// [module](self).
+    pub fn to_structural_json(&self) -> Value {
+        Value::Array(self.segments.iter().map(segment).collect())
+    }
+}
+
+fn segment(segment: &Segment) -> Value {
+    let elements = segment
+        .elements
+        .iter()
+        .map(|element| match element {
+            Either::Left(composite) => composite
+                .elements
+                .iter()
+                .map(|element| json!(element.value))
+                .collect(),
+            Either::Right(element) => json!(element.value),
+        })
+        .collect();
+    let mut object = Map::new();
+    object.insert(segment.tag.value.clone(), Value::Array(elements));
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::mig::decode::parser;
+
+    #[test]
+    fn test_to_structural_json() {
+        let input = "UNA:+.? 'UNB+UNOC:3+9900467000000:500'\
+            BGM+313+DOC?+1'NAD+MS++9900467000000::293'";
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        assert_eq!(
+            interchange.to_structural_json(),
+            json!([
+                {"UNB": [["UNOC", "3"], ["9900467000000", "500"]]},
+                {"BGM": ["313", "DOC+1"]},
+                {"NAD": ["MS", "", ["9900467000000", "", "293"]]},
+            ])
+        );
+    }
+}
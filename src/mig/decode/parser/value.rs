@@ -2,15 +2,16 @@
 use std::fmt;
 
 use combine::{
-    any, attempt, eof, Parser, position, RangeStream, sep_by, sep_by1, Stream,
+    any, attempt, eof, look_ahead, optional, Parser, position, RangeStream,
+    satisfy, sep_by, sep_by1, Stream,
 };
 use combine::error::ParseError;
 use combine::parser::char::{char, spaces, string};
 use combine::parser::combinator::recognize;
-use combine::parser::range::take_while1;
+use combine::parser::range::{recognize_with_value, take_while1};
 use combine::parser::repeat::{escaped, repeat_until};
 use combine::parser::token::value;
-use combine::stream::position::SourcePosition;
+use combine::stream::position::{Positioner, RangePositioner};
 use combine::stream::Range;
 use serde::{Deserialize, Serialize};
 
@@ -23,7 +24,7 @@ use crate::mig::either::Either;
 /// # Example
 ///
 /// Here is an example: UNA:+.? '
-#[derive(Clone, Debug, Serialize, Deserialize, Copy)]
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq, Eq)]
 pub struct UNA {
     pub component_sep: char,
     pub element_sep: char,
@@ -33,7 +34,7 @@ pub struct UNA {
     pub segment_sep: char,
 }
 
-impl UNA {
+impl Default for UNA {
     /// Create a UNA service segment with default separators.
     ///
     /// These are:
@@ -44,7 +45,7 @@ impl UNA {
     ///   - `?` as escaping symbol
     ///   - `'` as segment separator
     ///
-    pub fn default() -> UNA {
+    fn default() -> UNA {
         UNA {
             component_sep: ':',
             element_sep: '+',
@@ -54,7 +55,9 @@ impl UNA {
             segment_sep: '\'',
         }
     }
+}
 
+impl UNA {
     /// Create a new UNA service segment with the specified separators.
     pub fn new(
         component_sep: char,
@@ -65,20 +68,21 @@ impl UNA {
         segment_sep: char,
     ) -> UNA {
         UNA {
-            component_sep: component_sep,
-            element_sep: element_sep,
-            decimal_char: decimal_char,
-            escape: escape,
-            reserved: reserved,
-            segment_sep: segment_sep,
+            component_sep,
+            element_sep,
+            decimal_char,
+            escape,
+            reserved,
+            segment_sep,
         }
     }
 
-    /// Check, if the given character is a component, element or segment separator.
+    /// Check, if the given character is a component, element, repetition
+    /// or segment separator.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let x: bool = UNA::default().is_separator(':')
     /// assert_eq!(x, true)
     /// ```
@@ -86,13 +90,105 @@ impl UNA {
         self.component_sep == c
             || self.segment_sep == c
             || self.element_sep == c
+            || self.repetition_sep() == Some(c)
+    }
+
+    /// Returns the repetition separator of syntax version 4, which
+    /// separates the occurrences of a repeated element, e.g. `*`. In
+    /// earlier versions, the character is reserved and a space, see
+    /// [UNA::for_unb].
+    pub fn repetition_sep(&self) -> Option<char> {
+        (self.reserved != ' ').then_some(self.reserved)
+    }
+
+    /// Returns the service characters of an interchange starting with the
+    /// given UNB. Before the syntax version 4 declared in S001 of UNB,
+    /// the reserved character does not separate repetitions, so it is
+    /// read as a space.
+    pub fn for_unb(self, unb: &Segment) -> UNA {
+        let version = match unb.elements.first() {
+            Some(Either::Left(s001)) if unb.tag.value == "UNB" => {
+                s001.elements.get(1).map(|e| e.value.as_str())
+            }
+            _ => None,
+        };
+        match version.and_then(|version| version.parse::<u8>().ok()) {
+            Some(version) if version < 4 => UNA { reserved: ' ', ..self },
+            _ => self,
+        }
+    }
+
+    /// Removes the escape characters from the given value, e.g. `Wert?+X`
+    /// becomes `Wert+X`. An escape character, which does not precede a
+    /// service character, does not release anything and is kept, e.g.
+    /// `10?%` stays `10?%`.
+    pub fn unescape_value(&self, value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        let mut chars = value.chars().peekable();
+        while let Some(c) = chars.next() {
+            if self.is_escape(c) {
+                if let Some(next) = chars.next_if(|c| self.is_released(*c)) {
+                    result.push(next);
+                    continue;
+                }
+            }
+            result.push(c);
+        }
+        result
+    }
+
+    /// Check, if the given character is released by a preceding escape
+    /// character, which are the separators and the escape character
+    /// itself.
+    pub(crate) fn is_released(&self, c: char) -> bool {
+        self.is_separator(c) || self.is_escape(c)
+    }
+
+    /// Escapes all separators and the escape character in the given value,
+    /// so it can be written into a segment.
+    pub fn escape_value(&self, value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        for c in value.chars() {
+            if self.is_separator(c) || self.is_escape(c) {
+                result.push(self.escape);
+            }
+            result.push(c);
+        }
+        result
+    }
+
+    /// Returns the position of the first invalid service character, or
+    /// `None`, if all of them are valid.
+    ///
+    /// Separators and the escape character have to be distinct ASCII
+    /// punctuation characters, so they cannot be mistaken for the
+    /// content of a data element. The decimal character has to be `.` or
+    /// `,` and the reserved character may also be a space.
+    pub(crate) fn find_invalid(&self) -> Option<usize> {
+        let chars = [
+            self.component_sep,
+            self.element_sep,
+            self.decimal_char,
+            self.escape,
+            self.reserved,
+            self.segment_sep,
+        ];
+        (0..chars.len()).find(|&i| {
+            let c = chars[i];
+            let valid = match i {
+                2 => c == '.' || c == ',',
+                4 if c == ' ' => return false,
+                _ => c.is_ascii_punctuation(),
+            };
+            !valid || chars[..i].contains(&c)
+        })
     }
 
     /// Check, if the given character is the escape symbol.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let x: bool = UNA::default().is_escape('?')
     /// assert_eq!(x, true)
     /// ```
@@ -100,7 +196,7 @@ impl UNA {
         self.escape == c
     }
 
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
+    pub(crate) fn parser<Input>() -> impl Parser<Input, Output = Self>
         where
             Input: Stream<Token = char>,
             Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
@@ -138,6 +234,10 @@ impl fmt::Display for UNA {
 pub struct Interchange {
     pub una: UNA,
     pub segments: Vec<Segment>,
+    /// The segments, which could not be parsed. These are only collected
+    /// when salvaging an interchange.
+    #[serde(default)]
+    pub raw_segments: Vec<RawSegment>,
 }
 
 impl Interchange {
@@ -148,79 +248,171 @@ impl Interchange {
     /// does not have more structure.
     pub fn parser<Input>() -> impl Parser<Input, Output = Interchange>
         where
-            Input: RangeStream<Token = char, Position = SourcePosition>,
-            Input::Range: Range,
+            Input: RangeStream<Token = char, Position = Position>,
+            Input::Range: Range + ToString,
             Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
     {
-        UNA::parser().then(|una| {
-            repeat_until(attempt(Segment::parser(&una)), eof()).map(
-                move |segments| Interchange { una: una, segments: segments },
-            )
+        // Line breaks after the UNA are skipped like after any segment.
+        // The UNB is read ahead for its syntax version.
+        let unb = |una| look_ahead(optional(attempt(Segment::parser(&una))));
+        UNA::parser().skip(spaces()).then(move |una| {
+            unb(una).then(move |unb: Option<Segment>| {
+                let una = unb.map_or(una, |unb| una.for_unb(&unb));
+                segments(una)
+            })
         })
     }
 }
 
+/// Returns the parser of the segments of an interchange with the given
+/// service characters.
+fn segments<Input>(una: UNA) -> impl Parser<Input, Output = Interchange>
+    where
+        Input: RangeStream<Token = char, Position = Position>,
+        Input::Range: Range + ToString,
+        Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    repeat_until(attempt(Segment::parser(&una)), eof()).map(
+        move |mut segments: Vec<Segment>| {
+            for (pos, segment) in segments.iter_mut().enumerate() {
+                segment.pos = pos;
+            }
+            Interchange { una, segments, raw_segments: vec![] }
+        },
+    )
+}
+
 /// A `Segment` represents a segment, which always starts with a
 /// data element, called a `tag` and a number of follow-up
 /// elements.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Segment {
+    /// The position of this segment among all segments of the
+    /// interchange, not counting UNA.
+    #[serde(default)]
+    pub pos: usize,
     pub tag: DataElement,
+    /// The elements, where a repeated element is its first occurrence.
     pub elements: Vec<Either<Composite, DataElement>>,
+    /// The further occurrences of repeated elements with the position of
+    /// their element, e.g. `(1, B)` in `FII+A*B'`. They are separated by
+    /// the [repetition separator](UNA::repetition_sep) of syntax version
+    /// 4.
+    #[serde(default)]
+    pub repetitions: Vec<(usize, Either<Composite, DataElement>)>,
+    /// The segment as it has been received, including its terminator.
+    #[serde(default)]
+    pub raw: String,
+    #[serde(default)]
+    pub start: Position,
+    /// The position after the terminator of this segment.
+    #[serde(default)]
+    pub end: Position,
 }
 
 impl Segment {
     pub fn parser<Input>(una: &UNA) -> impl Parser<Input, Output = Segment>
         where
-            Input: RangeStream<Token = char, Position = SourcePosition>,
-            Input::Range: Range,
+            Input: RangeStream<Token = char, Position = Position>,
+            Input::Range: Range + ToString,
             Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
     {
-        let element = attempt(Composite::parser(una).map(|x| Either::Left(x)))
-            .or(DataElement::parser(una.clone()).map(|x| Either::Right(x)));
+        let element = attempt(Composite::parser(una).map(Either::Left))
+            .or(DataElement::parser(*una).map(Either::Right));
+        let repetition = una.repetition_sep();
+        let occurrences = sep_by1(
+            element,
+            satisfy(move |c| Some(c) == repetition),
+        );
 
-        (
-            DataElement::parser(una.clone()),
-            char(una.clone().element_sep),
-            sep_by(element, char(una.clone().element_sep)),
-            char(una.clone().segment_sep),
-            attempt(spaces()),
+        let segment = recognize_with_value((
+            DataElement::parser(*una),
+            char(una.element_sep),
+            sep_by(occurrences, char(una.element_sep)),
+            char(una.segment_sep),
+        ));
+
+        (position(), segment, position(), attempt(spaces())).map(
+            |(start, (raw, (tag, _, occurrences, _)), end, _): (
+                _,
+                (Input::Range, (_, _, Vec<Vec<_>>, _)),
+                _,
+                _,
+            )| {
+                let mut elements = vec![];
+                let mut repetitions = vec![];
+                for (pos, occurrences) in occurrences.into_iter().enumerate()
+                {
+                    let mut occurrences = occurrences.into_iter();
+                    elements.extend(occurrences.next());
+                    repetitions.extend(occurrences.map(|value| (pos, value)));
+                }
+                Segment {
+                    pos: 0,
+                    tag,
+                    elements,
+                    repetitions,
+                    raw: raw.to_string(),
+                    start,
+                    end,
+                }
+            },
         )
-            .map(|(tag, _, elements, _, _)| Segment {
-                tag,
-                elements,
-            })
     }
 }
 
+/// A `RawSegment` is a segment, which could not be parsed, e.g. because
+/// of broken escaping or stray characters. It is kept as is, including
+/// its terminator, if there is one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RawSegment {
+    /// The position of this segment among all segments of the
+    /// interchange, not counting UNA.
+    pub pos: usize,
+    pub start: Position,
+    pub value: String,
+}
+
 /// A `Composite` represents a composite element as part of
 /// a segment. It has to have at least two elements to be
 /// categorized as such.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Composite {
     pub elements: Vec<DataElement>,
+    #[serde(default)]
+    pub start: Position,
+    #[serde(default)]
+    pub end: Position,
 }
 
 impl Composite {
     pub fn parser<Input>(una: &UNA) -> impl Parser<Input, Output = Composite>
         where
-            Input: RangeStream<Token = char, Position = SourcePosition>,
+            Input: RangeStream<Token = char, Position = Position>,
             Input::Range: Range,
             Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
     {
         (
+            position(),
             DataElement::parser(*una),
-            char(una.clone().component_sep),
+            char(una.component_sep),
             sep_by1(
                 DataElement::parser(*una),
-                char(una.component_sep.clone()),
+                char(una.component_sep),
             ),
+            position(),
         )
             .map(
-                |(first, _, rest): (DataElement, char, Vec<DataElement>)| {
+                |(start, first, _, rest, end): (
+                    Position,
+                    DataElement,
+                    char,
+                    Vec<DataElement>,
+                    Position,
+                )| {
                     let mut elements = rest;
                     elements.insert(0, first);
-                    Composite { elements }
+                    Composite { elements, start, end }
                 },
             )
     }
@@ -232,16 +424,19 @@ impl Composite {
 pub struct DataElement {
     pub start: Position,
     pub end: Position,
-    /// The parsed value. Escaped characters will be contained without
-    /// the escaping character. Decimal strings will be normalized to
-    /// use '.'.
+    /// The parsed value. Escaped characters are contained without the
+    /// escape character, e.g. `Wert?+X` is parsed as `Wert+X`. Decimal
+    /// characters are kept as they have been received, since only the
+    /// description tells, whether a value is numeric. They are normalized
+    /// while decoding, see
+    /// [DecodeOptions::normalize_decimals](crate::mig::DecodeOptions).
     pub value: String,
 }
 
 impl DataElement {
     pub fn parser<Input>(una: UNA) -> impl Parser<Input, Output = DataElement>
         where
-            Input: RangeStream<Token = char, Position = SourcePosition>,
+            Input: RangeStream<Token = char, Position = Position>,
             Input::Range: Range,
             Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
     {
@@ -251,23 +446,70 @@ impl DataElement {
             any(),
         ));
         (position(), text, position()).map(
-            |(start, value, end): (SourcePosition, String, SourcePosition)| {
-                DataElement {
-                    start: Position { line: start.line, column: start.column },
-                    end: Position { line: end.line, column: end.column },
-                    value,
-                }
+            move |(start, value, end): (Position, String, Position)| {
+                DataElement { start, end, value: una.unescape_value(&value) }
             },
         )
     }
 }
 
-/// A `Position` is isomorphic to a `SourcePosition` and used to track
-/// the position of a data element in the input stream. It is used,
-/// to be able to implement serialize and deserialize traits.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A `Position` is used to track the position of a value in the input
+/// stream. Like a `SourcePosition`, it contains the line and column, but
+/// also the offset in bytes, e.g. to highlight a value in an editor.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
 pub struct Position {
     pub line: i32,
     pub column: i32,
+    /// The number of bytes before this position.
+    #[serde(default)]
+    pub offset: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position { line: 1, column: 1, offset: 0 }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line: {}, column: {}", self.line, self.column)
+    }
+}
+
+impl Positioner<char> for Position {
+    type Position = Position;
+    type Checkpoint = Self;
+
+    fn position(&self) -> Position {
+        *self
+    }
+
+    fn update(&mut self, token: &char) {
+        self.offset += token.len_utf8();
+        self.column += 1;
+        if *token == '\n' {
+            self.column = 1;
+            self.line += 1;
+        }
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        *self
+    }
+
+    fn reset(&mut self, checkpoint: Self::Checkpoint) {
+        *self = checkpoint;
+    }
+}
+
+impl<'a> RangePositioner<char, &'a str> for Position {
+    fn update_range(&mut self, range: &&'a str) {
+        for c in range.chars() {
+            self.update(&c);
+        }
+    }
 }
 
@@ -0,0 +1,224 @@
+//! Paths select values of parsed segments by their position, e.g. to
+//! extract the sender of every NAD+MS segment in an archive.
+//!
+//! A path consists of a tag, an optional qualifier and the optional
+//! position of an element and its component, e.g. `NAD+MS/2/1`. Positions
+//! start at 1 and do not count the tag. The qualifier is the first
+//! component of the first element, like `MS` in `NAD+MS` or `Z13` in
+//! `RFF+Z13:...`.
+//!
+//! Without an element, the value following the qualifier is selected, so
+//! `NAD+MS` selects the ID in the second element and `RFF+Z13` the
+//! reference in the second component. Paths without a qualifier select
+//! the first element.
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::mig::decode::parser::value::Interchange;
+use crate::mig::decode::routing::component;
+use crate::mig::decode::value;
+use crate::mig::either::Either;
+
+/// A `Path` to the values of segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    pub tag: String,
+    pub qualifier: Option<String>,
+    /// The position of the element, starting at 1.
+    pub element: Option<usize>,
+    /// The position of the component in the element, starting at 1.
+    pub component: Option<usize>,
+}
+
+/// The error returned, if a string is not a valid [Path].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidPath(String);
+
+impl fmt::Display for InvalidPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is not a valid path, expected e.g. NAD+MS or UNH/2/1",
+            self.0
+        )
+    }
+}
+
+impl error::Error for InvalidPath {}
+
+impl FromStr for Path {
+    type Err = InvalidPath;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidPath(s.to_string());
+        let position = |value: &str| match value.parse::<usize>() {
+            Ok(position) if position > 0 => Ok(position),
+            _ => Err(invalid()),
+        };
+
+        let mut parts = s.split('/');
+        let segment = parts.next().unwrap_or_default();
+        let element = parts.next().map(position).transpose()?;
+        let component = parts.next().map(position).transpose()?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        let (tag, qualifier) = match segment.split_once('+') {
+            Some((tag, qualifier)) if !qualifier.is_empty() => {
+                (tag, Some(qualifier.to_string()))
+            }
+            Some(_) => return Err(invalid()),
+            None => (segment, None),
+        };
+        if tag.len() != 3 || !tag.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(invalid());
+        }
+
+        Ok(Path { tag: tag.to_string(), qualifier, element, component })
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tag)?;
+        if let Some(qualifier) = &self.qualifier {
+            write!(f, "+{}", qualifier)?;
+        }
+        if let Some(element) = self.element {
+            write!(f, "/{}", element)?;
+        }
+        if let Some(component) = self.component {
+            write!(f, "/{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl Path {
+    /// Returns, whether the given decoded segment has the tag and, if
+    /// given, the qualifier of this path. The positions are ignored.
+    pub fn matches(&self, segment: &value::Segment) -> bool {
+        if segment.tag != self.tag {
+            return false;
+        }
+        let first = match segment.elements.first() {
+            Some(Either::Left(composite)) if composite.index == 0 => {
+                composite.elements.first()
+            }
+            Some(Either::Right(data_element)) => Some(data_element),
+            _ => None,
+        };
+        let qualifier = first
+            .filter(|data_element| data_element.index == 0)
+            .and_then(|data_element| data_element.text());
+        self.qualifier.is_none() || qualifier == self.qualifier.as_deref()
+    }
+
+    /// Returns the non-empty value of the given decoded segment selected
+    /// by this path, if the segment matches it.
+    pub fn value_of(&self, segment: &value::Segment) -> Option<String> {
+        if !self.matches(segment) {
+            return None;
+        }
+        let composite = matches!(
+            segment.elements.first(),
+            Some(Either::Left(composite)) if composite.index == 0
+        );
+        let (element, at) = self.positions(composite);
+        let data_element = segment.elements.iter().find_map(|e| match e {
+            Either::Left(composite) if composite.index == element - 1 => {
+                composite.elements.iter().find(|e| e.index == at - 1)
+            }
+            Either::Right(data_element)
+                if data_element.index == element - 1 && at == 1 =>
+            {
+                Some(data_element)
+            }
+            _ => None,
+        })?;
+        let value = data_element.value()?.to_string();
+        if value.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Returns the positions of the element and component selected, given
+    /// whether the first element of the segment is a composite.
+    fn positions(&self, composite: bool) -> (usize, usize) {
+        match (self.element, &self.qualifier) {
+            (Some(element), _) => (element, self.component.unwrap_or(1)),
+            (None, Some(_)) if composite => (1, 2),
+            (None, Some(_)) => (2, 1),
+            (None, None) => (1, 1),
+        }
+    }
+}
+
+impl Interchange {
+    /// Returns the non-empty values selected by the given path together
+    /// with the position of their segment.
+    pub fn select(&self, path: &Path) -> Vec<(usize, String)> {
+        let mut result = vec![];
+        for (pos, segment) in self.segments.iter().enumerate() {
+            if segment.tag.value != path.tag {
+                continue;
+            }
+            if path.qualifier.is_some()
+                && component(segment, 0, 0) != path.qualifier
+            {
+                continue;
+            }
+
+            let composite =
+                matches!(segment.elements.first(), Some(Either::Left(_)));
+            let (element, at) = path.positions(composite);
+            if let Some(value) = component(segment, element - 1, at - 1) {
+                result.push((pos, value));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::decode::parser;
+    use crate::mig::decode::path::Path;
+
+    fn select(path: &str) -> Vec<String> {
+        let input = &fixtures::aperak_with(
+            "DTM+137:202003070705:203'\
+             RFF+ACE:O1583553607732'\
+             NAD+MS+9900467000000::293'\
+             NAD+MR+9904590000002::293'",
+        );
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        let path: Path = path.parse().unwrap();
+        interchange.select(&path).into_iter().map(|(_, v)| v).collect()
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select("NAD+MS"), vec!["9900467000000"]);
+        assert_eq!(select("RFF+ACE"), vec!["O1583553607732"]);
+        assert_eq!(select("DTM+137/1/3"), vec!["203"]);
+        assert_eq!(select("UNH/2/1"), vec!["APERAK"]);
+        assert_eq!(select("NAD/2/3"), vec!["293", "293"]);
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            "NAD+MS/2/1".parse::<Path>().unwrap().to_string(),
+            "NAD+MS/2/1"
+        );
+        assert!("NAD+".parse::<Path>().is_err());
+        assert!("NAD/0".parse::<Path>().is_err());
+        assert!("nad".parse::<Path>().is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Extraction of the metadata needed to route or archive an interchange.
+//!
+//! The metadata are taken from the UNB and the first UNH segment of a
+//! parsed interchange by their position, so no description is needed.
+use chrono::TimeZone as _;
+use chrono_tz::Tz;
+
+use crate::mig::decode::parser::value::{Interchange, Segment};
+use crate::mig::description::MessageIdentifier;
+use crate::mig::either::Either;
+use crate::mig::validate::dtm::{TimeZone, Timestamp};
+
+/// The `Routing` metadata of an interchange.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Routing {
+    /// The ID of the sender from UNB 0004.
+    pub sender: Option<String>,
+    /// The code list of the sender ID from UNB 0007, e.g. `500`.
+    pub sender_code: Option<String>,
+    /// The ID of the receiver from UNB 0010.
+    pub receiver: Option<String>,
+    /// The code list of the receiver ID from UNB 0007.
+    pub receiver_code: Option<String>,
+    /// The interchange reference from UNB 0020.
+    pub reference: Option<String>,
+    /// The message type from UNH 0065, e.g. `UTILMD`.
+    pub message_type: Option<String>,
+    /// The version of the message type from UNH 0052 and 0054, e.g.
+    /// `D:11A`.
+    pub directory: Option<String>,
+    /// The version of the MIG from UNH 0057, e.g. `S2.1`.
+    pub version: Option<String>,
+    /// Whether the interchange is a test transmission (UNB 0035).
+    pub test: bool,
+    /// The creation date of the interchange from UNB 0017 and 0019 as
+    /// given, i.e. without a time zone.
+    pub created: Option<Timestamp>,
+}
+
+impl Routing {
+    /// Returns the creation date interpreted in the given time zone,
+    /// e.g. [TimeZone::German] within edi@energy, as a date and time of
+    /// chrono in its time zone of the tz database.
+    pub fn created_at(&self, zone: TimeZone) -> Option<chrono::DateTime<Tz>> {
+        let utc = zone.from_local(&self.created?).utc().to_naive()?;
+        Some(zone.tz().from_utc_datetime(&utc))
+    }
+}
+
+impl Interchange {
+    /// Returns the routing metadata of this interchange.
+    pub fn routing(&self) -> Routing {
+        let segment = |tag| self.segments.iter().find(|s| s.tag.value == tag);
+        let mut routing = Routing::default();
+
+        if let Some(unb) = segment("UNB") {
+            routing.sender = component(unb, 1, 0);
+            routing.sender_code = component(unb, 1, 1);
+            routing.receiver = component(unb, 2, 0);
+            routing.receiver_code = component(unb, 2, 1);
+            routing.reference = component(unb, 4, 0);
+            routing.test = component(unb, 10, 0).as_deref() == Some("1");
+            routing.created =
+                match (component(unb, 3, 0), component(unb, 3, 1)) {
+                    (Some(date), Some(time)) => parse_created(&date, &time),
+                    _ => None,
+                };
+        }
+
+        if let Some(unh) = segment("UNH") {
+            routing.message_type = component(unh, 1, 0);
+            routing.directory =
+                match (component(unh, 1, 1), component(unh, 1, 2)) {
+                    (Some(version), Some(release)) => {
+                        Some(format!("{}:{}", version, release))
+                    }
+                    _ => None,
+                };
+            routing.version = component(unh, 1, 4);
+        }
+
+        routing
+    }
+}
+
+/// Returns the message identifier S009 of the given UNH segment.
+pub(crate) fn message_identifier(unh: &Segment) -> MessageIdentifier {
+    MessageIdentifier {
+        message_type: component(unh, 1, 0),
+        version: component(unh, 1, 1),
+        release: component(unh, 1, 2),
+        agency: component(unh, 1, 3),
+        association_code: component(unh, 1, 4),
+    }
+}
+
+/// Returns the non-empty value of a component of the element at the
+/// given position. A simple data element is its own first component.
+pub(crate) fn component(segment: &Segment, element: usize, at: usize) -> Option<String> {
+    let value = match segment.elements.get(element)? {
+        Either::Left(composite) => composite.elements.get(at)?,
+        Either::Right(data_element) if at == 0 => data_element,
+        Either::Right(_) => return None,
+    };
+    if value.value.is_empty() {
+        None
+    } else {
+        Some(value.value.clone())
+    }
+}
+
+/// Parses the creation date of UNB, which is given as YYMMDD in syntax
+/// version 3 and CCYYMMDD in version 4.
+fn parse_created(date: &str, time: &str) -> Option<Timestamp> {
+    let number = |value: &str| -> Option<i64> {
+        if value.chars().all(|c| c.is_ascii_digit()) {
+            value.parse().ok()
+        } else {
+            None
+        }
+    };
+    if !date.is_ascii() || !time.is_ascii() || time.len() != 4 {
+        return None;
+    }
+
+    let (year, rest) = match date.len() {
+        6 => (2000 + number(&date[..2])?, &date[2..]),
+        8 => (number(&date[..4])?, &date[4..]),
+        _ => return None,
+    };
+    Some(Timestamp {
+        year,
+        month: number(&rest[..2])?,
+        day: number(&rest[2..])?,
+        hour: number(&time[..2])?,
+        minute: number(&time[2..])?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone as _, Timelike};
+    use chrono_tz::Europe::Berlin;
+
+    use crate::mig::decode::parser;
+    use crate::mig::validate::dtm::{TimeZone, Timestamp};
+
+    #[test]
+    fn test_routing() {
+        let input = "UNA:+.? 'UNB+UNOC:3+9900467000000:500+9904590000002:500\
+                     +200307:0705+C3AAAAAAAAHKLC++++++1'\
+                     UNH+1+APERAK:D:07B:UN:2.1d'UNT+2+1'\
+                     UNZ+1+C3AAAAAAAAHKLC'";
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        let routing = interchange.routing();
+
+        assert_eq!(routing.sender.as_deref(), Some("9900467000000"));
+        assert_eq!(routing.receiver_code.as_deref(), Some("500"));
+        assert_eq!(routing.reference.as_deref(), Some("C3AAAAAAAAHKLC"));
+        assert_eq!(routing.message_type.as_deref(), Some("APERAK"));
+        assert_eq!(routing.directory.as_deref(), Some("D:07B"));
+        assert_eq!(routing.version.as_deref(), Some("2.1d"));
+        assert!(routing.test);
+        assert_eq!(
+            routing.created,
+            Some(Timestamp {
+                year: 2020,
+                month: 3,
+                day: 7,
+                hour: 7,
+                minute: 5
+            })
+        );
+        let created = routing.created_at(TimeZone::German).unwrap();
+        let expected = Berlin.with_ymd_and_hms(2020, 3, 7, 7, 5, 0).unwrap();
+        assert_eq!(created, expected);
+        assert_eq!(created.naive_utc().hour(), 6);
+    }
+}
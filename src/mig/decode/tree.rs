@@ -0,0 +1,146 @@
+//! Rendering of decoded interchanges as an indented tree, e.g. to look
+//! at a large message on the command line:
+//!
+//! ```text
+//! 0000 UNB Nutzdaten-Kopfsegment
+//!   S001 Syntax-Bezeichner
+//!     0001 Syntax-Kennung: UNOC
+//! ...
+//! SG4 0160 Fehlerbeschreibung
+//!   0170 ERC Fehlercode
+//!     C901 Anwendungsfehler
+//!       9321 Anwendungsfehler, Code: Z29 (Erforderliche Angabe ...)
+//! ```
+//!
+//! Segments and segment groups start with their counter, data elements
+//! with their label. Data elements without a value are left out.
+use std::fmt::{self, Display, Formatter};
+
+use crate::mig::decode::value::{
+    DataElement, Interchange, Matched, Segment, Segmentgroup,
+};
+use crate::mig::description::Usage;
+use crate::mig::either::Either;
+
+impl Display for Interchange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_values(f, &self.segments, 0)
+    }
+}
+
+impl Display for Matched {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Matched::Text(text) => write!(f, "{}", text),
+            Matched::Int(int) => write!(f, "{}", int),
+            Matched::Decimal(decimal) => write!(f, "{}", decimal),
+        }
+    }
+}
+
+fn write_values(
+    f: &mut Formatter<'_>,
+    values: &[Either<Segmentgroup, Segment>],
+    depth: usize,
+) -> fmt::Result {
+    for value in values {
+        match value {
+            Either::Left(group) => write_group(f, group, depth)?,
+            Either::Right(segment) => write_segment(f, segment, depth)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_group(
+    f: &mut Formatter<'_>,
+    group: &Segmentgroup,
+    depth: usize,
+) -> fmt::Result {
+    indent(f, depth)?;
+    writeln!(f, "{} {} {}", group.label, group.counter, group.name)?;
+    write_values(f, &group.segments, depth + 1)
+}
+
+fn write_segment(
+    f: &mut Formatter<'_>,
+    segment: &Segment,
+    depth: usize,
+) -> fmt::Result {
+    indent(f, depth)?;
+    writeln!(f, "{} {} {}", segment.counter, segment.tag, segment.name)?;
+    for element in &segment.elements {
+        match element {
+            Either::Left(composite) => {
+                if composite.elements.iter().all(|e| e.value.is_none()) {
+                    continue;
+                }
+                indent(f, depth + 1)?;
+                writeln!(f, "{} {}", composite.label, composite.name)?;
+                for data_element in &composite.elements {
+                    write_data_element(f, data_element, depth + 2)?;
+                }
+            }
+            Either::Right(data_element) => {
+                write_data_element(f, data_element, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the data element with its value followed by the semantics of
+/// the code, if it has one.
+fn write_data_element(
+    f: &mut Formatter<'_>,
+    data_element: &DataElement,
+    depth: usize,
+) -> fmt::Result {
+    let Some(value) = &data_element.value else {
+        return Ok(());
+    };
+    let description = &data_element.description;
+    indent(f, depth)?;
+    write!(f, "{} {}: {}", description.label, description.name, value)?;
+    let semantics = match (&description.usage, data_element.text()) {
+        (Usage::OneOf { choices, .. }, Some(text)) => choices
+            .iter()
+            .find(|choice| choice.value == text)
+            .and_then(|choice| choice.semantics.as_deref()),
+        (Usage::Static { value, .. }, _) => value.semantics.as_deref(),
+        _ => None,
+    };
+    match semantics {
+        Some(semantics) => writeln!(f, " ({})", semantics),
+        None => writeln!(f),
+    }
+}
+
+fn indent(f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+    write!(f, "{:width$}", "", width = 2 * depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::{self, DecodeOptions};
+
+    #[test]
+    fn test_display() {
+        let desc = fixtures::aperak();
+        let options = DecodeOptions::default();
+        let interchange = mig::decode(
+            vec![desc],
+            &mut fixtures::APERAK.as_bytes(),
+            &options,
+        )
+        .unwrap();
+        let tree = interchange.to_string();
+        assert!(tree.starts_with("0000 UNB Nutzdaten-Kopfsegment\n"));
+        assert!(tree.contains("\n  S001 Syntax-Bezeichner\n"));
+        assert!(
+            tree.contains("\n    0007 Teilnehmerbezeichnung: 500 (BDEW)\n")
+        );
+        assert!(!tree.contains("0008"));
+    }
+}
@@ -1,146 +1,926 @@
 use crate::mig::description as desc;
-use crate::mig::description::{Format, Size, St, Usage};
+use crate::mig::description::{Format, Size, St};
 use crate::mig::either::Either;
-use crate::mig::error::{CompositeError, DataElementError, SegmentError, SyntaxError, InterchangeError, MessageError};
+use crate::mig::error::{
+    CompositeError, DataElementError, InterchangeError, MessageError,
+    SegmentError, ServiceSegmentError, Severity, SyntaxError,
+};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use combine::stream::position::Stream;
+use combine::Parser;
+use crate::mig::decode::compiled::{self, CompiledMig, Node};
+use crate::mig::decode::counters::Counters;
+use crate::mig::decode::hook::Observer;
+use crate::mig::decode::options::{
+    DecodeOptions, TestIndicator, UnknownSegments,
+};
 use crate::mig::decode::parser;
 use crate::mig::decode::parser::value;
+use crate::mig::decode::routing::{component, Routing};
+use crate::mig::decode::{Error, Limit};
+use crate::mig::security::{self, SecurityHeader, SecuritySegment};
+use crate::mig::validate::{dtm, ids};
+use std::cell::{Cell, RefCell};
+use std::error;
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+pub use crate::mig::decode::parser::value::{Position, RawSegment};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Interchange {
     pub segments: Vec<Either<Segmentgroup, Segment>>,
+    /// Whether the interchange is flagged as a test transmission in UNB
+    /// 0035.
+    #[serde(default)]
+    pub test: bool,
+    /// The segments, which could not be parsed and have been skipped
+    /// while matching, see [ParseMode::Salvage](crate::mig::ParseMode).
+    #[serde(default)]
+    pub raw_segments: Vec<RawSegment>,
+    /// The service characters of the interchange, which are the default
+    /// ones, if it does not start with a UNA. They are used to encode it
+    /// again, see [encode_with](crate::mig::encode_with).
+    #[serde(default)]
+    pub una: value::UNA,
+    /// The deviations, which have been tolerated, e.g. the value of a data
+    /// element with the status N, which is ignored, see [Severity].
+    #[serde(default, skip_deserializing)]
+    pub warnings: Vec<SegmentError>,
+    /// The security segments, which the description does not contain,
+    /// see [security](crate::mig::security).
+    #[serde(default)]
+    pub security: Vec<SecuritySegment>,
     //unb: Segment,
     //messages: Vec<Message>,
     //unz: Segment,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
-    unh: Segment,
-    segments: Vec<Either<Segmentgroup, Segment>>,
-    unt: Segment,
+    pub(crate) unh: Segment,
+    pub(crate) segments: Vec<Either<Segmentgroup, Segment>>,
+    pub(crate) unt: Segment,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Segmentgroup {
-    counter: String,
-    label: String,
-    st: desc::St,
-    max_reps: u64,
-    level: u64,
-    name: String,
-    comment: Option<String>,
-    segments: Vec<Either<Segmentgroup, Segment>>,
+    pub(crate) counter: String,
+    pub(crate) label: String,
+    pub(crate) st: desc::St,
+    pub(crate) max_reps: u64,
+    pub(crate) level: u64,
+    pub(crate) name: String,
+    pub(crate) comment: Option<String>,
+    pub(crate) segments: Vec<Either<Segmentgroup, Segment>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Segment {
-    index: usize,
-    counter: String,
-    number: u64,
-    tag: String,
-    st: desc::St,
-    max_reps: u64,
-    level: u64,
-    name: String,
-    comment: Option<String>,
-    elements: Vec<Either<Composite, DataElement>>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+    pub(crate) index: usize,
+    pub(crate) counter: String,
+    pub(crate) number: u64,
+    pub(crate) tag: String,
+    pub(crate) st: desc::St,
+    pub(crate) max_reps: u64,
+    pub(crate) level: u64,
+    pub(crate) name: String,
+    pub(crate) comment: Option<String>,
+    pub(crate) elements: Vec<Either<Composite, DataElement>>,
+    /// The segment as it has been received, including its terminator.
+    #[serde(default)]
+    pub(crate) raw: String,
+    #[serde(default)]
+    pub(crate) span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Composite {
-    index: usize,
-    label: String,
-    name: String,
-    st: desc::St,
-    elements: Vec<DataElement>,
+    pub(crate) index: usize,
+    pub(crate) label: String,
+    pub(crate) name: String,
+    pub(crate) st: desc::St,
+    pub(crate) elements: Vec<DataElement>,
+    #[serde(default)]
+    pub(crate) span: Span,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DataElement {
-    description: desc::DataElement,
-    index: usize,
-    value: Option<Matched>,
+    pub(crate) description: desc::DataElement,
+    pub(crate) index: usize,
+    pub(crate) value: Option<Matched>,
+    #[serde(default)]
+    pub(crate) span: Span,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A `Span` is the range of bytes a value occupies in the input, e.g. to
+/// highlight it in an editor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    fn between(start: &Position, end: &Position) -> Self {
+        Span { start: start.offset, end: end.offset }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Matched {
     Text(String),
     Int(u64),
     Decimal(f64),
 }
 
+/// The error returned, if a [Matched] value cannot be converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The value is not a text.
+    NotText,
+    /// The value is not an integer.
+    NotInteger,
+    /// The value is not a decimal number.
+    NotDecimal,
+    /// The format code of a date is not supported by [dtm::parse].
+    UnsupportedFormat(String),
+    /// The value does not match its format code or does not exist.
+    InvalidDate(dtm::Reason),
+    /// The segment does not contain a value with the given label.
+    Missing(String),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::NotText => write!(f, "The value is not a text"),
+            ValueError::NotInteger => {
+                write!(f, "The value is not an integer")
+            }
+            ValueError::NotDecimal => {
+                write!(f, "The value is not a decimal number")
+            }
+            ValueError::UnsupportedFormat(format) => {
+                write!(f, "The date format {} is not supported", format)
+            }
+            ValueError::InvalidDate(reason) => {
+                write!(f, "The date {}", reason)
+            }
+            ValueError::Missing(label) => {
+                write!(f, "There is no value for {}", label)
+            }
+        }
+    }
+}
+
+impl error::Error for ValueError {}
+
+impl Matched {
+    /// Returns this value, if it is a text.
+    pub fn as_str(&self) -> Result<&str, ValueError> {
+        match self {
+            Matched::Text(text) => Ok(text),
+            _ => Err(ValueError::NotText),
+        }
+    }
+
+    /// Returns this value as an integer, parsing it, if it is a text of
+    /// digits, like the ones of numeric data elements.
+    pub fn as_int(&self) -> Result<u64, ValueError> {
+        match self {
+            Matched::Int(int) => Ok(*int),
+            Matched::Text(text)
+                if text.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                text.parse().map_err(|_| ValueError::NotInteger)
+            }
+            _ => Err(ValueError::NotInteger),
+        }
+    }
+
+    /// Returns this value as a decimal number, parsing it, if it is a
+    /// text. The decimal character may be `.` or `,`.
+    pub fn as_decimal(&self) -> Result<f64, ValueError> {
+        match self {
+            Matched::Int(int) => Ok(*int as f64),
+            Matched::Decimal(decimal) => Ok(*decimal),
+            Matched::Text(text) => {
+                let is_number = text
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || b"-.,".contains(&b));
+                text.replace(',', ".")
+                    .parse()
+                    .ok()
+                    .filter(|_| is_number)
+                    .ok_or(ValueError::NotDecimal)
+            }
+        }
+    }
+
+    /// Returns this value as a date with the given format code of a DTM
+    /// segment (2379), see [dtm::parse].
+    pub fn as_date(&self, format: &str) -> Result<dtm::Date, ValueError> {
+        dtm::parse(self.as_str()?, format)
+            .ok_or_else(|| ValueError::UnsupportedFormat(format.to_string()))?
+            .map_err(ValueError::InvalidDate)
+    }
+}
+
+// QUERYING
+
+impl Interchange {
+    /// Returns all segments of this interchange in order, including the
+    /// ones nested in segment groups.
+    pub(crate) fn all_segments(&self) -> Vec<&Segment> {
+        let mut result = vec![];
+        collect_segments(&self.segments, &mut result);
+        result
+    }
+
+    /// Returns the messages of this interchange from their UNH to their
+    /// UNT in order.
+    pub fn messages(&self) -> Vec<Message> {
+        let mut result = vec![];
+        let mut current: Option<(Segment, Vec<_>)> = None;
+        for value in &self.segments {
+            match value {
+                Either::Right(unh) if unh.tag == "UNH" => {
+                    current = Some((unh.clone(), vec![]))
+                }
+                Either::Right(unt) if unt.tag == "UNT" => {
+                    if let Some((unh, segments)) = current.take() {
+                        let unt = unt.clone();
+                        result.push(Message { unh, segments, unt });
+                    }
+                }
+                value => {
+                    if let Some((_, segments)) = &mut current {
+                        segments.push(value.clone());
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the security headers of this interchange with their
+    /// trailers, see [security::headers].
+    pub fn security_headers(&self) -> Vec<SecurityHeader<'_>> {
+        security::headers(&self.security)
+    }
+}
+
+fn collect_segments<'a>(
+    values: &'a [Either<Segmentgroup, Segment>],
+    result: &mut Vec<&'a Segment>,
+) {
+    for value in values {
+        match value {
+            Either::Left(group) => collect_segments(&group.segments, result),
+            Either::Right(segment) => result.push(segment),
+        }
+    }
+}
+
+impl Message {
+    pub fn unh(&self) -> &Segment {
+        &self.unh
+    }
+
+    /// Returns all segments of this message from its UNH to its UNT in
+    /// order, including the ones nested in segment groups.
+    pub(crate) fn all_segments(&self) -> Vec<&Segment> {
+        let mut result = vec![&self.unh];
+        collect_segments(&self.segments, &mut result);
+        result.push(&self.unt);
+        result
+    }
+
+    pub fn segments(&self) -> &[Either<Segmentgroup, Segment>] {
+        &self.segments
+    }
+
+    pub fn unt(&self) -> &Segment {
+        &self.unt
+    }
+}
+
+impl Segmentgroup {
+    pub fn counter(&self) -> &str {
+        &self.counter
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn st(&self) -> St {
+        self.st
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn segments(&self) -> &[Either<Segmentgroup, Segment>] {
+        &self.segments
+    }
+}
+
+impl Segment {
+    /// Returns the position of this segment among all segments of the
+    /// interchange, not counting UNA.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn counter(&self) -> &str {
+        &self.counter
+    }
+
+    /// Returns the number of the segment in the MIG, e.g. to look it up
+    /// in a use case.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn st(&self) -> St {
+        self.st
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn elements(&self) -> &[Either<Composite, DataElement>] {
+        &self.elements
+    }
+
+    /// Returns the first composite with the given label, e.g. C082.
+    pub fn composite(&self, label: &str) -> Option<&Composite> {
+        self.elements.iter().find_map(|element| match element {
+            Either::Left(composite) if composite.label == label => {
+                Some(composite)
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the first data element with the given label, including the
+    /// ones nested in composites.
+    pub fn data_element(&self, label: &str) -> Option<&DataElement> {
+        self.data_elements()
+            .into_iter()
+            .find(|element| element.description.label == label)
+    }
+
+    /// Returns the segment as it has been received, including escape
+    /// characters and its terminator, e.g. to quote it in an error report.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Returns the bytes of the input this segment occupies, including
+    /// its terminator.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns all data elements of this segment in order, including the
+    /// ones nested in composites.
+    pub fn data_elements(&self) -> Vec<&DataElement> {
+        let mut result = vec![];
+        for element in &self.elements {
+            match element {
+                Either::Left(composite) => result.extend(&composite.elements),
+                Either::Right(data_element) => result.push(data_element),
+            }
+        }
+        result
+    }
+
+    /// Returns the text of the first data element with the given label.
+    pub fn text_of(&self, label: &str) -> Option<&str> {
+        self.data_element(label).and_then(|element| element.text())
+    }
+
+    /// Returns the date of this DTM segment, which is structured as given
+    /// by its format code, see [Matched::as_date].
+    pub fn date(&self) -> Result<dtm::Date, ValueError> {
+        let missing = |label: &str| ValueError::Missing(label.to_string());
+        let format = self.text_of("2379").ok_or_else(|| missing("2379"))?;
+        self.data_element("2380")
+            .and_then(|element| element.value())
+            .ok_or_else(|| missing("2380"))?
+            .as_date(format)
+    }
+
+    /// Returns the texts of all data elements with the given label, e.g.
+    /// the name parts 3036 of a NAD segment.
+    pub fn texts_of(&self, label: &str) -> Vec<&str> {
+        self.data_elements()
+            .into_iter()
+            .filter(|element| element.description.label == label)
+            .filter_map(|element| element.text())
+            .collect()
+    }
+}
+
+impl Composite {
+    /// Returns the position of this composite within its segment, not
+    /// counting the tag.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn st(&self) -> St {
+        self.st
+    }
+
+    pub fn elements(&self) -> &[DataElement] {
+        &self.elements
+    }
+
+    /// Returns the data element with the given label.
+    pub fn data_element(&self, label: &str) -> Option<&DataElement> {
+        self.elements
+            .iter()
+            .find(|element| element.description.label == label)
+    }
+
+    /// Returns the text of the data element with the given label.
+    pub fn text_of(&self, label: &str) -> Option<&str> {
+        self.data_element(label).and_then(|element| element.text())
+    }
+
+    /// Returns the bytes of the input this composite occupies.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl DataElement {
+    /// Returns the position of this data element within its segment or
+    /// composite.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn label(&self) -> &str {
+        &self.description.label
+    }
+
+    pub fn name(&self) -> &str {
+        &self.description.name
+    }
+
+    /// Returns the description this data element has been matched with.
+    pub fn description(&self) -> &desc::DataElement {
+        &self.description
+    }
+
+    pub fn value(&self) -> Option<&Matched> {
+        self.value.as_ref()
+    }
+
+    /// Returns the bytes of the input the value of this data element
+    /// occupies, including escape characters.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns the value of this data element, if it is a text.
+    pub fn text(&self) -> Option<&str> {
+        match &self.value {
+            Some(Matched::Text(text)) => Some(text.as_str()),
+            _ => None,
+        }
+    }
+}
+
 // MATCHING
 
-pub fn match_interchange(desc: &desc::Interchange, value: parser::value::Interchange) -> Result<Interchange, InterchangeError> {
-    let mut segments = vec![
-        Either::Right(desc.unb.clone()),
-        Either::Right(desc.message.unh.clone()),
-    ];
-    let mut s = desc.message.segments.clone();
-    segments.append(&mut s);
-    segments.append(&mut vec![
-        Either::Right(desc.message.unt.clone()),
-        Either::Right(desc.unz.clone()),
-    ]);
-    let mut values = value.segments;
+/// Returns an error of the data element at the given position of UNB.
+pub(super) fn unb_error(pos: usize, error: SyntaxError) -> InterchangeError {
+    InterchangeError {
+        pos: 0,
+        service_segment_error: Some(ServiceSegmentError {
+            tag: "UNB".to_string(),
+            error: Either::Right(DataElementError::new(pos, error)),
+        }),
+        message_errors: vec![],
+        una: None,
+    }
+}
+
+/// The `Context` contains everything needed for matching, which does
+/// not change throughout an interchange.
+pub(super) struct Context<'a> {
+    pub(super) options: &'a DecodeOptions,
+    pub(super) una: value::UNA,
+    /// The hook called with every matched segment, if any.
+    pub(super) hook: Option<&'a dyn Observer>,
+    /// Whether the segment groups are nested deeper than allowed by
+    /// [DecodeOptions::max_group_depth].
+    pub(super) too_deep: Cell<bool>,
+    /// The warnings of the segments matched so far.
+    pub(super) warnings: RefCell<Vec<SegmentError>>,
+}
+
+impl Context<'_> {
+    /// Returns, whether matching has to stop, because the hook broke or
+    /// a limit has been exceeded.
+    fn aborted(&self) -> bool {
+        self.hook.is_some_and(|hook| hook.aborted()) || self.too_deep.get()
+    }
+}
+
+/// A borrowed description of a segment group or segment together with
+/// its compiled node, so matching does not need to clone the description.
+pub(super) type Desc<'a> =
+    (Either<&'a desc::Segmentgroup, &'a desc::Segment>, &'a Node);
+
+/// The segment groups and segments matched or the errors found, when
+/// matching at a position.
+type Matches = Result<Vec<Either<Segmentgroup, Segment>>, Vec<SegmentError>>;
+
+/// The composite or data element matched or its error.
+type ElementMatch = Result<
+    Either<Composite, DataElement>,
+    Either<CompositeError, DataElementError>,
+>;
+
+/// Returns the descriptions of the interchange from UNB to UNZ with their
+/// nodes.
+pub(super) fn interchange_descs(
+    mig: &CompiledMig,
+) -> impl Iterator<Item = Desc<'_>> {
+    let desc = mig.description();
+    [
+        (Either::Right(&desc.unb), &mig.unb),
+        (Either::Right(&desc.message.unh), &mig.unh),
+    ]
+    .into_iter()
+    .chain(
+        desc.message
+            .segments
+            .iter()
+            .map(Either::as_ref)
+            .zip(&mig.segments),
+    )
+    .chain([
+        (Either::Right(&desc.message.unt), &mig.unt),
+        (Either::Right(&desc.unz), &mig.unz),
+    ])
+}
+
+/// Checks the test indicator, sender and receiver of UNB against the
+/// options.
+pub(super) fn check_routing(
+    routing: &Routing,
+    options: &DecodeOptions,
+) -> Result<(), InterchangeError> {
+    if routing.test && options.test_indicator == TestIndicator::Reject {
+        // The test indicator is the 11th element of UNB.
+        return Err(unb_error(10, SyntaxError::test_not_supported()));
+    }
+    if options.sender.is_some() && routing.sender != options.sender {
+        return Err(unb_error(1, SyntaxError::unknown_sender()));
+    }
+    if options.receiver.is_some() && routing.receiver != options.receiver {
+        return Err(unb_error(
+            2,
+            SyntaxError::receiver_is_not_actual_receiver(),
+        ));
+    }
+    Ok(())
+}
+
+pub fn match_interchange(
+    mig: &CompiledMig,
+    value: parser::value::Interchange,
+    options: &DecodeOptions,
+) -> Result<Interchange, Error> {
+    match_interchange_with(mig, value, options, None)
+}
+
+/// Matches the interchange like [match_interchange] and calls the given
+/// hook with every matched segment. If the hook breaks, the result is
+/// incomplete.
+pub(super) fn match_interchange_with(
+    mig: &CompiledMig,
+    value: parser::value::Interchange,
+    options: &DecodeOptions,
+    hook: Option<&dyn Observer>,
+) -> Result<Interchange, Error> {
+    let segments = interchange_descs(mig);
+    let routing = value.routing();
+    let raw_segments = value.raw_segments;
+    check_routing(&routing, options).map_err(|error| InterchangeError {
+        una: Some(value.una),
+        ..error
+    })?;
+
+    let ctx = Context {
+        options,
+        una: value.una,
+        hook,
+        too_deep: Cell::new(false),
+        warnings: RefCell::new(vec![]),
+    };
+    let mut errors: Vec<SegmentError> = vec![];
+    let mut values = vec![];
+    let mut security = vec![];
+    for segment in value.segments {
+        if mig.knows_tag(&segment.tag.value) {
+            values.push(segment);
+        } else if security::is_security_segment(&segment.tag.value) {
+            security.push(SecuritySegment::new(segment));
+        } else if options.unknown_segments == UnknownSegments::Reject {
+            errors.push(SegmentError {
+                pos: segment.pos,
+                severity: Severity::Error,
+                syntax_error: Some(
+                    SyntaxError::not_supported_at_this_position(),
+                ),
+                errors: vec![],
+                raw: Some(segment.raw),
+            });
+        }
+    }
+
+    // The messages are reported separately, see message_errors.
+    let unhs: Vec<usize> = values
+        .iter()
+        .filter(|value| value.tag.value == "UNH")
+        .map(|value| value.pos)
+        .collect();
+    let unz = values.iter().rfind(|value| value.tag.value == "UNZ");
+    let unz = unz.map(|value| value.pos);
+    let (unt_errors, unz_error) = counter_errors(&values);
     values.reverse();
-    match matching(0, &segments, &mut values) {
-        (_, Ok(result)) => {
-            Ok(Interchange { segments: result })
+    let result = match_messages(segments.collect(), &mut values, &ctx);
+    if let Some(max) = options.max_group_depth.filter(|_| ctx.too_deep.get())
+    {
+        return Err(Error::Limit(Limit::GroupDepth(max)));
+    }
+
+    // Segments left over after matching are not supported at their
+    // position, they either are out of order or repeated too often.
+    let complete = unz.is_some_and(|unz| values.iter().all(|v| v.pos > unz));
+    for value in values.iter().rev() {
+        let error = SegmentError {
+            pos: value.pos,
+            severity: Severity::Warning,
+            syntax_error: Some(SyntaxError::not_supported_at_this_position()),
+            errors: vec![],
+            raw: Some(value.raw.clone()),
+        };
+        if tolerates_leftover(value, complete, options) {
+            ctx.warnings.borrow_mut().push(error);
+        } else {
+            errors.push(SegmentError { severity: Severity::Error, ..error });
         }
-        (_, Err(error)) => {
-            let msg_error = MessageError {
-                pos: 0,
-                service_segment_error: None,
-                segment_errors: error,
-            };
+    }
 
-            Err(InterchangeError {
-                pos: 0,
-                message_errors: vec![msg_error],
-                service_segment_error: None
+    let counted = unt_errors.is_empty() && unz_error.is_none();
+    match result {
+        Ok(result) if errors.is_empty() && counted => {
+            Ok(Interchange {
+                segments: result,
+                test: routing.test,
+                raw_segments,
+                una: ctx.una,
+                warnings: ctx.warnings.take(),
+                security,
             })
         }
+        result => {
+            let mut segment_errors = result.err().unwrap_or_default();
+            segment_errors.append(&mut errors);
+            // The errors of unknown and left over segments are reported
+            // in the order of the segments.
+            segment_errors.sort_by_key(|error| error.pos);
+            let mut message_errors = message_errors(segment_errors, &unhs);
+            let mut left = options.max_errors.unwrap_or(usize::MAX);
+            for error in &mut message_errors {
+                if let Some(max) = options.max_identical_errors {
+                    error.limit_identical(max);
+                }
+                error.segment_errors.truncate(left);
+                left -= error.segment_errors.len();
+            }
+            message_errors.retain(|error| !error.segment_errors.is_empty());
+            for (pos, error) in unt_errors {
+                let i = message_errors.partition_point(|m| m.pos < pos);
+                match message_errors.get_mut(i).filter(|m| m.pos == pos) {
+                    Some(message) => {
+                        message.service_segment_error = Some(error)
+                    }
+                    None => message_errors.insert(i, MessageError {
+                        pos,
+                        service_segment_error: Some(error),
+                        segment_errors: vec![],
+                        repeated: vec![],
+                    }),
+                }
+            }
+
+            Err(Error::Mig(InterchangeError {
+                pos: 0,
+                message_errors,
+                service_segment_error: unz_error,
+                una: Some(ctx.una),
+            }))
+        }
     }
 }
 
-fn matching(
-    pos: usize,
-    descs: &Vec<Either<desc::Segmentgroup, desc::Segment>>,
+/// Returns the errors of the counters and references in the UNTs of the
+/// given segments, each with the position of the UNH of its message, and
+/// the error of the UNZ.
+fn counter_errors(
+    values: &[parser::value::Segment],
+) -> (Vec<(usize, ServiceSegmentError)>, Option<ServiceSegmentError>) {
+    let mut counters = Counters::default();
+    let mut unt_errors = vec![];
+    let mut unz_error = None;
+    // The UNH follows UNB at 1, if there is none.
+    let mut unh = 1;
+    for value in values {
+        let text = |element| component(value, element, 0);
+        match value.tag.value.as_str() {
+            "UNB" => counters.unb(text(4)),
+            "UNH" => {
+                unh = value.pos;
+                counters.unh(value.pos, text(0));
+            }
+            "UNT" => {
+                let error = counters.unt(value.pos, text(0), text(1));
+                unt_errors.extend(error.map(|error| (unh, error)));
+            }
+            "UNZ" => unz_error = counters.unz(text(0), text(1)),
+            _ => {}
+        }
+    }
+    (unt_errors, unz_error)
+}
+
+/// Returns, whether the given segment left over after matching is
+/// tolerated as a warning. Only segments after the UNZ of a completely
+/// matched interchange are, since any other one is missing in the decoded
+/// tree. A UNH, UNT or UNZ is never tolerated.
+pub(super) fn tolerates_leftover(
+    value: &parser::value::Segment,
+    complete: bool,
+    options: &DecodeOptions,
+) -> bool {
+    let service = matches!(value.tag.value.as_str(), "UNH" | "UNT" | "UNZ");
+    options.tolerates_warnings() && complete && !service
+}
+
+/// Matches the segments on the stack with the given descriptions of the
+/// interchange from UNB to UNZ. The descriptions from UNH to UNT are
+/// matched once for every message of the interchange.
+fn match_messages(
+    descs: Vec<Desc>,
     stack: &mut Vec<parser::value::Segment>,
-) -> (usize, Result<Vec<Either<Segmentgroup, Segment>>, Vec<SegmentError>>) {
-    let mut index = pos;
+    ctx: &Context,
+) -> Matches {
+    let (unb, rest) = descs.split_at(1.min(descs.len()));
+    let (message, unz) = rest.split_at(rest.len().saturating_sub(1));
+    let mut results = vec![matching(unb.iter().copied(), stack, ctx, 0)];
+    loop {
+        results.push(matching(message.iter().copied(), stack, ctx, 0));
+        let next = stack.last().map(|value| value.tag.value.as_str());
+        if next != Some("UNH") || ctx.aborted() {
+            break;
+        }
+    }
+    results.push(matching(unz.iter().copied(), stack, ctx, 0));
+
+    let mut matches = vec![];
+    let mut errors = vec![];
+    for result in results {
+        match result {
+            Ok(mut values) => matches.append(&mut values),
+            Err(mut error) => errors.append(&mut error),
+        }
+    }
+    if errors.is_empty() {
+        Ok(matches)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Returns the errors of every message with the given positions of their
+/// UNH, which has errors. The errors of UNB and UNZ are reported with the
+/// first and last message, respectively.
+fn message_errors(
+    errors: Vec<SegmentError>,
+    unhs: &[usize],
+) -> Vec<MessageError> {
+    // The UNH follows UNB at 1, if there is none.
+    let unhs = if unhs.is_empty() { &[1][..] } else { unhs };
+    let mut result: Vec<MessageError> = vec![];
+    for error in errors {
+        let i = unhs.iter().rposition(|pos| *pos <= error.pos).unwrap_or(0);
+        match result.last_mut() {
+            Some(last) if last.pos == unhs[i] => {
+                last.segment_errors.push(error)
+            }
+            _ => result.push(MessageError {
+                pos: unhs[i],
+                service_segment_error: None,
+                segment_errors: vec![error],
+                repeated: vec![],
+            }),
+        }
+    }
+    result
+}
+
+/// Matches the segments on the stack with the given descriptions, which
+/// are nested in the given number of segment groups.
+fn matching<'a>(
+    descs: impl Iterator<Item = Desc<'a>>,
+    stack: &mut Vec<parser::value::Segment>,
+    ctx: &Context,
+    depth: usize,
+) -> Matches {
     let mut matches: Vec<Either<Segmentgroup, Segment>> = vec![];
     let mut errors: Vec<SegmentError> = vec![];
-    for (_counter, next) in &descs.iter().group_by(|v| get_counter(v)) {
-        let mut next_descs: Vec<_> = next.collect();
-        let check_qualifier = next_descs.len() > 1;
+    for (_counter, next) in &descs.group_by(|v| get_counter(v)) {
+        let mut next_descs: Vec<Desc> = next.collect();
+        let check_qualifier =
+            ctx.options.check_qualifiers && next_descs.len() > 1;
         while let Some(v) = stack.pop() {
-            let next_match = next_descs.iter().position(|d| match d {
-                Either::Left(desc) => matches_segmentgroup(desc, check_qualifier, &v),
-                Either::Right(desc) => matches_segment(desc, check_qualifier, &v),
+            if ctx.options.reached_max_errors(errors.len()) || ctx.aborted() {
+                stack.push(v);
+                break;
+            }
+
+            let next_match = next_descs.iter().position(|(d, node)| match d {
+                Either::Left(desc) => {
+                    matches_segmentgroup(desc, node, check_qualifier, &v)
+                }
+                Either::Right(desc) => {
+                    matches_compiled(desc, node, check_qualifier, &v)
+                }
             });
 
             if let Some(i) = next_match {
-                match &next_descs[i] {
-                    Either::Right(desc) => {
-                        match match_segment(index, desc, &v) {
+                match next_descs[i] {
+                    (Either::Right(desc), _) => {
+                        match match_segment(v.pos, desc, &v, ctx) {
                             Ok(matched) => {
+                                if let Some(hook) = ctx.hook {
+                                    hook.call(&matched);
+                                }
                                 matches.push(Either::Right(matched))
                             }
                             Err(error) => errors.push(error),
                         };
-                        index += 1;
                         // TODO: Or if they have been consumed
                         if desc.max_reps == 1 {
                             next_descs.remove(i);
                         }
                     }
-                    Either::Left(desc) => {
-                        match matching(index, &desc.segments, stack) {
-                            (next, Ok(values)) => {
+                    (Either::Left(desc), node) => {
+                        // The value starts the group, so it has to be
+                        // matched by the first description of the group.
+                        stack.push(v);
+                        let max = ctx.options.max_group_depth;
+                        if max.is_some_and(|max| depth >= max) {
+                            ctx.too_deep.set(true);
+                            break;
+                        }
+                        let descs = children(desc, node);
+                        match matching(descs, stack, ctx, depth + 1) {
+                            Ok(values) => {
                                 matches.push(Either::Left(Segmentgroup {
                                     counter: desc.counter.clone(),
                                     label: desc.label.clone(),
@@ -151,12 +931,8 @@ fn matching(
                                     comment: desc.comment.clone(),
                                     segments: values,
                                 }));
-                                index += next;
-                            }
-                            (next, Err(mut error)) => {
-                                index += next;
-                                errors.append(&mut error)
                             }
+                            Err(mut error) => errors.append(&mut error),
                         }
                     }
                 }
@@ -169,83 +945,115 @@ fn matching(
     }
 
     if !errors.is_empty() {
-        (index, Err(errors))
+        Err(errors)
     } else {
-        (index, Ok(matches))
+        Ok(matches)
     }
 }
 
+/// Returns the descriptions of the given group with their nodes.
+pub(super) fn children<'a>(
+    desc: &'a desc::Segmentgroup,
+    node: &'a Node,
+) -> impl Iterator<Item = Desc<'a>> {
+    let nodes: &[Node] = match node {
+        Node::Group(nodes) => nodes,
+        Node::Segment(_) => &[],
+    };
+    desc.segments.iter().map(Either::as_ref).zip(nodes)
+}
+
 /// Returns, if this segmentgroup starts with the given value.
-fn matches_segmentgroup(
+pub(super) fn matches_segmentgroup(
     desc: &desc::Segmentgroup,
+    node: &Node,
     check_qualifier: bool,
     value: &value::Segment,
 ) -> bool {
-    match desc.segments.as_slice() {
-        [Either::Right(segment), ..] => {
-            matches_segment(segment, check_qualifier, &value)
+    match children(desc, node).next() {
+        Some((Either::Right(segment), node)) => {
+            matches_compiled(segment, node, check_qualifier, value)
         }
-        _ => false,
+        // The group starts with a nested group, which has to start with
+        // the value in turn.
+        Some((Either::Left(group), node)) => {
+            matches_segmentgroup(group, node, check_qualifier, value)
+        }
+        None => false,
     }
 }
 
-/// Returns, whether the given value matches this segment description.
-pub fn matches_segment(
+/// Returns, whether the given value matches the segment description and
+/// contains one of the allowed values in all of its compiled qualifiers,
+/// if they are checked.
+pub(super) fn matches_compiled(
     desc: &desc::Segment,
+    node: &Node,
     check_qualifier: bool,
     value: &value::Segment,
 ) -> bool {
-    if !check_qualifier {
-        return desc.tag == value.tag.value;
-    } else if desc.tag != value.tag.value {
+    if desc.tag != value.tag.value {
         return false;
+    } else if !check_qualifier {
+        return true;
     }
+    match node {
+        Node::Segment(qualifiers) if !qualifiers.is_empty() => {
+            qualifiers.iter().all(|qualifier| {
+                data_element(value, qualifier.element, qualifier.component)
+                    .is_some_and(|v| qualifier.values.contains(&v.value))
+            })
+        }
+        _ => false,
+    }
+}
 
-    let qualifier = desc
-        .elements
-        .get(0)
-        .and_then(|element| match element {
-            Either::Left(composite) => composite.elements.get(0),
-            Either::Right(data_element) => Some(data_element),
-        })
-        .and_then(|data_element| {
-            if data_element.is_qualifier() {
-                Some(data_element.usage.clone())
-            } else {
-                None
-            }
-        });
+/// Returns the data element at the given position of the value. A data
+/// element takes the place of the first component of a composite.
+fn data_element(
+    value: &value::Segment,
+    element: usize,
+    component: usize,
+) -> Option<&value::DataElement> {
+    match value.elements.get(element)? {
+        Either::Left(composite) => composite.elements.get(component),
+        Either::Right(data_element) if component == 0 => Some(data_element),
+        Either::Right(_) => None,
+    }
+}
 
-    let option_data_element =
-        value.elements.get(0).and_then(|element| match element {
-            Either::Left(composite) => composite.elements.get(0),
-            Either::Right(data_element) => Some(data_element),
-        });
+/// Returns, whether the given value matches this segment description.
+pub fn matches_segment(
+    desc: &desc::Segment,
+    check_qualifier: bool,
+    value: &value::Segment,
+) -> bool {
+    let node = Node::Segment(compiled::qualifiers(desc));
+    matches_compiled(desc, &node, check_qualifier, value)
+}
 
-    match (qualifier, option_data_element) {
-        (
-            Some(Usage::OneOf { choices, comment: _ }),
-            Some(data_element),
-        ) => choices.iter().any(|c| c.value == data_element.value),
-        (
-            Some(Usage::Static { value, comment: _ }),
-            Some(data_element),
-        ) => value.value == data_element.value,
-        _ => false,
+/// Returns, whether the given element does not contain any characters.
+fn is_empty(element: &Either<value::Composite, value::DataElement>) -> bool {
+    match element {
+        Either::Left(composite) => {
+            composite.elements.iter().all(|e| e.value.is_empty())
+        }
+        Either::Right(data_element) => data_element.value.is_empty(),
     }
 }
 
-fn get_counter(desc: &Either<desc::Segmentgroup, desc::Segment>) -> String {
+pub(super) fn get_counter<'a>((desc, _): &Desc<'a>) -> &'a str {
     match desc {
-        Either::Left(v) => v.counter.clone(),
-        Either::Right(v) => v.counter.clone(),
+        Either::Left(v) => &v.counter,
+        Either::Right(v) => &v.counter,
     }
 }
 
-fn match_segment(
+pub(super) fn match_segment(
     pos: usize,
     desc: &desc::Segment,
     segment: &parser::value::Segment,
+    ctx: &Context,
 ) -> Result<Segment, SegmentError> {
     let mut descs = desc.elements.iter();
     let mut values = segment.elements.iter();
@@ -257,21 +1065,31 @@ fn match_segment(
     let mut syntax_error: Option<SyntaxError> = None;
     let mut matches: Vec<Either<Composite, DataElement>> = vec![];
     let mut errors: Vec<Either<CompositeError, DataElementError>> = vec![];
+    let mut warning: Option<SyntaxError> = None;
+    let mut warnings: Vec<Either<CompositeError, DataElementError>> = vec![];
 
     loop {
         match (descs.next(), values.next()) {
             // No descriptions and no values anymore, we are done
             (None, None) => break,
-            (None, Some(_)) => {
+            (None, Some(value)) => {
                 // Too many elements. edi@energy does not support repetition,
-                // therefore no descriptions available anymore, bail
-                syntax_error = Some(SyntaxError::too_many_parts());
+                // therefore no descriptions available anymore, bail. Empty
+                // trailing elements may be tolerated though.
+                let tolerated = ctx.options.tolerates_warnings()
+                    && is_empty(value)
+                    && values.all(is_empty);
+                if tolerated {
+                    warning = Some(SyntaxError::too_many_parts());
+                } else {
+                    syntax_error = Some(SyntaxError::too_many_parts());
+                }
                 break;
             }
             (Some(Either::Right(desc)), None) => {
                 // Found a description, but no corresponding value. This is
                 // fine, if the element is not required.
-                if desc.st.is_required() {
+                if status(desc, ctx).is_required() {
                     errors.push(Either::Right(DataElementError::new(
                         position,
                         SyntaxError::missing(),
@@ -288,44 +1106,31 @@ fn match_segment(
                     )));
                 }
             }
-            (Some(Either::Right(_)), Some(Either::Left(_))) => {
-                // Assumption: Every composite with only one element is
-                // a  data element. Now: Expecting a data element, but
-                // finding a composite is completely wrong. If it had only
-                // one element, we could interpret it as a data element
-                // making the whole thing more robust, but we skip that here
-                errors.push(Either::Right(DataElementError::new(
-                    position,
-                    SyntaxError::invalid_value(),
-                )))
-            }
-            (Some(Either::Left(desc)), Some(Either::Right(value))) => {
-                // Found a composite description, but a data element value
-                // this is only okay, if the composite has one element or
-                // is not required and the value is empty
-                if !(value.value == "" && desc.st == St::N) {
-                    let composite_value =
-                        value::Composite { elements: vec![value.clone()] };
-                    match match_composite(position, desc, &composite_value) {
-                        Ok(composite) => matches.push(Either::Left(composite)),
-                        Err(error) => errors.push(Either::Left(error)),
+            (Some(desc), Some(value)) => {
+                let max = match desc {
+                    Either::Left(desc) => desc.max_occurs,
+                    Either::Right(desc) => desc.max_occurs,
+                };
+                let repetitions = segment
+                    .repetitions
+                    .iter()
+                    .filter(|(pos, _)| *pos == position)
+                    .map(|(_, value)| value);
+                let occurrences = std::iter::once(value).chain(repetitions);
+                for (i, value) in occurrences.enumerate() {
+                    if i >= max.unwrap_or(1) {
+                        errors.push(Either::Right(DataElementError::new(
+                            position,
+                            SyntaxError::too_many_repetitions(),
+                        )));
+                        break;
                     }
-                }
-            }
-            (Some(Either::Left(desc)), Some(Either::Left(value))) => {
-                match match_composite(position, desc, value) {
-                    Ok(composite) => matches.push(Either::Left(composite)),
-                    Err(error) => errors.push(Either::Left(error)),
-                }
-            }
-            (Some(Either::Right(desc)), Some(Either::Right(value))) => {
-                // TODO: make data_element borrow
-                match match_data_element(position, desc.clone(), value.clone())
-                {
-                    Ok(data_element) => {
-                        matches.push(Either::Right(data_element))
+                    let warnings = &mut warnings;
+                    match match_element(position, desc, value, ctx, warnings) {
+                        Some(Ok(matched)) => matches.push(matched),
+                        Some(Err(error)) => errors.push(error),
+                        None => {}
                     }
-                    Err(error) => errors.push(Either::Right(error)),
                 }
             }
         }
@@ -333,37 +1138,192 @@ fn match_segment(
     }
 
     if !errors.is_empty() || syntax_error.is_some() {
+        return Err(SegmentError {
+            pos,
+            severity: Severity::Error,
+            syntax_error,
+            errors,
+            raw: Some(segment.raw.clone()),
+        });
+    }
+
+    let segment = Segment {
+        index: pos,
+        counter: desc.counter.clone(),
+        number: desc.number,
+        tag: desc.tag.clone(),
+        st: desc.st,
+        max_reps: desc.max_reps,
+        level: desc.level,
+        name: desc.name.clone(),
+        comment: desc.comment.clone(),
+        elements: matches,
+        raw: segment.raw.clone(),
+        span: Span::between(&segment.start, &segment.end),
+    };
+
+    let errors = ids::check_segment(&segment, &ctx.options.identifiers);
+    if !errors.is_empty() {
         Err(SegmentError {
-            pos: pos,
-            syntax_error: syntax_error,
-            errors: errors,
+            pos,
+            severity: Severity::Error,
+            syntax_error: None,
+            errors,
+            raw: Some(segment.raw),
         })
     } else {
-        Ok(Segment {
-            index: pos,
-            counter: desc.counter.clone(),
-            number: desc.number,
-            tag: desc.tag.clone(),
-            st: desc.st,
-            max_reps: desc.max_reps,
-            level: desc.level,
-            name: desc.name.clone(),
-            comment: desc.comment.clone(),
-            elements: matches,
-        })
+        if warning.is_some() || !warnings.is_empty() {
+            ctx.warnings.borrow_mut().push(SegmentError {
+                pos,
+                severity: Severity::Warning,
+                syntax_error: warning,
+                errors: warnings,
+                raw: Some(segment.raw.clone()),
+            });
+        }
+        Ok(segment)
+    }
+}
+
+/// Matches an occurrence of an element at the given position of a
+/// segment with its description. Returns nothing, if an empty value of a
+/// composite not used is omitted.
+fn match_element(
+    position: usize,
+    desc: &Either<desc::Composite, desc::DataElement>,
+    value: &Either<value::Composite, value::DataElement>,
+    ctx: &Context,
+    warnings: &mut Vec<Either<CompositeError, DataElementError>>,
+) -> Option<ElementMatch> {
+    match (desc, value) {
+        (Either::Right(_), Either::Left(_)) => {
+            // Assumption: Every composite with only one element is
+            // a  data element. Now: Expecting a data element, but
+            // finding a composite is completely wrong. If it had only
+            // one element, we could interpret it as a data element
+            // making the whole thing more robust, but we skip that here
+            Some(Err(Either::Right(DataElementError::new(
+                position,
+                SyntaxError::invalid_value(),
+            ))))
+        }
+        (Either::Left(desc), Either::Right(value)) => {
+            // Found a composite description, but a data element value
+            // this is only okay, if the composite has one element or
+            // is not required and the value is empty
+            if value.value.is_empty() && desc.st == St::N {
+                return None;
+            }
+            let composite_value = value::Composite {
+                elements: vec![value.clone()],
+                start: value.start,
+                end: value.end,
+            };
+            let composite = &composite_value;
+            let result =
+                match_composite(position, desc, composite, ctx, warnings);
+            Some(result.map(Either::Left).map_err(Either::Left))
+        }
+        (Either::Left(desc), Either::Left(value)) => {
+            let result = match_composite(position, desc, value, ctx, warnings);
+            Some(result.map(Either::Left).map_err(Either::Left))
+        }
+        (Either::Right(desc), Either::Right(value)) => {
+            let mut value = value.clone();
+            if is_ignored(desc, &value, ctx) {
+                warnings.push(Either::Right(DataElementError::new(
+                    position,
+                    SyntaxError::invalid_value(),
+                )));
+                value.value.clear();
+            }
+            // TODO: make data_element borrow
+            let result =
+                match_data_element(position, desc.clone(), value, ctx);
+            Some(result.map(Either::Right).map_err(Either::Right))
+        }
     }
 }
 
+/// Matches the given text of a segment with the description, as if the
+/// segment was at the given position of an interchange with the default
+/// UNA. The text has to include the terminator.
+pub(crate) fn match_text(
+    pos: usize,
+    desc: &desc::Segment,
+    text: &str,
+) -> Result<Segment, SegmentError> {
+    let una = value::UNA::default();
+    let stream = Stream::with_positioner(text, value::Position::default());
+    let segment = match value::Segment::parser(&una).parse(stream) {
+        Ok((segment, rest))
+            if rest.input.is_empty() && segment.tag.value == desc.tag =>
+        {
+            segment
+        }
+        _ => {
+            return Err(SegmentError {
+                pos,
+                severity: Severity::Error,
+                syntax_error: Some(SyntaxError::invalid_value()),
+                errors: vec![],
+                raw: Some(text.to_string()),
+            })
+        }
+    };
+
+    let options = DecodeOptions::default();
+    let ctx = Context {
+        options: &options,
+        una,
+        hook: None,
+        too_deep: Cell::new(false),
+        warnings: RefCell::new(vec![]),
+    };
+    match_segment(pos, desc, &segment, &ctx)
+}
+
+/// Returns the errors of the given value, which is not escaped, for the
+/// description of its data element.
+pub(crate) fn check_value(
+    desc: &desc::DataElement,
+    value: &str,
+) -> Vec<SyntaxError> {
+    let mut errors: Vec<SyntaxError> =
+        check_st(desc.st, value).into_iter().collect();
+    if !value.is_empty() {
+        errors.extend(check_format(desc.format, desc.length, '.', value));
+    }
+    errors
+}
+
+/// Matches the given composite with its description. A warning about its
+/// data elements is added to the given warnings of its segment.
 fn match_composite(
     pos: usize,
     desc: &desc::Composite,
     composite: &parser::value::Composite,
+    ctx: &Context,
+    warnings: &mut Vec<Either<CompositeError, DataElementError>>,
 ) -> Result<Composite, CompositeError> {
     if desc.st.is_required() && composite.elements.is_empty() {
         Err(CompositeError::syntax_error(pos, SyntaxError::missing()))
     } else {
-        let result =
-            match_composite_help(pos, &desc.elements, &composite.elements);
+        let mut warning = CompositeError {
+            pos,
+            syntax_error: None,
+            errors: vec![],
+        };
+        let result = match_composite_help(
+            pos,
+            &desc.elements,
+            &composite.elements,
+            ctx,
+            &mut warning,
+        );
+        if warning.syntax_error.is_some() || !warning.errors.is_empty() {
+            warnings.push(Either::Left(warning));
+        }
 
         match result {
             Ok(matches) => Ok(Composite {
@@ -372,6 +1332,7 @@ fn match_composite(
                 st: desc.st,
                 name: desc.name.clone(),
                 elements: matches,
+                span: Span::between(&composite.start, &composite.end),
             }),
             Err(error) => Err(error),
         }
@@ -380,8 +1341,10 @@ fn match_composite(
 
 fn match_composite_help(
     pos: usize,
-    descs_vec: &Vec<desc::DataElement>,
-    values_vec: &Vec<parser::value::DataElement>,
+    descs_vec: &[desc::DataElement],
+    values_vec: &[parser::value::DataElement],
+    ctx: &Context,
+    warning: &mut CompositeError,
 ) -> Result<Vec<DataElement>, CompositeError> {
     let mut descs = descs_vec.iter();
     let mut values = values_vec.iter();
@@ -399,16 +1362,24 @@ fn match_composite_help(
         match (descs.next(), values.next()) {
             // No descriptions and no values anymore, we are done
             (None, None) => break,
-            (None, Some(_)) => {
+            (None, Some(value)) => {
                 // Too many data elements. edi@energy does not support repetition,
-                // therefore no descriptions available anymore, bail
-                syntax_error = Some(SyntaxError::too_many_parts());
+                // therefore no descriptions available anymore, bail. Empty
+                // trailing data elements may be tolerated though.
+                let tolerated = ctx.options.tolerates_warnings()
+                    && value.value.is_empty()
+                    && values.all(|v| v.value.is_empty());
+                if tolerated {
+                    warning.syntax_error = Some(SyntaxError::too_many_parts());
+                } else {
+                    syntax_error = Some(SyntaxError::too_many_parts());
+                }
                 break;
             }
             (Some(desc), None) => {
                 // Found a description, but no corresponding value. This is
                 // fine, if the data element is not required.
-                if desc.st.is_required() {
+                if status(desc, ctx).is_required() {
                     errors.push(DataElementError::new(
                         position,
                         SyntaxError::missing(),
@@ -416,8 +1387,15 @@ fn match_composite_help(
                 }
             }
             (Some(desc), Some(value)) => {
-                match match_data_element(position, desc.clone(), value.clone())
-                {
+                let mut value = value.clone();
+                if is_ignored(desc, &value, ctx) {
+                    warning.errors.push(DataElementError::new(
+                        position,
+                        SyntaxError::invalid_value(),
+                    ));
+                    value.value.clear();
+                }
+                match match_data_element(position, desc.clone(), value, ctx) {
                     Ok(matched) => matches.push(matched),
                     Err(error) => errors.push(error),
                 }
@@ -427,11 +1405,7 @@ fn match_composite_help(
     }
 
     if !errors.is_empty() || syntax_error.is_some() {
-        Err(CompositeError {
-            pos: pos,
-            syntax_error: syntax_error,
-            errors: errors,
-        })
+        Err(CompositeError { pos, syntax_error, errors })
     } else {
         Ok(matches)
     }
@@ -441,74 +1415,547 @@ fn match_data_element(
     pos: usize,
     desc: desc::DataElement,
     element: parser::value::DataElement,
+    ctx: &Context,
 ) -> Result<DataElement, DataElementError> {
-    let st_checked = check_st(desc.st, element.value)
-        .map_err(|e| DataElementError::new(pos, e))?;
+    let span = Span::between(&element.start, &element.end);
 
-    if st_checked.is_empty() {
-        Ok(DataElement { index: pos, description: desc, value: None })
-    } else {
-        let value =
-            check_format(desc.st, desc.format, desc.length, st_checked)
-                .map_err(|e| DataElementError::new(pos, e))?;
+    // All checks are run, so that every issue with the value is reported
+    // at once, instead of just the first one.
+    let mut errors: Vec<SyntaxError> = vec![];
+    errors.extend(check_st(status(&desc, ctx), &element.value));
+    if !element.value.is_empty() {
+        errors.extend(check_format(
+            desc.format,
+            desc.length,
+            ctx.una.decimal_char,
+            &element.value,
+        ));
+    }
+    let decimal_char = ctx.una.decimal_char;
+    let value = match desc.format {
+        Format::Numeric(_)
+            if ctx.options.normalize_decimals && decimal_char != '.' =>
+        {
+            element.value.replace(decimal_char, ".")
+        }
+        _ => element.value,
+    };
+    // The validators get the value as it is decoded.
+    if !value.is_empty() && errors.is_empty() {
+        errors.extend(ctx.options.validators.validate(&desc, &value));
+    }
 
+    if !errors.is_empty() {
+        Err(DataElementError::with_errors(pos, errors))
+    } else if value.is_empty() {
+        Ok(DataElement { index: pos, description: desc, value: None, span })
+    } else {
         Ok(DataElement {
             index: pos,
             description: desc,
             value: Some(Matched::Text(value)),
+            span,
         })
     }
 }
 
 // CHECKING
 
-fn check_st(st: St, input: String) -> Result<String, SyntaxError> {
+/// Returns the status of the given data element, which is R, if its
+/// status is D or C and its condition is fulfilled.
+fn status(desc: &desc::DataElement, ctx: &Context) -> St {
+    let fulfilled = desc
+        .condition
+        .and_then(|condition| ctx.options.conditions.get(condition));
+    match desc.st {
+        St::D | St::C if fulfilled == Some(true) => St::R,
+        st => st,
+    }
+}
+
+/// Returns, whether the value of a data element with the status N is
+/// ignored with a warning, instead of being an invalid value.
+fn is_ignored(
+    desc: &desc::DataElement,
+    element: &parser::value::DataElement,
+    ctx: &Context,
+) -> bool {
+    ctx.options.tolerates_warnings()
+        && !element.value.is_empty()
+        && status(desc, ctx).is_not_used()
+}
+
+fn check_st(st: St, input: &str) -> Option<SyntaxError> {
     if input.is_empty() && st.is_required() {
-        Err(SyntaxError::missing())
+        Some(SyntaxError::missing())
     } else if !input.is_empty() && st.is_not_used() {
-        Err(SyntaxError::invalid_value())
+        Some(SyntaxError::invalid_value())
     } else {
-        Ok(input)
+        None
     }
 }
 
+/// Checks the characters and the length of a non-empty value.
+///
+/// The sign and the decimal character of a numeric value do not count
+/// towards its length.
 fn check_format(
-    st: St,
     format: Format,
     length: usize,
-    input: String,
-) -> Result<String, SyntaxError> {
+    decimal_char: char,
+    input: &str,
+) -> Vec<SyntaxError> {
+    let mut errors = vec![];
     match format {
-        Format::Alphanumeric(size) => check_size(st, size, length, input),
-        Format::Alpha(size) => check_size(st, size, length, input),
-        Format::Numeric(size) => check_size(st, size, length, input),
+        Format::Alphanumeric(size) => {
+            errors.extend(check_size(size, length, input.chars().count()))
+        }
+        Format::Alpha(size) => {
+            errors.extend(check_alpha(input));
+            errors.extend(check_size(size, length, input.chars().count()))
+        }
+        Format::Numeric(size) => {
+            errors.extend(check_numeric(decimal_char, input));
+            let digits = input.chars().filter(|c| c.is_ascii_digit()).count();
+            errors.extend(check_size(size, length, digits))
+        }
+    }
+    errors
+}
+
+/// Checks, that an alphabetic value does not contain any digits.
+fn check_alpha(input: &str) -> Option<SyntaxError> {
+    if input.chars().any(|c| c.is_ascii_digit()) {
+        Some(SyntaxError::invalid_format())
+    } else {
+        None
+    }
+}
+
+/// Checks, that a numeric value consists of digits with an optional
+/// leading minus sign and an optional decimal character, which has to
+/// be preceded by at least one digit, e.g. `-12.5`.
+fn check_numeric(decimal_char: char, input: &str) -> Vec<SyntaxError> {
+    let unsigned = input.strip_prefix('-').unwrap_or(input);
+    let (integer, fraction) = match unsigned.split_once(decimal_char) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let mut errors = vec![];
+    if unsigned.is_empty()
+        || !is_digits(integer)
+        || !fraction.is_none_or(is_digits)
+    {
+        errors.push(SyntaxError::invalid_format());
+    }
+    if fraction.is_some() && integer.is_empty() {
+        errors.push(SyntaxError::missing_digit_in_front_of_decimal());
     }
+    errors
 }
 
 fn check_size(
-    st: St,
     size: Size,
     length: usize,
-    input: String,
-) -> Result<String, SyntaxError> {
+    actual: usize,
+) -> Option<SyntaxError> {
     match size {
         Size::Exactly => {
-            if (st.is_optional() || st.is_not_used()) && input == "" {
-                Ok(input)
-            } else if input.len() < length {
-                Err(SyntaxError::data_element_too_short())
-            } else if input.len() > length {
-                Err(SyntaxError::data_element_too_long())
+            if actual < length {
+                Some(SyntaxError::data_element_too_short())
+            } else if actual > length {
+                Some(SyntaxError::data_element_too_long())
             } else {
-                Ok(input)
+                None
             }
         }
         Size::AtMost => {
-            if input.len() > length {
-                Err(SyntaxError::data_element_too_long())
+            if actual > length {
+                Some(SyntaxError::data_element_too_long())
             } else {
-                Ok(input)
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::decode::compiled::CompiledMig;
+    use crate::mig::decode::options::{DecodeOptions, TestIndicator};
+    use crate::mig::decode::parser;
+    use crate::mig::decode::parser::value;
+    use crate::mig::decode::value::{
+        check_alpha, check_numeric, match_data_element, match_interchange,
+        matches_segment, Context, Matched, ValueError,
+    };
+    use crate::mig::decode::Error;
+    use crate::mig::description;
+    use crate::mig::either::Either;
+    use crate::mig::error::InterchangeError;
+    use crate::mig::validate::dtm::{Date, DateTime, Reason, Timestamp};
+    use std::cell::{Cell, RefCell};
+
+    /// Returns the error of an interchange, which does not match.
+    fn mig_error(error: Error) -> Option<InterchangeError> {
+        match error {
+            Error::Mig(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    fn codes(input: &str) -> Vec<u64> {
+        check_numeric('.', input).iter().map(|e| e.get_code()).collect()
+    }
+
+    #[test]
+    fn test_check_numeric() {
+        assert_eq!(codes("12"), Vec::<u64>::new());
+        assert_eq!(codes("-12.50"), Vec::<u64>::new());
+        assert_eq!(codes("12,5"), vec![37]);
+        assert_eq!(codes("1.2.3"), vec![37]);
+        assert_eq!(codes("1-2"), vec![37]);
+        assert_eq!(codes("-"), vec![37]);
+        assert_eq!(codes(".5"), vec![38]);
+        assert_eq!(check_numeric(',', "12,5").len(), 0);
+    }
+
+    #[test]
+    fn test_check_alpha() {
+        assert!(check_alpha("UNOC").is_none());
+        assert!(check_alpha("UNO3").is_some());
+    }
+
+    #[test]
+    fn test_normalize_decimals() {
+        let desc: description::DataElement =
+            serde_json::from_value(serde_json::json!({
+                "label": "6060", "name": "Menge", "st": "M",
+                "format": "n..", "length": 15,
+                "usage": {"type": "Decimal", "comment": null},
+            }))
+            .unwrap();
+        let decode = |options: &DecodeOptions| {
+            let ctx = Context {
+                options,
+                una: value::UNA { decimal_char: ',', ..value::UNA::default() },
+                hook: None,
+                too_deep: Cell::new(false),
+                warnings: RefCell::new(vec![]),
+            };
+            let element = value::DataElement {
+                start: value::Position::default(),
+                end: value::Position::default(),
+                value: "3,14".to_string(),
+            };
+            match_data_element(0, desc.clone(), element, &ctx)
+                .unwrap()
+                .text()
+                .map(str::to_string)
+        };
+
+        assert_eq!(decode(&DecodeOptions::default()).as_deref(), Some("3.14"));
+        let options = DecodeOptions {
+            normalize_decimals: false,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(decode(&options).as_deref(), Some("3,14"));
+    }
+
+    #[test]
+    fn test_conditional_data_element() {
+        let desc: description::DataElement =
+            serde_json::from_value(serde_json::json!({
+                "label": "3039", "name": "MP-ID", "st": "D",
+                "format": "an..", "length": 35,
+                "usage": {"type": "Text", "comment": null},
+                "condition": 492,
+            }))
+            .unwrap();
+        let decode = |options: &DecodeOptions| {
+            let ctx = Context {
+                options,
+                una: value::UNA::default(),
+                hook: None,
+                too_deep: Cell::new(false),
+                warnings: RefCell::new(vec![]),
+            };
+            let element = value::DataElement {
+                start: value::Position::default(),
+                end: value::Position::default(),
+                value: String::new(),
+            };
+            match_data_element(0, desc.clone(), element, &ctx).is_ok()
+        };
+
+        assert!(decode(&DecodeOptions::default()));
+        let options = DecodeOptions {
+            conditions: [(492, false)].into_iter().collect(),
+            ..DecodeOptions::default()
+        };
+        assert!(decode(&options));
+        let options = DecodeOptions {
+            conditions: [(492, true)].into_iter().collect(),
+            ..DecodeOptions::default()
+        };
+        assert!(!decode(&options));
+    }
+
+    #[test]
+    fn test_reject_test_interchange() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let input = "UNB+UNOC:3+9900467000000:500+9904590000002:500\
+                     +200307:0705+C3AAAAAAAAHKLC++++++1'";
+        let options = DecodeOptions {
+            test_indicator: TestIndicator::Reject,
+            ..DecodeOptions::default()
+        };
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+
+        let error = match_interchange(&mig, interchange, &options)
+            .err()
+            .and_then(mig_error)
+            .and_then(|error| error.service_segment_error)
+            .map(|error| error.error);
+        match error {
+            Some(Either::Right(error)) => {
+                assert_eq!(error.syntax_errors[0].get_code(), 25)
+            }
+            _ => panic!("Expected error 25"),
+        }
+    }
+
+    #[test]
+    fn test_matches_discriminators() {
+        let cci = |characteristic: &str| -> description::Segment {
+            let element = |label, name, value, discriminator| {
+                serde_json::json!({
+                    "type": "Element", "label": label, "name": name,
+                    "st": "M", "format": "an..", "length": 3,
+                    "usage": {
+                        "type": "Static", "comment": null,
+                        "value": {
+                            "value": value, "semantics": null,
+                            "comment": null
+                        }
+                    },
+                    "discriminator": discriminator,
+                })
+            };
+            serde_json::from_value(serde_json::json!({
+                "counter": "0010", "number": 1, "tag": "CCI", "st": "M",
+                "maxReps": 1, "level": 1, "name": "Merkmal",
+                "comment": null,
+                "elements": [
+                    element("7059", "Klassentyp, Code", "Z30", false),
+                    {
+                        "type": "Composite", "label": "C240",
+                        "name": "Merkmalsbeschreibung", "st": "M",
+                        "elements": [
+                            element("7037", "Merkmal, Code",
+                                    characteristic, true),
+                        ],
+                    },
+                ],
+            }))
+            .unwrap()
+        };
+        let input = "UNA:+.? 'CCI+Z30+Z15'";
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        let segment = &interchange.segments[0];
+
+        assert!(matches_segment(&cci("Z15"), true, segment));
+        assert!(!matches_segment(&cci("Z19"), true, segment));
+        assert!(matches_segment(&cci("Z19"), false, segment));
+    }
+
+    #[test]
+    fn test_match_nested_group_start() {
+        let mut desc = fixtures::aperak();
+        // Nest SG4 into groups, which only start with another group.
+        let mut group = desc.message.segments.pop().unwrap();
+        for label in ["SG9", "SG8", "SG7"] {
+            group = Either::Left(description::Segmentgroup {
+                counter: "0160".to_string(),
+                label: label.to_string(),
+                st: description::St::R,
+                max_reps: 1,
+                level: 1,
+                name: label.to_string(),
+                comment: None,
+                segments: vec![group],
+            });
+        }
+        desc.message.segments.push(group);
+
+        let mig = CompiledMig::from(desc);
+        let interchange =
+            parser::parse(&mut fixtures::APERAK_FULL.as_bytes()).unwrap();
+        let options = DecodeOptions::default();
+        let interchange =
+            match_interchange(&mig, interchange, &options).unwrap();
+
+        let mut labels = vec![];
+        let mut next = interchange.segments.iter().rev().nth(2);
+        while let Some(Either::Left(group)) = next {
+            labels.push(group.label());
+            next = group.segments.first();
+        }
+        assert_eq!(labels, vec!["SG7", "SG8", "SG9", "SG4"]);
+    }
+
+    #[test]
+    fn test_reject_other_receiver() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let input = "UNB+UNOC:3+9900467000000:500+9904590000002:500\
+                     +200307:0705+C3AAAAAAAAHKLC'";
+        let code = |options: &DecodeOptions| {
+            let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+            let error = match_interchange(&mig, interchange, options)
+                .err()
+                .and_then(mig_error)
+                .and_then(|error| error.service_segment_error)
+                .map(|error| error.error);
+            match error {
+                Some(Either::Right(error)) => {
+                    Some((error.pos, error.syntax_errors[0].get_code()))
+                }
+                _ => None,
+            }
+        };
+
+        let options = DecodeOptions {
+            sender: Some("9900467000000".to_string()),
+            receiver: Some("9900000000001".to_string()),
+            ..DecodeOptions::default()
+        };
+        assert_eq!(code(&options), Some((2, 7)));
+        let options = DecodeOptions {
+            sender: Some("9900000000001".to_string()),
+            ..DecodeOptions::default()
+        };
+        assert_eq!(code(&options), Some((1, 23)));
+    }
+
+    #[test]
+    fn test_raw_segments() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let input = "UNA:+.? 'UNB+UNOC:3+9900467000000:500+9904590000002:500\
+                     +200307:0705+C3AAAAAAAAHKLC'\nXYZ+1'";
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        assert_eq!(
+            interchange.segments[0].raw,
+            "UNB+UNOC:3+9900467000000:500+9904590000002:500\
+             +200307:0705+C3AAAAAAAAHKLC'"
+        );
+
+        let options = DecodeOptions::default();
+        let raw: Vec<Option<String>> =
+            match_interchange(&mig, interchange, &options)
+                .err()
+                .and_then(mig_error)
+                .into_iter()
+                .flat_map(|error| error.message_errors)
+                .flat_map(|error| error.segment_errors)
+                .map(|error| error.raw)
+                .collect();
+        assert!(raw.contains(&Some("XYZ+1'".to_string())));
+    }
+
+    #[test]
+    fn test_spans() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let input = fixtures::APERAK_FULL;
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        let options = DecodeOptions::default();
+        let interchange =
+            match_interchange(&mig, interchange, &options).unwrap();
+
+        for segment in interchange.all_segments() {
+            let span = segment.span();
+            assert_eq!(&input[span.start..span.end], segment.raw());
+            for element in segment.data_elements() {
+                let span = element.span();
+                let text = element.text().unwrap_or_default();
+                assert_eq!(&input[span.start..span.end], text);
             }
         }
     }
+
+    #[test]
+    fn test_accessors() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let interchange =
+            parser::parse(&mut fixtures::APERAK_FULL.as_bytes()).unwrap();
+        let options = DecodeOptions::default();
+        let interchange =
+            match_interchange(&mig, interchange, &options).unwrap();
+
+        let segments = interchange.all_segments();
+        let nad = segments.iter().find(|s| s.tag() == "NAD").unwrap();
+        assert_eq!(nad.index(), 6);
+        let composite = nad.composite("C082").unwrap();
+        assert_eq!(composite.index(), 1);
+        assert_eq!(composite.text_of("3039"), Some("9900467000000"));
+        let element = nad.data_element("3055").unwrap();
+        assert_eq!((element.label(), element.index()), ("3055", 2));
+        assert_eq!(element.text(), Some("293"));
+        assert!(nad.data_element("9999").is_none());
+        assert_eq!(interchange.clone(), interchange);
+    }
+
+    #[test]
+    fn test_matched() {
+        let text = |text: &str| Matched::Text(text.to_string());
+        assert_eq!(text("Z29").as_str(), Ok("Z29"));
+        assert_eq!(Matched::Int(1).as_str(), Err(ValueError::NotText));
+        assert_eq!(text("0042").as_int(), Ok(42));
+        assert_eq!(text("-1").as_int(), Err(ValueError::NotInteger));
+        assert_eq!(text("-1,5").as_decimal(), Ok(-1.5));
+        assert_eq!(text("1e5").as_decimal(), Err(ValueError::NotDecimal));
+
+        let at = |hour, minute| Timestamp {
+            year: 2020,
+            month: 7,
+            day: 1,
+            hour,
+            minute,
+        };
+        assert_eq!(
+            text("20200701").as_date("102"),
+            Ok(Date::Day { year: 2020, month: 7, day: 1 })
+        );
+        assert_eq!(
+            text("202007011200+02").as_date("303"),
+            Ok(Date::DateTime(DateTime { local: at(12, 0), offset: 2 }))
+        );
+        assert_eq!(
+            text("202007311200").as_date("102"),
+            Err(ValueError::InvalidDate(Reason::Malformed))
+        );
+        assert_eq!(
+            text("2020").as_date("602"),
+            Err(ValueError::UnsupportedFormat("602".to_string()))
+        );
+
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let interchange =
+            parser::parse(&mut fixtures::APERAK_FULL.as_bytes()).unwrap();
+        let options = DecodeOptions::default();
+        let interchange =
+            match_interchange(&mig, interchange, &options).unwrap();
+        let segments = interchange.all_segments();
+        let dtm = segments.iter().find(|s| s.tag() == "DTM").unwrap();
+        let creation = Timestamp { month: 3, day: 7, ..at(7, 5) };
+        assert_eq!(dtm.date(), Ok(Date::Timestamp(creation)));
+    }
 }
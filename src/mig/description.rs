@@ -9,23 +9,175 @@
 //! The problem with these specifications is, that they are not machine
 //! readable. The data types in this module try to formalize them.
 
+pub mod service;
+mod use_case;
+
 use crate::mig::either::Either;
 use serde::de::{self, Deserializer, Visitor};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::fmt;
 
+pub use crate::mig::description::use_case::apply_use_case;
+
 /// An envelope around a set of messages.
 ///
 /// An [Interchange] may start with a UNA, always followed by
 /// a UNB segment and ends with a UNZ segment. In the German
 /// energy market, interchanges are homogeneous, meaning, they
 /// only contain messages of the same type.
+///
+/// The service segments may be left out, in which case the ones in
+/// [service] are used.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Interchange {
+    #[serde(default = "service::unb")]
     pub unb: Segment,
     pub message: Message,
+    #[serde(default = "service::unz")]
     pub unz: Segment,
+    /// The metadata from the title page of the MIG, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+impl Interchange {
+    /// Returns the message type described, e.g. `UTILMD`, from the
+    /// metadata or the fixed value of 0065 in UNH.
+    pub fn message_type(&self) -> Option<&str> {
+        if let Some(message_type) = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.message_type.as_deref())
+        {
+            return Some(message_type);
+        }
+        let element = self.message.unh.elements.get(1)?;
+        let data_element = match element {
+            Either::Left(composite) => composite.elements.first()?,
+            Either::Right(data_element) => data_element,
+        };
+        match &data_element.usage {
+            Usage::Static { value, .. } => Some(&value.value),
+            _ => None,
+        }
+    }
+
+    /// Returns the message identifier described by the fixed values of
+    /// S009 in UNH. The message type and the association assigned code
+    /// are taken from the metadata, if they are not fixed.
+    pub fn message_identifier(&self) -> MessageIdentifier {
+        let component = |at: usize| {
+            let data_element = match self.message.unh.elements.get(1)? {
+                Either::Left(composite) => composite.elements.get(at)?,
+                Either::Right(data_element) if at == 0 => data_element,
+                Either::Right(_) => return None,
+            };
+            match &data_element.usage {
+                Usage::Static { value, .. } => Some(value.value.clone()),
+                _ => None,
+            }
+        };
+        let version = || {
+            let metadata = self.metadata.as_ref()?;
+            metadata.version.clone()
+        };
+        MessageIdentifier {
+            message_type: self.message_type().map(str::to_string),
+            version: component(1),
+            release: component(2),
+            agency: component(3),
+            association_code: component(4).or_else(version),
+        }
+    }
+}
+
+/// The `MessageIdentifier` S009 in UNH, e.g. `UTILMD:D:11A:UN:5.2e`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageIdentifier {
+    /// The message type from 0065, e.g. `UTILMD`.
+    pub message_type: Option<String>,
+    /// The version of the message type from 0052, e.g. `D`.
+    pub version: Option<String>,
+    /// The release of the message type from 0054, e.g. `11A`.
+    pub release: Option<String>,
+    /// The controlling agency from 0051, e.g. `UN`.
+    pub agency: Option<String>,
+    /// The association assigned code from 0057, e.g. `5.2e`.
+    pub association_code: Option<String>,
+}
+
+impl MessageIdentifier {
+    fn components(&self) -> [&Option<String>; 5] {
+        [
+            &self.message_type,
+            &self.version,
+            &self.release,
+            &self.agency,
+            &self.association_code,
+        ]
+    }
+
+    /// Returns, whether the given identifier of an interchange matches
+    /// this described one. Components, which are not described, match
+    /// any value.
+    pub fn matches(&self, actual: &MessageIdentifier) -> bool {
+        self.components()
+            .iter()
+            .zip(actual.components())
+            .all(|(expected, actual)| {
+                expected.is_none() || *expected == actual
+            })
+    }
+}
+
+impl fmt::Display for MessageIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let components: Vec<&str> = self
+            .components()
+            .iter()
+            .map(|component| component.as_deref().unwrap_or(""))
+            .collect();
+        write!(f, "{}", components.join(":").trim_end_matches(':'))
+    }
+}
+
+/// The `Metadata` of a MIG, e.g. to select the description, which
+/// applies at the date of an interchange. Dates are given as
+/// `YYYY-MM-DD`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    /// The message type, e.g. `UTILMD`.
+    pub message_type: Option<String>,
+    /// The version of the MIG, e.g. `5.2e`.
+    pub version: Option<String>,
+    /// The first day, on which the MIG applies.
+    pub valid_from: Option<String>,
+    /// The last day, on which the MIG applies.
+    pub valid_until: Option<String>,
+    /// The revision of the document, e.g. its publication date.
+    pub revision: Option<String>,
+}
+
+impl Metadata {
+    /// Returns, whether the MIG applies on the given date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use edifact::mig::description::Metadata;
+    /// let metadata = Metadata {
+    ///     valid_from: Some("2023-04-01".to_string()),
+    ///     ..Metadata::default()
+    /// };
+    /// assert!(metadata.is_valid_at("2023-04-01"));
+    /// assert!(!metadata.is_valid_at("2023-03-31"));
+    /// ```
+    pub fn is_valid_at(&self, date: &str) -> bool {
+        self.valid_from.as_deref().is_none_or(|from| from <= date)
+            && self.valid_until.as_deref().is_none_or(|until| date <= until)
+    }
 }
 
 /// An envelope around a set of segments.
@@ -37,8 +189,10 @@ pub struct Interchange {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Message {
+    #[serde(default = "service::unh")]
     pub unh: Segment,
     pub segments: Vec<Either<Segmentgroup, Segment>>,
+    #[serde(default = "service::unt")]
     pub unt: Segment,
 }
 
@@ -78,10 +232,16 @@ pub struct Segment {
     pub name: String,
     pub comment: Option<String>,
     pub elements: Vec<Either<Composite, DataElement>>,
+    /// The number of the condition of a use case, which decides whether
+    /// this segment is required, if its status is D or C, see
+    /// [Conditions](crate::usecase::conditions::Conditions).
+    #[serde(default)]
+    pub condition: Option<u16>,
 }
 
 
-///
+/// A set of [DataElement](struct.DataElement.html) elements, which are
+/// separated by the component separator within a segment.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Composite {
@@ -89,6 +249,11 @@ pub struct Composite {
     pub name: String,
     pub st: St,
     pub elements: Vec<DataElement>,
+    /// The number of times this element may occur in a segment, separated
+    /// by the repetition separator of syntax version 4. If not given, it
+    /// occurs at most once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_occurs: Option<usize>,
 }
 
 
@@ -99,7 +264,7 @@ pub struct Composite {
 ///
 /// The following example has been taken from the UCI segment in the [CONTRL](https://www.edi-energy.de/index.php?id=38&tx_bdew_bdew%5Buid%5D=697&tx_bdew_bdew%5Baction%5D=download&tx_bdew_bdew%5Bcontroller%5D=Dokument&cHash=27293a7bdcf9496c3e997789ba07d658) MIG.
 ///
-/// ```
+/// ```text
 /// 0020 | Datenaustauschreferenz | M | an..14 | Eindeutige Referenz...
 /// ^^^^   ^^^^^^^^^^^^^^^^^^^^^^   ^   ^^^^^^   ^^^^^^^^^^^^^^^^^^^^^^
 ///   1              2              3     4                5
@@ -121,6 +286,23 @@ pub struct DataElement {
     pub format: Format,
     pub length: usize,
     pub usage: Usage,
+    /// Whether this element discriminates segments with the same tag and
+    /// counter, like the characteristic in CCI. If no element of a
+    /// segment is flagged, its first element is used, if it is a
+    /// qualifier.
+    #[serde(default)]
+    pub discriminator: bool,
+    /// The number of the condition of a use case, which decides whether
+    /// this data element is required, if its status is D or C. Unless
+    /// the condition has been evaluated as fulfilled, the data element
+    /// is optional.
+    #[serde(default)]
+    pub condition: Option<u16>,
+    /// The number of times this element may occur in a segment, separated
+    /// by the repetition separator of syntax version 4. If not given, it
+    /// occurs at most once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_occurs: Option<usize>,
 }
 
 impl DataElement {
@@ -181,9 +363,10 @@ impl St {
     /// # Examples
     ///
     /// ```
-    /// assert_eq!(St::O.is_optional(), true)
-    /// assert_eq!(St::C.is_optional(), true)
-    /// assert_eq!(St::D.is_optional(), true)
+    /// # use edifact::mig::description::St;
+    /// assert_eq!(St::O.is_optional(), true);
+    /// assert_eq!(St::C.is_optional(), true);
+    /// assert_eq!(St::D.is_optional(), true);
     /// ```
     pub fn is_optional(&self) -> bool {
         self == &St::O || self == &St::C || self == &St::D
@@ -198,8 +381,9 @@ impl St {
     /// # Examples
     ///
     /// ```
-    /// assert_eq!(St::M.is_required(), true)
-    /// assert_eq!(St::R.is_required(), true)
+    /// # use edifact::mig::description::St;
+    /// assert_eq!(St::M.is_required(), true);
+    /// assert_eq!(St::R.is_required(), true);
     /// ```
     pub fn is_required(&self) -> bool {
         self == &St::R || self == &St::M
@@ -215,7 +399,8 @@ impl St {
     /// # Examples
     ///
     /// ```
-    /// assert_eq!(St::N.is_not_used(), true)
+    /// # use edifact::mig::description::St;
+    /// assert_eq!(St::N.is_not_used(), true);
     /// ```
     pub fn is_not_used(&self) -> bool {
         self == &St::N
@@ -229,7 +414,7 @@ pub struct Choice {
     pub comment: Option<String>,
 }
 
-#[derive(Debug, Eq, PartialEq, Serialize, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum Format {
     Alphanumeric(Size),
     Alpha(Size),
@@ -242,6 +427,24 @@ pub enum Size {
     AtMost,
 }
 
+impl Serialize for Format {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // The same representation, which is accepted by the FormatVisitor.
+        let value = match self {
+            Format::Alphanumeric(Size::Exactly) => "an",
+            Format::Alphanumeric(Size::AtMost) => "an..",
+            Format::Alpha(Size::Exactly) => "a",
+            Format::Alpha(Size::AtMost) => "a..",
+            Format::Numeric(Size::Exactly) => "n",
+            Format::Numeric(Size::AtMost) => "n..",
+        };
+        serializer.serialize_str(value)
+    }
+}
+
 struct FormatVisitor;
 
 impl<'de> Visitor<'de> for FormatVisitor {
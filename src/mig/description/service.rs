@@ -0,0 +1,346 @@
+//! The service segments UNB, UNH, UNT and UNZ, as defined by the syntax
+//! standard ISO 9735 version 3.
+//!
+//! Their layout does not depend on the MIG, so a description may leave
+//! them out and these ones are used. A description giving its own service
+//! segments overrides them, e.g. to restrict the syntax identifiers to
+//! `UNOC` or to leave out the optional data elements.
+//!
+//! The service segments are numbered 0, since they are not numbered in
+//! the MIGs. UNT has the counter `9999`, so it follows every segment of a
+//! message.
+use crate::mig::description::{
+    Choice, Composite, DataElement, Format, Segment, Size, St, Usage,
+};
+use crate::mig::either::Either;
+
+/// Returns the interchange header.
+pub fn unb() -> Segment {
+    segment(
+        "UNB",
+        "0000",
+        "Nutzdaten-Kopfsegment",
+        vec![
+            composite(
+                "S001",
+                "Syntax-Bezeichner",
+                St::M,
+                vec![
+                    element(
+                        "0001",
+                        "Syntaxkennung",
+                        St::M,
+                        Format::Alpha(Size::Exactly),
+                        4,
+                        choices(&[
+                            "UNOA", "UNOB", "UNOC", "UNOD", "UNOE", "UNOF",
+                        ]),
+                    ),
+                    element(
+                        "0002",
+                        "Syntaxversionsnummer",
+                        St::M,
+                        Format::Numeric(Size::Exactly),
+                        1,
+                        choices(&["1", "2", "3"]),
+                    ),
+                ],
+            ),
+            composite(
+                "S002",
+                "Absender der Übertragungsdatei",
+                St::M,
+                vec![
+                    text("0004", "Absenderbezeichnung", St::M, 35),
+                    text("0007", "Teilnehmerbezeichnung, Qualifier", St::C, 4),
+                    text("0008", "Adresse für Rückleitung", St::C, 14),
+                ],
+            ),
+            composite(
+                "S003",
+                "Empfänger der Übertragungsdatei",
+                St::M,
+                vec![
+                    text("0010", "Empfängerbezeichnung", St::M, 35),
+                    text("0007", "Teilnehmerbezeichnung, Qualifier", St::C, 4),
+                    text("0014", "Routing-Adresse", St::C, 14),
+                ],
+            ),
+            composite(
+                "S004",
+                "Datum/Uhrzeit der Erstellung",
+                St::M,
+                vec![number("0017", "Datum", 6), number("0019", "Uhrzeit", 4)],
+            ),
+            Either::Right(text("0020", "Datenaustauschreferenz", St::M, 14)),
+            composite(
+                "S005",
+                "Referenz/Passwort des Empfängers",
+                St::C,
+                vec![
+                    text("0022", "Referenz/Passwort", St::M, 14),
+                    element(
+                        "0025",
+                        "Referenz/Passwort, Qualifier",
+                        St::C,
+                        Format::Alphanumeric(Size::Exactly),
+                        2,
+                        Usage::Text { comment: None },
+                    ),
+                ],
+            ),
+            Either::Right(text("0026", "Anwendungsreferenz", St::C, 14)),
+            Either::Right(element(
+                "0029",
+                "Priorität für Übertragung",
+                St::C,
+                Format::Alpha(Size::Exactly),
+                1,
+                Usage::Text { comment: None },
+            )),
+            Either::Right(element(
+                "0031",
+                "Anforderung Bestätigung",
+                St::C,
+                Format::Numeric(Size::Exactly),
+                1,
+                choices(&["1"]),
+            )),
+            Either::Right(text(
+                "0032",
+                "Kommunikationsvereinbarung",
+                St::C,
+                35,
+            )),
+            Either::Right(element(
+                "0035",
+                "Testkennzeichen",
+                St::C,
+                Format::Numeric(Size::Exactly),
+                1,
+                choices(&["1"]),
+            )),
+        ],
+    )
+}
+
+/// Returns the message header.
+pub fn unh() -> Segment {
+    segment(
+        "UNH",
+        "0010",
+        "Nachrichten-Kopfsegment",
+        vec![
+            Either::Right(text(
+                "0062",
+                "Nachrichten-Referenznummer",
+                St::M,
+                14,
+            )),
+            composite(
+                "S009",
+                "Nachrichten-Kennung",
+                St::M,
+                vec![
+                    text("0065", "Nachrichtentyp-Kennung", St::M, 6),
+                    text(
+                        "0052",
+                        "Versionsnummer des Nachrichtentyps",
+                        St::M,
+                        3,
+                    ),
+                    text(
+                        "0054",
+                        "Freigabenummer des Nachrichtentyps",
+                        St::M,
+                        3,
+                    ),
+                    text("0051", "Verwaltende Organisation", St::M, 2),
+                    text("0057", "Anwendungscode", St::C, 6),
+                ],
+            ),
+            Either::Right(text(
+                "0068",
+                "Allgemeine Zuordnungs-Referenz",
+                St::C,
+                35,
+            )),
+            composite(
+                "S010",
+                "Status der Übermittlung",
+                St::C,
+                vec![
+                    element(
+                        "0070",
+                        "Übermittlungsfolgenummer",
+                        St::M,
+                        Format::Numeric(Size::AtMost),
+                        2,
+                        Usage::Integer { comment: None },
+                    ),
+                    element(
+                        "0073",
+                        "Erste und letzte Übermittlung",
+                        St::C,
+                        Format::Alpha(Size::Exactly),
+                        1,
+                        choices(&["C", "F"]),
+                    ),
+                ],
+            ),
+        ],
+    )
+}
+
+/// Returns the message trailer.
+pub fn unt() -> Segment {
+    segment(
+        "UNT",
+        "9999",
+        "Nachrichten-Endesegment",
+        vec![
+            Either::Right(counter(
+                "0074",
+                "Anzahl der Segmente in einer Nachricht",
+            )),
+            Either::Right(text(
+                "0062",
+                "Nachrichten-Referenznummer",
+                St::M,
+                14,
+            )),
+        ],
+    )
+}
+
+/// Returns the interchange trailer.
+pub fn unz() -> Segment {
+    segment(
+        "UNZ",
+        "0000",
+        "Nutzdaten-Endesegment",
+        vec![
+            Either::Right(counter("0036", "Datenaustauschzähler")),
+            Either::Right(text("0020", "Datenaustauschreferenz", St::M, 14)),
+        ],
+    )
+}
+
+fn segment(
+    tag: &str,
+    counter: &str,
+    name: &str,
+    elements: Vec<Either<Composite, DataElement>>,
+) -> Segment {
+    Segment {
+        counter: counter.to_string(),
+        number: 0,
+        tag: tag.to_string(),
+        st: St::M,
+        max_reps: 1,
+        level: 0,
+        name: name.to_string(),
+        comment: None,
+        elements,
+        condition: None,
+    }
+}
+
+fn composite(
+    label: &str,
+    name: &str,
+    st: St,
+    elements: Vec<DataElement>,
+) -> Either<Composite, DataElement> {
+    Either::Left(Composite {
+        label: label.to_string(),
+        name: name.to_string(),
+        st,
+        elements,
+        max_occurs: None,
+    })
+}
+
+fn element(
+    label: &str,
+    name: &str,
+    st: St,
+    format: Format,
+    length: usize,
+    usage: Usage,
+) -> DataElement {
+    DataElement {
+        label: label.to_string(),
+        name: name.to_string(),
+        st,
+        format,
+        length,
+        usage,
+        discriminator: false,
+        condition: None,
+        max_occurs: None,
+    }
+}
+
+/// Returns an alphanumeric data element of at most the given length.
+fn text(label: &str, name: &str, st: St, length: usize) -> DataElement {
+    let format = Format::Alphanumeric(Size::AtMost);
+    element(label, name, st, format, length, Usage::Text { comment: None })
+}
+
+/// Returns a mandatory numeric data element of exactly the given length.
+fn number(label: &str, name: &str, length: usize) -> DataElement {
+    let format = Format::Numeric(Size::Exactly);
+    element(label, name, St::M, format, length, Usage::Text { comment: None })
+}
+
+/// Returns a mandatory counter of at most 6 digits.
+fn counter(label: &str, name: &str) -> DataElement {
+    let format = Format::Numeric(Size::AtMost);
+    element(label, name, St::M, format, 6, Usage::Integer { comment: None })
+}
+
+fn choices(values: &[&str]) -> Usage {
+    let choices = values
+        .iter()
+        .map(|value| Choice {
+            value: value.to_string(),
+            semantics: None,
+            comment: None,
+        })
+        .collect();
+    Usage::OneOf { choices, comment: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::description::Interchange;
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_service_segments() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(fixtures::APERAK_JSON).unwrap();
+        json.as_object_mut().unwrap().remove("unb");
+        json.as_object_mut().unwrap().remove("unz");
+        let message = json["message"].as_object_mut().unwrap();
+        message.remove("unh");
+        message.remove("unt");
+        let desc: Interchange = serde_json::from_value(json).unwrap();
+        assert_eq!(desc.unb.tag, "UNB");
+
+        let options = DecodeOptions::default();
+        let decode = |input: &str| {
+            mig::decode(vec![desc.clone()], &mut input.as_bytes(), &options)
+        };
+        let interchange = decode(fixtures::APERAK).unwrap();
+        let segments = interchange.all_segments();
+        let unt = segments.iter().find(|s| s.tag() == "UNT").unwrap();
+        assert_eq!(unt.text_of("0062"), Some("1"));
+        assert!(
+            decode(&fixtures::APERAK.replace("UNOC:3", "UNOCC:3")).is_err()
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Specialization of a MIG with a use case of an AHB, so the decoder
+//! validates the conformance to the use case in the same pass.
+//!
+//! The segments of the use case are identified by their number in the
+//! MIG, the segment groups by the number of their first segment and the
+//! data elements by their label in order of their occurrence within the
+//! segment. The MIG is narrowed as follows:
+//!
+//! - Segments and segment groups, which are not part of the use case, are
+//!   removed, so they are rejected like unknown segments.
+//! - The status depends on the first necessity: *Muss* is M, *Muss* with a
+//!   condition is D and *Soll* and *Kann* are O. A data element of the
+//!   use case is R, unless it is M already or has a condition, all others
+//!   are N.
+//! - The codes of a data element are restricted to the ones of the use
+//!   case, if it lists any.
+//!
+//! A simple condition, like `[492]`, is kept with the status D, so it is
+//! required, once the condition has been evaluated as fulfilled, see
+//! [Conditions](crate::usecase::conditions::Conditions).
+use std::collections::HashMap;
+
+use crate::mig::description::{
+    Choice, Composite, DataElement, Interchange, Segment, Segmentgroup, St,
+    Usage,
+};
+use crate::mig::either::Either;
+use crate::usecase::description as ahb;
+use crate::usecase::description::{Condition, Necessity};
+
+/// The segments and segment groups of a use case by their number.
+#[derive(Default)]
+struct Numbers<'a> {
+    segments: HashMap<u64, &'a ahb::Segment>,
+    groups: HashMap<u64, &'a ahb::Segmentgroup>,
+}
+
+/// Returns the given MIG specialized with the given use case, see the
+/// [module](self).
+pub fn apply_use_case(
+    interchange: &Interchange,
+    use_case: &ahb::UseCase,
+) -> Interchange {
+    let mut numbers = Numbers::default();
+    collect(&use_case.data, &mut numbers);
+    let mut result = interchange.clone();
+    result.message.segments =
+        apply_segments(&interchange.message.segments, &numbers);
+    result
+}
+
+fn collect<'a>(
+    data: &'a [Either<ahb::Segmentgroup, ahb::Segment>],
+    numbers: &mut Numbers<'a>,
+) {
+    for value in data {
+        match value {
+            Either::Left(group) => {
+                if let Some(first) = first_segment(&group.segments) {
+                    numbers.groups.insert(u64::from(first.order), group);
+                }
+                collect(&group.segments, numbers);
+            }
+            Either::Right(segment) => {
+                numbers.segments.insert(u64::from(segment.order), segment);
+            }
+        }
+    }
+}
+
+fn first_segment(
+    data: &[Either<ahb::Segmentgroup, ahb::Segment>],
+) -> Option<&ahb::Segment> {
+    match data.first()? {
+        Either::Left(group) => first_segment(&group.segments),
+        Either::Right(segment) => Some(segment),
+    }
+}
+
+/// Returns the number of the first segment of the given MIG segments.
+fn first_number(segments: &[Either<Segmentgroup, Segment>]) -> Option<u64> {
+    match segments.first()? {
+        Either::Left(group) => first_number(&group.segments),
+        Either::Right(segment) => Some(segment.number),
+    }
+}
+
+fn apply_segments(
+    segments: &[Either<Segmentgroup, Segment>],
+    numbers: &Numbers,
+) -> Vec<Either<Segmentgroup, Segment>> {
+    let mut result = vec![];
+    for value in segments {
+        match value {
+            Either::Left(group) => {
+                let Some(ahb) = first_number(&group.segments)
+                    .and_then(|number| numbers.groups.get(&number))
+                else {
+                    continue;
+                };
+                let segments = apply_segments(&group.segments, numbers);
+                if segments.is_empty() {
+                    continue;
+                }
+                let (st, _) = status(&ahb.necessities);
+                result.push(Either::Left(Segmentgroup {
+                    st,
+                    segments,
+                    ..group.clone()
+                }));
+            }
+            Either::Right(segment) => {
+                if let Some(ahb) = numbers.segments.get(&segment.number) {
+                    result.push(Either::Right(apply_segment(segment, ahb)));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn apply_segment(segment: &Segment, ahb: &ahb::Segment) -> Segment {
+    let (st, condition) = status(&ahb.necessities);
+    let mut used = vec![false; ahb.elements.len()];
+    let mut apply = |element: &DataElement| {
+        let position = ahb
+            .elements
+            .iter()
+            .enumerate()
+            .position(|(i, e)| !used[i] && e.label == element.label);
+        match position {
+            Some(i) => {
+                used[i] = true;
+                apply_data_element(element, &ahb.elements[i])
+            }
+            None => DataElement { st: St::N, ..element.clone() },
+        }
+    };
+    let elements = segment
+        .elements
+        .iter()
+        .map(|element| match element {
+            Either::Left(composite) => {
+                let elements: Vec<DataElement> =
+                    composite.elements.iter().map(&mut apply).collect();
+                let st = if elements.iter().all(|e| e.st.is_not_used()) {
+                    St::N
+                } else {
+                    composite.st
+                };
+                Either::Left(Composite { st, elements, ..composite.clone() })
+            }
+            Either::Right(element) => Either::Right(apply(element)),
+        })
+        .collect();
+    Segment { st, elements, condition, ..segment.clone() }
+}
+
+fn apply_data_element(
+    element: &DataElement,
+    ahb: &ahb::DataElement,
+) -> DataElement {
+    let (st, condition) = match &ahb.cond {
+        _ if element.st == St::M => (St::M, None),
+        None => (St::R, None),
+        Some(Condition::Ref(number)) => (St::D, Some(*number)),
+        Some(Condition::Cond(..)) => (St::D, None),
+    };
+    let codes: Vec<&str> =
+        ahb.values.iter().map(|value| value.code.as_str()).collect();
+    let usage = match &element.usage {
+        _ if codes.is_empty() => element.usage.clone(),
+        Usage::OneOf { choices, comment } => Usage::OneOf {
+            choices: choices
+                .iter()
+                .filter(|choice| codes.contains(&choice.value.as_str()))
+                .cloned()
+                .collect(),
+            comment: comment.clone(),
+        },
+        Usage::Text { comment } => Usage::OneOf {
+            choices: codes
+                .iter()
+                .map(|code| Choice {
+                    value: code.to_string(),
+                    semantics: None,
+                    comment: None,
+                })
+                .collect(),
+            comment: comment.clone(),
+        },
+        usage => usage.clone(),
+    };
+    DataElement { st, usage, condition, ..element.clone() }
+}
+
+/// Returns the status and the condition of the first of the given
+/// necessities.
+fn status(necessities: &[Necessity]) -> (St, Option<u16>) {
+    match necessities.first() {
+        Some(Necessity::Must(None)) => (St::M, None),
+        Some(Necessity::Must(Some(Condition::Ref(number)))) => {
+            (St::D, Some(*number))
+        }
+        Some(Necessity::Must(Some(_))) => (St::D, None),
+        _ => (St::O, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::description::{apply_use_case, St, Usage};
+    use crate::mig::either::Either;
+    use crate::usecase::description as ahb;
+    use crate::usecase::description::{Condition, Necessity};
+
+    fn segment(
+        order: u16,
+        necessity: Necessity,
+        elements: Vec<ahb::DataElement>,
+    ) -> Either<ahb::Segmentgroup, ahb::Segment> {
+        Either::Right(ahb::Segment {
+            order,
+            name: String::new(),
+            necessities: vec![necessity],
+            elements,
+        })
+    }
+
+    fn element(label: &str, codes: &[&str]) -> ahb::DataElement {
+        ahb::DataElement {
+            label: label.to_string(),
+            op: None,
+            cond: None,
+            values: codes
+                .iter()
+                .map(|code| ahb::Value { code: code.to_string(), op: None })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_apply_use_case() {
+        let desc = fixtures::aperak();
+        let erc = ahb::Segmentgroup {
+            order: 10,
+            name: String::new(),
+            necessities: vec![Necessity::Must(None)],
+            segments: vec![
+                segment(
+                    10,
+                    Necessity::Must(None),
+                    vec![element("9321", &["Z29", "Z30"])],
+                ),
+                segment(
+                    11,
+                    Necessity::Must(Some(Condition::Ref(492))),
+                    vec![element("4451", &[]), element("4440", &[])],
+                ),
+            ],
+        };
+        let use_case = ahb::UseCase {
+            ident: None,
+            data: vec![
+                segment(
+                    2,
+                    Necessity::Must(None),
+                    vec![element("1001", &[]), element("1004", &[])],
+                ),
+                Either::Left(erc),
+            ],
+        };
+
+        let result = apply_use_case(&desc, &use_case);
+        let segments = &result.message.segments;
+        assert_eq!(segments.len(), 2);
+        let Either::Left(group) = &segments[1] else {
+            panic!("Expected SG4");
+        };
+        assert_eq!(group.label, "SG4");
+        assert_eq!(group.segments.len(), 2);
+
+        let Either::Right(erc) = &group.segments[0] else {
+            panic!("Expected ERC");
+        };
+        let Either::Left(composite) = &erc.elements[0] else {
+            panic!("Expected C901");
+        };
+        let Usage::OneOf { choices, .. } = &composite.elements[0].usage else {
+            panic!("Expected codes");
+        };
+        let codes: Vec<&str> =
+            choices.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(codes, vec!["Z30", "Z29"]);
+
+        let Either::Right(ftx) = &group.segments[1] else {
+            panic!("Expected FTX");
+        };
+        assert_eq!((ftx.st, ftx.condition), (St::D, Some(492)));
+        let statuses: Vec<St> = ftx
+            .elements
+            .iter()
+            .flat_map(|element| match element {
+                Either::Left(composite) => {
+                    composite.elements.iter().map(|e| e.st).collect()
+                }
+                Either::Right(element) => vec![element.st],
+            })
+            .collect();
+        assert_eq!(statuses, vec![St::M, St::N, St::N, St::M, St::N]);
+    }
+}
@@ -0,0 +1,329 @@
+//! Structural comparison of decoded interchanges, e.g. to see what
+//! changed between an interchange sent and the one a partner claims to
+//! have received.
+//!
+//! Unlike comparing the raw text, separators, escaping and the order of
+//! segments do not matter. Segments and segment groups are paired by
+//! their tag or label and qualifier, e.g. `NAD+MS` or `SG4` starting with
+//! `LOC+172`, and the n-th occurrence on one side is compared with the
+//! n-th occurrence on the other side. Paired segments are compared value
+//! by value.
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::mig::decode::value::{
+    DataElement, Interchange, Matched, Segment, Segmentgroup,
+};
+use crate::mig::either::Either;
+
+type Value = Either<Segmentgroup, Segment>;
+
+/// A `Difference` between two interchanges. The path names the segment
+/// by its segment groups, tag and qualifier, e.g. `SG4/FTX+AAO`, with
+/// the occurrence in brackets, if it is not the first one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The segment only exists in the left interchange.
+    Removed { path: String, raw: String },
+    /// The segment only exists in the right interchange.
+    Added { path: String, raw: String },
+    /// The value of a data element differs. The position gives the
+    /// element and component starting at 1, e.g. `4:1`.
+    Changed {
+        path: String,
+        position: String,
+        label: String,
+        left: Option<String>,
+        right: Option<String>,
+    },
+}
+
+impl Difference {
+    /// Returns the label of the data element, if a value changed.
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Difference::Changed { label, .. } => Some(label),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Removed { path, raw } => {
+                write!(f, "- {}: {}", path, raw.trim_end())
+            }
+            Difference::Added { path, raw } => {
+                write!(f, "+ {}: {}", path, raw.trim_end())
+            }
+            Difference::Changed { path, position, label, left, right } => {
+                write!(
+                    f,
+                    "~ {} {} {}: '{}' -> '{}'",
+                    path,
+                    position,
+                    label,
+                    left.as_deref().unwrap_or_default(),
+                    right.as_deref().unwrap_or_default()
+                )
+            }
+        }
+    }
+}
+
+/// Returns the differences between the interchanges in the order of the
+/// left one, followed by the segments only in the right one.
+pub fn diff(left: &Interchange, right: &Interchange) -> Vec<Difference> {
+    let mut result = vec![];
+    diff_values(&left.segments, &right.segments, "", &mut result);
+    result
+}
+
+fn diff_values(
+    left: &[Value],
+    right: &[Value],
+    path: &str,
+    result: &mut Vec<Difference>,
+) {
+    let left_keys = occurrences(left);
+    let right_keys = occurrences(right);
+    let mut paired = vec![false; right.len()];
+    for (value, key) in left.iter().zip(&left_keys) {
+        let path = join(path, key);
+        match right_keys.iter().position(|other| other == key) {
+            Some(i) => {
+                paired[i] = true;
+                diff_value(value, &right[i], &path, result);
+            }
+            None => each_segment(value, &path, &mut |path, segment| {
+                result.push(Difference::Removed {
+                    path,
+                    raw: segment.raw().to_string(),
+                })
+            }),
+        }
+    }
+    for (i, value) in right.iter().enumerate().filter(|(i, _)| !paired[*i]) {
+        let path = join(path, &right_keys[i]);
+        each_segment(value, &path, &mut |path, segment| {
+            result.push(Difference::Added {
+                path,
+                raw: segment.raw().to_string(),
+            })
+        });
+    }
+}
+
+fn diff_value(
+    left: &Value,
+    right: &Value,
+    path: &str,
+    result: &mut Vec<Difference>,
+) {
+    match (left, right) {
+        (Either::Left(left), Either::Left(right)) => {
+            diff_values(&left.segments, &right.segments, path, result)
+        }
+        (Either::Right(left), Either::Right(right)) => {
+            diff_segment(left, right, path, result)
+        }
+        // Groups and segments never share a key.
+        _ => {}
+    }
+}
+
+fn diff_segment(
+    left: &Segment,
+    right: &Segment,
+    path: &str,
+    result: &mut Vec<Difference>,
+) {
+    let left = positioned(left);
+    let right = positioned(right);
+    let mut positions: Vec<&String> =
+        left.keys().chain(right.keys()).collect();
+    positions.sort_by_key(|position| sort_key(position));
+    positions.dedup();
+    for position in positions {
+        let (l, r) = (left.get(position), right.get(position));
+        let text =
+            |element: Option<&&DataElement>| element.and_then(|e| text(e));
+        if text(l) != text(r) {
+            let label = l.or(r).map(|e| e.description().label.clone());
+            result.push(Difference::Changed {
+                path: path.to_string(),
+                position: position.clone(),
+                label: label.unwrap_or_default(),
+                left: text(l),
+                right: text(r),
+            });
+        }
+    }
+}
+
+/// Returns the data elements of the segment by their position, e.g.
+/// `3:1` for the first component of the third element.
+fn positioned(segment: &Segment) -> HashMap<String, &DataElement> {
+    let mut result = HashMap::new();
+    for element in segment.elements() {
+        match element {
+            Either::Left(composite) => {
+                for data_element in composite.elements() {
+                    let position = format!(
+                        "{}:{}",
+                        composite.index + 1,
+                        data_element.index + 1
+                    );
+                    result.insert(position, data_element);
+                }
+            }
+            Either::Right(data_element) => {
+                result.insert(
+                    (data_element.index + 1).to_string(),
+                    data_element,
+                );
+            }
+        }
+    }
+    result
+}
+
+fn sort_key(position: &str) -> Vec<usize> {
+    position.split(':').filter_map(|part| part.parse().ok()).collect()
+}
+
+fn text(data_element: &DataElement) -> Option<String> {
+    match data_element.value()? {
+        Matched::Text(text) => Some(text.clone()),
+        Matched::Int(int) => Some(int.to_string()),
+        Matched::Decimal(decimal) => Some(decimal.to_string()),
+    }
+}
+
+/// Returns the keys of the values, numbering repeated keys, e.g.
+/// `NAD+MS`, `SG3(NAD+MR)` and `SG3(NAD+MR)[2]`.
+fn occurrences(values: &[Value]) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    values
+        .iter()
+        .map(|value| {
+            let key = key(value);
+            let count = counts.entry(key.clone()).or_default();
+            *count += 1;
+            if *count > 1 {
+                format!("{}[{}]", key, count)
+            } else {
+                key
+            }
+        })
+        .collect()
+}
+
+fn key(value: &Value) -> String {
+    match value {
+        Either::Left(group) => {
+            let first = group.segments.first().map(key).unwrap_or_default();
+            format!("{}({})", group.label, first)
+        }
+        Either::Right(segment) => {
+            let qualifier = segment
+                .data_elements()
+                .first()
+                .filter(|element| element.description().is_qualifier())
+                .and_then(|element| element.text());
+            match qualifier {
+                Some(qualifier) => format!("{}+{}", segment.tag(), qualifier),
+                None => segment.tag().to_string(),
+            }
+        }
+    }
+}
+
+/// Returns the path of a value, which names segment groups by their
+/// label only, since their first segment follows anyway.
+fn join(path: &str, key: &str) -> String {
+    let key = match (key.find('('), key.rfind(')')) {
+        (Some(start), Some(end)) => {
+            format!("{}{}", &key[..start], &key[end + 1..])
+        }
+        _ => key.to_string(),
+    };
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}/{}", path, key)
+    }
+}
+
+fn each_segment(
+    value: &Value,
+    path: &str,
+    f: &mut dyn FnMut(String, &Segment),
+) {
+    match value {
+        Either::Left(group) => {
+            let keys = occurrences(&group.segments);
+            for (value, key) in group.segments.iter().zip(&keys) {
+                each_segment(value, &join(path, key), f);
+            }
+        }
+        Either::Right(segment) => f(path.to_string(), segment),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::diff::{diff, Difference};
+    use crate::mig::DecodeOptions;
+
+    fn input() -> String {
+        fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'\
+             RFF+ACE:O1583553607732'\
+             DTM+171:202003070500:203'\
+             NAD+MS+9900467000000::293'\
+             NAD+MR+9904590000002::293'\
+             ERC+Z29'\
+             FTX+ABO+++LOC17251283352734'\
+             RFF+ACW:V1583553607732'\
+             RFF+AGO:9904590000002ORD1583553607706'\
+             FTX+Z02+++10'",
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let desc = fixtures::aperak();
+        let decode = |input: &str| {
+            let options = DecodeOptions::default();
+            mig::decode(vec![desc.clone()], &mut input.as_bytes(), &options)
+                .unwrap()
+        };
+        let left = decode(&input());
+        assert_eq!(diff(&left, &left), vec![]);
+
+        let input = input()
+            .replace("UNA:+.? '", "UNA*|.? '")
+            .replace('+', "|")
+            .replace(':', "*")
+            .replace("RFF|ACW*V1583553607732'", "")
+            .replace("|10'", "|11'")
+            .replace("UNT|13", "UNT|12");
+        let differences = diff(&left, &decode(&input));
+        let lines: Vec<String> =
+            differences.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- SG4/SG5/RFF+ACW: RFF+ACW:V1583553607732'",
+                "~ SG4/SG5/FTX+Z02 4:1 4440: '10' -> '11'",
+                "~ UNT 1 0074: '13' -> '12'",
+            ]
+        );
+        assert!(matches!(differences[0], Difference::Removed { .. }));
+    }
+}
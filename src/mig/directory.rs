@@ -0,0 +1,499 @@
+//! The segments, composites and data elements of a UN/EDIFACT directory
+//! with their official names and representations, e.g. to cross-check
+//! descriptions parsed from MIGs, which may only restrict them.
+//!
+//! The directory D.07B is embedded with the entries used by the supported
+//! messages. Other directories can be given in the same format, which has
+//! one entry per line:
+//!
+//! ```text
+//! S|DTM|Date/time/period|C507 M
+//! C|C507|Date/time/period|2005 M|2380 C|2379 C
+//! E|2005|Date or time or period function code qualifier|an..3
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::mig::compact;
+use crate::mig::description as desc;
+use crate::mig::description::{Format, Size, St};
+use crate::mig::either::Either;
+
+/// A `Directory` contains the segments, composites and data elements of
+/// a UN/EDIFACT directory by their tag or label.
+#[derive(Debug, Clone, Default)]
+pub struct Directory {
+    segments: HashMap<String, Segment>,
+    composites: HashMap<String, Composite>,
+    data_elements: HashMap<String, DataElement>,
+}
+
+/// A `Segment` or `Composite` of the directory with the labels and status
+/// of its elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub tag: String,
+    pub name: String,
+    pub elements: Vec<(String, St)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composite {
+    pub label: String,
+    pub name: String,
+    pub elements: Vec<(String, St)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataElement {
+    pub label: String,
+    pub name: String,
+    pub format: Format,
+    pub length: usize,
+}
+
+/// The error returned, if the given line of a directory is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {} is invalid: {}", self.line, self.reason)
+    }
+}
+
+/// A `Deviation` of a description from the directory in the segment with
+/// the given counter and tag. The position is the label of the data
+/// element or composite, e.g. `C507/2005` or `3035`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deviation {
+    pub counter: String,
+    pub tag: String,
+    pub position: String,
+    pub kind: DeviationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviationKind {
+    /// The directory has another element at this position.
+    Label { expected: String },
+    /// The representation allows values, which the directory does not.
+    Representation { expected: String },
+    /// The element is mandatory in the directory.
+    Status { expected: St },
+    /// The directory has no element at this position.
+    Unexpected,
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}: ", self.counter, self.tag, self.position)?;
+        match &self.kind {
+            DeviationKind::Label { expected } => {
+                write!(f, "The directory expects {} here", expected)
+            }
+            DeviationKind::Representation { expected } => write!(
+                f,
+                "The representation is not within {} of the directory",
+                expected
+            ),
+            DeviationKind::Status { expected } => {
+                write!(f, "The directory has the status {:?}", expected)
+            }
+            DeviationKind::Unexpected => {
+                write!(f, "The directory has no element here")
+            }
+        }
+    }
+}
+
+impl Directory {
+    /// Returns the embedded directory D.07B.
+    pub fn d07b() -> Self {
+        Directory::parse(include_str!("../../directories/D07B.txt"))
+            .expect("The embedded directory is valid")
+    }
+
+    /// Parses a directory in the format described in the [module
+    /// documentation](self). Empty lines and lines starting with `#` are
+    /// skipped.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let mut directory = Directory::default();
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| Error {
+                line: i + 1,
+                reason: reason.to_string(),
+            };
+            let fields: Vec<&str> = line.split('|').collect();
+            let (label, name, rest) = match fields.as_slice() {
+                [_, label, name, rest @ ..] => {
+                    (label.to_string(), name.to_string(), rest)
+                }
+                _ => return Err(invalid("expected a label and a name")),
+            };
+            match fields[0] {
+                "S" => {
+                    let elements = elements(rest).ok_or_else(|| {
+                        invalid("expected elements like C507 M")
+                    })?;
+                    let segment =
+                        Segment { tag: label.clone(), name, elements };
+                    directory.segments.insert(label, segment);
+                }
+                "C" => {
+                    let elements = elements(rest).ok_or_else(|| {
+                        invalid("expected data elements like 2005 M")
+                    })?;
+                    let composite =
+                        Composite { label: label.clone(), name, elements };
+                    directory.composites.insert(label, composite);
+                }
+                "E" => {
+                    let (format, length) = match rest {
+                        [representation] => {
+                            compact::parse_format(representation)
+                        }
+                        _ => None,
+                    }
+                    .ok_or_else(|| invalid("expected a format like an..35"))?;
+                    let data_element = DataElement {
+                        label: label.clone(),
+                        name,
+                        format,
+                        length,
+                    };
+                    directory.data_elements.insert(label, data_element);
+                }
+                _ => return Err(invalid("expected S, C or E")),
+            }
+        }
+        Ok(directory)
+    }
+
+    pub fn segment(&self, tag: &str) -> Option<&Segment> {
+        self.segments.get(tag)
+    }
+
+    pub fn composite(&self, label: &str) -> Option<&Composite> {
+        self.composites.get(label)
+    }
+
+    pub fn data_element(&self, label: &str) -> Option<&DataElement> {
+        self.data_elements.get(label)
+    }
+
+    /// Returns the deviations of the given description from this
+    /// directory. Segments, composites and data elements, which are not in
+    /// the directory, are skipped.
+    pub fn check(&self, desc: &desc::Interchange) -> Vec<Deviation> {
+        let mut deviations = vec![];
+        for segment in segments(desc) {
+            self.check_segment(segment, &mut deviations);
+        }
+        deviations
+    }
+
+    /// Sets the names, which are missing in the given description, to the
+    /// official ones of this directory.
+    pub fn enrich(&self, desc: &mut desc::Interchange) {
+        self.enrich_segment(&mut desc.unb);
+        self.enrich_segment(&mut desc.message.unh);
+        self.enrich_segments(&mut desc.message.segments);
+        self.enrich_segment(&mut desc.message.unt);
+        self.enrich_segment(&mut desc.unz);
+    }
+
+    fn check_segment(
+        &self,
+        desc: &desc::Segment,
+        deviations: &mut Vec<Deviation>,
+    ) {
+        let Some(segment) = self.segment(&desc.tag) else {
+            return;
+        };
+        let mut deviation = |position: &str, kind: DeviationKind| {
+            deviations.push(Deviation {
+                counter: desc.counter.clone(),
+                tag: desc.tag.clone(),
+                position: position.to_string(),
+                kind,
+            })
+        };
+
+        for (i, element) in desc.elements.iter().enumerate() {
+            let (label, st) = match element {
+                Either::Left(composite) => (&composite.label, composite.st),
+                Either::Right(data_element) => {
+                    (&data_element.label, data_element.st)
+                }
+            };
+            let Some((expected, expected_st)) = segment.elements.get(i) else {
+                deviation(label, DeviationKind::Unexpected);
+                continue;
+            };
+            if label != expected {
+                let expected = expected.clone();
+                deviation(label, DeviationKind::Label { expected });
+                continue;
+            }
+            if expected_st.is_required() && !st.is_required() {
+                let kind = DeviationKind::Status { expected: *expected_st };
+                deviation(label, kind);
+            }
+
+            match element {
+                Either::Left(composite) => {
+                    let Some(expected) = self.composite(label) else {
+                        continue;
+                    };
+                    for (j, data_element) in
+                        composite.elements.iter().enumerate()
+                    {
+                        let position =
+                            format!("{}/{}", label, data_element.label);
+                        match expected.elements.get(j) {
+                            None => {
+                                deviation(&position, DeviationKind::Unexpected)
+                            }
+                            Some((expected, _))
+                                if expected != &data_element.label =>
+                            {
+                                let expected = expected.clone();
+                                let kind = DeviationKind::Label { expected };
+                                deviation(&position, kind);
+                            }
+                            Some((_, expected_st)) => {
+                                // Components are only mandatory, if their
+                                // composite is used.
+                                let expected_st = if composite.st.is_not_used()
+                                {
+                                    St::C
+                                } else {
+                                    *expected_st
+                                };
+                                let kinds = self.check_data_element(
+                                    data_element,
+                                    expected_st,
+                                );
+                                for kind in kinds {
+                                    deviation(&position, kind);
+                                }
+                            }
+                        }
+                    }
+                }
+                Either::Right(data_element) => {
+                    // The status has been checked above already.
+                    let kinds = self.check_data_element(data_element, St::C);
+                    for kind in kinds {
+                        deviation(label, kind);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_data_element(
+        &self,
+        desc: &desc::DataElement,
+        expected_st: St,
+    ) -> Vec<DeviationKind> {
+        let mut kinds = vec![];
+        if expected_st.is_required() && !desc.st.is_required() {
+            kinds.push(DeviationKind::Status { expected: expected_st });
+        }
+        if let Some(expected) = self.data_element(&desc.label) {
+            if !within(desc.format, desc.length, expected) {
+                kinds.push(DeviationKind::Representation {
+                    expected: compact::format(
+                        expected.format,
+                        expected.length,
+                    ),
+                });
+            }
+        }
+        kinds
+    }
+
+    fn enrich_segments(
+        &self,
+        descs: &mut [Either<desc::Segmentgroup, desc::Segment>],
+    ) {
+        for desc in descs {
+            match desc {
+                Either::Left(group) => {
+                    self.enrich_segments(&mut group.segments)
+                }
+                Either::Right(segment) => self.enrich_segment(segment),
+            }
+        }
+    }
+
+    fn enrich_segment(&self, desc: &mut desc::Segment) {
+        if let Some(segment) = self.segment(&desc.tag) {
+            fill(&mut desc.name, &segment.name);
+        }
+        for element in &mut desc.elements {
+            match element {
+                Either::Left(composite) => {
+                    if let Some(expected) = self.composite(&composite.label) {
+                        fill(&mut composite.name, &expected.name);
+                    }
+                    for data_element in &mut composite.elements {
+                        self.enrich_data_element(data_element);
+                    }
+                }
+                Either::Right(data_element) => {
+                    self.enrich_data_element(data_element)
+                }
+            }
+        }
+    }
+
+    fn enrich_data_element(&self, desc: &mut desc::DataElement) {
+        if let Some(expected) = self.data_element(&desc.label) {
+            fill(&mut desc.name, &expected.name);
+        }
+    }
+}
+
+fn elements(fields: &[&str]) -> Option<Vec<(String, St)>> {
+    fields
+        .iter()
+        .map(|field| {
+            let (label, st) = field.split_once(' ')?;
+            let st = match st {
+                "M" => St::M,
+                "C" => St::C,
+                _ => return None,
+            };
+            Some((label.to_string(), st))
+        })
+        .collect()
+}
+
+/// Returns the segments of the given description in order.
+fn segments(desc: &desc::Interchange) -> Vec<&desc::Segment> {
+    fn walk<'a>(
+        descs: &'a [Either<desc::Segmentgroup, desc::Segment>],
+        result: &mut Vec<&'a desc::Segment>,
+    ) {
+        for desc in descs {
+            match desc {
+                Either::Left(group) => walk(&group.segments, result),
+                Either::Right(segment) => result.push(segment),
+            }
+        }
+    }
+
+    let mut result = vec![&desc.unb, &desc.message.unh];
+    walk(&desc.message.segments, &mut result);
+    result.extend([&desc.message.unt, &desc.unz]);
+    result
+}
+
+/// Returns, whether all values of the given format and length are allowed
+/// by the data element of the directory.
+fn within(format: Format, length: usize, expected: &DataElement) -> bool {
+    let (chars, size) = parts(format);
+    let (expected_chars, expected_size) = parts(expected.format);
+    let chars = expected_chars == "an" || chars == expected_chars;
+    let size = match (size, expected_size) {
+        (_, Size::AtMost) => length <= expected.length,
+        (Size::Exactly, Size::Exactly) => length == expected.length,
+        (Size::AtMost, Size::Exactly) => false,
+    };
+    chars && size
+}
+
+fn parts(format: Format) -> (&'static str, Size) {
+    match format {
+        Format::Alphanumeric(size) => ("an", size),
+        Format::Alpha(size) => ("a", size),
+        Format::Numeric(size) => ("n", size),
+    }
+}
+
+fn fill(name: &mut String, official: &str) {
+    if name.trim().is_empty() {
+        *name = official.to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::description::{Format, Size};
+    use crate::mig::directory::{DeviationKind, Directory};
+    use crate::mig::either::Either;
+
+    #[test]
+    fn test_check() {
+        let directory = Directory::d07b();
+        let mut desc = fixtures::aperak();
+        assert_eq!(directory.check(&desc), vec![]);
+        let contrl = fixtures::contrl();
+        assert_eq!(directory.check(&contrl), vec![]);
+
+        // DTM+137 with a text of 70 characters.
+        let Either::Right(dtm) = &mut desc.message.segments[1] else {
+            panic!("Expected DTM");
+        };
+        let Either::Left(c507) = &mut dtm.elements[0] else {
+            panic!("Expected C507");
+        };
+        c507.elements[1].length = 70;
+        c507.elements.swap(0, 2);
+
+        let deviations = directory.check(&desc);
+        let kinds: Vec<&DeviationKind> =
+            deviations.iter().map(|d| &d.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &DeviationKind::Label { expected: "2005".to_string() },
+                &DeviationKind::Representation {
+                    expected: "an..35".to_string()
+                },
+                &DeviationKind::Label { expected: "2379".to_string() },
+            ]
+        );
+        assert_eq!(
+            deviations[1].to_string(),
+            "0030 DTM C507/2380: The representation is not within an..35 of \
+             the directory"
+        );
+    }
+
+    #[test]
+    fn test_enrich() {
+        let directory = Directory::d07b();
+        let mut desc = fixtures::aperak();
+        desc.message.unh.name = String::new();
+        directory.enrich(&mut desc);
+        assert_eq!(desc.message.unh.name, "Message header");
+        assert_eq!(desc.unb.name, "Nutzdaten-Kopfsegment");
+    }
+
+    #[test]
+    fn test_parse() {
+        let directory =
+            Directory::parse("# D.07B\nE|1004|Document|an..70\n").unwrap();
+        let data_element = directory.data_element("1004").unwrap();
+        assert_eq!(data_element.format, Format::Alphanumeric(Size::AtMost));
+        assert_eq!(data_element.length, 70);
+
+        let error = Directory::parse("\nS|DTM|Date/time/period|C507\n");
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Line 2 is invalid: expected elements like C507 M"
+        );
+    }
+}
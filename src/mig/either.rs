@@ -17,3 +17,13 @@ pub enum Either<L, R> {
     Left(L),
     Right(R),
 }
+
+impl<L, R> Either<L, R> {
+    /// Converts from `&Either<L, R>` to `Either<&L, &R>`.
+    pub fn as_ref(&self) -> Either<&L, &R> {
+        match self {
+            Either::Left(left) => Either::Left(left),
+            Either::Right(right) => Either::Right(right),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Wrapping of encoded messages into an interchange, e.g. to send
+//! messages generated by another system.
+//!
+//! The [EnvelopeBuilder] writes the UNB with the partners, the creation
+//! date and a new interchange reference, the messages as they are and a
+//! UNZ with their count:
+//!
+//! ```
+//! # use edifact::mig::encode::envelope::{EnvelopeBuilder, ReferenceStrategy};
+//! # use edifact::mig::validate::dtm::Timestamp;
+//! let created =
+//!     Timestamp { year: 2020, month: 3, day: 7, hour: 7, minute: 5 };
+//! let mut next = 41;
+//! let interchange = EnvelopeBuilder::new("9900467000000", "9904590000002")
+//!     .created(created)
+//!     .reference(ReferenceStrategy::Sequential(Box::new(move || {
+//!         next += 1;
+//!         next
+//!     })))
+//!     .message("UNH+1+APERAK:D:07B:UN:2.1d'BGM+313+DOC1'UNT+3+1'")
+//!     .build();
+//! assert_eq!(
+//!     interchange,
+//!     "UNA:+.? 'UNB+UNOC:3+9900467000000:500+9904590000002:500\
+//!      +200307:0705+42'UNH+1+APERAK:D:07B:UN:2.1d'BGM+313+DOC1'\
+//!      UNT+3+1'UNZ+1+42'"
+//! );
+//! ```
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::encode::options::{EncodeOptions, UnaPolicy};
+use crate::mig::encode::wrap;
+use crate::mig::validate::dtm::{TimeZone, Timestamp};
+
+/// The characters of a random interchange reference.
+const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The maximum length of the interchange reference in UNB 0020.
+const REFERENCE_LENGTH: usize = 14;
+
+/// Defines, how the interchange reference in UNB 0020 and UNZ 0020 is
+/// generated.
+pub enum ReferenceStrategy {
+    /// A random reference of 14 digits and upper case letters.
+    Random,
+    /// The number returned by the callback, e.g. the next value of a
+    /// sequence in a database, which guarantees that references are not
+    /// reused.
+    Sequential(Box<dyn FnMut() -> u64>),
+}
+
+/// The `EnvelopeBuilder` of an interchange, see the [module](self).
+///
+/// Partner IDs use the code list `500` by default, the creation date is
+/// the current time in UTC and the reference is random. Within
+/// edi@energy, the creation date is expected in German civil time, see
+/// [EnvelopeBuilder::time_zone].
+pub struct EnvelopeBuilder {
+    syntax: String,
+    version: String,
+    sender: String,
+    sender_code: String,
+    receiver: String,
+    receiver_code: String,
+    created: Option<Timestamp>,
+    time_zone: TimeZone,
+    reference: ReferenceStrategy,
+    test: bool,
+    messages: Vec<String>,
+    options: EncodeOptions,
+}
+
+impl EnvelopeBuilder {
+    /// Creates a builder for an interchange from the given sender to the
+    /// given receiver.
+    pub fn new(
+        sender: impl Into<String>,
+        receiver: impl Into<String>,
+    ) -> Self {
+        EnvelopeBuilder {
+            syntax: "UNOC".to_string(),
+            version: "3".to_string(),
+            sender: sender.into(),
+            sender_code: "500".to_string(),
+            receiver: receiver.into(),
+            receiver_code: "500".to_string(),
+            created: None,
+            time_zone: TimeZone::Utc,
+            reference: ReferenceStrategy::Random,
+            test: false,
+            messages: vec![],
+            options: EncodeOptions::default(),
+        }
+    }
+
+    /// Sets the syntax identifier (UNB 0001) and version (UNB 0002),
+    /// e.g. `UNOC` and `3`. From version 4 on, the creation date has four
+    /// digits for the year.
+    pub fn syntax(
+        mut self,
+        syntax: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        self.syntax = syntax.into();
+        self.version = version.into();
+        self
+    }
+
+    /// Sets the code list of the sender ID (UNB 0007), e.g. `500` for a
+    /// BDEW code or `502` for a DVGW code.
+    pub fn sender_code(mut self, code: impl Into<String>) -> Self {
+        self.sender_code = code.into();
+        self
+    }
+
+    /// Sets the code list of the receiver ID (UNB 0007).
+    pub fn receiver_code(mut self, code: impl Into<String>) -> Self {
+        self.receiver_code = code.into();
+        self
+    }
+
+    /// Sets the creation date (UNB 0017 and 0019), which is written as
+    /// given.
+    pub fn created(mut self, created: Timestamp) -> Self {
+        self.created = Some(created);
+        self
+    }
+
+    /// Sets the time zone the current time is written in, if no creation
+    /// date is given.
+    pub fn time_zone(mut self, time_zone: TimeZone) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    pub fn reference(mut self, reference: ReferenceStrategy) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    /// Flags the interchange as a test transmission (UNB 0035).
+    pub fn test(mut self, test: bool) -> Self {
+        self.test = test;
+        self
+    }
+
+    /// Adds an encoded message from its UNH to its UNT, including the
+    /// segment terminators.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.messages.push(message.into());
+        self
+    }
+
+    /// Sets the layout of the interchange. The messages are written as
+    /// they are, but are wrapped like the rest.
+    pub fn options(mut self, options: EncodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the interchange.
+    pub fn build(mut self) -> String {
+        let una = UNA::default();
+        let reference = match &mut self.reference {
+            ReferenceStrategy::Random => random_reference(),
+            ReferenceStrategy::Sequential(next) => next().to_string(),
+        };
+        let created = self
+            .created
+            .unwrap_or_else(|| self.time_zone.from_utc(&now()).local);
+        let date = if self.version.parse::<u32>().is_ok_and(|v| v >= 4) {
+            format!(
+                "{:04}{:02}{:02}",
+                created.year, created.month, created.day
+            )
+        } else {
+            format!(
+                "{:02}{:02}{:02}",
+                created.year % 100,
+                created.month,
+                created.day
+            )
+        };
+
+        let mut unb = format!(
+            "UNB+{}:{}+{}:{}+{}:{}+{}:{:02}{:02}+{}",
+            self.syntax,
+            self.version,
+            una.escape_value(&self.sender),
+            self.sender_code,
+            una.escape_value(&self.receiver),
+            self.receiver_code,
+            date,
+            created.hour,
+            created.minute,
+            reference
+        );
+        if self.test {
+            unb.push_str("++++++1");
+        }
+
+        let newline =
+            if self.options.newline_after_segment { "\n" } else { "" };
+        let mut result = String::new();
+        if self.options.una_policy == UnaPolicy::Always {
+            result.push_str("UNA:+.? '");
+            result.push_str(newline);
+        }
+        result.push_str(&unb);
+        result.push('\'');
+        result.push_str(newline);
+        for message in &self.messages {
+            result.push_str(message);
+            if !newline.is_empty() && !message.ends_with('\n') {
+                result.push_str(newline);
+            }
+        }
+        result.push_str(&format!(
+            "UNZ+{}+{}'",
+            self.messages.len(),
+            reference
+        ));
+        result.push_str(newline);
+        match self.options.max_line_length {
+            Some(max) if max > 0 => wrap(&result, max),
+            _ => result,
+        }
+    }
+}
+
+/// Returns the current time in UTC.
+fn now() -> Timestamp {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    Timestamp::from_minutes(seconds as i64 / 60)
+}
+
+/// Returns a random reference, which is unique with high probability.
+fn random_reference() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    hasher.write_u128(nanos);
+    let mut result = String::with_capacity(REFERENCE_LENGTH);
+    for i in 0..REFERENCE_LENGTH {
+        hasher.write_usize(i);
+        let value = hasher.finish() as usize;
+        result.push(ALPHABET[value % ALPHABET.len()] as char);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::Error;
+    use crate::mig::either::Either;
+    use crate::mig::encode::envelope::EnvelopeBuilder;
+    use crate::mig::{DecodeOptions, EncodeOptions};
+
+    #[test]
+    fn test_envelope() {
+        let options = EncodeOptions {
+            newline_after_segment: true,
+            ..EncodeOptions::default()
+        };
+        let build = || {
+            EnvelopeBuilder::new("9900467000000", "4012345000023")
+                .receiver_code("502")
+                .test(true)
+                .message("UNH+1+APERAK:D:07B:UN:2.1d'\nUNT+2+1'")
+                .message("UNH+2+APERAK:D:07B:UN:2.1d'\nUNT+2+2'\n")
+                .options(options.clone())
+                .build()
+        };
+        let interchange = build();
+        assert_eq!(interchange.lines().count(), 7);
+        assert!(interchange.ends_with("'\n"));
+
+        let parsed = mig::parse(&mut interchange.as_bytes()).unwrap();
+        let routing = parsed.routing();
+        assert_eq!(routing.receiver_code.as_deref(), Some("502"));
+        assert!(routing.test);
+        let reference = routing.reference.unwrap();
+        assert_eq!(reference.len(), 14);
+        assert!(interchange.contains(&format!("UNZ+2+{}'", reference)));
+        assert_ne!(build(), interchange);
+    }
+
+    #[test]
+    fn test_decode_messages() {
+        let desc = fixtures::aperak();
+        let message = |reference: &str, document: &str| {
+            format!(
+                "UNH+{0}+APERAK:D:07B:UN:2.1d'BGM+313+{1}'\
+                 DTM+137:202003070705:203'NAD+MS+9900467000000::293'\
+                 NAD+MR+9904590000002::293'ERC+Z29'UNT+7+{0}'",
+                reference, document
+            )
+        };
+        let build = |second: String| {
+            EnvelopeBuilder::new("9900467000000", "9904590000002")
+                .message(message("1", "DOC1"))
+                .message(second)
+                .build()
+        };
+        let decode = |input: String| {
+            let options = DecodeOptions::default();
+            mig::decode(vec![desc.clone()], &mut input.as_bytes(), &options)
+        };
+
+        let interchange = decode(build(message("2", "DOC2"))).unwrap();
+        let messages = interchange.messages();
+        let documents: Vec<_> = messages
+            .iter()
+            .filter_map(|message| match &message.segments()[0] {
+                Either::Right(bgm) => bgm.text_of("1004"),
+                Either::Left(_) => None,
+            })
+            .collect();
+        assert_eq!(documents, vec!["DOC1", "DOC2"]);
+        assert_eq!(messages[1].unt().text_of("0062"), Some("2"));
+
+        // The errors are reported with the message they occur in.
+        let second = message("2", "DOC2").replace("Z29", "Z29+X");
+        let Err(Error::Mig(error)) = decode(build(second)) else {
+            panic!("Expected an error in the second message");
+        };
+        assert_eq!(error.message_errors.len(), 1);
+        assert_eq!(error.message_errors[0].pos, 8);
+        assert_eq!(error.message_errors[0].segment_errors[0].pos, 13);
+    }
+}
@@ -0,0 +1,398 @@
+//! This module encodes decoded interchanges back into EDIFACT.
+//!
+//! Elements, which have been empty or omitted in the decoded message, are
+//! restored from the positions of their neighbours, so the segments keep
+//! their structure. Empty elements at the end of a segment or composite
+//! are omitted. The occurrences of a repeated element are separated by
+//! the repetition separator of the UNA. If it has none, `*`, the default
+//! one of syntax version 4, is declared in the UNA written.
+//!
+//! The layout of the output, e.g. a newline after every segment, is
+//! defined by [EncodeOptions]. The service characters are the ones of the
+//! decoded interchange, unless others are given, so it can be encoded
+//! like it has been received.
+//!
+//! The [security segments](crate::mig::security) of the interchange are
+//! encoded again before the segment, which has followed them in the
+//! input. Their signatures only match, if the interchange has not been
+//! transformed.
+pub mod envelope;
+pub mod options;
+
+use std::iter::Peekable;
+use std::slice;
+
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::decode::value::{
+    Composite, DataElement, Interchange, Matched, Segment, Segmentgroup,
+};
+use crate::mig::description::Format;
+use crate::mig::either::Either;
+use crate::mig::security::SecuritySegment;
+use options::{EncodeOptions, UnaPolicy};
+
+/// Encodes the given interchange with its service characters and the
+/// default [EncodeOptions].
+///
+/// Separators and escape characters contained in values are escaped.
+pub fn encode(interchange: &Interchange) -> String {
+    encode_with(interchange, &EncodeOptions::default())
+}
+
+/// Encodes the given interchange with the service characters and the
+/// layout given by the options.
+///
+/// A UNA is always written, if the service characters are not the default
+/// ones, since they could not be read otherwise.
+pub fn encode_with(
+    interchange: &Interchange,
+    options: &EncodeOptions,
+) -> String {
+    let mut una = options.una.unwrap_or(interchange.una);
+    if una.repetition_sep().is_none() && repeats(&interchange.segments) {
+        una.reserved = '*';
+    }
+    let mut result = String::new();
+    if options.una_policy == UnaPolicy::Always || una != UNA::default() {
+        result.push_str(&una.to_string());
+        if options.newline_after_segment {
+            result.push('\n');
+        }
+    }
+    let mut security = interchange.security.iter().peekable();
+    encode_segments(
+        &interchange.segments,
+        &una,
+        options,
+        &mut security,
+        &mut result,
+    );
+    for segment in security {
+        push_segment(
+            &encode_security(segment, &una),
+            &una,
+            options,
+            &mut result,
+        );
+    }
+    match options.max_line_length {
+        Some(max) if max > 0 => wrap(&result, max),
+        _ => result,
+    }
+}
+
+fn encode_segments(
+    values: &[Either<Segmentgroup, Segment>],
+    una: &UNA,
+    options: &EncodeOptions,
+    security: &mut Peekable<slice::Iter<SecuritySegment>>,
+    result: &mut String,
+) {
+    for value in values {
+        match value {
+            Either::Left(group) => encode_segments(
+                &group.segments,
+                una,
+                options,
+                security,
+                result,
+            ),
+            Either::Right(segment) => {
+                while let Some(secured) =
+                    security.next_if(|secured| secured.pos < segment.index)
+                {
+                    let encoded = encode_security(secured, una);
+                    push_segment(&encoded, una, options, result);
+                }
+                let encoded = encode_segment_with(segment, una);
+                push_segment(&encoded, una, options, result);
+            }
+        }
+    }
+}
+
+/// Appends the given encoded segment with its terminator.
+fn push_segment(
+    segment: &str,
+    una: &UNA,
+    options: &EncodeOptions,
+    result: &mut String,
+) {
+    result.push_str(segment);
+    result.push(una.segment_sep);
+    if options.newline_after_segment {
+        result.push('\n');
+    }
+}
+
+/// Encodes the given security segment with the given service characters
+/// without its terminator.
+fn encode_security(segment: &SecuritySegment, una: &UNA) -> String {
+    let component_sep = una.component_sep.to_string();
+    let mut result = segment.tag.clone();
+    for element in &segment.elements {
+        let components: Vec<String> =
+            element.iter().map(|value| una.escape_value(value)).collect();
+        result.push(una.element_sep);
+        result.push_str(&components.join(&component_sep));
+    }
+    result
+}
+
+/// Returns, whether any of the given segments has a repeated element.
+fn repeats(values: &[Either<Segmentgroup, Segment>]) -> bool {
+    values.iter().any(|value| match value {
+        Either::Left(group) => repeats(&group.segments),
+        Either::Right(segment) => {
+            // The occurrences of a repeated element share its position.
+            let indices: Vec<usize> = segment
+                .elements
+                .iter()
+                .map(|element| match element {
+                    Either::Left(composite) => composite.index,
+                    Either::Right(data_element) => data_element.index,
+                })
+                .collect();
+            indices.windows(2).any(|pair| pair[0] == pair[1])
+        }
+    })
+}
+
+/// Breaks the lines of the given text after the given number of
+/// characters.
+fn wrap(text: &str, max: usize) -> String {
+    let mut result = String::with_capacity(text.len() + text.len() / max);
+    let mut column = 0;
+    for c in text.chars() {
+        if c == '\n' {
+            column = 0;
+        } else if column == max {
+            result.push('\n');
+            column = 1;
+        } else {
+            column += 1;
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Encodes the given segment with the default separators without its
+/// terminator.
+pub(crate) fn encode_segment(segment: &Segment) -> String {
+    encode_segment_with(segment, &UNA::default())
+}
+
+/// Encodes the given segment with the given service characters without
+/// its terminator. Repeated elements are separated by `*`, if the
+/// service characters have no repetition separator.
+pub(crate) fn encode_segment_with(segment: &Segment, una: &UNA) -> String {
+    let mut values: Vec<(usize, String)> = vec![];
+    for element in &segment.elements {
+        let (index, value) = match element {
+            Either::Left(composite) => {
+                (composite.index, encode_composite(composite, una))
+            }
+            Either::Right(data_element) => {
+                (data_element.index, encode_value(data_element, una))
+            }
+        };
+        // The occurrences of a repeated element share its position.
+        match values.last_mut() {
+            Some((last, occurrences)) if *last == index => {
+                occurrences.push(una.repetition_sep().unwrap_or('*'));
+                occurrences.push_str(&value);
+            }
+            _ => values.push((index, value)),
+        }
+    }
+    let elements = positioned(values.into_iter());
+
+    let mut result = segment.tag.clone();
+    for element in elements {
+        result.push(una.element_sep);
+        result.push_str(&element);
+    }
+    result
+}
+
+fn encode_composite(composite: &Composite, una: &UNA) -> String {
+    let values =
+        composite.elements.iter().map(|e| (e.index, encode_value(e, una)));
+    positioned(values).join(&una.component_sep.to_string())
+}
+
+fn encode_value(data_element: &DataElement, una: &UNA) -> String {
+    let numeric =
+        matches!(data_element.description.format, Format::Numeric(_));
+    match &data_element.value {
+        // A numeric value may have been normalized to `.`, see
+        // DecodeOptions::normalize_decimals.
+        Some(Matched::Text(text)) if numeric => {
+            text.replace(['.', ','], &una.decimal_char.to_string())
+        }
+        Some(Matched::Text(text)) => una.escape_value(text),
+        Some(Matched::Int(int)) => int.to_string(),
+        Some(Matched::Decimal(decimal)) => {
+            decimal.to_string().replace('.', &una.decimal_char.to_string())
+        }
+        None => String::new(),
+    }
+}
+
+/// Places the given values at their positions, filling the gaps with
+/// empty values and omitting empty values at the end.
+fn positioned(values: impl Iterator<Item = (usize, String)>) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for (index, value) in values {
+        if result.len() <= index {
+            result.resize(index + 1, String::new());
+        }
+        result[index] = value;
+    }
+    while result.last().is_some_and(|value| value.is_empty()) {
+        result.pop();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::parser::value::UNA;
+    use crate::mig::encode::options::{EncodeOptions, UnaPolicy};
+    use crate::mig::encode::{encode, encode_with};
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_encode() {
+        let desc = fixtures::aperak();
+        let input = fixtures::APERAK_FULL;
+        let options = DecodeOptions::default();
+        let interchange =
+            mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap();
+
+        assert_eq!(encode(&interchange), input);
+
+        let mut options = EncodeOptions {
+            newline_after_segment: true,
+            max_line_length: None,
+            una_policy: UnaPolicy::Omit,
+            una: None,
+        };
+        let output = encode_with(&interchange, &options);
+        assert!(output.starts_with("UNB+UNOC:3+"));
+        assert_eq!(output.lines().count(), 16);
+        assert_eq!(output.replace('\n', ""), input["UNA:+.? '".len()..]);
+
+        options.newline_after_segment = false;
+        options.max_line_length = Some(80);
+        let output = encode_with(&interchange, &options);
+        assert!(output.lines().all(|line| line.chars().count() <= 80));
+        assert_eq!(output.replace('\n', ""), input["UNA:+.? '".len()..]);
+    }
+
+    #[test]
+    fn test_encode_security() {
+        let desc = fixtures::aperak();
+        let input = fixtures::APERAK_FULL
+            .replace("'BGM", "'USH+7+1?+2+3:4'BGM")
+            .replace("'UNT+14", "'UST+1?+2+2'UNT+16")
+            .replace("'UNZ", "'USR+1:ABC'UNZ");
+        let options = DecodeOptions::default();
+        let interchange =
+            mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap();
+        assert_eq!(interchange.security.len(), 3);
+
+        assert_eq!(encode(&interchange), input);
+    }
+
+    #[test]
+    fn test_encode_una() {
+        let desc = fixtures::aperak();
+        let input = "UNA|*,! ~\
+                     UNB*UNOC|3*9900467000000|500*9904590000002|500\
+                     *200307|0705*C3AAAAAAAAHKLC~\
+                     UNH*1*APERAK|D|07B|UN|2.1d~\
+                     BGM*313*53ff5de4caab4ea18abafab5e6036991~\
+                     DTM*137|202003070705|203~\
+                     NAD*MS*9900467000000||293~\
+                     NAD*MR*9904590000002||293~\
+                     ERC*Z29~\
+                     FTX*AAO***Wert 10!*3 ungültig!|~\
+                     UNT*8*1~\
+                     UNZ*1*C3AAAAAAAAHKLC~";
+        let options = DecodeOptions::default();
+        let interchange =
+            mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap();
+        assert_eq!(interchange.una, UNA::new('|', '*', ',', '!', ' ', '~'));
+
+        let mut options = EncodeOptions {
+            una_policy: UnaPolicy::Omit,
+            ..EncodeOptions::default()
+        };
+        assert_eq!(encode_with(&interchange, &options), input);
+
+        options.una = Some(UNA::default());
+        let output = encode_with(&interchange, &options);
+        assert!(output.starts_with("UNB+UNOC:3+"));
+        assert!(output.contains("FTX+AAO+++Wert 10*3 ungültig|'"));
+    }
+
+    #[test]
+    fn test_encode_decimals() {
+        // The decimals are normalized to `.` when decoding by default.
+        let message = ["00'", "50'"].iter().fold(
+            fixtures::INVOIC.to_string(),
+            |message, digits| {
+                let from = format!(".{}", digits);
+                message.replace(&from, &format!(",{}", digits))
+            },
+        );
+        let input = fixtures::interchange(&[&message]).replacen(
+            "UNA:+.? '",
+            "UNA:+,? '",
+            1,
+        );
+        assert!(input.contains("MOA+203:250,00'"));
+        let options = DecodeOptions::default();
+        let known = vec![fixtures::invoic()];
+        let interchange =
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap();
+
+        assert_eq!(encode(&interchange), input);
+        let options =
+            EncodeOptions { una: Some(UNA::default()), ..Default::default() };
+        let output = encode_with(&interchange, &options);
+        assert!(output.contains("MOA+203:250.00'"));
+    }
+
+    proptest! {
+        #[test]
+        fn test_encode_escaped(text in "[ -~äöüß]{1,64}") {
+            let desc = fixtures::aperak();
+            let input = fixtures::aperak_with(&format!(
+                "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+                 DTM+137:202003070705:203'\
+                 NAD+MS+9900467000000::293'\
+                 NAD+MR+9904590000002::293'\
+                 ERC+Z29'\
+                 FTX+AAO+++{}'",
+                UNA::default().escape_value(&text)
+            ));
+
+            let interchange = mig::parse(&mut input.as_bytes()).unwrap();
+            let path = "FTX+AAO/4/1".parse().unwrap();
+            prop_assert_eq!(&interchange.select(&path)[0].1, &text);
+
+            let options = DecodeOptions::default();
+            let interchange =
+                mig::decode(vec![desc], &mut input.as_bytes(), &options)
+                    .unwrap();
+            prop_assert_eq!(encode(&interchange), input);
+        }
+    }
+}
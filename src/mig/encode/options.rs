@@ -0,0 +1,43 @@
+//! This module contains the options, which control how an interchange is
+//! laid out, when it is encoded, so the output matches what the receiver
+//! expects.
+use crate::mig::decode::parser::value::UNA;
+
+/// Defines, whether the encoded interchange starts with a UNA service
+/// segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaPolicy {
+    /// Always start with a UNA giving the separators.
+    Always,
+    /// Omit the UNA, which is allowed, if the default separators are
+    /// used.
+    Omit,
+}
+
+/// `EncodeOptions` are passed to [encode_with](crate::mig::encode_with)
+/// and define the layout of the output.
+///
+/// The default options write a single line starting with a UNA.
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+    /// Whether every segment terminator is followed by a newline.
+    pub newline_after_segment: bool,
+    /// The maximum number of characters of a line. Longer lines are
+    /// wrapped, even in the middle of a segment, like in fixed length
+    /// transmissions. `None` means, that lines are not wrapped.
+    pub max_line_length: Option<usize>,
+    pub una_policy: UnaPolicy,
+    /// The service characters, by default the ones of the interchange.
+    pub una: Option<UNA>,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            newline_after_segment: false,
+            max_line_length: None,
+            una_policy: UnaPolicy::Always,
+            una: None,
+        }
+    }
+}
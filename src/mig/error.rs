@@ -1,38 +1,197 @@
 /// This module contains types for errors, which may happen during
 /// parsing and matching of messages.
+use crate::mig::decode::parser::value::UNA;
 use crate::mig::either::Either;
 use std::fmt;
 use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct InterchangeError {
     pub pos: usize,
     pub service_segment_error: Option<ServiceSegmentError>,
-    pub message_errors: Vec<MessageError>
+    pub message_errors: Vec<MessageError>,
+    /// The service characters of the interchange, which the raw segments
+    /// of the errors are written with, if they are known.
+    pub una: Option<UNA>,
 }
 
-#[derive(Debug, Clone)]
+impl InterchangeError {
+    /// Returns all syntax errors of this interchange, including the ones
+    /// of its messages, segments, composites and data elements.
+    pub fn syntax_errors(&self) -> Vec<&SyntaxError> {
+        let mut result = vec![];
+        let service_segment_errors = self
+            .service_segment_error
+            .iter()
+            .chain(self.message_errors.iter().flat_map(|error| {
+                error.service_segment_error.iter()
+            }));
+        for error in service_segment_errors {
+            collect(&error.error, &mut result);
+        }
+
+        let segment_errors =
+            self.message_errors.iter().flat_map(|error| &error.segment_errors);
+        for error in segment_errors {
+            result.extend(&error.syntax_error);
+            for error in &error.errors {
+                collect(error, &mut result);
+            }
+        }
+        result
+    }
+}
+
+fn collect<'a>(
+    error: &'a Either<CompositeError, DataElementError>,
+    result: &mut Vec<&'a SyntaxError>,
+) {
+    match error {
+        Either::Left(error) => {
+            result.extend(&error.syntax_error);
+            for error in &error.errors {
+                result.extend(&error.syntax_errors);
+            }
+        }
+        Either::Right(error) => result.extend(&error.syntax_errors),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct MessageError {
+    /// The position of the UNH of the message among all segments of the
+    /// interchange.
     pub pos: usize,
     pub service_segment_error: Option<ServiceSegmentError>,
-    pub segment_errors: Vec<SegmentError>
+    pub segment_errors: Vec<SegmentError>,
+    /// The segment errors omitted, because they are identical to the ones
+    /// kept, see [MessageError::limit_identical].
+    pub repeated: Vec<Repeated>,
+}
+
+impl MessageError {
+    /// Keeps at most the given number of identical segment errors, e.g.
+    /// of every repetition of a malformed segment group, and counts the
+    /// omitted ones in [MessageError::repeated].
+    pub fn limit_identical(&mut self, max: usize) {
+        // The first one of identical errors is always kept.
+        let max = max.max(1);
+        let mut kept: Vec<SegmentError> = vec![];
+        // The index of the first of identical errors and their number.
+        let mut groups: Vec<(usize, usize)> = vec![];
+        for error in self.segment_errors.drain(..) {
+            let group =
+                groups.iter_mut().find(|(i, _)| kept[*i].is_identical(&error));
+            match group {
+                Some((_, count)) if *count >= max => *count += 1,
+                Some((_, count)) => {
+                    *count += 1;
+                    kept.push(error);
+                }
+                None => {
+                    groups.push((kept.len(), 1));
+                    kept.push(error);
+                }
+            }
+        }
+        for (i, count) in groups.into_iter().filter(|(_, n)| *n > max) {
+            self.repeated.push(Repeated {
+                first: kept[i].pos,
+                syntax_errors: kept[i].syntax_errors(),
+                count,
+                omitted: count - max,
+            });
+        }
+        self.segment_errors = kept;
+    }
+}
+
+/// Identical segment errors omitted from a [MessageError].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Repeated {
+    /// The position of the first of the identical segment errors.
+    pub first: usize,
+    /// The syntax errors of every one of them.
+    pub syntax_errors: Vec<SyntaxError>,
+    /// The number of identical segment errors, including the kept ones.
+    pub count: usize,
+    /// The number of identical segment errors omitted.
+    pub omitted: usize,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for Repeated {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let codes: Vec<String> = self
+            .syntax_errors
+            .iter()
+            .map(|error| error.get_code().to_string())
+            .collect();
+        write!(
+            f,
+            "The error {} occurred {} times, first at segment {}",
+            codes.join(", "),
+            self.count,
+            self.first
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ServiceSegmentError {
     pub tag: String,
     pub error: Either<CompositeError, DataElementError>,
 }
 
-#[derive(Debug, Clone)]
+/// The `Severity` of a [SegmentError]. Warnings are deviations, which
+/// do not change the meaning of a message and are reported with the
+/// decoded interchange instead of failing the decode, if the
+/// [DecodeOptions](crate::mig::DecodeOptions) tolerate them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct SegmentError {
     pub pos: usize,
+    pub severity: Severity,
     pub syntax_error: Option<SyntaxError>,
     pub errors: Vec<Either<CompositeError, DataElementError>>,
+    /// The segment as it has been received, if it exists.
+    pub raw: Option<String>,
+}
+
+impl SegmentError {
+    /// Returns, whether the given error is the same as this one except
+    /// for its position, e.g. of another repetition of the same segment.
+    pub fn is_identical(&self, other: &SegmentError) -> bool {
+        self.tag() == other.tag()
+            && self.severity == other.severity
+            && self.syntax_error == other.syntax_error
+            && self.errors == other.errors
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.raw.as_deref().and_then(|raw| raw.get(..3))
+    }
+
+    /// Returns all syntax errors of this segment, including the ones of
+    /// its composites and data elements.
+    pub fn syntax_errors(&self) -> Vec<SyntaxError> {
+        let mut result: Vec<&SyntaxError> =
+            self.syntax_error.iter().collect();
+        for error in &self.errors {
+            collect(error, &mut result);
+        }
+        result.into_iter().copied().collect()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct CompositeError {
     pub pos: usize,
     pub syntax_error: Option<SyntaxError>,
@@ -49,29 +208,52 @@ impl CompositeError {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A `DataElementError` contains all issues found with the value of a
+/// single data element, e.g. being too long and containing invalid
+/// characters at the same time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct DataElementError {
     pub pos: usize,
-    pub syntax_error: SyntaxError,
+    pub syntax_errors: Vec<SyntaxError>,
 }
 
 impl DataElementError {
     pub fn new(pos: usize, syntax_error: SyntaxError) -> Self {
         DataElementError {
             pos,
-            syntax_error
+            syntax_errors: vec![syntax_error]
+        }
+    }
+
+    pub fn with_errors(pos: usize, syntax_errors: Vec<SyntaxError>) -> Self {
+        DataElementError {
+            pos,
+            syntax_errors
         }
     }
 }
 
 impl fmt::Display for DataElementError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}:{}", self.pos, self.syntax_error)
+        for syntax_error in &self.syntax_errors {
+            writeln!(f, "{}:{}", self.pos, syntax_error)?;
+        }
+        Ok(())
     }
 }
 
+/// The `Language` of the names and messages of syntax errors. The ones
+/// of edi@energy are German.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    German,
+    English,
+}
+
 /// A `SyntaxError` is one of the error codes defined in a CONTRL message.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SyntaxError {
     code: u64,
     name: &'static str,
@@ -168,11 +350,9 @@ impl SyntaxError {
     pub fn unknown_sender() -> Self {
         SyntaxError {
             code: 23,
-            name: "Ungültige(s) Zeichen",
-            message: "Mitteilung, dass ein oder mehrere in der Übertragungsdateiverwendete \
-                      Zeichen nach der definierten Syntax-Ebene imSegment UNB ungültig sind. \
-                      Das ungültige Zeichen ist Teilder Bezugsebene oder folgt unmittelbar dem \
-                      identifizierten Teil der Übertragungsdatei."
+            name: "Unbekannter Absender",
+            message: "Mitteilung, dass der Absender der Übertragungsdatei \
+                      (S002) unbekannt ist."
                     
         }
     }
@@ -236,6 +416,15 @@ impl SyntaxError {
         }
     }
 
+    pub fn too_many_repetitions() -> Self {
+        SyntaxError {
+            code: 35,
+            name: "Zu viele Wiederholungen",
+            message: "Mitteilung, dass ein (Gruppen-)Datenelement zu oft \
+                      wiederholt wurde."
+        }
+    }
+
     pub fn too_many_segmentgroup_repetitions() -> Self {
         SyntaxError {
             code: 36,
@@ -283,6 +472,34 @@ impl SyntaxError {
         }
     }
 
+    /// Returns the syntax error with the given code, e.g. 12 for an
+    /// invalid value.
+    pub fn from_code(code: u64) -> Option<Self> {
+        [
+            Self::syntax_version_or_level_not_supported(),
+            Self::receiver_is_not_actual_receiver(),
+            Self::invalid_value(),
+            Self::missing(),
+            Self::not_supported_at_this_position(),
+            Self::too_many_parts(),
+            Self::invalid_service_chars(),
+            Self::invalid_characters(),
+            Self::unknown_sender(),
+            Self::test_not_supported(),
+            Self::duplicate_found(),
+            Self::references_not_equal(),
+            Self::counter_not_equal(),
+            Self::lower_levels_empty(),
+            Self::too_many_segment_repetitions(),
+            Self::too_many_segmentgroup_repetitions(),
+            Self::invalid_format(),
+            Self::missing_digit_in_front_of_decimal(),
+            Self::data_element_too_long(),
+            Self::data_element_too_short(),
+        ]
+        .into_iter()
+        .find(|error| error.code == code)
+    }
 
     pub fn get_code(&self) -> u64 {
         self.code
@@ -296,4 +513,161 @@ impl SyntaxError {
         self.message
     }
 
+    /// Returns the name of this error in the given language.
+    pub fn get_name_in(&self, language: Language) -> &'static str {
+        match language {
+            Language::German => self.name,
+            Language::English => english(self.code).map_or(self.name, |e| e.0),
+        }
+    }
+
+    /// Returns the message of this error in the given language.
+    pub fn get_message_in(&self, language: Language) -> &'static str {
+        match language {
+            Language::German => self.message,
+            Language::English => {
+                english(self.code).map_or(self.message, |e| e.1)
+            }
+        }
+    }
+
+    /// Returns this error formatted like its `Display` implementation,
+    /// but in the given language.
+    pub fn display_in(&self, language: Language) -> impl fmt::Display {
+        Localized { error: *self, language }
+    }
+}
+
+struct Localized {
+    error: SyntaxError,
+    language: Language,
+}
+
+impl fmt::Display for Localized {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}\n\n{}",
+            self.error.get_code(),
+            self.error.get_name_in(self.language),
+            self.error.get_message_in(self.language)
+        )
+    }
+}
+
+/// Returns the English name and message of the error with the given code
+/// as defined for the syntax error codes 0085 of UN/EDIFACT.
+fn english(code: u64) -> Option<(&'static str, &'static str)> {
+    let texts = match code {
+        2 => (
+            "Syntax version or level not supported",
+            "Notification that the syntax version and/or level is not \
+             supported by the recipient.",
+        ),
+        7 => (
+            "Interchange recipient not actual recipient",
+            "Notification that the interchange recipient (S003) is \
+             different from the actual recipient.",
+        ),
+        12 => (
+            "Invalid value",
+            "Notification that the value of a stand-alone data element, \
+             composite data element or component data element does not \
+             conform to the relevant specifications for the value.",
+        ),
+        13 => (
+            "Missing",
+            "Notification that a service or user segment, data element, \
+             composite data element or component data element, which is \
+             marked M or R, is missing.",
+        ),
+        15 => (
+            "Not supported in this position",
+            "Notification that the recipient does not support use of the \
+             specific type of segment in the identified position.",
+        ),
+        16 => (
+            "Too many constituents",
+            "Notification that the identified segment contained too many \
+             data elements or composite data elements.",
+        ),
+        20 => (
+            "Character invalid as service character",
+            "Notification that a character advised in UNA is invalid as a \
+             service character.",
+        ),
+        21 => (
+            "Invalid character(s)",
+            "Notification that one or more characters used in the \
+             interchange are not valid as defined by the syntax level \
+             indicated in UNB. The invalid character is part of the \
+             referenced level or follows immediately after the identified \
+             part of the interchange.",
+        ),
+        23 => (
+            "Unknown interchange sender",
+            "Notification that the interchange sender (S002) is unknown.",
+        ),
+        25 => (
+            "Test indicator not supported",
+            "Notification that a test processing could not be performed \
+             for the identified interchange, group or message.",
+        ),
+        26 => (
+            "Duplicate detected",
+            "Notification that a possible duplicate of a previously \
+             received interchange has been detected. The earlier \
+             transmission may have been rejected (interchange control \
+             reference of the sender already known to the recipient).",
+        ),
+        28 => (
+            "References do not match",
+            "Notification that the control reference in UNB does not \
+             match the one in UNZ.",
+        ),
+        29 => (
+            "Control count does not match number of instances received",
+            "Notification that the number of messages does not match the \
+             count given in UNZ.",
+        ),
+        32 => (
+            "Lower level empty",
+            "Notification that the interchange did not contain any \
+             messages.",
+        ),
+        35 => (
+            "Too many segment repetitions",
+            "Notification that a segment was repeated too many times.",
+        ),
+        36 => (
+            "Too many segment group repetitions",
+            "Notification that a segment group was repeated too many times.",
+        ),
+        37 => (
+            "Invalid type of character(s)",
+            "Notification that one or more numeric characters were used in \
+             an alphabetic (component) data element or that one or more \
+             alphabetic characters were used in a numeric (component) data \
+             element.",
+        ),
+        38 => (
+            "Missing digit in front of decimal sign",
+            "Notification that a decimal sign is not preceded by one or \
+             more digits.",
+        ),
+        39 => (
+            "Data element too long",
+            "Notification that the length of the data element received \
+             exceeded the maximum length specified in the data element \
+             description.",
+        ),
+        40 => (
+            "Data element too short",
+            "Notification that the length of the data element received is \
+             shorter than the minimum length specified in the data element \
+             description.",
+        ),
+        _ => return None,
+    };
+    Some(texts)
 }
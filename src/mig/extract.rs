@@ -0,0 +1,635 @@
+//! Extraction of flat key/value pairs from decoded interchanges with
+//! rules declared in YAML, e.g. to feed the business content of messages
+//! into another system without traversing them.
+//!
+//! Every rule maps a key to a [Selector]:
+//!
+//! ```yaml
+//! marktlokation: SG5/LOC[3227=172]/C517.3225
+//! sender: SG2/NAD[3035=MS]/C082.3039
+//! ```
+//!
+//! A selector consists of the labels of segment groups, followed by the
+//! tag of a segment and the value to extract, separated by `/`. Segment
+//! groups and segments are matched as direct children, except for the
+//! first step, which may be nested at any depth. A segment may be
+//! restricted to the ones, where data elements have the given values,
+//! like `LOC[3227=172]`. The value is a data element given by its label,
+//! optionally within a composite, like `C517.3225`.
+//!
+//! The first value found is extracted for every key, or `null`, if there
+//! is none.
+//!
+//! The other way around, [Extractor::build] populates an interchange from
+//! the values of the keys with a description as template, e.g. a MIG
+//! specialized with a use case by
+//! [apply_use_case](crate::mig::description::apply_use_case()). Every
+//! required segment and segment group is added once, as well as every
+//! other one, which contains a value. Static values, the values of the
+//! conditions of selectors and the counters in UNT and UNZ are filled in,
+//! so only the business values have to be given.
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::mig::decode;
+use crate::mig::decode::value::{Interchange, Segment, Segmentgroup};
+use crate::mig::description as desc;
+use crate::mig::description::Usage;
+use crate::mig::either::Either;
+use crate::mig::migrate::render;
+use crate::mig::DecodeOptions;
+
+/// A `Selector` of values in a decoded interchange, see the
+/// [module](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    /// The labels of the segment groups leading to the segment.
+    pub groups: Vec<String>,
+    pub tag: String,
+    /// The labels and values of data elements the segment must contain.
+    pub conditions: Vec<(String, String)>,
+    /// The label of the composite containing the value, if any.
+    pub composite: Option<String>,
+    /// The label of the data element containing the value.
+    pub element: String,
+}
+
+/// The error returned, if rules are not valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+    /// The rules are not a YAML mapping of keys to selectors.
+    InvalidRules(String),
+    /// A string is not a valid [Selector].
+    InvalidSelector(String),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::InvalidRules(error) => {
+                write!(f, "The rules are invalid: {}", error)
+            }
+            ExtractError::InvalidSelector(selector) => write!(
+                f,
+                "'{}' is not a valid selector, expected e.g. \
+                 SG5/LOC[3227=172]/C517.3225",
+                selector
+            ),
+        }
+    }
+}
+
+impl error::Error for ExtractError {}
+
+impl FromStr for Selector {
+    type Err = ExtractError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ExtractError::InvalidSelector(s.to_string());
+        let mut steps: Vec<&str> = s.split('/').collect();
+        let value = steps.pop().filter(|_| !steps.is_empty());
+        let (segment, value) = steps.pop().zip(value).ok_or_else(invalid)?;
+
+        let (composite, element) = match value.split_once('.') {
+            Some((composite, element)) => (Some(composite), element),
+            None => (None, value),
+        };
+        if element.is_empty() || composite.is_some_and(str::is_empty) {
+            return Err(invalid());
+        }
+
+        let (tag, conditions) = match segment.split_once('[') {
+            Some((tag, rest)) => {
+                let rest = rest.strip_suffix(']').ok_or_else(invalid)?;
+                let conditions = rest
+                    .split("][")
+                    .map(|condition| {
+                        let (label, value) =
+                            condition.split_once('=').ok_or_else(invalid)?;
+                        Ok((label.to_string(), value.to_string()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (tag, conditions)
+            }
+            None => (segment, vec![]),
+        };
+        if tag.len() != 3 || !tag.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(invalid());
+        }
+        if steps.iter().any(|group| !group.starts_with("SG")) {
+            return Err(invalid());
+        }
+
+        Ok(Selector {
+            groups: steps.iter().map(|group| group.to_string()).collect(),
+            tag: tag.to_string(),
+            conditions,
+            composite: composite.map(str::to_string),
+            element: element.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for group in &self.groups {
+            write!(f, "{}/", group)?;
+        }
+        write!(f, "{}", self.tag)?;
+        for (label, value) in &self.conditions {
+            write!(f, "[{}={}]", label, value)?;
+        }
+        match &self.composite {
+            Some(composite) => write!(f, "/{}.{}", composite, self.element),
+            None => write!(f, "/{}", self.element),
+        }
+    }
+}
+
+impl Selector {
+    /// Returns the non-empty values selected in the given interchange in
+    /// order.
+    pub fn values<'a>(&self, interchange: &'a Interchange) -> Vec<&'a str> {
+        let mut segments = vec![];
+        self.segments(&interchange.segments, 0, true, &mut segments);
+        segments
+            .into_iter()
+            .filter_map(|segment| self.value_of(segment))
+            .collect()
+    }
+
+    fn segments<'a>(
+        &self,
+        values: &'a [Either<Segmentgroup, Segment>],
+        step: usize,
+        nested: bool,
+        result: &mut Vec<&'a Segment>,
+    ) {
+        for value in values {
+            match value {
+                Either::Left(group) => {
+                    if self.groups.get(step) == Some(&group.label) {
+                        self.segments(
+                            &group.segments,
+                            step + 1,
+                            false,
+                            result,
+                        );
+                    }
+                    if nested {
+                        self.segments(&group.segments, step, true, result);
+                    }
+                }
+                Either::Right(segment) => {
+                    if step == self.groups.len() && self.matches(segment) {
+                        result.push(segment);
+                    }
+                }
+            }
+        }
+    }
+
+    fn matches(&self, segment: &Segment) -> bool {
+        segment.tag == self.tag
+            && self.conditions.iter().all(|(label, value)| {
+                segment.texts_of(label).contains(&value.as_str())
+            })
+    }
+
+    fn value_of<'a>(&self, segment: &'a Segment) -> Option<&'a str> {
+        let Some(label) = &self.composite else {
+            let value = segment.text_of(&self.element);
+            return value.filter(|value| !value.is_empty());
+        };
+        segment
+            .elements
+            .iter()
+            .find_map(|element| match element {
+                Either::Left(composite) if composite.label == *label => {
+                    composite
+                        .elements
+                        .iter()
+                        .find(|e| e.description.label == self.element)
+                }
+                _ => None,
+            })
+            .and_then(|element| element.text())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// An `Extractor` of the values of several keys, see the [module](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extractor {
+    pub rules: Vec<(String, Selector)>,
+}
+
+impl Extractor {
+    /// Returns the extractor of the rules given in YAML.
+    pub fn from_yaml(input: &str) -> Result<Extractor, ExtractError> {
+        let rules: serde_yaml::Mapping = serde_yaml::from_str(input)
+            .map_err(|error| ExtractError::InvalidRules(error.to_string()))?;
+        let mut extractor = Extractor::default();
+        for (key, selector) in rules {
+            let (Some(key), Some(selector)) =
+                (key.as_str(), selector.as_str())
+            else {
+                return Err(ExtractError::InvalidRules(
+                    "Expected a mapping of keys to selectors".to_string(),
+                ));
+            };
+            extractor.rules.push((key.to_string(), selector.parse()?));
+        }
+        Ok(extractor)
+    }
+
+    /// Returns the first value of every key in the given interchange as a
+    /// JSON object.
+    pub fn extract(&self, interchange: &Interchange) -> Map<String, Value> {
+        self.rules
+            .iter()
+            .map(|(key, selector)| {
+                let value = selector.values(interchange).first().copied();
+                (key.clone(), value.map_or(Value::Null, Value::from))
+            })
+            .collect()
+    }
+}
+
+impl Selector {
+    /// Returns, whether the given described segment within the given
+    /// segment groups is the one this selector selects values of.
+    fn targets(&self, groups: &[&str], segment: &desc::Segment) -> bool {
+        let data_elements = || {
+            segment.elements.iter().flat_map(|element| match element {
+                Either::Left(composite) => composite
+                    .elements
+                    .iter()
+                    .map(|e| (Some(&composite.label), e))
+                    .collect(),
+                Either::Right(data_element) => vec![(None, data_element)],
+            })
+        };
+        let admits = |label: &str, value: &str| {
+            data_elements().any(|(_, e)| {
+                e.label == label
+                    && match &e.usage {
+                        Usage::Static { value: choice, .. } => {
+                            choice.value == value
+                        }
+                        Usage::OneOf { choices, .. } => {
+                            choices.iter().any(|choice| choice.value == value)
+                        }
+                        _ => true,
+                    }
+            })
+        };
+        segment.tag == self.tag
+            && groups.ends_with(
+                &self.groups.iter().map(String::as_str).collect::<Vec<_>>(),
+            )
+            && self.conditions.iter().all(|(l, v)| admits(l, v))
+            && data_elements().any(|(composite, e)| {
+                composite == self.composite.as_ref() && e.label == self.element
+            })
+    }
+}
+
+impl Extractor {
+    /// Returns an interchange populated from the given values by key
+    /// with the given description as template, see the [module](self).
+    /// It is decoded with the description, so an error is returned, if
+    /// a required value is missing.
+    pub fn build(
+        &self,
+        desc: &desc::Interchange,
+        values: &Map<String, Value>,
+    ) -> Result<Interchange, decode::Error> {
+        let mut builder = Builder { targets: vec![], rows: vec![] };
+        for (key, selector) in &self.rules {
+            let value = match values.get(key) {
+                None | Some(Value::Null) => continue,
+                Some(Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+            };
+            builder.targets.push((selector, value, false));
+        }
+
+        let message = &desc.message;
+        let mut unb = builder.row(&[], &desc.unb);
+        fill(&mut unb, &desc.unb, "0001", "UNOC");
+        fill(&mut unb, &desc.unb, "0002", "3");
+        let mut unh = builder.row(&[], &message.unh);
+        let identifier = desc.message_identifier();
+        let components = [
+            ("0065", identifier.message_type),
+            ("0052", identifier.version),
+            ("0054", identifier.release),
+            ("0051", identifier.agency),
+            ("0057", identifier.association_code),
+        ];
+        for (label, value) in components {
+            if let Some(value) = value {
+                fill(&mut unh, &message.unh, label, &value);
+            }
+        }
+        fill(&mut unh, &message.unh, "0062", "1");
+        builder.segments(&message.segments, &mut vec![]);
+
+        // UNH and UNT count towards the number of segments.
+        let count = (builder.rows.len() + 2).to_string();
+        let mut unt = builder.row(&[], &message.unt);
+        fill(&mut unt, &message.unt, "0074", &count);
+        fill(&mut unt, &message.unt, "0062", &get(&unh, &message.unh, "0062"));
+        let mut unz = builder.row(&[], &desc.unz);
+        fill(&mut unz, &desc.unz, "0036", "1");
+        fill(&mut unz, &desc.unz, "0020", &get(&unb, &desc.unb, "0020"));
+
+        let mut text = String::from("UNA:+.? '");
+        text.push_str(&render(&desc.unb.tag, &unb));
+        text.push_str(&render(&message.unh.tag, &unh));
+        for (tag, row) in &builder.rows {
+            text.push_str(&render(tag, row));
+        }
+        text.push_str(&render(&message.unt.tag, &unt));
+        text.push_str(&render(&desc.unz.tag, &unz));
+        let options = DecodeOptions::default();
+        decode::decode(vec![desc.clone()], &mut text.as_bytes(), &options)
+    }
+}
+
+/// The values of the elements of a segment, where every element is a
+/// list of its components.
+type Row = Vec<Vec<String>>;
+
+/// The `Builder` of the segments of a message from the values of
+/// selectors, which are marked once they have been used.
+struct Builder<'a> {
+    targets: Vec<(&'a Selector, String, bool)>,
+    rows: Vec<(String, Row)>,
+}
+
+impl Builder<'_> {
+    fn segments<'d>(
+        &mut self,
+        descs: &'d [Either<desc::Segmentgroup, desc::Segment>],
+        groups: &mut Vec<&'d str>,
+    ) {
+        for desc in descs {
+            match desc {
+                Either::Left(group) => {
+                    groups.push(&group.label);
+                    if group.st.is_required()
+                        || self.contains_target(&group.segments, groups)
+                    {
+                        self.segments(&group.segments, groups);
+                    }
+                    groups.pop();
+                }
+                Either::Right(segment) => {
+                    let targeted = self.targets.iter().any(|(s, _, used)| {
+                        !used && s.targets(groups, segment)
+                    });
+                    if segment.st.is_required() || targeted {
+                        let row = self.row(groups, segment);
+                        self.rows.push((segment.tag.clone(), row));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns, whether any of the given descriptions is the target of a
+    /// selector, which has not been used yet.
+    fn contains_target<'d>(
+        &self,
+        descs: &'d [Either<desc::Segmentgroup, desc::Segment>],
+        groups: &mut Vec<&'d str>,
+    ) -> bool {
+        descs.iter().any(|desc| match desc {
+            Either::Left(group) => {
+                groups.push(&group.label);
+                let result = self.contains_target(&group.segments, groups);
+                groups.pop();
+                result
+            }
+            Either::Right(segment) => self
+                .targets
+                .iter()
+                .any(|(s, _, used)| !used && s.targets(groups, segment)),
+        })
+    }
+
+    /// Returns the values of the given segment from the selectors, which
+    /// target it, and its required static values.
+    fn row(&mut self, groups: &[&str], segment: &desc::Segment) -> Row {
+        let mut row = empty(segment);
+        for (selector, value, used) in &mut self.targets {
+            if *used || !selector.targets(groups, segment) {
+                continue;
+            }
+            *used = true;
+            for (label, value) in &selector.conditions {
+                fill(&mut row, segment, label, value);
+            }
+            let at = position(segment, selector.composite.as_ref(), |e| {
+                e.label == selector.element
+            });
+            if let Some([element, component]) = at {
+                row[element][component] = value.clone();
+            }
+        }
+        for (i, element) in segment.elements.iter().enumerate() {
+            let data_elements = match element {
+                Either::Left(composite) => composite.elements.iter().collect(),
+                Either::Right(data_element) => vec![data_element],
+            };
+            for (j, data_element) in data_elements.into_iter().enumerate() {
+                if let Usage::Static { value, .. } = &data_element.usage {
+                    if data_element.st.is_required() && row[i][j].is_empty() {
+                        row[i][j] = value.value.clone();
+                    }
+                }
+            }
+        }
+        row
+    }
+}
+
+/// Returns the empty values of the elements of the given segment.
+fn empty(segment: &desc::Segment) -> Row {
+    segment
+        .elements
+        .iter()
+        .map(|element| match element {
+            Either::Left(composite) => {
+                vec![String::new(); composite.elements.len()]
+            }
+            Either::Right(_) => vec![String::new()],
+        })
+        .collect()
+}
+
+/// Returns the position of the first data element of the given segment,
+/// which is in the given composite and for which the predicate holds.
+fn position(
+    segment: &desc::Segment,
+    composite: Option<&String>,
+    predicate: impl Fn(&desc::DataElement) -> bool,
+) -> Option<[usize; 2]> {
+    segment.elements.iter().enumerate().find_map(
+        |(i, element)| match element {
+            Either::Left(c) if composite.is_none_or(|l| *l == c.label) => {
+                let j = c.elements.iter().position(&predicate)?;
+                Some([i, j])
+            }
+            Either::Right(e) if composite.is_none() && predicate(e) => {
+                Some([i, 0])
+            }
+            _ => None,
+        },
+    )
+}
+
+/// Sets the first data element with the given label to the given value,
+/// if it is empty.
+fn fill(row: &mut Row, segment: &desc::Segment, label: &str, value: &str) {
+    if let Some([i, j]) = position(segment, None, |e| e.label == label) {
+        if row[i][j].is_empty() {
+            row[i][j] = value.to_string();
+        }
+    }
+}
+
+/// Returns the value of the first data element with the given label.
+fn get(row: &Row, segment: &desc::Segment, label: &str) -> String {
+    position(segment, None, |e| e.label == label)
+        .map(|[i, j]| row[i][j].clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::fixtures;
+    use crate::mig::extract::{Extractor, Selector};
+    use crate::mig::{self, DecodeOptions};
+
+    #[test]
+    fn test_parse_selector() {
+        let selector: Selector =
+            "SG5/LOC[3227=172]/C517.3225".parse().unwrap();
+        assert_eq!(selector.groups, vec!["SG5"]);
+        assert_eq!(selector.tag, "LOC");
+        assert_eq!(
+            selector.conditions,
+            vec![("3227".to_string(), "172".to_string())]
+        );
+        assert_eq!(selector.composite.as_deref(), Some("C517"));
+        assert_eq!(selector.element, "3225");
+        assert_eq!(selector.to_string(), "SG5/LOC[3227=172]/C517.3225");
+
+        assert!("BGM".parse::<Selector>().is_err());
+        assert!("LOC[3227]/3225".parse::<Selector>().is_err());
+        assert!("X5/LOC/3225".parse::<Selector>().is_err());
+    }
+
+    #[test]
+    fn test_extract() {
+        let desc = fixtures::aperak();
+        let options = DecodeOptions::default();
+        let interchange = mig::decode(
+            vec![desc],
+            &mut fixtures::APERAK_FULL.as_bytes(),
+            &options,
+        )
+        .unwrap();
+        let extractor = Extractor::from_yaml(
+            "sender: SG3/NAD[3035=MS]/C082.3039\n\
+             document: BGM/C106.1004\n\
+             reference: SG5/RFF[1153=ACW]/C506.1154\n\
+             nested: SG4/RFF/C506.1154\n\
+             missing: SG3/NAD[3035=DP]/C082.3039\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::Value::Object(extractor.extract(&interchange)),
+            json!({
+                "sender": "9900467000000",
+                "document": "53ff5de4caab4ea18abafab5e6036991",
+                "reference": "V1583553607732",
+                "nested": null,
+                "missing": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let desc = fixtures::aperak();
+        let extractor = Extractor::from_yaml(
+            "sender: UNB/S002.0004\n\
+             sender_code: UNB/S002.0007\n\
+             receiver: UNB/S003.0010\n\
+             receiver_code: UNB/S003.0007\n\
+             date: UNB/S004.0017\n\
+             time: UNB/S004.0019\n\
+             reference: UNB/0020\n\
+             document: BGM/C106.1004\n\
+             created: DTM[2005=137]/C507.2380\n\
+             referenced: SG2/RFF/C506.1154\n\
+             received: SG2/DTM/C507.2380\n\
+             from: SG3/NAD[3035=MS]/C082.3039\n\
+             from_code: SG3/NAD[3035=MS]/C082.3055\n\
+             to: SG3/NAD[3035=MR]/C082.3039\n\
+             to_code: SG3/NAD[3035=MR]/C082.3055\n\
+             error: SG4/ERC/C901.9321\n\
+             transaction: SG5/RFF[1153=ACW]/C506.1154\n\
+             order: SG5/RFF[1153=AGO]/C506.1154\n",
+        )
+        .unwrap();
+        let values = json!({
+            "sender": "9900467000000",
+            "sender_code": 500,
+            "receiver": "9904590000002",
+            "receiver_code": 500,
+            "date": "200307",
+            "time": "0705",
+            "reference": "C3AAAAAAAAHKLC",
+            "document": "53ff5de4caab4ea18abafab5e6036991",
+            "created": "202003070705",
+            "referenced": "O1583553607732",
+            "received": "202003070500",
+            "from": "9900467000000",
+            "from_code": "293",
+            "to": "9904590000002",
+            "to_code": "293",
+            "error": "Z29",
+            "transaction": "V1583553607732",
+            "order": "9904590000002ORD1583553607706",
+            "unknown": null,
+        });
+
+        let interchange =
+            extractor.build(&desc, values.as_object().unwrap()).unwrap();
+        assert_eq!(
+            mig::encode(&interchange),
+            fixtures::aperak_with(
+                "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+                 DTM+137:202003070705:203'\
+                 RFF+ACE:O1583553607732'\
+                 DTM+171:202003070500:203'\
+                 NAD+MS+9900467000000::293'\
+                 NAD+MR+9904590000002::293'\
+                 ERC+Z29'\
+                 RFF+ACW:V1583553607732'\
+                 RFF+AGO:9904590000002ORD1583553607706'",
+            )
+        );
+    }
+}
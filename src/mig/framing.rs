@@ -0,0 +1,287 @@
+//! Framing of interchanges in a stream of bytes, e.g. to consume them
+//! from a message broker or a socket, which delivers them in arbitrary
+//! chunks or several of them at once.
+//!
+//! An interchange starts with UNA or UNB and ends with the terminator of
+//! its UNZ. Line breaks and other whitespace between interchanges are
+//! skipped, the ones inside an interchange are kept. The separators are
+//! taken from the UNA, so an escaped terminator does not end a segment.
+//!
+//! ```
+//! # use edifact::mig::framing::Framer;
+//! let mut framer = Framer::new();
+//! framer.push(b"UNB+UNOC:3+S+R+200307:0705+1'UNZ+0+1'\nUNB+UNOC");
+//! let first = framer.next_interchange().unwrap().unwrap();
+//! assert!(first.ends_with(b"UNZ+0+1'"));
+//! assert!(framer.next_interchange().unwrap().is_none());
+//! framer.push(b":3+S+R+200307:0705+2'UNZ+0+2'");
+//! assert!(framer.next_interchange().unwrap().is_some());
+//! ```
+use std::fmt;
+use std::io::{self, Read};
+
+/// The number of bytes read at once by [Interchanges].
+const CHUNK: usize = 8 * 1024;
+
+/// The error returned while framing interchanges.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The bytes before the next interchange, which do not start with
+    /// UNA or UNB, have been skipped. Framing can continue.
+    Skipped(Vec<u8>),
+    /// The stream ended within an interchange.
+    Incomplete(Vec<u8>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => error.fmt(f),
+            Error::Skipped(bytes) => {
+                write!(
+                    f,
+                    "Skipped {} bytes outside of an interchange",
+                    bytes.len()
+                )
+            }
+            Error::Incomplete(bytes) => {
+                write!(
+                    f,
+                    "The stream ended within an interchange after {} bytes",
+                    bytes.len()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// The separators of the interchange being framed.
+#[derive(Debug, Clone, Copy)]
+struct Separators {
+    terminator: u8,
+    escape: u8,
+}
+
+/// A `Framer` reassembles interchanges from chunks of a stream, see the
+/// [module](self).
+#[derive(Debug, Clone, Default)]
+pub struct Framer {
+    buffer: Vec<u8>,
+    /// The separators, once the start of an interchange has been found.
+    separators: Option<Separators>,
+    /// The number of bytes of the buffer, which have been scanned.
+    scanned: usize,
+    /// The start of the segment being scanned.
+    segment: usize,
+    /// The number of escape characters just before the scanned bytes.
+    escapes: usize,
+    /// The bytes skipped before the next interchange.
+    skipped: Vec<u8>,
+}
+
+impl Framer {
+    pub fn new() -> Framer {
+        Framer::default()
+    }
+
+    /// Appends the given chunk of the stream.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Returns the bytes, which have been pushed, but do not form a
+    /// complete interchange yet.
+    pub fn remainder(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns the next complete interchange including its UNA, if one
+    /// has been pushed.
+    pub fn next_interchange(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let separators = match self.separators {
+            Some(separators) => separators,
+            None => match self.start()? {
+                Some(separators) => separators,
+                None => return Ok(None),
+            },
+        };
+
+        while self.scanned < self.buffer.len() {
+            let byte = self.buffer[self.scanned];
+            self.scanned += 1;
+            if byte == separators.escape {
+                self.escapes += 1;
+                continue;
+            }
+            let escaped = self.escapes % 2 == 1;
+            self.escapes = 0;
+            if byte != separators.terminator || escaped {
+                continue;
+            }
+
+            let segment =
+                self.buffer[self.segment..self.scanned].trim_ascii_start();
+            self.segment = self.scanned;
+            if segment.starts_with(b"UNZ") {
+                let interchange = self.buffer.drain(..self.scanned).collect();
+                self.reset();
+                return Ok(Some(interchange));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Finds the start of the next interchange and returns its
+    /// separators, if enough bytes have been pushed.
+    fn start(&mut self) -> Result<Option<Separators>, Error> {
+        let separators = loop {
+            let whitespace = self
+                .buffer
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+            self.buffer.drain(..whitespace);
+
+            if self.buffer.len() < 3 {
+                return Ok(None);
+            }
+            if self.buffer.starts_with(b"UNA") {
+                // UNA is followed by the component and element separators,
+                // the decimal character, the escape character, a reserved
+                // character and the terminator.
+                if self.buffer.len() < 9 {
+                    return Ok(None);
+                }
+                self.scanned = 9;
+                let (terminator, escape) = (self.buffer[8], self.buffer[6]);
+                break Separators { terminator, escape };
+            }
+            if self.buffer.starts_with(b"UNB") {
+                break Separators { terminator: b'\'', escape: b'?' };
+            }
+
+            // The last two bytes may be the start of a UNA or UNB.
+            let next = (1..self.buffer.len())
+                .find(|i| {
+                    let rest = &self.buffer[*i..];
+                    let rest = &rest[..rest.len().min(3)];
+                    b"UNA".starts_with(rest) || b"UNB".starts_with(rest)
+                })
+                .unwrap_or(self.buffer.len());
+            self.skipped.extend(self.buffer.drain(..next));
+        };
+        self.segment = self.scanned;
+        self.separators = Some(separators);
+        if self.skipped.is_empty() {
+            Ok(Some(separators))
+        } else {
+            Err(Error::Skipped(std::mem::take(&mut self.skipped)))
+        }
+    }
+
+    fn reset(&mut self) {
+        self.separators = None;
+        self.scanned = 0;
+        self.segment = 0;
+        self.escapes = 0;
+    }
+}
+
+/// An iterator over the `Interchanges` of a stream, which reads it in
+/// chunks with a [Framer].
+pub struct Interchanges<R> {
+    input: R,
+    framer: Framer,
+    done: bool,
+}
+
+impl<R: Read> Interchanges<R> {
+    pub fn new(input: R) -> Self {
+        Interchanges { input, framer: Framer::new(), done: false }
+    }
+}
+
+impl<R: Read> Iterator for Interchanges<R> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = [0; CHUNK];
+        loop {
+            match self.framer.next_interchange() {
+                Ok(Some(interchange)) => return Some(Ok(interchange)),
+                Ok(None) => {}
+                Err(error) => return Some(Err(error)),
+            }
+            if self.done {
+                if !self.framer.skipped.is_empty() {
+                    let skipped = std::mem::take(&mut self.framer.skipped);
+                    return Some(Err(Error::Skipped(skipped)));
+                }
+                let rest = std::mem::take(&mut self.framer.buffer);
+                self.framer.reset();
+                return if rest.trim_ascii().is_empty() {
+                    None
+                } else {
+                    Some(Err(Error::Incomplete(rest)))
+                };
+            }
+            match self.input.read(&mut chunk) {
+                Ok(0) => self.done = true,
+                Ok(read) => self.framer.push(&chunk[..read]),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Some(Err(Error::Io(error))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::framing::{Error, Framer, Interchanges};
+
+    const INPUT: &[u8] = b"UNA:+.? '\
+        UNB+UNOC:3+9900467000000:500+9904590000002:500\
+        +200307:0705+C3AAAAAAAAHKLC'\n\
+        UNH+1+APERAK:D:07B:UN:2.1d'\n\
+        FTX+Z02+++UNZ?'10'\n\
+        UNT+3+1'\n\
+        UNZ+1+C3AAAAAAAAHKLC'\n";
+
+    #[test]
+    fn test_framer() {
+        let mut stream = b"\r\n".to_vec();
+        stream.extend_from_slice(INPUT);
+        stream.extend_from_slice(&INPUT.to_ascii_lowercase());
+        stream.extend_from_slice(INPUT);
+        stream.extend_from_slice(&INPUT[..20]);
+
+        // Every chunk is a single byte.
+        let mut framer = Framer::new();
+        let mut results = vec![];
+        for byte in &stream {
+            framer.push(&[*byte]);
+            while let Some(result) = framer.next_interchange().transpose() {
+                results.push(result);
+            }
+        }
+        let expected = INPUT.trim_ascii_end();
+        assert!(matches!(&results[0], Ok(i) if i == expected));
+        assert!(matches!(&results[1], Err(Error::Skipped(_))));
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[2], Ok(i) if i == expected));
+        assert_eq!(framer.remainder(), &INPUT[..20]);
+
+        let results: Vec<_> = Interchanges::new(&stream[..]).collect();
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results.last(), Some(Err(Error::Incomplete(_)))));
+    }
+}
@@ -0,0 +1,419 @@
+//! Checks of descriptions for mistakes, which otherwise only surface as
+//! confusing errors while decoding, e.g. a segment group, which can never
+//! be matched, since its sibling with the same counter takes all of its
+//! segments.
+//!
+//! Parsed interchanges can be checked with [lint_text] for service
+//! characters, which the sender most likely forgot to escape.
+//!
+//! ```
+//! # use edifact::mig::description::Interchange;
+//! let desc: Interchange =
+//!     serde_json::from_str(include_str!("../../APERAK.json")).unwrap();
+//! assert!(edifact::mig::lint::lint(&desc).is_empty());
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::mig::decode::compiled::{qualifiers, Qualifier};
+use crate::mig::decode::parser::value as parsed;
+use crate::mig::description as desc;
+use crate::mig::description::Usage;
+use crate::mig::either::Either;
+
+/// A `Lint` is an issue of the segment or segment group with the given
+/// counter and tag or label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub counter: String,
+    pub label: String,
+    pub issue: Issue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The counter is also used by a segment or group with another label.
+    DuplicateCounter { other: String },
+    /// The segment group does not contain any segments.
+    EmptyGroup,
+    /// The first segment of the group, which starts it, is not required.
+    NoTrigger,
+    /// The segment or group may not occur at all.
+    NoRepetitions,
+    /// The data element has a length of 0.
+    ZeroLength { label: String },
+    /// The data element is not used, but has a code list.
+    UnusedCodes { label: String },
+    /// The data element has a code list without any codes.
+    NoChoices { label: String },
+    /// A sibling with the same counter and tag has no qualifiers, so
+    /// segments cannot be told apart by their qualifiers.
+    NoQualifier,
+    /// A sibling with the same counter and tag allows the same value in
+    /// its qualifier, so only the first one is ever matched.
+    AmbiguousQualifier { value: String },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: ", self.counter, self.label)?;
+        match &self.issue {
+            Issue::DuplicateCounter { other } => {
+                write!(f, "The counter is also used by {}", other)
+            }
+            Issue::EmptyGroup => write!(f, "The group contains no segments"),
+            Issue::NoTrigger => {
+                write!(f, "The group does not start with a required segment")
+            }
+            Issue::NoRepetitions => write!(f, "The maximum repetitions are 0"),
+            Issue::ZeroLength { label } => {
+                write!(f, "The data element {} has a length of 0", label)
+            }
+            Issue::UnusedCodes { label } => write!(
+                f,
+                "The data element {} is not used, but has codes",
+                label
+            ),
+            Issue::NoChoices { label } => {
+                write!(f, "The data element {} has no codes", label)
+            }
+            Issue::NoQualifier => write!(
+                f,
+                "A sibling with the same counter has no qualifier to tell \
+                 them apart"
+            ),
+            Issue::AmbiguousQualifier { value } => write!(
+                f,
+                "A sibling with the same counter has the qualifier {} too",
+                value
+            ),
+        }
+    }
+}
+
+/// Returns the issues found in the given description in the order of
+/// its segments.
+pub fn lint(desc: &desc::Interchange) -> Vec<Lint> {
+    let mut linter = Linter::default();
+    linter.segment(&desc.unb);
+    linter.segment(&desc.message.unh);
+    linter.segments(&desc.message.segments);
+    linter.segment(&desc.message.unt);
+    linter.segment(&desc.unz);
+    linter.lints
+}
+
+#[derive(Default)]
+struct Linter {
+    /// The label of the first segment or group by counter. UNB and UNZ
+    /// usually share theirs, so service segments are not added.
+    labels: HashMap<String, String>,
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn push(&mut self, counter: &str, label: &str, issue: Issue) {
+        self.lints.push(Lint {
+            counter: counter.to_string(),
+            label: label.to_string(),
+            issue,
+        });
+    }
+
+    fn counter(&mut self, counter: &str, label: &str) {
+        let other = self
+            .labels
+            .entry(counter.to_string())
+            .or_insert_with(|| label.to_string())
+            .clone();
+        if other != label {
+            self.push(counter, label, Issue::DuplicateCounter { other });
+        }
+    }
+
+    fn segments(
+        &mut self,
+        descs: &[Either<desc::Segmentgroup, desc::Segment>],
+    ) {
+        for desc in descs {
+            match desc {
+                Either::Left(group) => self.group(group),
+                Either::Right(segment) => {
+                    self.counter(&segment.counter, &segment.tag);
+                    if segment.max_reps == 0 {
+                        self.push(
+                            &segment.counter,
+                            &segment.tag,
+                            Issue::NoRepetitions,
+                        );
+                    }
+                    self.segment(segment);
+                }
+            }
+        }
+        self.siblings(descs);
+    }
+
+    fn group(&mut self, desc: &desc::Segmentgroup) {
+        self.counter(&desc.counter, &desc.label);
+        if desc.max_reps == 0 {
+            self.push(&desc.counter, &desc.label, Issue::NoRepetitions);
+        }
+        match desc.segments.first() {
+            None => self.push(&desc.counter, &desc.label, Issue::EmptyGroup),
+            Some(Either::Right(segment)) if segment.st.is_required() => {}
+            Some(_) => self.push(&desc.counter, &desc.label, Issue::NoTrigger),
+        }
+        self.segments(&desc.segments);
+    }
+
+    fn segment(&mut self, desc: &desc::Segment) {
+        let data_elements = desc.elements.iter().flat_map(|e| match e {
+            Either::Left(composite) => composite.elements.iter().collect(),
+            Either::Right(data_element) => vec![data_element],
+        });
+        for data_element in data_elements {
+            let label = data_element.label.clone();
+            if data_element.length == 0 {
+                let issue = Issue::ZeroLength { label: label.clone() };
+                self.push(&desc.counter, &desc.tag, issue);
+            }
+            match &data_element.usage {
+                Usage::OneOf { choices, comment: _ } if choices.is_empty() => {
+                    let issue = Issue::NoChoices { label };
+                    self.push(&desc.counter, &desc.tag, issue);
+                }
+                Usage::OneOf { .. } if data_element.st.is_not_used() => {
+                    let issue = Issue::UnusedCodes { label };
+                    self.push(&desc.counter, &desc.tag, issue);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks, that siblings with the same counter and tag, which are
+    /// matched by their qualifiers while decoding, can be told apart.
+    fn siblings(
+        &mut self,
+        descs: &[Either<desc::Segmentgroup, desc::Segment>],
+    ) {
+        let triggers: Vec<(&str, &str, &desc::Segment)> = descs
+            .iter()
+            .filter_map(|desc| match desc {
+                Either::Left(group) => trigger(group)
+                    .map(|segment| (&*group.counter, &*group.label, segment)),
+                Either::Right(segment) => {
+                    Some((&*segment.counter, &*segment.tag, segment))
+                }
+            })
+            .collect();
+
+        for (i, (counter, label, segment)) in triggers.iter().enumerate() {
+            let earlier = triggers[..i]
+                .iter()
+                .filter(|(c, _, s)| c == counter && s.tag == segment.tag);
+            let earlier: Vec<Vec<Qualifier>> =
+                earlier.map(|(_, _, s)| qualifiers(s)).collect();
+            if earlier.is_empty() {
+                continue;
+            }
+
+            let current = qualifiers(segment);
+            if current.is_empty() || earlier.iter().any(Vec::is_empty) {
+                self.push(counter, label, Issue::NoQualifier);
+                continue;
+            }
+            let ambiguous = earlier.iter().flatten().find_map(|other| {
+                current
+                    .iter()
+                    .filter(|q| {
+                        q.element == other.element
+                            && q.component == other.component
+                    })
+                    .find_map(|q| q.values.intersection(&other.values).min())
+                    .cloned()
+            });
+            if let Some(value) = ambiguous {
+                self.push(counter, label, Issue::AmbiguousQualifier { value });
+            }
+        }
+    }
+}
+
+/// Returns the segment starting the given group.
+fn trigger(desc: &desc::Segmentgroup) -> Option<&desc::Segment> {
+    match desc.segments.first()? {
+        Either::Left(group) => trigger(group),
+        Either::Right(segment) => Some(segment),
+    }
+}
+
+/// A `TextLint` is an issue of the segment at the given position of a
+/// parsed interchange, not counting UNA. It does not prevent parsing,
+/// but the text is likely not what the sender intended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextLint {
+    pub pos: usize,
+    pub tag: String,
+    /// The start of the value or segment with the issue in the input.
+    pub start: parsed::Position,
+    pub issue: TextIssue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextIssue {
+    /// The value contains an escape character, which does not release a
+    /// service character, e.g. `10?%`. It is kept in the value.
+    UnescapedRelease { value: String },
+    /// The tag is not made of three letters or digits. Usually, this is
+    /// the rest of a free text with an unescaped segment terminator,
+    /// e.g. `Brien` of `FTX+AAO+++Kunde O'Brien+Dublin'`.
+    InvalidTag,
+}
+
+impl fmt::Display for TextLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}: ", self.tag, self.start)?;
+        match &self.issue {
+            TextIssue::UnescapedRelease { value } => write!(
+                f,
+                "The escape character in {} does not release a service \
+                 character",
+                value
+            ),
+            TextIssue::InvalidTag => write!(
+                f,
+                "The tag is invalid, maybe the terminator of the segment \
+                 before has not been escaped"
+            ),
+        }
+    }
+}
+
+/// Returns the issues found in the text of the given parsed interchange
+/// in the order of its segments.
+pub fn lint_text(interchange: &parsed::Interchange) -> Vec<TextLint> {
+    let una = &interchange.una;
+    let mut lints = vec![];
+    for (pos, segment) in interchange.segments.iter().enumerate() {
+        let lint = |start, issue| TextLint {
+            pos,
+            tag: segment.tag.value.clone(),
+            start,
+            issue,
+        };
+        let tag = &segment.tag.value;
+        let valid = |c: char| c.is_ascii_uppercase() || c.is_ascii_digit();
+        if tag.len() != 3 || !tag.chars().all(valid) {
+            lints.push(lint(segment.start, TextIssue::InvalidTag));
+        }
+
+        let elements = segment.elements.iter().flat_map(|element| {
+            match element {
+                Either::Left(composite) => composite.elements.iter(),
+                Either::Right(element) => std::slice::from_ref(element).iter(),
+            }
+        });
+        for element in elements {
+            let from = element.start.offset - segment.start.offset;
+            let to = element.end.offset - segment.start.offset;
+            let Some(raw) = segment.raw.get(from..to) else {
+                continue;
+            };
+            let mut chars = raw.chars();
+            while let Some(c) = chars.next() {
+                if c == una.escape
+                    && !chars.next().is_some_and(|c| una.is_released(c))
+                {
+                    let issue = TextIssue::UnescapedRelease {
+                        value: raw.to_string(),
+                    };
+                    lints.push(lint(element.start, issue));
+                    break;
+                }
+            }
+        }
+    }
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::decode::parser;
+    use crate::mig::description::Usage;
+    use crate::mig::either::Either;
+    use crate::mig::lint::{lint, lint_text, Issue, TextIssue};
+
+    #[test]
+    fn test_lint() {
+        let mut desc = fixtures::aperak();
+        assert_eq!(lint(&desc), vec![]);
+
+        // Make the second SG3 discriminate by the same qualifier.
+        let Either::Left(sg3) = &mut desc.message.segments[4] else {
+            panic!("Expected SG3");
+        };
+        sg3.segments = sg3.segments[..1].to_vec();
+        let Either::Right(nad) = &mut sg3.segments[0] else {
+            panic!("Expected NAD");
+        };
+        let Either::Right(qualifier) = &mut nad.elements[0] else {
+            panic!("Expected 3035");
+        };
+        if let Usage::Static { value, comment: _ } = &mut qualifier.usage {
+            value.value = "MS".to_string();
+        }
+        qualifier.length = 0;
+        let Either::Right(dtm) = &mut desc.message.segments[1] else {
+            panic!("Expected DTM");
+        };
+        dtm.counter = "0020".to_string();
+
+        let lints = lint(&desc);
+        let issues: Vec<&Issue> = lints.iter().map(|l| &l.issue).collect();
+        assert_eq!(
+            issues,
+            vec![
+                &Issue::DuplicateCounter { other: "BGM".to_string() },
+                &Issue::ZeroLength { label: "3035".to_string() },
+                &Issue::AmbiguousQualifier { value: "MS".to_string() },
+            ]
+        );
+        assert_eq!(
+            lints[2].to_string(),
+            "0120 SG3: A sibling with the same counter has the qualifier MS \
+             too"
+        );
+    }
+
+    #[test]
+    fn test_lint_text() {
+        let input = "UNA:+.? 'UNB+UNOC:3'\
+            FTX+AAO+++LOC 3225 ung?'ltiger Wert:Preis 10?%'\
+            FTX+AAO+++Kunde O'Brien+Dublin'";
+        let interchange = parser::parse(&mut input.as_bytes()).unwrap();
+        let Either::Left(text) = &interchange.segments[1].elements[3] else {
+            panic!("Expected 4440");
+        };
+        assert_eq!(text.elements[0].value, "LOC 3225 ung'ltiger Wert");
+        assert_eq!(text.elements[1].value, "Preis 10?%");
+
+        let lints = lint_text(&interchange);
+        let issues: Vec<(usize, &TextIssue)> =
+            lints.iter().map(|l| (l.pos, &l.issue)).collect();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    1,
+                    &TextIssue::UnescapedRelease {
+                        value: "Preis 10?%".to_string()
+                    }
+                ),
+                (3, &TextIssue::InvalidTag),
+            ]
+        );
+    }
+}
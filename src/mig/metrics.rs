@@ -0,0 +1,276 @@
+//! Metrics of decoding interchanges, e.g. for a service validating the
+//! interchanges of many partners.
+//!
+//! Every decoded input is reported to a [Metrics] implementation as an
+//! [Observation] by [measure]:
+//!
+//! ```
+//! # use edifact::mig::{self, DecodeOptions};
+//! # use edifact::mig::description::Interchange;
+//! # use edifact::mig::metrics::{self, Counters};
+//! # let desc: Interchange =
+//! #     serde_json::from_str(include_str!("../../APERAK.json")).unwrap();
+//! let input = "UNA:+.? '\
+//!              UNB+UNOC:3+9900467000000:500+9904590000002:500\
+//!              +200307:0705+C3AAAAAAAAHKLC'\
+//!              UNH+1+APERAK:D:07B:UN:2.1d'\
+//!              BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+//!              DTM+137:202003070705:203'\
+//!              UNT+4+1'\
+//!              UNZ+1+C3AAAAAAAAHKLC'";
+//! let counters = Counters::new(["APERAK"]);
+//! let options = DecodeOptions::default();
+//! metrics::measure(&counters, input.as_bytes(), || {
+//!     mig::decode(vec![desc], &mut input.as_bytes(), &options)
+//! })
+//! .unwrap();
+//! assert!(counters.render().contains(
+//!     "edifact_interchanges_total{message_type=\"APERAK\",\
+//!      result=\"valid\"} 1"
+//! ));
+//! ```
+//!
+//! The [Counters] implement them with counters in the OpenMetrics text
+//! format, which Prometheus scrapes. Since the message type is taken from
+//! the untrusted input, only the message types given to the counters,
+//! e.g. those of the loaded descriptions, are used as labels and any
+//! other is counted as `other`. Other telemetry stacks can be
+//! connected by implementing [Metrics].
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::mig::decode::Error;
+use crate::mig::error::InterchangeError;
+use crate::mig::routing;
+
+/// The `Outcome` of decoding an input.
+#[derive(Debug, Clone, Copy)]
+pub enum Outcome<'a> {
+    Valid,
+    /// The input does not conform to its description.
+    Invalid(&'a InterchangeError),
+    /// The input could not be decoded at all, e.g. since it cannot be
+    /// parsed.
+    Unreadable,
+}
+
+impl Outcome<'_> {
+    /// Returns the name of this outcome used as a label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Valid => "valid",
+            Outcome::Invalid(_) => "invalid",
+            Outcome::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// An `Observation` of decoding an input.
+#[derive(Debug, Clone, Copy)]
+pub struct Observation<'a> {
+    /// The message type from the first UNH, if it could be read.
+    pub message_type: Option<&'a str>,
+    /// The size of the input.
+    pub bytes: usize,
+    /// The time decoding took.
+    pub elapsed: Duration,
+    pub outcome: Outcome<'a>,
+}
+
+/// `Metrics` receive an [Observation] of every input decoded with
+/// [measure].
+pub trait Metrics {
+    fn observe(&self, observation: &Observation);
+}
+
+/// Decodes the given input with the given function and reports the
+/// observation to the given metrics.
+pub fn measure<T>(
+    metrics: &dyn Metrics,
+    input: &[u8],
+    decode: impl FnOnce() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let start = Instant::now();
+    let result = decode();
+    let elapsed = start.elapsed();
+    let routing = routing(&mut &input[..]).ok();
+    let outcome = match &result {
+        Ok(_) => Outcome::Valid,
+        Err(Error::Mig(error)) => Outcome::Invalid(error),
+        Err(_) => Outcome::Unreadable,
+    };
+    metrics.observe(&Observation {
+        message_type: routing.as_ref().and_then(|r| r.message_type.as_deref()),
+        bytes: input.len(),
+        elapsed,
+        outcome,
+    });
+    result
+}
+
+/// `Counters` of the observations, which are rendered in the OpenMetrics
+/// text format, see the [module](self).
+#[derive(Debug, Default)]
+pub struct Counters {
+    /// The message types used as labels.
+    message_types: BTreeSet<String>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The number of interchanges by message type and outcome.
+    interchanges: BTreeMap<(String, &'static str), u64>,
+    /// The number of syntax errors by code.
+    errors: BTreeMap<u64, u64>,
+    bytes: u64,
+    /// The number of interchanges and the seconds decoding took by
+    /// message type.
+    seconds: BTreeMap<String, (u64, f64)>,
+}
+
+impl Counters {
+    /// Returns counters labelling the given message types by their name
+    /// and any other message type as `other`.
+    pub fn new<I, S>(message_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Counters {
+            message_types: message_types.into_iter().map(Into::into).collect(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the label of the given message type.
+    fn label(&self, message_type: Option<&str>) -> String {
+        match message_type {
+            Some(known) if self.message_types.contains(known) => {
+                known.to_string()
+            }
+            Some(_) => "other".to_string(),
+            None => "unknown".to_string(),
+        }
+    }
+
+    /// Renders the counters in the OpenMetrics text format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut output = String::new();
+        let out = &mut output;
+        let _ = writeln!(out, "# TYPE edifact_interchanges counter");
+        let _ = writeln!(out, "# HELP edifact_interchanges Decoded inputs.");
+        for ((message_type, outcome), count) in &state.interchanges {
+            let _ = writeln!(
+                out,
+                "edifact_interchanges_total{{message_type=\"{}\",\
+                 result=\"{}\"}} {}",
+                escape(message_type),
+                outcome,
+                count
+            );
+        }
+        let _ = writeln!(out, "# TYPE edifact_syntax_errors counter");
+        let _ = writeln!(
+            out,
+            "# HELP edifact_syntax_errors Syntax errors by CONTRL code."
+        );
+        for (code, count) in &state.errors {
+            let _ = writeln!(
+                out,
+                "edifact_syntax_errors_total{{code=\"{}\"}} {}",
+                code, count
+            );
+        }
+        let _ = writeln!(out, "# TYPE edifact_bytes counter");
+        let _ = writeln!(out, "# HELP edifact_bytes Bytes of decoded inputs.");
+        let _ = writeln!(out, "edifact_bytes_total {}", state.bytes);
+        let _ = writeln!(out, "# TYPE edifact_decode_seconds summary");
+        let _ = writeln!(
+            out,
+            "# HELP edifact_decode_seconds Time taken to decode an input."
+        );
+        for (message_type, (count, sum)) in &state.seconds {
+            let message_type = escape(message_type);
+            let _ = writeln!(
+                out,
+                "edifact_decode_seconds_sum{{message_type=\"{}\"}} {}",
+                message_type, sum
+            );
+            let _ = writeln!(
+                out,
+                "edifact_decode_seconds_count{{message_type=\"{}\"}} {}",
+                message_type, count
+            );
+        }
+        let _ = writeln!(out, "# EOF");
+        output
+    }
+}
+
+impl Metrics for Counters {
+    fn observe(&self, observation: &Observation) {
+        let message_type = self.label(observation.message_type);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (message_type.clone(), observation.outcome.name());
+        *state.interchanges.entry(key).or_insert(0) += 1;
+        if let Outcome::Invalid(error) = observation.outcome {
+            for syntax_error in error.syntax_errors() {
+                *state.errors.entry(syntax_error.get_code()).or_insert(0) += 1;
+            }
+        }
+        state.bytes += observation.bytes as u64;
+        let (count, sum) = state.seconds.entry(message_type).or_default();
+        *count += 1;
+        *sum += observation.elapsed.as_secs_f64();
+    }
+}
+
+/// Escapes a label value, which may contain any character.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::decode::Error;
+    use crate::mig::metrics::{measure, Counters};
+
+    #[test]
+    fn test_counters() {
+        let counters = Counters::new(["UTIL\"MD"]);
+        let input = "UNA:+.? 'UNB+UNOC:3+1:500+2:500+200307:0705+R'\
+                     UNH+1+UTIL\"MD:D:11A:UN:S2.1'";
+        let result: Result<(), Error> =
+            measure(&counters, input.as_bytes(), || Err(Error::NoDescription));
+        assert!(result.is_err());
+        let _ = measure(&counters, b"garbage", || Ok(()));
+        for message_type in ["X1", "X2"] {
+            let input = format!("UNA:+.? 'UNH+1+{}:D:11A:UN:1'", message_type);
+            let _ = measure(&counters, input.as_bytes(), || Ok(()));
+        }
+
+        let output = counters.render();
+        assert!(output.contains(
+            "edifact_interchanges_total{message_type=\"UTIL\\\"MD\",\
+             result=\"unreadable\"} 1\n"
+        ));
+        assert!(output.contains(
+            "edifact_interchanges_total{message_type=\"unknown\",\
+             result=\"valid\"} 1\n"
+        ));
+        assert!(output.contains(
+            "edifact_interchanges_total{message_type=\"other\",\
+             result=\"valid\"} 2\n"
+        ));
+        assert!(!output.contains("X1"));
+        assert!(output.contains(&format!(
+            "edifact_bytes_total {}\n",
+            input.len() + "garbage".len() + 2 * 29
+        )));
+        assert!(output.ends_with("# EOF\n"));
+    }
+}
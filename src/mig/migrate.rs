@@ -0,0 +1,281 @@
+//! Migration of interchanges from one version of a MIG to another, e.g.
+//! for messages still following the previous version during a format
+//! change.
+//!
+//! An interchange is decoded with the previous version first, so it is
+//! known to conform to it. Then the rules of a [Mapping] are applied to
+//! its segments, the association code in UNH 0057 is replaced with the
+//! one of the new version and the result is decoded with the new version.
+//!
+//! A mapping is usually given in JSON:
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     {"type": "RenameQualifier", "tag": "RFF", "from": "Z13", "to": "Z14"},
+//!     {
+//!       "type": "MoveElement", "tag": "FTX", "qualifier": "Z02",
+//!       "from": [3, 0], "to": [3, 1]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Elements are addressed by their position in the segment and within
+//! their composite, starting at 0 and not counting the tag.
+use std::fmt;
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mig::decode::options::{DecodeOptions, Encoding};
+use crate::mig::decode::parser::value::{Segment, UNA};
+use crate::mig::decode::{self, charset, parser, value};
+use crate::mig::either::Either;
+use crate::mig::CompiledMig;
+
+/// A `Mapping` from one version of a MIG to another.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Mapping {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+/// A `Rule` changing the segments with the given tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Rule {
+    /// Renames the qualifier in the first element, e.g. `RFF+Z13` to
+    /// `RFF+Z14`.
+    RenameQualifier { tag: String, from: String, to: String },
+    /// Moves a value to another position, if the segment has the given
+    /// qualifier or any, if none is given.
+    MoveElement {
+        tag: String,
+        #[serde(default)]
+        qualifier: Option<String>,
+        from: [usize; 2],
+        to: [usize; 2],
+    },
+}
+
+/// The error returned, if an interchange could not be migrated.
+#[derive(Debug)]
+pub enum MigrateError {
+    /// The interchange does not conform to the previous version.
+    From(decode::Error),
+    /// The migrated interchange does not conform to the new version.
+    To(decode::Error),
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::From(error) => {
+                write!(f, "The interchange could not be decoded: {}", error)
+            }
+            MigrateError::To(error) => write!(
+                f,
+                "The migrated interchange could not be decoded: {}",
+                error
+            ),
+        }
+    }
+}
+
+/// Migrates the given input from one version of a MIG to another with
+/// the given mapping, see the [module](self).
+pub fn migrate<R: Read>(
+    from: &CompiledMig,
+    to: &CompiledMig,
+    mapping: &Mapping,
+    input: &mut R,
+    options: &DecodeOptions,
+) -> Result<value::Interchange, MigrateError> {
+    let contents =
+        charset::read(input, options).map_err(MigrateError::From)?;
+    // The contents have been decoded already.
+    let options =
+        DecodeOptions { encoding: Encoding::Utf8, ..options.clone() };
+    decode::decode(from, &mut contents.as_bytes(), &options)
+        .map_err(MigrateError::From)?;
+
+    let interchange = parser::parse_text(&contents, options.skip_preamble)
+        .map_err(MigrateError::From)?;
+    let association_code =
+        to.description().message_identifier().association_code;
+    let mut output = String::new();
+    for segment in &interchange.segments {
+        let mut elements = elements(segment);
+        if segment.tag.value == "UNH" {
+            if let Some(code) = &association_code {
+                set(&mut elements, [1, 4], code.clone());
+            }
+        }
+        for rule in &mapping.rules {
+            apply(rule, &segment.tag.value, &mut elements);
+        }
+        output.push_str(&render(&segment.tag.value, &elements));
+    }
+    decode::decode(to, &mut output.as_bytes(), &options)
+        .map_err(MigrateError::To)
+}
+
+/// Returns the values of the elements of the given segment, where every
+/// element is a list of its components.
+fn elements(segment: &Segment) -> Vec<Vec<String>> {
+    segment
+        .elements
+        .iter()
+        .map(|element| match element {
+            Either::Left(composite) => composite
+                .elements
+                .iter()
+                .map(|element| element.value.clone())
+                .collect(),
+            Either::Right(element) => vec![element.value.clone()],
+        })
+        .collect()
+}
+
+fn apply(rule: &Rule, tag: &str, elements: &mut Vec<Vec<String>>) {
+    let qualifier = get(elements, [0, 0]).to_string();
+    match rule {
+        Rule::RenameQualifier { tag: expected, from, to } => {
+            if tag == expected && qualifier == *from {
+                set(elements, [0, 0], to.clone());
+            }
+        }
+        Rule::MoveElement { tag: expected, qualifier: q, from, to } => {
+            if tag == expected && q.iter().all(|q| *q == qualifier) {
+                let value = std::mem::take(get_mut(elements, *from));
+                set(elements, *to, value);
+            }
+        }
+    }
+}
+
+fn get(elements: &[Vec<String>], [element, component]: [usize; 2]) -> &str {
+    elements
+        .get(element)
+        .and_then(|components| components.get(component))
+        .map_or("", |value| value.as_str())
+}
+
+fn get_mut(
+    elements: &mut Vec<Vec<String>>,
+    [element, component]: [usize; 2],
+) -> &mut String {
+    if elements.len() <= element {
+        elements.resize(element + 1, vec![]);
+    }
+    let components = &mut elements[element];
+    if components.len() <= component {
+        components.resize(component + 1, String::new());
+    }
+    &mut components[component]
+}
+
+fn set(elements: &mut Vec<Vec<String>>, at: [usize; 2], value: String) {
+    *get_mut(elements, at) = value;
+}
+
+/// Renders a segment with the default separators, omitting empty
+/// elements and components at the end. A segment keeps at least one
+/// element, even if it is empty, so it can still be parsed.
+pub(crate) fn render(tag: &str, elements: &[Vec<String>]) -> String {
+    let una = UNA::default();
+    let elements: Vec<String> = elements
+        .iter()
+        .map(|components| {
+            let len = components
+                .iter()
+                .rposition(|c| !c.is_empty())
+                .map_or(0, |i| i + 1);
+            let components: Vec<String> = components[..len]
+                .iter()
+                .map(|c| una.escape_value(c))
+                .collect();
+            components.join(":")
+        })
+        .collect();
+    let len = elements
+        .iter()
+        .rposition(|e| !e.is_empty())
+        .map_or(elements.len().min(1), |i| i + 1);
+    let mut result = tag.to_string();
+    for element in &elements[..len] {
+        result.push('+');
+        result.push_str(element);
+    }
+    result.push('\'');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::description::Interchange;
+    use crate::mig::migrate::{migrate, Mapping, MigrateError, Rule};
+    use crate::mig::{encode, CompiledMig, DecodeOptions};
+
+    #[test]
+    fn test_migrate() {
+        let from = CompiledMig::from(fixtures::aperak());
+        // The next version only allows numeric error codes.
+        let json =
+            fixtures::APERAK_JSON.replace("\"2.1d\"", "\"2.1e\"").replace(
+                "\"Anwendungsfehler, Code\",\"st\":\"M\",\"format\":\"an..\"",
+                "\"Anwendungsfehler, Code\",\"st\":\"M\",\"format\":\"n..\"",
+            );
+        let desc: Interchange = serde_json::from_str(&json).unwrap();
+        let to = CompiledMig::from(desc);
+        let options = DecodeOptions::default();
+
+        let mapping = Mapping::default();
+        let result = migrate(
+            &from,
+            &to,
+            &mapping,
+            &mut fixtures::APERAK_FULL.as_bytes(),
+            &options,
+        );
+        assert!(matches!(result, Err(MigrateError::To(_))));
+
+        let mapping: Mapping = serde_json::from_str(
+            r#"{"rules": [
+                {"type": "RenameQualifier", "tag": "ERC",
+                 "from": "Z29", "to": "29"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mapping.rules[0],
+            Rule::RenameQualifier {
+                tag: "ERC".to_string(),
+                from: "Z29".to_string(),
+                to: "29".to_string(),
+            }
+        );
+        let result = migrate(
+            &from,
+            &to,
+            &mapping,
+            &mut fixtures::APERAK_FULL.as_bytes(),
+            &options,
+        )
+        .unwrap();
+        let output = encode(&result);
+        assert!(output.contains("UNH+1+APERAK:D:07B:UN:2.1e'"));
+        assert!(output.contains("ERC+29'"));
+
+        let result = migrate(
+            &to,
+            &to,
+            &mapping,
+            &mut fixtures::APERAK_FULL.as_bytes(),
+            &options,
+        );
+        assert!(matches!(result, Err(MigrateError::From(_))));
+    }
+}
@@ -10,17 +10,129 @@
 //! will contact the old energy supplier, requesting data, such as the expiration
 //! date of the customers contract with the old energy supplier.
 
+pub mod bundled;
+pub mod compact;
+pub mod contrl;
+pub mod conversation;
+pub mod coverage;
 pub mod description;
+pub mod diff;
+pub mod directory;
 pub mod either;
 pub mod error;
-mod decode;
+pub mod extract;
+pub mod framing;
+pub mod lint;
+pub mod metrics;
+pub mod migrate;
+pub mod signature;
+pub mod report;
+pub mod security;
+pub mod decode;
 pub mod encode;
+#[cfg(feature = "spec")]
 pub mod spec;
+pub mod stats;
+pub mod table;
+#[cfg(feature = "template")]
+pub mod template;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transform;
+pub mod validate;
 
-use std::io::Read;
+use std::io::{BufRead, Read};
 use crate::mig::decode::value;
 
+pub use crate::mig::decode::compare::Comparer;
+pub use crate::mig::decode::compiled::CompiledMig;
+pub use crate::mig::decode::events::{DecodeEvents, Event};
+pub use crate::mig::decode::hook::SegmentHook;
+pub use crate::mig::decode::options::{
+    DecodeOptions, Encoding, ParseMode, Strictness, TestIndicator,
+    UnknownSegments,
+};
+pub use crate::mig::decode::path::Path;
+pub use crate::mig::decode::routing::Routing;
+pub use crate::mig::encode::options::{EncodeOptions, UnaPolicy};
+pub use crate::mig::validate::summary::ValidationSummary;
 
-pub fn decode<R: Read>(known: Vec<description::Interchange>, input: &mut R) -> Result<value::Interchange, decode::Error> {
-    decode::decode(known, input)
+#[cfg(feature = "tokio")]
+pub use crate::mig::decode::asynchronous::{decode_async, SegmentReader};
+
+/// Decodes the given input with one of the `known` descriptions, which
+/// are either a `Vec` of descriptions or [CompiledMig]s.
+///
+/// The [DecodeOptions] define, how tolerant the input is checked against
+/// the description.
+pub fn decode<'a, R: Read>(
+    known: impl Into<decode::Known<'a>>,
+    input: &mut R,
+    options: &DecodeOptions,
+) -> Result<value::Interchange, decode::Error> {
+    decode::decode(known, input, options)
+}
+
+/// Decodes the given input like [decode] and calls the hook with every
+/// matched segment, which may stop decoding early, see [SegmentHook].
+pub fn decode_with_hook<'a, R: Read>(
+    known: impl Into<decode::Known<'a>>,
+    input: &mut R,
+    options: &DecodeOptions,
+    hook: &mut dyn SegmentHook,
+) -> Result<value::Interchange, decode::Error> {
+    decode::decode_with_hook(known, input, options, hook)
+}
+
+/// Decodes the given input incrementally with the given description and
+/// returns the events, see [DecodeEvents].
+pub fn decode_events<'a, R: BufRead>(
+    mig: &'a CompiledMig,
+    input: R,
+    options: &'a DecodeOptions,
+) -> DecodeEvents<'a, R> {
+    DecodeEvents::new(mig, input, options)
+}
+
+/// Parses the given input into its segments, without decoding it with a
+/// description, e.g. to show an interchange, which cannot be decoded.
+///
+/// ```
+/// let input = "UNB+UNOC:3+9900467000000:500+9904590000002:500\
+///              +200307:0705+REF1'UNH+1+APERAK:D:07B:UN:2.1d'\
+///              FTX+Z02+++Wert?+X'UNT+3+1'UNZ+1+REF1'";
+/// let interchange = edifact::mig::parse(&mut input.as_bytes()).unwrap();
+/// let tags: Vec<&str> = interchange
+///     .segments
+///     .iter()
+///     .map(|segment| segment.tag.value.as_str())
+///     .collect();
+/// assert_eq!(tags, vec!["UNB", "UNH", "FTX", "UNT", "UNZ"]);
+/// assert_eq!(interchange.routing().message_type.unwrap(), "APERAK");
+/// ```
+pub fn parse<R: Read>(
+    input: &mut R,
+) -> Result<decode::parser::value::Interchange, decode::Error> {
+    decode::parser::parse(input)
+}
+
+/// Returns the routing metadata of the given input, e.g. the sender,
+/// receiver and message type, without decoding it with a description.
+/// Only the segments up to the first UNH are read.
+pub fn routing<R: Read>(input: &mut R) -> Result<Routing, decode::Error> {
+    decode::routing(input)
+}
+
+/// Encodes the given decoded interchange into EDIFACT.
+pub fn encode(interchange: &value::Interchange) -> String {
+    encode::encode(interchange)
+}
+
+/// Encodes the given decoded interchange into EDIFACT with the layout
+/// given by the options, e.g. a newline after every segment.
+pub fn encode_with(
+    interchange: &value::Interchange,
+    options: &EncodeOptions,
+) -> String {
+    encode::encode_with(interchange, options)
 }
@@ -0,0 +1,447 @@
+//! Interpretation of received CONTRL and APERAK messages, which report
+//! the errors the receiver found in one of our interchanges.
+//!
+//! A CONTRL points to the erroneous parts by position: UCI refers to the
+//! interchange, UCM to one of its messages, UCS to a segment of that
+//! message and UCD to a data element of that segment. An APERAK refers
+//! to the message by its document number and describes the error with a
+//! code in ERC and free texts in FTX. Codes are resolved with the
+//! semantics given by the description, the received message has been
+//! decoded with.
+//!
+//! With the originally sent interchange, positions are resolved to their
+//! segments and data elements, see [Report::resolve].
+pub mod html;
+
+use std::fmt;
+
+use crate::mig::decode::value::{DataElement, Interchange, Segment};
+use crate::mig::description::Usage;
+use crate::mig::either::Either;
+use crate::mig::error::SyntaxError;
+
+/// The kind of message a [Report] has been taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Contrl,
+    Aperak,
+}
+
+/// A `Report` is the interpretation of a received CONTRL or APERAK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub kind: Kind,
+    /// The reference of the interchange the report refers to from UCI
+    /// 0020. An APERAK does not refer to an interchange.
+    pub interchange: Option<String>,
+    /// Whether the interchange has been accepted according to UCI 0083.
+    /// An APERAK always reports errors, so it is never accepted.
+    pub accepted: bool,
+    /// The references of the rejected messages, which are the message
+    /// references from UCM 0062 or the document numbers from RFF+ACE.
+    pub rejected: Vec<String>,
+    pub findings: Vec<Finding>,
+}
+
+/// A `Finding` is a single error reported for an interchange, one of
+/// its messages or a part of it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Finding {
+    /// The reference of the message, if the error is not reported for
+    /// the whole interchange.
+    pub message: Option<String>,
+    /// The position of the segment in the message starting with 1 for
+    /// UNH, see UCS 0096.
+    pub position: Option<usize>,
+    /// The tag of the segment, either reported for a service segment in
+    /// 0013 or resolved from the sent interchange.
+    pub segment: Option<String>,
+    /// The position of the element in the segment starting with 1 for
+    /// the tag, see 0098.
+    pub element: Option<usize>,
+    /// The position of the component in a composite starting with 1,
+    /// see 0104.
+    pub component: Option<usize>,
+    /// The label of the data element or composite resolved from the sent
+    /// interchange, e.g. `3055`.
+    pub label: Option<String>,
+    /// The error code from 0085 or ERC 9321.
+    pub code: String,
+    /// The semantics of the error code, if known.
+    pub reason: Option<String>,
+    /// Further information, e.g. the free texts of an APERAK.
+    pub details: Vec<String>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(interchange) = &self.interchange {
+            let action = if self.accepted { "accepted" } else { "rejected" };
+            writeln!(f, "Interchange {} {}", interchange, action)?;
+        }
+        for message in &self.rejected {
+            writeln!(f, "Message {} rejected", message)?;
+        }
+        for finding in &self.findings {
+            writeln!(f, "{}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "Message {}", message)?,
+            None => write!(f, "Interchange")?,
+        }
+        match (self.position, &self.segment) {
+            (Some(position), Some(tag)) => {
+                write!(f, ", segment {} {}", position, tag)?
+            }
+            (Some(position), None) => write!(f, ", segment {}", position)?,
+            (None, Some(tag)) => write!(f, ", segment {}", tag)?,
+            (None, None) => {}
+        }
+        if let Some(element) = self.element {
+            write!(f, ", element {}", element)?;
+            if let Some(component) = self.component {
+                write!(f, ":{}", component)?;
+            }
+            if let Some(label) = &self.label {
+                write!(f, " {}", label)?;
+            }
+        }
+        write!(f, ": {}", self.code)?;
+        if let Some(reason) = &self.reason {
+            write!(f, " {}", reason)?;
+        }
+        for detail in &self.details {
+            write!(f, "\n  {}", detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the report of the given interchange, if it contains a CONTRL
+/// or APERAK.
+pub fn report(interchange: &Interchange) -> Option<Report> {
+    let segments = interchange.all_segments();
+    let unh = segments.iter().find(|segment| segment.tag == "UNH")?;
+    match unh.text_of("0065")? {
+        "CONTRL" => Some(contrl(&segments)),
+        "APERAK" => Some(aperak(&segments)),
+        _ => None,
+    }
+}
+
+fn contrl(segments: &[&Segment]) -> Report {
+    let mut report = Report {
+        kind: Kind::Contrl,
+        interchange: None,
+        accepted: false,
+        rejected: vec![],
+        findings: vec![],
+    };
+    let mut message = None;
+    let mut position = None;
+    for segment in segments {
+        match segment.tag.as_str() {
+            "UCI" => {
+                report.interchange = segment.text_of("0020").map(From::from);
+                report.accepted = segment.text_of("0083") == Some("7");
+                report.findings.extend(service_finding(segment, &None));
+            }
+            "UCM" => {
+                message = segment.text_of("0062").map(String::from);
+                report.rejected.extend(message.clone());
+                report.findings.extend(service_finding(segment, &message));
+            }
+            "UCS" => {
+                position = segment.text_of("0096").and_then(number);
+                if let Some((code, reason)) = code(segment, "0085") {
+                    report.findings.push(Finding {
+                        message: message.clone(),
+                        position,
+                        code,
+                        reason,
+                        ..Finding::default()
+                    });
+                }
+            }
+            "UCD" => {
+                if let Some((code, reason)) = code(segment, "0085") {
+                    report.findings.push(Finding {
+                        message: message.clone(),
+                        position,
+                        element: segment.text_of("0098").and_then(number),
+                        component: segment.text_of("0104").and_then(number),
+                        code,
+                        reason,
+                        ..Finding::default()
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+/// Returns the finding of a UCI or UCM, which reports an error of a
+/// service segment, e.g. a wrong counter in UNT.
+fn service_finding(
+    segment: &Segment,
+    message: &Option<String>,
+) -> Option<Finding> {
+    let (code, reason) = code(segment, "0085")?;
+    Some(Finding {
+        message: message.clone(),
+        segment: segment.text_of("0013").map(String::from),
+        element: segment.text_of("0098").and_then(number),
+        component: segment.text_of("0104").and_then(number),
+        code,
+        reason,
+        ..Finding::default()
+    })
+}
+
+fn aperak(segments: &[&Segment]) -> Report {
+    let mut report = Report {
+        kind: Kind::Aperak,
+        interchange: None,
+        accepted: false,
+        rejected: vec![],
+        findings: vec![],
+    };
+    let mut message = None;
+    for segment in segments {
+        match segment.tag.as_str() {
+            "RFF"
+                if report.findings.is_empty()
+                    && segment.text_of("1153") == Some("ACE") =>
+            {
+                message = segment.text_of("1154").map(String::from);
+                report.rejected.extend(message.clone());
+            }
+            "ERC" => {
+                if let Some((code, reason)) = code(segment, "9321") {
+                    report.findings.push(Finding {
+                        message: message.clone(),
+                        code,
+                        reason,
+                        ..Finding::default()
+                    });
+                }
+            }
+            "FTX" | "RFF" => {
+                let Some(finding) = report.findings.last_mut() else {
+                    continue;
+                };
+                let detail = if segment.tag == "FTX" {
+                    segment.texts_of("4440").concat()
+                } else {
+                    let (qualifier, reference) = segment
+                        .data_elements()
+                        .into_iter()
+                        .fold((None, None), |(q, r), element| {
+                            match element.description().label.as_str() {
+                                "1153" => (Some(element), r),
+                                "1154" => (q, element.text()),
+                                _ => (q, r),
+                            }
+                        });
+                    let qualifier = qualifier
+                        .and_then(|q| semantics(q).or(q.text()))
+                        .unwrap_or("RFF");
+                    format!("{}: {}", qualifier, reference.unwrap_or(""))
+                };
+                if !detail.is_empty() {
+                    finding.details.push(detail);
+                }
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+impl Report {
+    /// Resolves the positions of the findings to the tags of the
+    /// segments and the labels of the data elements in the originally
+    /// sent interchange. Returns false, if the given interchange is not
+    /// the one this report refers to.
+    pub fn resolve(&mut self, sent: &Interchange) -> bool {
+        let segments = sent.all_segments();
+        let reference = segments
+            .iter()
+            .find(|segment| segment.tag == "UNB")
+            .and_then(|unb| unb.text_of("0020"));
+        if self.interchange.is_some()
+            && self.interchange.as_deref() != reference
+        {
+            return false;
+        }
+
+        for finding in &mut self.findings {
+            let (Some(message), Some(position)) =
+                (&finding.message, finding.position)
+            else {
+                continue;
+            };
+            let unh = segments.iter().find(|segment| {
+                segment.tag == "UNH"
+                    && segment.text_of("0062") == Some(message.as_str())
+            });
+            let Some(segment) = unh.and_then(|unh| {
+                let index = unh.index + position.checked_sub(1)?;
+                segments.iter().find(|segment| segment.index == index)
+            }) else {
+                continue;
+            };
+            finding.segment = Some(segment.tag.clone());
+            finding.label = finding.element.and_then(|element| {
+                label(segment, element, finding.component)
+            });
+        }
+        true
+    }
+}
+
+/// Returns the label of the data element or composite at the given
+/// positions, which count the tag and the first component as 1.
+fn label(
+    segment: &Segment,
+    element: usize,
+    component: Option<usize>,
+) -> Option<String> {
+    let element = element.checked_sub(2)?;
+    let value = segment.elements.iter().find(|e| match e {
+        Either::Left(composite) => composite.index == element,
+        Either::Right(data_element) => data_element.index == element,
+    })?;
+    match (value, component) {
+        (Either::Left(composite), Some(component)) => composite
+            .elements
+            .iter()
+            .find(|e| Some(e.index) == component.checked_sub(1))
+            .map(|e| e.description().label.clone()),
+        (Either::Left(composite), None) => Some(composite.label.clone()),
+        (Either::Right(data_element), _) => {
+            Some(data_element.description().label.clone())
+        }
+    }
+}
+
+/// Returns the code of the first data element with the given label
+/// together with its semantics, which is taken from the description or
+/// the known syntax errors.
+fn code(segment: &Segment, label: &str) -> Option<(String, Option<String>)> {
+    let data_element = segment
+        .data_elements()
+        .into_iter()
+        .find(|element| element.description().label == label)?;
+    let code = data_element.text()?;
+    let reason = semantics(data_element).map(String::from).or_else(|| {
+        let error = SyntaxError::from_code(number(code)? as u64)?;
+        Some(error.get_name().to_string())
+    });
+    Some((code.to_string(), reason))
+}
+
+/// Returns the semantics of the code in the given data element.
+fn semantics(data_element: &DataElement) -> Option<&str> {
+    let text = data_element.text()?;
+    let choice = match &data_element.description().usage {
+        Usage::OneOf { choices, comment: _ } => {
+            choices.iter().find(|choice| choice.value == text)?
+        }
+        Usage::Static { value, comment: _ } if value.value == text => value,
+        _ => return None,
+    };
+    choice.semantics.as_deref()
+}
+
+fn number(text: &str) -> Option<usize> {
+    text.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::description::Interchange;
+    use crate::mig::report::{report, Kind};
+    use crate::mig::DecodeOptions;
+
+    /// Returns the APERAK rejected by [CONTRL].
+    fn aperak() -> String {
+        fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'\
+             RFF+ACE:O1583553607732'\
+             NAD+MS+9900467000000::293'\
+             NAD+MR+9904590000002::293'\
+             ERC+Z29'\
+             FTX+AAO+++LOC Datenelement 3225 ungültiger Wert'\
+             RFF+ACW:V1583553607732'",
+        )
+    }
+
+    const CONTRL: &str = "UNA:+.? '\
+        UNB+UNOC:3+9904590000002:500+9900467000000:500\
+        +200307:0710+C3BBBBBBBBHKLC'\
+        UNH+1+CONTRL:D:3:UN:2.0a'\
+        UCI+C3AAAAAAAAHKLC+9900467000000:500+9904590000002:500+4'\
+        UCM+1+APERAK:D:07B:UN:2.1d+4'\
+        UCS+5'\
+        UCD+12+3:3'\
+        UNT+6+1'\
+        UNZ+1+C3BBBBBBBBHKLC'";
+
+    fn decode(
+        desc: Interchange,
+        input: &str,
+    ) -> mig::decode::value::Interchange {
+        let options = DecodeOptions::lenient();
+        mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_contrl() {
+        let contrl = decode(fixtures::contrl(), CONTRL);
+        let mut report = report(&contrl).unwrap();
+        assert_eq!(report.kind, Kind::Contrl);
+        assert_eq!(report.interchange.as_deref(), Some("C3AAAAAAAAHKLC"));
+        assert!(!report.accepted);
+        assert_eq!(report.rejected, vec!["1"]);
+        assert_eq!(
+            report.findings[0].to_string(),
+            "Message 1, segment 5, element 3:3: 12 Ungültiger Wert"
+        );
+
+        let aperak = decode(fixtures::aperak(), &aperak());
+        assert!(report.resolve(&aperak));
+        assert_eq!(
+            report.findings[0].to_string(),
+            "Message 1, segment 5 NAD, element 3:3 3055: 12 Ungültiger Wert"
+        );
+        assert!(!report.resolve(&contrl));
+    }
+
+    #[test]
+    fn test_aperak() {
+        let aperak = decode(fixtures::aperak(), &aperak());
+        let report = report(&aperak).unwrap();
+        assert_eq!(report.kind, Kind::Aperak);
+        assert_eq!(report.rejected, vec!["O1583553607732"]);
+        assert_eq!(report.findings.len(), 1);
+        let finding = &report.findings[0];
+        assert_eq!(finding.code, "Z29");
+        assert_eq!(
+            finding.details[0],
+            "LOC Datenelement 3225 ungültiger Wert"
+        );
+        assert_eq!(finding.details.len(), 2);
+    }
+}
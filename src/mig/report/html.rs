@@ -0,0 +1,406 @@
+//! Reports of validation results as standalone HTML files, e.g. to send
+//! them to a market partner or attach them to a ticket.
+//!
+//! A [Document] shows every interchange added as its original text with
+//! one segment per line. Erroneous segments and data elements are
+//! highlighted and their errors shown as tooltips. If the interchange
+//! has been decoded, the tooltips of all segments and data elements show
+//! their names and comments from the MIG as well.
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::mig::decode::parser::{self, value as parsed};
+use crate::mig::decode::value::{Interchange, Segment};
+use crate::mig::description::{self as desc, Usage};
+use crate::mig::either::Either;
+use crate::mig::error::{
+    CompositeError, DataElementError, InterchangeError, SyntaxError,
+};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+pre { background: #f6f8fa; padding: 1em; overflow-x: auto; }
+.segment { display: block; }
+.segment.error { background: #fde7e9; }
+.element[title] { border-bottom: 1px dotted #888; }
+.element.error { background: #f8b4bb; border-bottom: 2px solid #c00; }
+.element.empty { padding: 0 0.3em; }
+.valid { color: #080; }
+.invalid { color: #c00; }";
+
+/// A `Document` is an HTML report of the validation of interchanges, see
+/// the [module](self).
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    sections: Vec<String>,
+}
+
+/// The `Note` of a segment or data element shown as its tooltip.
+#[derive(Debug, Default)]
+struct Note {
+    lines: Vec<String>,
+    error: bool,
+}
+
+impl Note {
+    fn describe(&mut self, line: String) {
+        self.lines.insert(0, line);
+    }
+
+    fn fail(&mut self, error: &SyntaxError) {
+        self.error = true;
+        self.lines.push(format!("{}: {}", error.get_code(), error.get_name()));
+    }
+}
+
+/// The notes of an interchange by the position of the segment and the
+/// offset of the data element in the input.
+#[derive(Default)]
+struct Notes {
+    segments: HashMap<usize, Note>,
+    elements: HashMap<usize, Note>,
+}
+
+impl Document {
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    /// Adds the given input with the title, e.g. its file name. The
+    /// interchange is the input decoded and the error the one found
+    /// while validating it, if any. The interchange may have been decoded
+    /// leniently to show the names of the MIG, even if it is invalid.
+    pub fn add(
+        &mut self,
+        title: &str,
+        input: &str,
+        interchange: Option<&Interchange>,
+        error: Option<&InterchangeError>,
+    ) {
+        let mut html = format!("<section>\n<h2>{}</h2>\n", escape(title));
+        match error {
+            Some(error) => html.push_str(&format!(
+                "<p class=\"invalid\">{} errors</p>\n",
+                error.syntax_errors().len()
+            )),
+            None => html.push_str("<p class=\"valid\">Valid</p>\n"),
+        }
+        html.push_str("<pre>");
+        match parser::parse(&mut input.as_bytes()) {
+            Ok(parsed) => {
+                // The UNA is shown as is.
+                let head = match parsed.segments.first() {
+                    Some(first) => &input[..first.start.offset],
+                    None => input,
+                };
+                if !head.trim().is_empty() {
+                    html.push_str(&escape(head.trim_end()));
+                    html.push('\n');
+                }
+                let mut notes = Notes::default();
+                if let Some(interchange) = interchange {
+                    notes.describe(&parsed.segments, interchange);
+                }
+                if let Some(error) = error {
+                    notes.fail(&parsed.segments, error);
+                }
+                for (i, segment) in parsed.segments.iter().enumerate() {
+                    render_segment(&mut html, input, i, segment, &notes);
+                }
+            }
+            // Inputs, which cannot be parsed, have no positions to
+            // highlight.
+            Err(error) => {
+                html.push_str(&escape(input));
+                html.push_str(&format!(
+                    "</pre>\n<p class=\"invalid\">{}</p>\n<pre>",
+                    escape(&error.to_string())
+                ));
+            }
+        }
+        html.push_str("</pre>\n</section>\n");
+        self.sections.push(html);
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "<!DOCTYPE html>")?;
+        writeln!(f, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(f, "<title>Validation report</title>")?;
+        writeln!(f, "<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+        writeln!(f, "<h1>Validation report</h1>")?;
+        for section in &self.sections {
+            write!(f, "{}", section)?;
+        }
+        writeln!(f, "</body>\n</html>")
+    }
+}
+
+impl Notes {
+    /// Notes the names and comments of the MIG for the segments and data
+    /// elements of the decoded interchange.
+    fn describe(
+        &mut self,
+        segments: &[parsed::Segment],
+        interchange: &Interchange,
+    ) {
+        for segment in interchange.all_segments() {
+            let Some(pos) = segments
+                .iter()
+                .position(|s| s.start.offset == segment.span.start)
+            else {
+                continue;
+            };
+            let mut line = format!(
+                "{} {} {}",
+                segment.counter, segment.tag, segment.name
+            );
+            if let Some(comment) = &segment.comment {
+                line.push_str(&format!("\n{}", comment));
+            }
+            self.segments.entry(pos).or_default().describe(line);
+
+            for data_element in data_elements(segment) {
+                let description = &data_element.description;
+                let note =
+                    self.elements.entry(data_element.span.start).or_default();
+                note.describe(describe(description, data_element.text()));
+            }
+        }
+    }
+
+    /// Notes the errors of the interchange at their segments and data
+    /// elements.
+    fn fail(
+        &mut self,
+        segments: &[parsed::Segment],
+        error: &InterchangeError,
+    ) {
+        let service_errors = error.service_segment_error.iter().chain(
+            error
+                .message_errors
+                .iter()
+                .flat_map(|error| &error.service_segment_error),
+        );
+        for error in service_errors {
+            if let Some(pos) =
+                segments.iter().position(|s| s.tag.value == error.tag)
+            {
+                self.fail_element(segments, pos, &error.error);
+            }
+        }
+
+        let segment_errors = error
+            .message_errors
+            .iter()
+            .flat_map(|error| &error.segment_errors);
+        for error in segment_errors {
+            // The position does not count unknown segments, which have
+            // been skipped, so the raw segment is preferred.
+            let raw = |pos: &usize| {
+                let segment = segments.get(*pos)?;
+                error
+                    .raw
+                    .as_ref()
+                    .is_none_or(|raw| *raw == segment.raw)
+                    .then_some(*pos)
+            };
+            let pos = raw(&error.pos).or_else(|| {
+                let raw = error.raw.as_ref()?;
+                segments.iter().position(|s| s.raw == *raw)
+            });
+            let Some(pos) = pos else {
+                continue;
+            };
+            if let Some(syntax_error) = &error.syntax_error {
+                self.segments.entry(pos).or_default().fail(syntax_error);
+            }
+            for error in &error.errors {
+                self.fail_element(segments, pos, error);
+            }
+        }
+    }
+
+    fn fail_element(
+        &mut self,
+        segments: &[parsed::Segment],
+        pos: usize,
+        error: &Either<CompositeError, DataElementError>,
+    ) {
+        let element = segments[pos].elements.get(match error {
+            Either::Left(error) => error.pos,
+            Either::Right(error) => error.pos,
+        });
+        let components: Vec<&parsed::DataElement> = match element {
+            Some(Either::Left(composite)) => {
+                composite.elements.iter().collect()
+            }
+            Some(Either::Right(data_element)) => vec![data_element],
+            None => vec![],
+        };
+        let mut errors: Vec<(Option<usize>, &SyntaxError)> = vec![];
+        match error {
+            Either::Left(error) => {
+                errors.extend(error.syntax_error.iter().map(|e| (None, e)));
+                for error in &error.errors {
+                    let at = Some(error.pos);
+                    errors.extend(error.syntax_errors.iter().map(|e| (at, e)));
+                }
+            }
+            Either::Right(error) => {
+                errors.extend(error.syntax_errors.iter().map(|e| (None, e)));
+            }
+        }
+
+        for (at, syntax_error) in errors {
+            let targets: Vec<&parsed::DataElement> = match at {
+                Some(at) => components.get(at).copied().into_iter().collect(),
+                None => components.clone(),
+            };
+            // Missing elements are noted at their segment.
+            if targets.is_empty() {
+                self.segments.entry(pos).or_default().fail(syntax_error);
+            }
+            for target in targets {
+                let note =
+                    self.elements.entry(target.start.offset).or_default();
+                note.fail(syntax_error);
+            }
+        }
+    }
+}
+
+/// Returns all data elements of the segment including the ones nested in
+/// composites.
+fn data_elements(
+    segment: &Segment,
+) -> impl Iterator<Item = &crate::mig::decode::value::DataElement> {
+    segment.elements.iter().flat_map(|element| match element {
+        Either::Left(composite) => composite.elements.iter().collect(),
+        Either::Right(data_element) => vec![data_element],
+    })
+}
+
+/// Returns the label and name of the data element followed by the
+/// semantics of its value and the comment of the MIG.
+fn describe(description: &desc::DataElement, text: Option<&str>) -> String {
+    let mut line = format!("{} {}", description.label, description.name);
+    let (semantics, comment) = match &description.usage {
+        Usage::OneOf { choices, comment } => {
+            let choice =
+                choices.iter().find(|c| Some(c.value.as_str()) == text);
+            (choice.and_then(|c| c.semantics.as_deref()), comment)
+        }
+        Usage::Static { value, comment } => {
+            (value.semantics.as_deref(), comment)
+        }
+        Usage::Text { comment }
+        | Usage::Integer { comment }
+        | Usage::Decimal { comment } => (None, comment),
+    };
+    if let Some(semantics) = semantics {
+        line.push_str(&format!(": {}", semantics));
+    }
+    if let Some(comment) = comment {
+        line.push_str(&format!("\n{}", comment));
+    }
+    line
+}
+
+fn render_segment(
+    html: &mut String,
+    input: &str,
+    pos: usize,
+    segment: &parsed::Segment,
+    notes: &Notes,
+) {
+    open(html, "segment", notes.segments.get(&pos));
+    let mut offset = segment.start.offset;
+    let elements = std::iter::once(&segment.tag).chain(
+        segment.elements.iter().flat_map(|element| match element {
+            Either::Left(composite) => composite.elements.iter().collect(),
+            Either::Right(data_element) => vec![data_element],
+        }),
+    );
+    for data_element in elements {
+        let (start, end) =
+            (data_element.start.offset, data_element.end.offset);
+        html.push_str(&escape(&input[offset..start]));
+        let note = notes.elements.get(&start);
+        match note {
+            Some(note) => {
+                let class =
+                    if start == end { "element empty" } else { "element" };
+                open(html, class, Some(note));
+                html.push_str(&escape(&input[start..end]));
+                html.push_str("</span>");
+            }
+            None => html.push_str(&escape(&input[start..end])),
+        }
+        offset = end;
+    }
+    // The terminator is part of the segment, but the following line
+    // breaks are not.
+    let end = segment.start.offset + segment.raw.len();
+    html.push_str(&escape(&input[offset..end]));
+    html.push_str("</span>\n");
+}
+
+/// Opens a span with the given class and the note as its tooltip.
+fn open(html: &mut String, class: &str, note: Option<&Note>) {
+    html.push_str(&format!("<span class=\"{}", class));
+    if note.is_some_and(|note| note.error) {
+        html.push_str(" error");
+    }
+    html.push('"');
+    if let Some(note) = note {
+        let title = note.lines.join("\n");
+        html.push_str(&format!(" title=\"{}\"", escape(&title)));
+    }
+    html.push('>');
+}
+
+/// Escapes the characters with a special meaning in HTML.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::report::html::Document;
+    use crate::mig::{self, decode, DecodeOptions};
+
+    #[test]
+    fn test_html() {
+        let desc = fixtures::aperak();
+        let input = fixtures::APERAK.replace('\'', "'\n");
+        let invalid = input.replace("6991", "6991ABCDEFGH");
+        let decode = |input: &str, options: &DecodeOptions| {
+            let known = vec![desc.clone()];
+            mig::decode(known, &mut input.as_bytes(), options)
+        };
+
+        let mut document = Document::new();
+        let valid = decode(&input, &DecodeOptions::default()).unwrap();
+        document.add("valid.edi", &input, Some(&valid), None);
+        let Err(decode::Error::Mig(error)) =
+            decode(&invalid, &DecodeOptions::default())
+        else {
+            panic!("Expected the interchange to be invalid");
+        };
+        document.add("invalid.edi", &invalid, None, Some(&error));
+        let html = document.to_string();
+
+        assert!(html.contains("<h2>valid.edi</h2>\n<p class=\"valid\">"));
+        assert!(html.contains("title=\"3035 Beteiligter, Qualifier: "));
+        assert!(html.contains("<span class=\"element error\" title=\"39: "));
+        assert!(html.contains("6991ABCDEFGH</span>"));
+        assert!(html.contains("<pre>UNA:+.? '\n<span class=\"segment\""));
+    }
+}
@@ -0,0 +1,205 @@
+//! The security segments of ISO 9735 parts 5 to 7 and 9, which secure
+//! interchanges and messages, e.g. with a signature, and make up the
+//! AUTACK and KEYMAN messages.
+//!
+//! MIGs of business messages do not describe them, so the security
+//! segments of an interchange are not matched with a description, but
+//! kept in [Interchange::security](crate::mig::decode::value::Interchange)
+//! as they have been received. A secured interchange therefore decodes
+//! like one without security, instead of failing with unknown segments.
+//! A description, which does contain them, e.g. the
+//! [bundled](crate::mig::bundled::autack) one of AUTACK, has them matched
+//! like any other segment.
+//!
+//! The security header group starts with USH and is related to its
+//! security trailer group starting with UST by the security reference
+//! number 0534, see [headers]. They are encoded again at their
+//! positions, but the signatures of an interchange do not match anymore,
+//! once it has been transformed.
+use serde::{Deserialize, Serialize};
+
+use crate::mig::decode::parser::value;
+use crate::mig::either::Either;
+
+/// The tags of the security segments.
+pub const TAGS: [&str; 14] = [
+    "USA", "USB", "USC", "USD", "USE", "USF", "USH", "USJ", "USL", "USR",
+    "UST", "USU", "USX", "USY",
+];
+
+/// Returns, whether the given tag is the one of a security segment.
+pub fn is_security_segment(tag: &str) -> bool {
+    TAGS.contains(&tag)
+}
+
+/// A `SecuritySegment` as it has been received.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecuritySegment {
+    /// The position of this segment among all segments of the
+    /// interchange, not counting UNA.
+    pub pos: usize,
+    pub tag: String,
+    /// The values of the elements, where every element has the values of
+    /// its components.
+    pub elements: Vec<Vec<String>>,
+    /// The segment as it has been received, including its terminator.
+    pub raw: String,
+}
+
+impl SecuritySegment {
+    pub(crate) fn new(segment: value::Segment) -> Self {
+        let elements = segment
+            .elements
+            .into_iter()
+            .map(|element| match element {
+                Either::Left(composite) => {
+                    composite.elements.into_iter().map(|e| e.value).collect()
+                }
+                Either::Right(data_element) => vec![data_element.value],
+            })
+            .collect();
+        SecuritySegment {
+            pos: segment.pos,
+            tag: segment.tag.value,
+            elements,
+            raw: segment.raw,
+        }
+    }
+
+    /// Returns the value of the given component of the given element,
+    /// both starting with 0, if it is not empty.
+    pub fn value(&self, element: usize, component: usize) -> Option<&str> {
+        let value = self.elements.get(element)?.get(component)?;
+        (!value.is_empty()).then_some(value.as_str())
+    }
+}
+
+/// A `SecurityHeader` with its security trailer, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityHeader<'a> {
+    /// The USH starting the security header group followed by its other
+    /// segments, e.g. USA with the algorithm and USC with the
+    /// certificate.
+    pub header: Vec<&'a SecuritySegment>,
+    /// The UST with the same security reference number followed by its
+    /// USR with the result, e.g. the signature.
+    pub trailer: Vec<&'a SecuritySegment>,
+}
+
+impl SecurityHeader<'_> {
+    /// Returns the security service 0501 of USH, e.g. `7` for the
+    /// integrity of the content.
+    pub fn service(&self) -> Option<&str> {
+        self.header.first()?.value(0, 0)
+    }
+
+    /// Returns the security reference number 0534 of USH.
+    pub fn reference(&self) -> Option<&str> {
+        self.header.first()?.value(1, 0)
+    }
+
+    /// Returns the scope of the security application 0541 of USH.
+    pub fn scope(&self) -> Option<&str> {
+        self.header.first()?.value(2, 0)
+    }
+
+    /// Returns the validation values 0560 of the USR in the trailer.
+    pub fn results(&self) -> Vec<&str> {
+        let usr = self.trailer.iter().find(|s| s.tag == "USR");
+        let values = usr.and_then(|usr| usr.elements.first());
+        // The first component is the validation value qualifier 0563.
+        values.map_or(vec![], |values| {
+            values.iter().skip(1).map(String::as_str).collect()
+        })
+    }
+}
+
+/// Returns the security headers of the given security segments with
+/// their trailers in the order they have been received. The segments up
+/// to the next USH, UST or a segment not following directly belong to a
+/// header or trailer, respectively.
+pub fn headers(segments: &[SecuritySegment]) -> Vec<SecurityHeader<'_>> {
+    let mut groups: Vec<Vec<&SecuritySegment>> = vec![];
+    for segment in segments {
+        let follows = groups
+            .last()
+            .and_then(|group| group.last())
+            .is_some_and(|last| last.pos + 1 == segment.pos);
+        match groups.last_mut() {
+            Some(group)
+                if follows && !matches!(&*segment.tag, "USH" | "UST") =>
+            {
+                group.push(segment)
+            }
+            _ => groups.push(vec![segment]),
+        }
+    }
+
+    let (headers, trailers): (Vec<_>, Vec<_>) =
+        groups.into_iter().partition(|group| group[0].tag == "USH");
+    headers
+        .into_iter()
+        .map(|header| {
+            let reference = header[0].value(1, 0);
+            let trailer = trailers
+                .iter()
+                .find(|trailer| {
+                    trailer[0].tag == "UST"
+                        && trailer[0].value(0, 0) == reference
+                })
+                .cloned()
+                .unwrap_or_default();
+            SecurityHeader { header, trailer }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::Error;
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_secured_interchange() {
+        let desc = fixtures::aperak();
+        let input = &fixtures::aperak_with(
+            "USH+7+1+3+1+2+1++++1:20200307:070500'\
+             USA+1:16:1:6:1:7:1'\
+             USC+AXZ4711+4::9900467000000+3'\
+             BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'\
+             UST+1+3'\
+             USR+1:30450221:00ab'",
+        );
+        let options = DecodeOptions::strict();
+        let interchange =
+            mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap();
+
+        let tags: Vec<&str> =
+            interchange.security.iter().map(|s| s.tag.as_str()).collect();
+        assert_eq!(tags, vec!["USH", "USA", "USC", "UST", "USR"]);
+        let headers = interchange.security_headers();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].service(), Some("7"));
+        assert_eq!(headers[0].reference(), Some("1"));
+        assert_eq!(headers[0].header.len(), 3);
+        assert_eq!(headers[0].results(), vec!["30450221", "00ab"]);
+        assert_eq!(interchange.security[4].pos, 8);
+
+        // Errors are reported at the position of the received segment.
+        let input = input.replace("BGM+313", "BGM+313+1+2+3+4");
+        let desc = fixtures::aperak();
+        let result = mig::decode(vec![desc], &mut input.as_bytes(), &options);
+        let Err(Error::Mig(error)) = result else {
+            panic!("Expected an error, got {:?}", result)
+        };
+        let positions: Vec<usize> = error.message_errors[0]
+            .segment_errors
+            .iter()
+            .map(|error| error.pos)
+            .collect();
+        assert_eq!(positions, vec![5]);
+    }
+}
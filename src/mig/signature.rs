@@ -0,0 +1,272 @@
+//! Detached signatures and hashes of interchanges, for processes
+//! requiring the integrity of payloads.
+//!
+//! A [Signature] is computed over the [content] of an interchange, which
+//! is its UNA and every segment as received, so it covers segments
+//! skipped while decoding leniently as well. Only the line breaks between
+//! segments are not signed. The signature is kept apart from the
+//! interchange, e.g. in a file next to it, and is written as the
+//! algorithm and the value in hex, e.g. `sha256:ba78…`.
+//!
+//! The algorithm is given by a [Signer]: [Sha256] hashes the content and
+//! [HmacSha256] authenticates it with a secret shared with the partner.
+//! Other algorithms, e.g. asymmetric signatures with the key of a
+//! certificate, are used by implementing [Signer].
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha2::Digest;
+
+use crate::mig::decode::parser::value::Interchange;
+
+/// A `Signer` computes and verifies signatures of a content with an
+/// algorithm.
+pub trait Signer {
+    /// The name of the algorithm written with a signature, e.g. `sha256`.
+    fn algorithm(&self) -> &str;
+
+    /// Returns the signature of the given content.
+    fn sign(&self, content: &[u8]) -> Vec<u8>;
+
+    /// Returns whether the given signature is the one of the content. By
+    /// default, the signature is compared with the one computed, which
+    /// takes the same time regardless of where they differ.
+    fn verify(&self, content: &[u8], signature: &[u8]) -> bool {
+        let expected = self.sign(content);
+        expected.len() == signature.len()
+            && expected
+                .iter()
+                .zip(signature)
+                .fold(0, |result, (a, b)| result | (a ^ b))
+                == 0
+    }
+}
+
+/// A detached `Signature` of an interchange, see the [module](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub algorithm: String,
+    pub value: Vec<u8>,
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.algorithm)?;
+        for byte in &self.value {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Signature {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some((algorithm, hex)) = s.split_once(':') else {
+            return Err(SignatureError::Malformed);
+        };
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(SignatureError::Malformed);
+        }
+        let value = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| SignatureError::Malformed)?;
+        Ok(Signature { algorithm: algorithm.to_string(), value })
+    }
+}
+
+/// The error returned, if a signature could not be verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The signature is not an algorithm followed by a value in hex.
+    Malformed,
+    /// The signature has been computed with another algorithm.
+    Algorithm { expected: String, actual: String },
+    /// The signature is not the one of the interchange, which therefore
+    /// has been changed or signed with another key.
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::Malformed => write!(
+                f,
+                "The signature is malformed, expected an algorithm and a \
+                 value in hex, e.g. sha256:ba78"
+            ),
+            SignatureError::Algorithm { expected, actual } => write!(
+                f,
+                "The signature has been computed with {}, expected {}",
+                actual, expected
+            ),
+            SignatureError::Mismatch => {
+                write!(f, "The signature does not match the interchange")
+            }
+        }
+    }
+}
+
+/// Returns the content of the given parsed interchange, which is signed:
+/// its UNA followed by the raw text of all of its segments.
+pub fn content(interchange: &Interchange) -> String {
+    let mut content = interchange.una.to_string();
+    for segment in &interchange.segments {
+        content.push_str(segment.raw.trim());
+    }
+    content
+}
+
+/// Signs the given parsed interchange with the given signer.
+pub fn sign(interchange: &Interchange, signer: &dyn Signer) -> Signature {
+    let content = content(interchange);
+    Signature {
+        algorithm: signer.algorithm().to_string(),
+        value: signer.sign(content.as_bytes()),
+    }
+}
+
+/// Verifies, that the signature is the one of the given parsed
+/// interchange.
+pub fn verify(
+    interchange: &Interchange,
+    signer: &dyn Signer,
+    signature: &Signature,
+) -> Result<(), SignatureError> {
+    if signature.algorithm != signer.algorithm() {
+        return Err(SignatureError::Algorithm {
+            expected: signer.algorithm().to_string(),
+            actual: signature.algorithm.clone(),
+        });
+    }
+    let content = content(interchange);
+    if signer.verify(content.as_bytes(), &signature.value) {
+        Ok(())
+    } else {
+        Err(SignatureError::Mismatch)
+    }
+}
+
+/// The SHA-256 hash of FIPS 180-4.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+impl Signer for Sha256 {
+    fn algorithm(&self) -> &str {
+        "sha256"
+    }
+
+    fn sign(&self, content: &[u8]) -> Vec<u8> {
+        sha2::Sha256::digest(content).to_vec()
+    }
+}
+
+/// The HMAC of RFC 2104 with SHA-256 and a shared secret.
+#[derive(Clone)]
+pub struct HmacSha256 {
+    mac: Hmac<sha2::Sha256>,
+}
+
+impl HmacSha256 {
+    pub fn new(secret: &[u8]) -> Self {
+        let mac = Hmac::new_from_slice(secret)
+            .expect("An HMAC takes secrets of any length");
+        HmacSha256 { mac }
+    }
+}
+
+impl fmt::Debug for HmacSha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The secret is not printed, e.g. in logs.
+        f.debug_struct("HmacSha256").finish_non_exhaustive()
+    }
+}
+
+impl Signer for HmacSha256 {
+    fn algorithm(&self) -> &str {
+        "hmac-sha256"
+    }
+
+    fn sign(&self, content: &[u8]) -> Vec<u8> {
+        let mut mac = self.mac.clone();
+        mac.update(content);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn verify(&self, content: &[u8], signature: &[u8]) -> bool {
+        let mut mac = self.mac.clone();
+        mac.update(content);
+        mac.verify_slice(signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::signature::{
+        sign, verify, HmacSha256, Sha256, Signature, SignatureError, Signer,
+    };
+
+    #[test]
+    fn test_signers() {
+        let hex = |value: Vec<u8>| {
+            Signature { algorithm: "x".to_string(), value }.to_string()[2..]
+                .to_string()
+        };
+        assert_eq!(
+            hex(Sha256.sign(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(Sha256.sign(&[b'a'; 1000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+        assert_eq!(
+            hex(Sha256.sign(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        // The test case 2 of RFC 4231.
+        let hmac = HmacSha256::new(b"Jefe");
+        assert_eq!(
+            hex(hmac.sign(b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let input = &fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'",
+        );
+        let decode = |input: &str| mig::parse(&mut input.as_bytes()).unwrap();
+        let signer = HmacSha256::new(b"secret");
+        let signature = sign(&decode(input), &signer);
+        let parsed: Signature = signature.to_string().parse().unwrap();
+        assert_eq!(parsed, signature);
+
+        let reformatted = input.replace('\'', "'\r\n");
+        assert_eq!(verify(&decode(&reformatted), &signer, &signature), Ok(()));
+        // A segment skipped while decoding leniently is signed as well.
+        let injected = input.replace("DTM+137", "XYZ+1'DTM+137");
+        let changed = input.replace("+53ff", "+63ff");
+        for input in [injected, changed] {
+            assert_eq!(
+                verify(&decode(&input), &signer, &signature),
+                Err(SignatureError::Mismatch)
+            );
+        }
+        assert!(!signer.verify(b"abc", &signer.sign(b"abc")[1..]));
+        assert!(matches!(
+            verify(&decode(input), &Sha256, &signature),
+            Err(SignatureError::Algorithm { .. })
+        ));
+        assert!("sha256:abc".parse::<Signature>().is_err());
+    }
+}
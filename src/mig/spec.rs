@@ -34,84 +34,245 @@
 //! about an error case, thus mitigating the non-descriptive error
 //! handling by attoparsec, as well as catching an error for every
 //! segment, instead of just for the first, if doing one pass.
+use std::fmt;
+use std::io;
 use std::ops::{Range, RangeFrom, RangeTo};
 use std::path::Path;
 use std::process;
+use std::string::FromUtf8Error;
 
-use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while, take_while1};
-use nom::character::complete::{
-    line_ending, multispace0, not_line_ending, space0,
-};
+use nom::character::complete::{line_ending, multispace0, space0};
 use nom::combinator::{map};
 use nom::error::{convert_error, VerboseError};
 use nom::multi::many_till;
-use nom::sequence::{delimited, tuple};
+use nom::sequence::tuple;
 use nom::{
     AsChar, Compare, IResult, InputIter, InputLength, InputTake,
     InputTakeAtPosition, Slice,
 };
 
 use crate::mig::description as desc;
+use crate::mig::either::Either;
 
 #[derive(Debug)]
 pub enum Error {
+    /// `pdftotext` failed with the contained error output.
     PdfToText(String),
     PathCannotBeConvertedToStr(),
     CouldNotReadTxtFile(std::io::Error),
+    /// `pdftotext` is not available on the path.
+    PdfToTextNotFound,
+    /// `pdftotext` could not be started for another reason.
+    CouldNotRunPdfToText(std::io::Error),
+    /// The output of `pdftotext` is not valid UTF-8.
+    InvalidUtf8Output(FromUtf8Error),
+    /// The document does not contain the section with the given title.
+    MissingSection(&'static str),
+    /// The message structure could not be parsed, with the trace of the
+    /// parser.
+    Structure(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PdfToText(error) => {
+                write!(f, "pdftotext failed: {}", error.trim())
+            }
+            Error::PathCannotBeConvertedToStr() => {
+                write!(f, "The path is not valid UTF-8")
+            }
+            Error::CouldNotReadTxtFile(error) => {
+                write!(f, "Could not read the text file: {}", error)
+            }
+            Error::PdfToTextNotFound => write!(
+                f,
+                "pdftotext could not be found. Please install it, e.g. with \
+                 the package poppler-utils, and make sure it is on the PATH"
+            ),
+            Error::CouldNotRunPdfToText(error) => {
+                write!(f, "Could not run pdftotext: {}", error)
+            }
+            Error::InvalidUtf8Output(error) => {
+                write!(f, "pdftotext returned invalid UTF-8: {}", error)
+            }
+            Error::MissingSection(title) => {
+                write!(f, "The section {} could not be found", title)
+            }
+            Error::Structure(trace) => {
+                write!(f, "The message structure is invalid:\n{}", trace)
+            }
+        }
+    }
 }
 
 /// Parses the given [path] into a [desc::Interchange].
 pub fn parse<P: AsRef<Path>>(path: P) -> Result<desc::Interchange, Error> {
-    let file =
-        path.as_ref().to_str().ok_or(Error::PathCannotBeConvertedToStr())?;
+    parse_string(text(path)?)
+}
+
+/// Parses the change log of the document at the given [path].
+pub fn parse_changelog<P: AsRef<Path>>(path: P) -> Result<Vec<Change>, Error> {
+    changelog(&text(path)?)
+}
+
+/// Returns the text of the given PDF file as converted by `pdftotext` or
+/// the contents of the given text file, if it has already been converted.
+fn text<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let file = path
+        .as_ref()
+        .to_str()
+        .ok_or(Error::PathCannotBeConvertedToStr())?;
     if file.ends_with(".txt") {
-        let content = std::fs::read_to_string(file)
-            .map_err(Error::CouldNotReadTxtFile)?;
-        parse_string(content)
+        std::fs::read_to_string(file).map_err(Error::CouldNotReadTxtFile)
     } else {
         let output = process::Command::new("pdftotext")
             .arg("-layout")
             .arg(file)
             .arg("-")
             .output()
-            .expect("Failed to execute pdftotext");
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::NotFound => Error::PdfToTextNotFound,
+                _ => Error::CouldNotRunPdfToText(error),
+            })?;
 
         if !output.status.success() {
-            let error = String::from_utf8(output.stderr).expect("Works");
-            Err(Error::PdfToText(error))
+            let error = String::from_utf8_lossy(&output.stderr);
+            Err(Error::PdfToText(error.into_owned()))
         } else {
-            let input = String::from_utf8(output.stdout).expect("Works");
-            parse_string(input)
+            String::from_utf8(output.stdout).map_err(Error::InvalidUtf8Output)
         }
     }
 }
 
 type ParseResult<I, O, E = VerboseError<I>> = IResult<I, O, E>;
 
+/// Parses the given text of a document into a [desc::Interchange] with
+/// the metadata of its title page and the segments and segment groups of
+/// its message structure. The segment layouts are not parsed yet, so the
+/// segments other than the service segments do not have any elements.
 pub fn parse_string(input: String) -> Result<desc::Interchange, Error> {
-    let result = mig(input.as_str());
-    match result {
+    match mig(input.as_str()) {
+        Ok((_, (metadata, rows))) => Ok(interchange(metadata, &rows)),
         Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
-            println!("Error:\n{}", convert_error(input.as_str(), e));
+            Err(Error::Structure(convert_error(input.as_str(), e)))
         }
-        Err(_error) => println!("Error happened"),
-        Ok((_, wohoo)) => {
-            println!("{}", wohoo)
+        Err(nom::Err::Incomplete(_)) => {
+            Err(Error::MissingSection("Nachrichtenstruktur"))
+        }
+    }
+}
+
+/// Returns the description of the given rows of the message structure.
+/// A segment group contains the rows up to the next row on its level or
+/// above, except for its trigger segment, which is on its level.
+fn interchange(metadata: desc::Metadata, rows: &[Row]) -> desc::Interchange {
+    let service = |row: &Row, mut segment: desc::Segment| {
+        segment.counter = row.counter.clone();
+        segment.number = row.number.unwrap_or(segment.number);
+        segment.name = row.name.clone();
+        segment
+    };
+    let mut interchange = desc::Interchange {
+        unb: desc::service::unb(),
+        message: desc::Message {
+            unh: desc::service::unh(),
+            segments: vec![],
+            unt: desc::service::unt(),
+        },
+        unz: desc::service::unz(),
+        metadata: Some(metadata),
+    };
+    // The open segment groups with their levels.
+    let mut groups: Vec<(u64, desc::Segmentgroup)> = vec![];
+    let mut trigger = false;
+    for row in rows {
+        let (st, max_reps) = row.bdew;
+        match row.label.as_str() {
+            "UNA" => continue,
+            "UNB" => interchange.unb = service(row, interchange.unb),
+            "UNH" => {
+                let unh = interchange.message.unh;
+                interchange.message.unh = service(row, unh);
+            }
+            "UNT" => {
+                close(&mut groups, 0, &mut interchange.message.segments);
+                let unt = interchange.message.unt;
+                interchange.message.unt = service(row, unt);
+            }
+            "UNZ" => interchange.unz = service(row, interchange.unz),
+            label if label.starts_with("SG") => {
+                let segments = &mut interchange.message.segments;
+                close(&mut groups, row.level, segments);
+                groups.push((row.level, desc::Segmentgroup {
+                    counter: row.counter.clone(),
+                    label: row.label.clone(),
+                    st,
+                    max_reps,
+                    level: row.level,
+                    name: row.name.clone(),
+                    comment: None,
+                    segments: vec![],
+                }));
+                trigger = true;
+                continue;
+            }
+            tag => {
+                if !trigger {
+                    let segments = &mut interchange.message.segments;
+                    close(&mut groups, row.level, segments);
+                }
+                let segment = desc::Segment {
+                    counter: row.counter.clone(),
+                    number: row.number.unwrap_or_default(),
+                    tag: tag.to_string(),
+                    st,
+                    max_reps,
+                    level: row.level,
+                    name: row.name.clone(),
+                    comment: None,
+                    elements: vec![],
+                    condition: None,
+                };
+                let segments = match groups.last_mut() {
+                    Some((_, group)) => &mut group.segments,
+                    None => &mut interchange.message.segments,
+                };
+                segments.push(Either::Right(segment));
+            }
         }
+        trigger = false;
     }
+    close(&mut groups, 0, &mut interchange.message.segments);
+    interchange
+}
 
-    Err(Error::PdfToText("Wooohooo".to_string()))
+/// Closes the open segment groups on the given level or deeper and adds
+/// them to their parents.
+fn close(
+    groups: &mut Vec<(u64, desc::Segmentgroup)>,
+    level: u64,
+    segments: &mut Vec<Either<desc::Segmentgroup, desc::Segment>>,
+) {
+    while let Some((_, group)) = groups.pop_if(|(open, _)| *open >= level) {
+        match groups.last_mut() {
+            Some((_, parent)) => parent.segments.push(Either::Left(group)),
+            None => segments.push(Either::Left(group)),
+        }
+    }
 }
 
-fn mig(input: &str) -> ParseResult<&str, String> {
+fn mig(input: &str) -> ParseResult<&str, (desc::Metadata, Vec<Row>)> {
     map(
         tuple((
             many_till(line, start_of_toc),
             many_till(line, start_of_message_structure),
             message_structure,
         )),
-        |(_, _, structure)| structure,
+        |((title_page_lines, _), _, structure)| {
+            (title_page(&title_page_lines), structure)
+        },
     )(input)
 }
 
@@ -154,10 +315,6 @@ fn start_of_message_structure(input: &str) -> ParseResult<&str, ()> {
     start_of("Nachrichtenstruktur")(input)
 }
 
-fn start_of_segment_layout(input: &str) -> ParseResult<&str, ()> {
-    start_of("Segmentlayout")(input)
-}
-
 fn start_of_diagram(input: &str) -> ParseResult<&str, ()> {
     start_of("Diagramm")(input)
 }
@@ -166,102 +323,588 @@ fn start_of_changelog(input: &str) -> ParseResult<&str, ()> {
     start_of("Änderungshistorie")(input)
 }
 
-/// Parsing the message structure is straight forward. The only caveat to
-/// consider is to filter out the resulting lines, which are part of the
-/// footer.
-fn message_structure(input: &str) -> ParseResult<&str, String> {
-    let is_relevant = |l: &str| {
-        !is_part_of_message_structure_header(l)
-            && !is_part_of_footer(l)
-            && !l.starts_with("BDEW")
-            && l != ""
+// TITLE PAGE
+
+/// Returns the metadata given on the title page, e.g.
+///
+/// ```text
+/// Nachrichtenbeschreibung UTILMD
+/// Version: 5.2e
+/// Gültig ab: 01.04.2023
+/// Publikationsdatum: 01.10.2022
+/// ```
+///
+/// The message type is the first word of six upper case letters.
+fn title_page(lines: &[&str]) -> desc::Metadata {
+    let mut metadata = desc::Metadata::default();
+    for line in lines {
+        let line = line.trim_start_matches('\u{c}').trim();
+        let value = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| Some(line.split_once(key)?.1.trim()))
+                .filter(|value| !value.is_empty())
+        };
+
+        if metadata.message_type.is_none() {
+            metadata.message_type = line
+                .split_whitespace()
+                .find(|word| {
+                    word.len() == 6
+                        && word.chars().all(|c| c.is_ascii_uppercase())
+                })
+                .map(str::to_string);
+        }
+        if let Some(version) = value(&["Version:"]) {
+            let version = version.split_whitespace().next();
+            metadata.version =
+                metadata.version.or(version.map(str::to_string));
+        }
+        if let Some(date) = value(&["Gültig ab:", "Anwendbar ab:"]) {
+            metadata.valid_from = metadata.valid_from.or(iso_date(date));
+        }
+        if let Some(date) = value(&["Gültig bis:", "Anwendbar bis:"]) {
+            metadata.valid_until = metadata.valid_until.or(iso_date(date));
+        }
+        if let Some(revision) = value(&["Publikationsdatum:", "Stand:"]) {
+            let revision = Some(revision.to_string());
+            metadata.revision = metadata.revision.or(revision);
+        }
+    }
+    metadata
+}
+
+/// Converts a date like `01.04.2023` into `2023-04-01`.
+fn iso_date(date: &str) -> Option<String> {
+    let date = date.split_whitespace().next()?;
+    let mut parts = date.splitn(3, '.');
+    let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+    let is_number = |part: &str, len: usize| {
+        part.len() == len && part.chars().all(|c| c.is_ascii_digit())
     };
+    if is_number(day, 2) && is_number(month, 2) && is_number(year, 4) {
+        Some(format!("{}-{}-{}", year, month, day))
+    } else {
+        None
+    }
+}
 
-    let (input, (lines, _)) = many_till(
-        delimited(space0, not_line_ending, line_ending),
-        start_of_diagram,
-    )(input)?;
-
-    let filtered_lines = lines
-        .iter()
-        .map(|l| l.trim_start().to_string())
-        .filter(|l| is_relevant(l))
-        .collect::<Vec<_>>();
-
-    let mut result = String::new();
-    for line in filtered_lines {
-        if line.starts_with("0") {
-            result.push('\n');
-            result.push_str(line.as_str());
-        } else {
-            result.push(' ');
-            result.push_str(line.as_str());
+// MESSAGE STRUCTURE
+
+/// A `Row` of the message structure, which lists the segments and
+/// segment groups of the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub counter: String,
+    /// The number of the segment, which segment groups do not have.
+    pub number: Option<u64>,
+    /// The tag of the segment or the label of the segment group.
+    pub label: String,
+    /// The status and maximum repetitions of the standard, which older
+    /// documents do not list.
+    pub standard: Option<(desc::St, u64)>,
+    /// The status and maximum repetitions of the BDEW.
+    pub bdew: (desc::St, u64),
+    pub level: u64,
+    pub name: String,
+}
+
+/// A `Profile` describes the page headers and footers of a version of the
+/// documents, which interrupt the tables. Each entry is the start of a
+/// line, which is followed by whitespace or ends the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    pub name: &'static str,
+    /// The text identifying documents of this profile.
+    pub marker: &'static str,
+    pub header: &'static [&'static str],
+    pub footer: &'static [&'static str],
+}
+
+/// The profiles of the known document versions. The first one is used
+/// for documents without any marker.
+pub const PROFILES: &[Profile] = &[
+    Profile {
+        name: "EDI@Energy",
+        marker: "EDI@Energy",
+        header: &[
+            "EDI@Energy",
+            "Nachrichtenstruktur",
+            "Zähler",
+            "Standard",
+            "Strom",
+            "Gas",
+            "UTILMD",
+            "UTILMD-",
+            "MSCONS",
+            "MSCONS-",
+        ],
+        footer: &[
+            "Bez =",
+            "Nr =",
+            "St =",
+            "MaxWdh =",
+            "Zähler =",
+            "Ebene =",
+            "EDI@Energy",
+            "Version",
+            "Seite",
+        ],
+    },
+    Profile {
+        name: "BDEW",
+        marker: "BDEW Bundesverband",
+        header: &[
+            "Nachrichtenstruktur",
+            "Zähler",
+            "Status",
+            "UTILMD",
+            "MSCONS",
+        ],
+        footer: &["BDEW Bundesverband", "Version", "Stand:", "Seite"],
+    },
+];
+
+impl Profile {
+    /// Returns the profile of the given document.
+    pub fn detect(input: &str) -> &'static Profile {
+        PROFILES
+            .iter()
+            .find(|profile| input.contains(profile.marker))
+            .unwrap_or(&PROFILES[0])
+    }
+
+    fn is_header_or_footer(&self, line: &str) -> bool {
+        let starts_with = |start: &&str| match line.strip_prefix(*start) {
+            Some(rest) => {
+                rest.is_empty()
+                    || rest.starts_with(char::is_whitespace)
+                    || start.ends_with(|c: char| !c.is_alphanumeric())
+            }
+            None => false,
+        };
+        self.header.iter().any(starts_with)
+            || self.footer.iter().any(starts_with)
+    }
+}
+
+/// Parses the rows of the message structure up to the diagram. The lines
+/// of the table are interrupted by page headers and footers, which are
+/// skipped according to the profile of the document. Names wrap into
+/// lines starting in the column of the name, even across pages.
+fn message_structure(input: &str) -> ParseResult<&str, Vec<Row>> {
+    let (input, (lines, _)) = many_till(line, start_of_diagram)(input)?;
+    let profile = Profile::detect(&lines.join("\n"));
+    Ok((input, rows(profile, &lines)))
+}
+
+fn rows(profile: &Profile, lines: &[&str]) -> Vec<Row> {
+    let mut rows: Vec<Row> = vec![];
+    // The column of the names, which may differ between pages.
+    let mut name_column = None;
+    for line in lines {
+        let line = line.trim_start_matches('\u{c}').trim_end();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("Zähler") && trimmed.contains("Name") {
+            name_column = line.find("Name").map(|i| line[..i].chars().count());
+            continue;
+        } else if trimmed.is_empty() || profile.is_header_or_footer(trimmed) {
+            continue;
+        }
+
+        if let Some(row) = row(trimmed) {
+            rows.push(row);
+            continue;
         }
+        // pdftotext may shift the name of a continued line by a character.
+        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+        let is_continued =
+            name_column.is_some_and(|column| indent + 2 >= column);
+        if let Some(last) = rows.last_mut().filter(|_| is_continued) {
+            append(&mut last.name, trimmed);
+        }
+    }
+    rows
+}
+
+/// Parses a row like `0030 5 DTM M 35 M 1 1 Nachrichtendatum`, which does
+/// not have a number for segment groups and may only list the status and
+/// maximum repetitions of the BDEW.
+fn row(line: &str) -> Option<Row> {
+    let mut tokens = vec![];
+    let mut rest = line;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |end| start + end);
+        tokens.push((&rest[start..end], &rest[end..]));
+        rest = &rest[end..];
+    }
+
+    let is_number =
+        |t: &str| !t.is_empty() && t.chars().all(|c| c.is_ascii_digit());
+    let mut tokens = tokens.into_iter().peekable();
+    let (counter, _) = tokens
+        .next()
+        .filter(|(t, _)| t.len() == 4 && is_number(t))?;
+    let number = match tokens.next_if(|(t, _)| is_number(t)) {
+        Some((number, _)) => Some(number.parse().ok()?),
+        None => None,
+    };
+    let (label, _) = tokens.next().filter(|(t, _)| is_label(t))?;
+
+    let mut columns = vec![];
+    while let Some((status, _)) = tokens.next_if(|(t, _)| st(t).is_some()) {
+        let (max_reps, _) = tokens.next().filter(|(t, _)| is_number(t))?;
+        columns.push((st(status)?, max_reps.parse().ok()?));
     }
+    let (standard, bdew) = match columns.as_slice() {
+        [bdew] => (None, *bdew),
+        [standard, bdew] => (Some(*standard), *bdew),
+        _ => return None,
+    };
+    let (level, name) = tokens.next().filter(|(t, _)| is_number(t))?;
 
-    Ok((input, result))
+    Some(Row {
+        counter: counter.to_string(),
+        number,
+        label: label.to_string(),
+        standard,
+        bdew,
+        level: level.parse().ok()?,
+        name: name.trim().to_string(),
+    })
 }
 
-fn is_part_of_message_structure_header(line: &str) -> bool {
-    let line = line.trim_start();
-    line.starts_with("Nachrichtenstruktur")
-        || line.starts_with("Zähler")
-        || (line.starts_with("Status") && line.contains("MaxWdh"))
+/// Returns, whether the given text is a tag like `DTM` or the label of a
+/// segment group like `SG2`.
+fn is_label(text: &str) -> bool {
+    let is_tag =
+        text.len() == 3 && text.chars().all(|c| c.is_ascii_uppercase());
+    let is_group = text.strip_prefix("SG").is_some_and(|n| {
+        !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+    });
+    is_tag || is_group
 }
 
-fn is_part_of_footer(line: &str) -> bool {
-    let trimmed_line = line.trim();
-    trimmed_line.starts_with("Zähler =")
-        || trimmed_line.starts_with("Bez =")
-        || trimmed_line.starts_with("Nr =")
-        || trimmed_line.starts_with("MaxWdh =")
-        || trimmed_line.starts_with("EDI@Energy")
-        || trimmed_line.starts_with("Version")
-        || trimmed_line.starts_with("UTILMD-")
-        || trimmed_line == "Strom"
+fn st(text: &str) -> Option<desc::St> {
+    match text {
+        "M" => Some(desc::St::M),
+        "R" => Some(desc::St::R),
+        "O" => Some(desc::St::O),
+        "D" => Some(desc::St::D),
+        "C" => Some(desc::St::C),
+        "N" => Some(desc::St::N),
+        _ => None,
+    }
 }
 
-// SEGMENTS
+// CHANGELOG
 
-fn end_of_segment_layout(input: &str) -> ParseResult<&str, ()> {
-    map(alt((start_of_segment_layout, end_of_segment_layout)), |_| ())(input)
+/// A `Change` listed in the change log of a MIG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The ID of the change, which newer documents give instead of a date.
+    pub id: Option<String>,
+    /// The date of the change as `YYYY-MM-DD`.
+    pub date: Option<String>,
+    /// The changed segment or data element, e.g. `SG4 FTX 4440`.
+    pub reference: String,
+    pub text: String,
+    /// The reason of the change, which only newer documents give.
+    pub reason: Option<String>,
 }
 
-//fn start_of_elements(input: &str) -> ParseResult<&str, ()> {}
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = self.date.as_ref().or(self.id.as_ref());
+        write!(
+            f,
+            "{} {}: {}",
+            at.map_or("-", |at| at),
+            self.reference,
+            self.text
+        )?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
 
-fn standard_bdew_line(input: &str) -> ParseResult<&str, ()> {
-    map(
-        tuple((
-            tuple((space0, tag("Standard"))),
-            tuple((space0, tag("BDEW"))),
-        )),
-        |_| (),
-    )(input)
+/// The columns of the change log table, which start at the given
+/// characters. The first column contains the date or ID of a change.
+#[derive(Debug, Clone, Copy)]
+struct Columns {
+    reference: usize,
+    text: usize,
+    reason: Option<usize>,
 }
 
-fn segment_column_headers(input: &str) -> ParseResult<&str, ()> {
-    map(
-        tuple((
-            tuple((space0, tag("Zähler"))),
-            tuple((space0, tag("Nr"))),
-            tuple((space0, tag("Bez"))),
-            tuple((space0, tag("St"))),
-            tuple((space0, tag("MaxWdh"))),
-            tuple((space0, tag("St"))),
-            tuple((space0, tag("MaxWdh"))),
-            tuple((space0, tag("Ebene"))),
-            tuple((space0, tag("Name"))),
-        )),
-        |_| (),
-    )(input)
+impl Columns {
+    /// Returns the columns of the given header, e.g. `Datum Ort Änderung`
+    /// or `Änd-ID Ort Änderungen Grund der Anpassung`.
+    fn of(header: &str) -> Option<Columns> {
+        let column = |title: &str| {
+            header.find(title).map(|i| header[..i].chars().count())
+        };
+        Some(Columns {
+            reference: column("Ort")?,
+            text: column("Änderung")?,
+            reason: column("Grund der Anpassung"),
+        })
+    }
+
+    /// Splits the given line into the texts of the columns.
+    fn split(&self, line: &str) -> Vec<String> {
+        let chars: Vec<char> = line.chars().collect();
+        let columns = [Some(self.reference), Some(self.text), self.reason];
+        let mut cuts = vec![0];
+        for column in columns.into_iter().flatten() {
+            let mut cut = column.min(chars.len());
+            // pdftotext may shift a column by a character, which must not
+            // split its first word.
+            for _ in 0..2 {
+                if cut > 0
+                    && cut < chars.len()
+                    && !chars[cut - 1].is_whitespace()
+                    && !chars[cut].is_whitespace()
+                {
+                    cut -= 1;
+                }
+            }
+            cuts.push(cut.max(cuts[cuts.len() - 1]));
+        }
+        cuts.push(chars.len());
+        cuts.windows(2)
+            .map(|cut| {
+                let text: String = chars[cut[0]..cut[1]].iter().collect();
+                text.trim().to_string()
+            })
+            .collect()
+    }
+}
+
+/// Parses the change log, which is the last section of a document. An
+/// entry starts with its date or ID and continues in the following lines,
+/// even across pages.
+fn changelog(input: &str) -> Result<Vec<Change>, Error> {
+    let (rest, _) = many_till(line, start_of_changelog)(input)
+        .map_err(|_| Error::MissingSection("Änderungshistorie"))?;
+    let lines: Vec<&str> = rest.lines().collect();
+    Ok(changes(Profile::detect(input), &lines))
+}
+
+fn changes(profile: &Profile, lines: &[&str]) -> Vec<Change> {
+    let mut changes: Vec<Change> = vec![];
+    let mut columns = None;
+    for line in lines {
+        let line = line.trim_start_matches('\u{c}').trim_end();
+        let trimmed = line.trim_start();
+        let is_header =
+            trimmed.starts_with("Datum") || trimmed.starts_with("Änd-ID");
+        if let Some(header) = Columns::of(line).filter(|_| is_header) {
+            columns = Some(header);
+            continue;
+        } else if trimmed.is_empty()
+            || trimmed == "Änderungshistorie"
+            || profile.is_header_or_footer(trimmed)
+        {
+            continue;
+        }
+        let Some(columns) = columns else {
+            continue;
+        };
+
+        let texts = columns.split(line);
+        let text = |i: usize| texts.get(i).map_or("", String::as_str);
+        let is_id =
+            !text(0).is_empty() && text(0).chars().all(|c| c.is_ascii_digit());
+        let date = iso_date(text(0));
+        if date.is_some() || is_id {
+            changes.push(Change {
+                id: Some(text(0).to_string()).filter(|_| is_id),
+                date,
+                reference: text(1).to_string(),
+                text: text(2).to_string(),
+                reason: columns.reason.map(|_| text(3).to_string()),
+            });
+        } else if let Some(last) = changes.last_mut() {
+            append(&mut last.reference, text(1));
+            append(&mut last.text, text(2));
+            if let Some(reason) = &mut last.reason {
+                append(reason, text(3));
+            }
+        }
+    }
+    changes
+}
+
+/// Appends the given text of a wrapped line to the given text, which is
+/// separated by a space, unless the word has been hyphenated.
+fn append(to: &mut String, text: &str) {
+    if text.is_empty() {
+        return;
+    } else if !to.is_empty() && !to.ends_with('-') {
+        to.push(' ');
+    }
+    to.push_str(text);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::mig::spec::start_of;
+    use crate::mig::description::{Metadata, St};
+    use crate::mig::either::Either;
+    use crate::mig::spec::{
+        changelog, mig, parse, parse_string, start_of, Change, Profile, Row,
+        PROFILES,
+    };
+
+    /// Returns the labels and names of the rows parsed from the given
+    /// document.
+    fn names(input: &str) -> Vec<(String, String)> {
+        let (_, (_, rows)) = mig(input).unwrap();
+        rows.into_iter().map(|row| (row.label, row.name)).collect()
+    }
 
     #[test]
     fn test_start_of() {
         assert_eq!(start_of("[my_section]")(" [my_section]\n"), Ok(("", ())));
     }
+
+    #[test]
+    fn test_message_structure_utilmd() {
+        let input = include_str!("../../specs/UTILMD_MIG_5.2a.txt");
+        assert_eq!(Profile::detect(input), &PROFILES[0]);
+
+        let (rest, (metadata, rows)) = mig(input).unwrap();
+        assert!(rest.contains("Segmentlayout"));
+        assert_eq!(
+            metadata,
+            Metadata {
+                message_type: Some("UTILMD".to_string()),
+                version: Some("5.2a".to_string()),
+                valid_from: Some("2022-04-01".to_string()),
+                valid_until: Some("2022-09-30".to_string()),
+                revision: Some("01.10.2021".to_string()),
+            }
+        );
+        assert_eq!(
+            rows[5],
+            Row {
+                counter: "0070".to_string(),
+                number: None,
+                label: "SG2".to_string(),
+                standard: Some((St::C, 99)),
+                bdew: (St::M, 1),
+                level: 1,
+                name: "MP-ID Absender".to_string(),
+            }
+        );
+
+        let names = names(input);
+        assert_eq!(names.len(), 19);
+        assert_eq!(names[7].1, "Kontaktinformationen des Absenders");
+        assert_eq!(names[9].1, "Kommunikations-verbindung");
+        // The name continues on the next page.
+        assert_eq!(names[13].1, "Identifikation einer Transaktion");
+        assert_eq!(names[15].1, "Beteiligter an einer Marktlokation");
+        assert_eq!(names[18].0, "UNZ");
+    }
+
+    #[test]
+    fn test_parse() {
+        let file =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/specs/UTILMD_MIG_5.2a.txt");
+        let interchange = parse(file).unwrap();
+        let metadata = interchange.metadata.unwrap();
+        assert_eq!(metadata.version.as_deref(), Some("5.2a"));
+
+        let message = interchange.message;
+        assert_eq!(message.unh.number, 3);
+        assert_eq!(interchange.unz.counter, "0380");
+        let labels: Vec<&str> = message
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Either::Left(group) => group.label.as_str(),
+                Either::Right(segment) => segment.tag.as_str(),
+            })
+            .collect();
+        assert_eq!(labels, vec!["BGM", "DTM", "SG2", "SG2", "SG4"]);
+        let Either::Left(sg4) = &message.segments[4] else {
+            panic!("Expected SG4");
+        };
+        let Either::Left(sg12) = sg4.segments.last().unwrap() else {
+            panic!("Expected SG12 at the end of SG4");
+        };
+        assert_eq!((sg12.label.as_str(), sg12.max_reps), ("SG12", 99));
+        assert_eq!(sg12.segments.len(), 1);
+    }
+
+    #[test]
+    fn test_changelog() {
+        let input = include_str!("../../specs/UTILMD_MIG_5.2a.txt");
+        let changes = changelog(input).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes[1],
+            Change {
+                id: Some("21002".to_string()),
+                date: None,
+                reference: "SG12 NAD 3039".to_string(),
+                text: "Die Länge wurde von an..35 auf an..70 erweitert."
+                    .to_string(),
+                reason: Some("Anforderung der Marktteilnehmer".to_string()),
+            }
+        );
+
+        let input = include_str!("../../specs/MSCONS_MIG_2.2.txt");
+        let changes: Vec<String> = changelog(input)
+            .unwrap()
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                "2013-04-01 SG1 RFF: Die Gruppe ist nun optional.",
+                "2013-04-01 UNB 0035: Das Test-Kennzeichen darf nicht mehr \
+                 verwendet werden. Stattdessen wird UNB 0026 genutzt.",
+                "2013-07-15 SG2 NAD: Die Reihenfolge wurde geändert.",
+            ]
+        );
+
+        assert!(changelog("Nachrichtenstruktur\n").is_err());
+    }
+
+    #[test]
+    fn test_message_structure_mscons() {
+        let input = include_str!("../../specs/MSCONS_MIG_2.2.txt");
+        assert_eq!(Profile::detect(input), &PROFILES[1]);
+
+        let (_, (metadata, rows)) = mig(input).unwrap();
+        assert_eq!(metadata.message_type.as_deref(), Some("MSCONS"));
+        assert_eq!(metadata.valid_from.as_deref(), Some("2013-04-01"));
+        assert_eq!(metadata.valid_until, None);
+        assert_eq!(rows[4].standard, None);
+        assert_eq!(rows[4].bdew, (St::M, 9));
+
+        let names = names(input);
+        assert_eq!(names.len(), 11);
+        assert_eq!(names[5].1, "Referenz auf eine vorangegangene Nachricht");
+        assert_eq!(names[6].1, "Referenz auf eine vorangegangene Nachricht");
+        assert_eq!(names[10].0, "UNZ");
+
+        let input = input.to_string();
+        let message = parse_string(input).unwrap().message;
+        let Either::Left(sg1) = &message.segments[2] else {
+            panic!("Expected SG1 after BGM and DTM");
+        };
+        assert_eq!((sg1.label.as_str(), sg1.segments.len()), ("SG1", 1));
+        let Either::Left(sg2) = &message.segments[3] else {
+            panic!("Expected SG2 after SG1");
+        };
+        assert_eq!((sg2.st, sg2.max_reps), (St::M, 99));
+        assert_eq!(message.unt.number, 8);
+    }
 }
@@ -0,0 +1,181 @@
+//! Statistics of a corpus of interchanges, e.g. for a quick overview of
+//! an archive or an inbound queue.
+//!
+//! The interchanges are only parsed and not decoded with a MIG, so the
+//! statistics cover interchanges of any message type. [Stats] count the
+//! message types, their versions, the senders and receivers and collect
+//! the number of segments and bytes of the messages.
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::mig::decode::parser::value::Interchange;
+use crate::mig::decode::routing;
+
+/// The minimum, maximum and sum of a number of values, e.g. the number
+/// of segments of every message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: usize,
+    pub max: usize,
+    pub sum: usize,
+}
+
+impl Distribution {
+    pub fn add(&mut self, value: usize) {
+        self.min = if self.count == 0 { value } else { self.min.min(value) };
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Returns the mean of the values, if there are any.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
+
+/// The `Stats` of the interchanges added, see the [module](self).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub interchanges: usize,
+    /// The number of files, which could not be parsed.
+    pub unreadable: usize,
+    pub messages: usize,
+    /// The number of messages by their type from UNH 0065.
+    pub message_types: BTreeMap<String, usize>,
+    /// The number of messages by their identifier from UNH S009, e.g.
+    /// `APERAK:D:07B:UN:2.1d`.
+    pub versions: BTreeMap<String, usize>,
+    /// The number of interchanges by their sender from UNB 0004.
+    pub senders: BTreeMap<String, usize>,
+    /// The number of interchanges by their receiver from UNB 0010.
+    pub receivers: BTreeMap<String, usize>,
+    /// The number of segments of every message from UNH to UNT.
+    pub segments: Distribution,
+    /// The number of bytes of every message from UNH to UNT.
+    pub bytes: Distribution,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats::default()
+    }
+
+    /// Adds the given interchange.
+    pub fn add(&mut self, interchange: &Interchange) {
+        self.interchanges += 1;
+        let routing = interchange.routing();
+        let unknown = || "unknown".to_string();
+        count(&mut self.senders, routing.sender.unwrap_or_else(unknown));
+        count(&mut self.receivers, routing.receiver.unwrap_or_else(unknown));
+
+        let mut message: Option<(usize, usize)> = None;
+        for segment in &interchange.segments {
+            let tag = segment.tag.value.as_str();
+            if tag == "UNH" {
+                let identifier = routing::message_identifier(segment);
+                let message_type =
+                    identifier.message_type.clone().unwrap_or_else(unknown);
+                count(&mut self.message_types, message_type);
+                count(&mut self.versions, identifier.to_string());
+                self.messages += 1;
+                message = Some((0, 0));
+            }
+            if let Some((segments, bytes)) = &mut message {
+                *segments += 1;
+                *bytes += segment.raw.len();
+            }
+            if tag == "UNT" {
+                if let Some((segments, bytes)) = message.take() {
+                    self.segments.add(segments);
+                    self.bytes.add(bytes);
+                }
+            }
+        }
+    }
+
+    /// Counts a file, which could not be parsed.
+    pub fn add_unreadable(&mut self) {
+        self.unreadable += 1;
+    }
+
+    /// Returns the statistics as rows of a kind, a key and a value, e.g.
+    /// `["message_type", "UTILMD", "12"]` or `["segments", "max", "42"]`.
+    pub fn rows(&self) -> Vec<[String; 3]> {
+        let row = |kind: &str, key: &str, value: String| {
+            [kind.to_string(), key.to_string(), value]
+        };
+        let mut rows = vec![
+            row("total", "interchanges", self.interchanges.to_string()),
+            row("total", "unreadable", self.unreadable.to_string()),
+            row("total", "messages", self.messages.to_string()),
+        ];
+        let counts = [
+            ("message_type", &self.message_types),
+            ("version", &self.versions),
+            ("sender", &self.senders),
+            ("receiver", &self.receivers),
+        ];
+        for (kind, counts) in counts {
+            for (key, n) in counts {
+                rows.push(row(kind, key, n.to_string()));
+            }
+        }
+        for (kind, distribution) in
+            [("segments", &self.segments), ("bytes", &self.bytes)]
+        {
+            rows.push(row(kind, "min", distribution.min.to_string()));
+            rows.push(row(kind, "max", distribution.max.to_string()));
+            let mean = distribution.mean().unwrap_or_default();
+            rows.push(row(kind, "mean", format!("{:.1}", mean)));
+        }
+        rows
+    }
+}
+
+fn count(counts: &mut BTreeMap<String, usize>, key: String) {
+    *counts.entry(key).or_insert(0) += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig;
+    use crate::mig::stats::{Distribution, Stats};
+
+    #[test]
+    fn test_stats() {
+        let input = "UNA:+.? '\
+                     UNB+UNOC:3+9900467000000:500+9904590000002:500\
+                     +200307:0705+REF1'\
+                     UNH+1+APERAK:D:07B:UN:2.1d'\
+                     BGM+313+DOC1'\
+                     UNT+3+1'\
+                     UNH+2+APERAK:D:07B:UN:2.1e'\
+                     BGM+313+DOC2'\
+                     DTM+137:202003070705:203'\
+                     UNT+4+2'\
+                     UNZ+2+REF1'";
+        let mut stats = Stats::new();
+        stats.add(&mig::parse(&mut input.as_bytes()).unwrap());
+        stats.add_unreadable();
+
+        assert_eq!(stats.interchanges, 1);
+        assert_eq!(stats.messages, 2);
+        assert_eq!(stats.message_types["APERAK"], 2);
+        assert_eq!(stats.versions["APERAK:D:07B:UN:2.1e"], 1);
+        assert_eq!(stats.senders["9900467000000"], 1);
+        assert_eq!(
+            stats.segments,
+            Distribution { count: 2, min: 3, max: 4, sum: 7 }
+        );
+        assert_eq!(stats.segments.mean(), Some(3.5));
+        let rows = stats.rows();
+        assert!(rows.contains(&[
+            "total".to_string(),
+            "unreadable".to_string(),
+            "1".to_string(),
+        ]));
+    }
+}
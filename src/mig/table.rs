@@ -0,0 +1,218 @@
+//! Tables of repeating segment groups, e.g. the quantities of the SG10
+//! in MSCONS or the transactions of the SG4 in UTILMD, to open them in a
+//! spreadsheet.
+//!
+//! Every repetition of the segment group is a row and every column is
+//! selected by a [Path], e.g. `QTY+220` or `DTM+163`. A column takes the
+//! value of the first segment of the group, which matches its path, and
+//! falls back to the segments of the enclosing groups, so a row of SG10
+//! shows the location of its SG5 in MSCONS as well.
+//!
+//! With the feature `arrow`, a table is converted to an Arrow record batch
+//! or written as a Parquet file to ingest it into a data lake.
+use crate::mig::decode::path::Path;
+use crate::mig::decode::value::{Interchange, Segment, Segmentgroup};
+use crate::mig::either::Either;
+
+/// A `Table` of the repetitions of a segment group, see the
+/// [module](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub columns: Vec<Path>,
+    /// The values of every row in the order of the columns.
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+impl Table {
+    /// Creates a table of the rows of the segment group with the given
+    /// label, e.g. `SG10`, in the given interchange.
+    pub fn new(
+        interchange: &Interchange,
+        label: &str,
+        columns: &[Path],
+    ) -> Table {
+        let mut table = Table { columns: columns.to_vec(), rows: vec![] };
+        table.add(interchange, label);
+        table
+    }
+
+    /// Adds the rows of the segment group with the given label in the
+    /// given interchange, e.g. to build a table of many interchanges.
+    pub fn add(&mut self, interchange: &Interchange, label: &str) {
+        let mut ancestors = vec![segments(&interchange.segments)];
+        self.add_values(&interchange.segments, label, &mut ancestors);
+    }
+
+    /// Returns the rows as an Arrow record batch with a nullable text
+    /// column for every column, which is named by its path.
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(
+        &self,
+    ) -> Result<arrow_array::RecordBatch, arrow_schema::ArrowError> {
+        use std::sync::Arc;
+
+        use arrow_array::{ArrayRef, StringArray};
+        use arrow_schema::{DataType, Field, Schema};
+
+        let fields: Vec<Field> = self
+            .columns
+            .iter()
+            .map(|column| Field::new(column.to_string(), DataType::Utf8, true))
+            .collect();
+        let arrays = (0..self.columns.len())
+            .map(|index| {
+                let values = self.rows.iter().map(|row| row[index].as_deref());
+                Arc::new(values.collect::<StringArray>()) as ArrayRef
+            })
+            .collect();
+        arrow_array::RecordBatch::try_new(
+            Arc::new(Schema::new(fields)),
+            arrays,
+        )
+    }
+
+    /// Writes the rows as a Parquet file with the columns of
+    /// [Table::to_record_batch].
+    #[cfg(feature = "arrow")]
+    pub fn write_parquet<W: std::io::Write + Send>(
+        &self,
+        writer: W,
+    ) -> Result<(), parquet::errors::ParquetError> {
+        let batch = self.to_record_batch()?;
+        let mut writer = parquet::arrow::ArrowWriter::try_new(
+            writer,
+            batch.schema(),
+            None,
+        )?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    fn add_values<'a>(
+        &mut self,
+        values: &'a [Either<Segmentgroup, Segment>],
+        label: &str,
+        ancestors: &mut Vec<Vec<&'a Segment>>,
+    ) {
+        for value in values {
+            let Either::Left(group) = value else {
+                continue;
+            };
+            if group.label == label {
+                let row = self
+                    .columns
+                    .iter()
+                    .map(|column| value_of(column, group, ancestors))
+                    .collect();
+                self.rows.push(row);
+            } else {
+                ancestors.push(segments(&group.segments));
+                self.add_values(&group.segments, label, ancestors);
+                ancestors.pop();
+            }
+        }
+    }
+}
+
+/// Returns the value of the column in the group or the innermost of its
+/// enclosing groups.
+fn value_of(
+    column: &Path,
+    group: &Segmentgroup,
+    ancestors: &[Vec<&Segment>],
+) -> Option<String> {
+    let mut all = vec![];
+    collect_segments(&group.segments, &mut all);
+    let mut candidates = all.into_iter().chain(
+        ancestors.iter().rev().flat_map(|segments| segments.iter().copied()),
+    );
+    candidates.find_map(|segment| column.value_of(segment))
+}
+
+/// Returns the segments, which are not nested in a segment group.
+fn segments(values: &[Either<Segmentgroup, Segment>]) -> Vec<&Segment> {
+    values
+        .iter()
+        .filter_map(|value| match value {
+            Either::Right(segment) => Some(segment),
+            Either::Left(_) => None,
+        })
+        .collect()
+}
+
+fn collect_segments<'a>(
+    values: &'a [Either<Segmentgroup, Segment>],
+    result: &mut Vec<&'a Segment>,
+) {
+    for value in values {
+        match value {
+            Either::Left(group) => collect_segments(&group.segments, result),
+            Either::Right(segment) => result.push(segment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::table::Table;
+    use crate::mig::{self, DecodeOptions, Path};
+
+    #[test]
+    fn test_table() {
+        let desc = fixtures::aperak();
+        let options = DecodeOptions::default();
+        let interchange = mig::decode(
+            vec![desc],
+            &mut fixtures::APERAK.as_bytes(),
+            &options,
+        )
+        .unwrap();
+        let columns: Vec<Path> = ["ERC", "FTX+Z02/4/1", "DTM+137", "RFF+ACE"]
+            .iter()
+            .map(|column| column.parse().unwrap())
+            .collect();
+        let table = Table::new(&interchange, "SG4", &columns);
+        let row = |values: &[Option<&str>]| {
+            values.iter().map(|v| v.map(str::to_string)).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            table.rows,
+            vec![row(&[Some("Z29"), Some("10"), Some("202003070705"), None])]
+        );
+        assert!(Table::new(&interchange, "SG9", &columns).rows.is_empty());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_write_parquet() {
+        use arrow_array::{Array, StringArray};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+
+        let options = DecodeOptions::default();
+        let decode = |input: &str| {
+            let known = vec![fixtures::aperak()];
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap()
+        };
+        let columns: Vec<Path> = ["ERC", "RFF+ACW"]
+            .iter()
+            .map(|column| column.parse().unwrap())
+            .collect();
+        let mut table = Table::new(&decode(fixtures::APERAK), "SG4", &columns);
+        table.add(&decode(fixtures::APERAK_FULL), "SG4");
+
+        let mut output = vec![];
+        table.write_parquet(&mut output).unwrap();
+        let mut reader =
+            ParquetRecordBatchReader::try_new(bytes::Bytes::from(output), 16)
+                .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch, table.to_record_batch().unwrap());
+        assert_eq!(batch.schema().field(1).name(), "RFF+ACW");
+        let references =
+            batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(references.is_null(0));
+        assert_eq!(references.value(1), "V1583553607732");
+    }
+}
@@ -0,0 +1,137 @@
+//! Templates of interchanges in the [Tera](https://keats.github.io/tera)
+//! syntax, for partners who prefer to write the segments of a message
+//! themselves:
+//!
+//! ```text
+//! UNH+1+APERAK:D:07B:UN:2.1i'
+//! BGM+313+{{ document }}'
+//! {% for error in errors -%}
+//! ERC+{{ error.code }}'
+//! {% endfor -%}
+//! ```
+//!
+//! The template is rendered with the JSON object given to
+//! [Template::render]. Every value is escaped with the default
+//! separators, so a `+` or `'` in a value does not end its data element
+//! or segment, and `null` is rendered as an empty value.
+//!
+//! The template is not aware of the MIG, so the result has to be decoded
+//! to make sure it conforms to it.
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+use tera::{Context, Tera};
+
+use crate::mig::decode::parser::value::UNA;
+
+/// The name of the template within [Tera].
+const NAME: &str = "template";
+
+/// A parsed `Template`, see the [module](self).
+#[derive(Debug, Clone)]
+pub struct Template {
+    tera: Tera,
+}
+
+/// The error returned, if a template is not valid or could not be
+/// rendered with the given values.
+#[derive(Debug)]
+pub enum TemplateError {
+    /// The template is not valid.
+    Syntax(tera::Error),
+    /// The template could not be rendered with the values, e.g. because
+    /// one is missing.
+    Render(tera::Error),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let error = match self {
+            TemplateError::Syntax(error) => {
+                write!(f, "The template is invalid: ")?;
+                error
+            }
+            TemplateError::Render(error) => {
+                write!(f, "The template could not be rendered: ")?;
+                error
+            }
+        };
+        // The reason is given by the innermost error of Tera.
+        let mut reason: &dyn error::Error = error;
+        while let Some(source) = reason.source() {
+            reason = source;
+        }
+        write!(f, "{}", reason)
+    }
+}
+
+impl error::Error for TemplateError {}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tera = Tera::default();
+        // Every template is escaped, regardless of its name.
+        tera.autoescape_on(vec![""]);
+        tera.set_escape_fn(escape);
+        tera.add_raw_template(NAME, s).map_err(TemplateError::Syntax)?;
+        Ok(Template { tera })
+    }
+}
+
+fn escape(value: &str) -> String {
+    UNA::default().escape_value(value)
+}
+
+impl Template {
+    /// Renders this template with the given JSON object, see the
+    /// [module](self).
+    pub fn render(&self, values: &Value) -> Result<String, TemplateError> {
+        let context = Context::from_value(values.clone())
+            .map_err(TemplateError::Render)?;
+        self.tera.render(NAME, &context).map_err(TemplateError::Render)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::mig::template::{Template, TemplateError};
+
+    #[test]
+    fn test_render() {
+        let template: Template = "BGM+313+{{ document }}'\n\
+            {% for error in errors -%}\n\
+            ERC+{{ error.code }}'FTX+AAO+++{{ error.text }}'\n\
+            {% endfor -%}\n\
+            UNT+{{ count }}+1'"
+            .parse()
+            .unwrap();
+        let values = json!({
+            "document": "DOC1",
+            "errors": [
+                {"code": "Z29", "text": "Wert? falsch: 3+4"},
+                {"code": "Z30", "text": null},
+            ],
+            "count": 6,
+        });
+        assert_eq!(
+            template.render(&values).unwrap(),
+            "BGM+313+DOC1'\n\
+             ERC+Z29'FTX+AAO+++Wert?? falsch?: 3?+4'\n\
+             ERC+Z30'FTX+AAO+++'\n\
+             UNT+6+1'"
+        );
+        assert!(matches!(
+            template.render(&json!({"document": "DOC1"})),
+            Err(TemplateError::Render(_))
+        ));
+
+        let result = "{% for x in xs %}{{ x }}".parse::<Template>();
+        assert!(matches!(result, Err(TemplateError::Syntax(_))));
+    }
+}
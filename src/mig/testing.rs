@@ -0,0 +1,332 @@
+//! Generators of random interchanges conforming to a description, e.g. to
+//! test a processing pipeline with valid messages instead of a handful
+//! of fixtures. The generators are [proptest] strategies and require the
+//! `testing` feature.
+//!
+//! ```
+//! # use edifact::mig::{self, DecodeOptions};
+//! # use edifact::mig::description::Interchange;
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//!
+//! let desc: Interchange =
+//!     serde_json::from_str(include_str!("../../APERAK.json")).unwrap();
+//! let options = DecodeOptions::default();
+//! TestRunner::default()
+//!     .run(&mig::testing::interchange(&desc), |input| {
+//!         let known = vec![desc.clone()];
+//!         let decoded = mig::decode(known, &mut input.as_bytes(), &options);
+//!         prop_assert!(decoded.is_ok());
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+//!
+//! Generated values have the format and length of their description and
+//! escape separators with the default UNA. Segments and segment groups
+//! are repeated at most [MAX_REPS] times. UNT and UNZ contain the number
+//! of segments and messages and the references of UNH and UNB, all other
+//! values are random.
+use proptest::prelude::*;
+use proptest::string::string_regex;
+
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::description as desc;
+use crate::mig::description::{Format, Size, Usage};
+use crate::mig::either::Either;
+
+/// The maximum number of repetitions of a segment or segment group.
+pub const MAX_REPS: usize = 3;
+
+/// The maximum length of values, which may be shorter than described,
+/// so descriptions allowing 512 characters do not generate huge values.
+const MAX_LENGTH: usize = 64;
+
+/// A generated segment, which is rendered once UNT and UNZ are set.
+type Values = (String, Vec<String>);
+
+/// The characters of generated texts.
+#[derive(Debug, Clone, Copy)]
+enum Chars {
+    /// All printable characters including separators and umlauts.
+    Any,
+    /// Upper case letters and digits, like in references and IDs.
+    Plausible,
+}
+
+/// Returns a strategy generating interchanges in EDIFACT, which conform
+/// to the given description.
+pub fn interchange(desc: &desc::Interchange) -> BoxedStrategy<String> {
+    generate(desc, Chars::Any)
+}
+
+/// Returns a strategy generating interchanges like [interchange], whose
+/// texts only contain upper case letters and digits, e.g. to send them
+/// to the systems of market partners.
+pub fn example(desc: &desc::Interchange) -> BoxedStrategy<String> {
+    generate(desc, Chars::Plausible)
+}
+
+fn generate(desc: &desc::Interchange, chars: Chars) -> BoxedStrategy<String> {
+    let message = &desc.message;
+    (
+        values(&desc.unb, chars),
+        values(&message.unh, chars),
+        segments(&message.segments, chars),
+        values(&message.unt, chars),
+        values(&desc.unz, chars),
+    )
+        .prop_map(|(unb, unh, segments, mut unt, mut unz)| {
+            // UNH and UNT count towards the number of segments.
+            set(&mut unt, 0, (segments.len() + 2).to_string());
+            set(&mut unt, 1, first(&unh));
+            set(&mut unz, 0, "1".to_string());
+            set(&mut unz, 1, unb.1.get(4).cloned().unwrap_or_default());
+
+            let mut result = String::from("UNA:+.? '");
+            let all = [unb, unh].into_iter().chain(segments).chain([unt, unz]);
+            for segment in all {
+                result.push_str(&render(segment));
+                result.push('\'');
+            }
+            result
+        })
+        .boxed()
+}
+
+/// Returns a strategy generating a segment in EDIFACT including its
+/// terminator, which conforms to the given description.
+pub fn segment(desc: &desc::Segment) -> BoxedStrategy<String> {
+    values(desc, Chars::Any).prop_map(|values| render(values) + "'").boxed()
+}
+
+fn render((tag, elements): Values) -> String {
+    let mut result = tag;
+    for element in trimmed(elements) {
+        result.push('+');
+        result.push_str(&element);
+    }
+    result
+}
+
+fn set((_, elements): &mut Values, pos: usize, value: String) {
+    if let Some(element) = elements.get_mut(pos) {
+        *element = value;
+    }
+}
+
+fn first((_, elements): &Values) -> String {
+    elements.first().cloned().unwrap_or_default()
+}
+
+/// Returns a strategy generating the segments of the given descriptions
+/// in order, each repeated as often as its status and maximum allow.
+fn segments(
+    descs: &[Either<desc::Segmentgroup, desc::Segment>],
+    chars: Chars,
+) -> BoxedStrategy<Vec<Values>> {
+    let strategies: Vec<BoxedStrategy<Vec<Values>>> = descs
+        .iter()
+        .map(|desc| match desc {
+            Either::Left(group) => {
+                repeated(group.st, group.max_reps, group_values(group, chars))
+            }
+            Either::Right(segment) => repeated(
+                segment.st,
+                segment.max_reps,
+                values(segment, chars).prop_map(|values| vec![values]).boxed(),
+            ),
+        })
+        .collect();
+    strategies.prop_map(|values| values.concat()).boxed()
+}
+
+/// Returns a strategy generating one repetition of the given group. The
+/// first segment starts the group, so it is always generated.
+fn group_values(
+    desc: &desc::Segmentgroup,
+    chars: Chars,
+) -> BoxedStrategy<Vec<Values>> {
+    let (first, rest) = match desc.segments.split_first() {
+        Some(segments) => segments,
+        None => return Just(vec![]).boxed(),
+    };
+    let first = match first {
+        Either::Left(group) => group_values(group, chars),
+        Either::Right(segment) => {
+            values(segment, chars).prop_map(|values| vec![values]).boxed()
+        }
+    };
+    (first, segments(rest, chars))
+        .prop_map(|(first, rest)| [first, rest].concat())
+        .boxed()
+}
+
+fn repeated(
+    st: desc::St,
+    max_reps: u64,
+    strategy: BoxedStrategy<Vec<Values>>,
+) -> BoxedStrategy<Vec<Values>> {
+    let max = usize::try_from(max_reps).unwrap_or(MAX_REPS).min(MAX_REPS);
+    let min = if st.is_required() { 1.min(max) } else { 0 };
+    if st.is_not_used() {
+        return Just(vec![]).boxed();
+    }
+    prop::collection::vec(strategy, min..=max)
+        .prop_map(|values| values.concat())
+        .boxed()
+}
+
+fn values(desc: &desc::Segment, chars: Chars) -> BoxedStrategy<Values> {
+    let elements: Vec<BoxedStrategy<String>> = desc
+        .elements
+        .iter()
+        .map(|element| match element {
+            Either::Left(composite) => composite_value(composite, chars),
+            Either::Right(data_element) => {
+                data_element_value(data_element, chars)
+            }
+        })
+        .collect();
+    let tag = desc.tag.clone();
+    elements.prop_map(move |elements| (tag.clone(), elements)).boxed()
+}
+
+fn composite_value(
+    desc: &desc::Composite,
+    chars: Chars,
+) -> BoxedStrategy<String> {
+    let elements: Vec<BoxedStrategy<String>> = desc
+        .elements
+        .iter()
+        .map(|desc| data_element_value(desc, chars))
+        .collect();
+    let value = elements.prop_map(|elements| trimmed(elements).join(":"));
+    optional(desc.st, value.boxed())
+}
+
+fn data_element_value(
+    desc: &desc::DataElement,
+    chars: Chars,
+) -> BoxedStrategy<String> {
+    let value = match &desc.usage {
+        Usage::Static { value, comment: _ } => {
+            Just(value.value.clone()).boxed()
+        }
+        Usage::OneOf { choices, comment: _ } if !choices.is_empty() => {
+            let choices: Vec<String> =
+                choices.iter().map(|c| c.value.clone()).collect();
+            prop::sample::select(choices).boxed()
+        }
+        Usage::Decimal { comment: _ } => {
+            decimal(desc.format, desc.length, chars)
+        }
+        _ => formatted(desc.format, desc.length, chars),
+    };
+    optional(
+        desc.st,
+        value.prop_map(|v| UNA::default().escape_value(&v)).boxed(),
+    )
+}
+
+/// Returns a strategy generating values, which are empty, if the status
+/// allows it.
+fn optional(
+    st: desc::St,
+    value: BoxedStrategy<String>,
+) -> BoxedStrategy<String> {
+    if st.is_required() {
+        value
+    } else if st.is_not_used() {
+        Just(String::new()).boxed()
+    } else {
+        prop::option::of(value).prop_map(Option::unwrap_or_default).boxed()
+    }
+}
+
+/// Returns a strategy generating texts with the given format and length.
+fn formatted(
+    format: Format,
+    length: usize,
+    chars: Chars,
+) -> BoxedStrategy<String> {
+    let (chars, size) = match (format, chars) {
+        (Format::Alphanumeric(size), Chars::Any) => ("[ -~äöüßÄÖÜ]", size),
+        (Format::Alphanumeric(size), Chars::Plausible) => ("[A-Z0-9]", size),
+        (Format::Alpha(size), Chars::Any) => ("[ A-Za-zäöüßÄÖÜ]", size),
+        (Format::Alpha(size), Chars::Plausible) => ("[A-Z]", size),
+        (Format::Numeric(size), _) => ("[0-9]", size),
+    };
+    let pattern = match size {
+        Size::Exactly => format!("{}{{{}}}", chars, length),
+        Size::AtMost => {
+            format!("{}{{1,{}}}", chars, length.clamp(1, MAX_LENGTH))
+        }
+    };
+    string_regex(&pattern).unwrap().boxed()
+}
+
+/// Returns a strategy generating decimals with the given number of
+/// digits, e.g. `12.5`.
+fn decimal(
+    format: Format,
+    length: usize,
+    chars: Chars,
+) -> BoxedStrategy<String> {
+    formatted(format, length, chars)
+        .prop_flat_map(|digits| {
+            let len = digits.len();
+            (Just(digits), 0..len)
+        })
+        .prop_map(|(digits, at)| match at {
+            0 => digits,
+            at => format!("{}.{}", &digits[..at], &digits[at..]),
+        })
+        .boxed()
+}
+
+/// Returns the given values without the empty ones at the end.
+fn trimmed(mut values: Vec<String>) -> Vec<String> {
+    while values.last().is_some_and(|value| value.is_empty()) {
+        values.pop();
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::description::Interchange;
+    use crate::mig::testing::{example, interchange};
+    use crate::mig::DecodeOptions;
+
+    /// Decodes the given input and checks, that it is encoded back into
+    /// the same input.
+    fn roundtrip(desc: Interchange, input: &str) -> Result<(), TestCaseError> {
+        let options = DecodeOptions::default();
+        let decoded = mig::decode(vec![desc], &mut input.as_bytes(), &options);
+        prop_assert!(decoded.is_ok(), "{:?}", decoded.err());
+        prop_assert_eq!(mig::encode(&decoded.unwrap()), input);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip_aperak(input in interchange(&fixtures::aperak())) {
+            roundtrip(fixtures::aperak(), &input)?;
+        }
+
+        #[test]
+        fn test_roundtrip_example(input in example(&fixtures::aperak())) {
+            roundtrip(fixtures::aperak(), &input)?;
+        }
+
+        #[test]
+        fn test_roundtrip_contrl(input in interchange(&fixtures::contrl())) {
+            roundtrip(fixtures::contrl(), &input)?;
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Editing of decoded interchanges, e.g. to fix a rejected interchange
+//! and send it again.
+//!
+//! Segments are addressed by their position in the interchange, starting
+//! with 0 for UNB, like in errors. After every edit, the positions are
+//! renumbered and the counters in UNT and UNZ recomputed.
+use std::fmt;
+
+use crate::mig::decode::value::{
+    self, Interchange, Matched, Segment, Segmentgroup,
+};
+use crate::mig::description as desc;
+use crate::mig::either::Either;
+use crate::mig::encode;
+use crate::mig::error::{SegmentError, SyntaxError};
+use crate::mig::transform::{recount, Counter};
+
+/// The error returned, if an edit would make an interchange invalid.
+#[derive(Debug)]
+pub enum EditError {
+    /// There is no segment, segment group or data element at the given
+    /// position.
+    NotFound,
+    /// The inserted segment does not conform to its description.
+    InvalidSegment(SegmentError),
+    /// The value does not conform to the description of its data element.
+    InvalidValue(Vec<SyntaxError>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NotFound => write!(f, "Nothing found at the position"),
+            EditError::InvalidSegment(_) => {
+                write!(f, "The segment does not conform to its description")
+            }
+            EditError::InvalidValue(_) => {
+                write!(f, "The value does not conform to its description")
+            }
+        }
+    }
+}
+
+impl Interchange {
+    /// Inserts a segment given in EDIFACT with the default UNA, e.g.
+    /// `FTX+AAO+++Text'`, before the segment at the given position and in
+    /// the same segment group. The segment is checked against the given
+    /// description.
+    pub fn insert_segment(
+        &mut self,
+        pos: usize,
+        desc: &desc::Segment,
+        text: &str,
+    ) -> Result<(), EditError> {
+        let path = find(&self.segments, &|segment| segment.index == pos, &[])
+            .ok_or(EditError::NotFound)?;
+        let text = if text.ends_with('\'') {
+            text.to_string()
+        } else {
+            format!("{}'", text)
+        };
+        let segment = value::match_text(pos, desc, &text)
+            .map_err(EditError::InvalidSegment)?;
+
+        let (last, parents) = path.split_last().ok_or(EditError::NotFound)?;
+        values_at(&mut self.segments, parents)
+            .insert(*last, Either::Right(segment));
+        self.recount();
+        Ok(())
+    }
+
+    /// Replaces the value of the data element at the given positions of
+    /// the segment, starting at 0 and not counting the tag. The value is
+    /// not escaped and has to conform to the description of the data
+    /// element. An empty value removes it, if it is not required.
+    pub fn replace_value(
+        &mut self,
+        pos: usize,
+        element: usize,
+        component: usize,
+        value: &str,
+    ) -> Result<(), EditError> {
+        let path = find(&self.segments, &|segment| segment.index == pos, &[])
+            .ok_or(EditError::NotFound)?;
+        let segment = segment_at(&mut self.segments, &path)
+            .ok_or(EditError::NotFound)?;
+        let data_element = segment
+            .elements
+            .iter_mut()
+            .find_map(|e| match e {
+                Either::Left(composite) if composite.index == element => {
+                    composite
+                        .elements
+                        .iter_mut()
+                        .find(|e| e.index == component)
+                }
+                Either::Right(e) if e.index == element && component == 0 => {
+                    Some(e)
+                }
+                _ => None,
+            })
+            .ok_or(EditError::NotFound)?;
+
+        let errors = value::check_value(&data_element.description, value);
+        if !errors.is_empty() {
+            return Err(EditError::InvalidValue(errors));
+        }
+        data_element.value = if value.is_empty() {
+            None
+        } else {
+            Some(Matched::Text(value.to_string()))
+        };
+        segment.raw = encode::encode_segment(segment) + "'";
+        self.recount();
+        Ok(())
+    }
+
+    /// Removes the innermost segment group starting with the segment at
+    /// the given position and returns it. Groups, which become empty, are
+    /// removed as well.
+    pub fn remove_group(
+        &mut self,
+        pos: usize,
+    ) -> Result<Segmentgroup, EditError> {
+        let path =
+            find_group(&self.segments, pos, &[]).ok_or(EditError::NotFound)?;
+        let removed = remove(&mut self.segments, &path);
+        self.recount();
+        match removed {
+            Some(Either::Left(group)) => Ok(group),
+            _ => Err(EditError::NotFound),
+        }
+    }
+
+    fn recount(&mut self) {
+        recount(&mut self.segments, &mut Counter::default());
+    }
+}
+
+/// Returns the indices leading to the first segment, for which the given
+/// predicate is true.
+fn find(
+    values: &[Either<Segmentgroup, Segment>],
+    predicate: &impl Fn(&Segment) -> bool,
+    path: &[usize],
+) -> Option<Vec<usize>> {
+    for (i, value) in values.iter().enumerate() {
+        let path = [path, &[i]].concat();
+        match value {
+            Either::Left(group) => {
+                if let Some(path) = find(&group.segments, predicate, &path) {
+                    return Some(path);
+                }
+            }
+            Either::Right(segment) if predicate(segment) => return Some(path),
+            Either::Right(_) => {}
+        }
+    }
+    None
+}
+
+/// Returns the indices leading to the innermost group, which starts with
+/// the segment at the given position.
+fn find_group(
+    values: &[Either<Segmentgroup, Segment>],
+    pos: usize,
+    path: &[usize],
+) -> Option<Vec<usize>> {
+    for (i, value) in values.iter().enumerate() {
+        if let Either::Left(group) = value {
+            let path = [path, &[i]].concat();
+            let inner = find_group(&group.segments, pos, &path);
+            if inner.is_some() {
+                return inner;
+            } else if first_segment(&group.segments)
+                .is_some_and(|segment| segment.index == pos)
+            {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Returns the segments of the group at the given indices, which have
+/// been returned by [find] without the last one.
+fn values_at<'a>(
+    values: &'a mut Vec<Either<Segmentgroup, Segment>>,
+    path: &[usize],
+) -> &'a mut Vec<Either<Segmentgroup, Segment>> {
+    let Some((first, rest)) = path.split_first() else {
+        return values;
+    };
+    match &mut values[*first] {
+        Either::Left(group) => values_at(&mut group.segments, rest),
+        Either::Right(_) => unreachable!("Only groups contain segments"),
+    }
+}
+
+fn first_segment(
+    values: &[Either<Segmentgroup, Segment>],
+) -> Option<&Segment> {
+    match values.first()? {
+        Either::Left(group) => first_segment(&group.segments),
+        Either::Right(segment) => Some(segment),
+    }
+}
+
+fn segment_at<'a>(
+    values: &'a mut [Either<Segmentgroup, Segment>],
+    path: &[usize],
+) -> Option<&'a mut Segment> {
+    let (first, rest) = path.split_first()?;
+    match values.get_mut(*first)? {
+        Either::Left(group) => segment_at(&mut group.segments, rest),
+        Either::Right(segment) => Some(segment),
+    }
+}
+
+/// Removes the value at the given indices and the groups, which become
+/// empty by it.
+fn remove(
+    values: &mut Vec<Either<Segmentgroup, Segment>>,
+    path: &[usize],
+) -> Option<Either<Segmentgroup, Segment>> {
+    let (first, rest) = path.split_first()?;
+    if rest.is_empty() {
+        return Some(values.remove(*first));
+    }
+    let group = match values.get_mut(*first)? {
+        Either::Left(group) => group,
+        Either::Right(_) => return None,
+    };
+    let removed = remove(&mut group.segments, rest);
+    if group.segments.is_empty() {
+        values.remove(*first);
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::description::{Interchange, Segment};
+    use crate::mig::either::Either;
+    use crate::mig::transform::EditError;
+    use crate::mig::DecodeOptions;
+
+    /// Returns the description of the first FTX in SG4.
+    fn ftx(desc: &Interchange) -> &Segment {
+        let sg4 = desc.message.segments.iter().find_map(|s| match s {
+            Either::Left(group) if group.label == "SG4" => Some(group),
+            _ => None,
+        });
+        match &sg4.unwrap().segments[1] {
+            Either::Right(segment) => segment,
+            Either::Left(_) => panic!("Expected FTX"),
+        }
+    }
+
+    #[test]
+    fn test_edit() {
+        let desc = fixtures::aperak();
+        let options = DecodeOptions::default();
+        let mut interchange = mig::decode(
+            vec![desc.clone()],
+            &mut fixtures::APERAK.as_bytes(),
+            &options,
+        )
+        .unwrap();
+
+        interchange
+            .insert_segment(7, ftx(&desc), "FTX+ABO+++Geprüft")
+            .unwrap();
+        interchange.replace_value(2, 1, 0, "Neu?").unwrap();
+        assert!(matches!(
+            interchange.replace_value(4, 0, 0, "ABCD"),
+            Err(EditError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            interchange.insert_segment(99, ftx(&desc), "FTX+ABO+++X'"),
+            Err(EditError::NotFound)
+        ));
+        assert!(matches!(
+            interchange.insert_segment(7, ftx(&desc), "DTM+137'"),
+            Err(EditError::InvalidSegment(_))
+        ));
+        let expected = fixtures::APERAK
+            .replace("53ff5de4caab4ea18abafab5e6036991", "Neu??")
+            .replace("FTX+Z02", "FTX+ABO+++Geprüft'FTX+Z02")
+            .replace("UNT+8", "UNT+9");
+        assert_eq!(mig::encode(&interchange), expected);
+
+        let group = interchange.remove_group(6).unwrap();
+        assert_eq!(group.label(), "SG4");
+        assert!(mig::encode(&interchange).ends_with(
+            "NAD+MR+9904590000002::293'UNT+6+1'UNZ+1+C3AAAAAAAAHKLC'"
+        ));
+    }
+}
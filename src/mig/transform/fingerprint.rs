@@ -0,0 +1,89 @@
+//! Fingerprints of interchanges, e.g. to detect an interchange sent
+//! again with a new envelope.
+//!
+//! The fingerprint is computed over the [canonicalized](super::canonicalize)
+//! segments, so it does not depend on separators and layout. The control
+//! references and counters and the creation date in UNB are excluded, as
+//! they change with every transmission.
+use crate::mig::decode::value::{Interchange, Segment};
+use crate::mig::either::Either;
+use crate::mig::encode;
+use crate::mig::transform::canonicalize;
+
+/// The labels of the data elements excluded from fingerprints: the date
+/// and time of preparation in UNB, the interchange and message
+/// references and the counters in UNT and UNZ.
+const EXCLUDED: [&str; 6] = ["0017", "0019", "0020", "0036", "0062", "0074"];
+
+/// The offset basis and prime of the 64 bit FNV-1a hash.
+const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl Interchange {
+    /// Returns a fingerprint of the business content of this interchange.
+    ///
+    /// Two interchanges with the same content have the same fingerprint,
+    /// even if they have been sent with different references. The
+    /// fingerprint is stable across versions and platforms, so it can be
+    /// stored, e.g. in an index.
+    pub fn fingerprint(&self) -> u64 {
+        let canonical = canonicalize(self);
+        let mut hash = OFFSET;
+        for segment in canonical.all_segments() {
+            for byte in encode::encode_segment(&content(segment)).bytes() {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+            }
+            // Segments are separated, so their contents cannot run into
+            // each other.
+            hash = (hash ^ u64::from(b'\'')).wrapping_mul(PRIME);
+        }
+        hash
+    }
+}
+
+/// Returns the given segment without the excluded data elements.
+fn content(segment: &Segment) -> Segment {
+    let excluded = |label: &str| EXCLUDED.contains(&label);
+    let mut result = segment.clone();
+    for element in &mut result.elements {
+        if let Either::Left(composite) = element {
+            composite.elements.retain(|e| !excluded(&e.description.label));
+        }
+    }
+    result.elements.retain(|element| match element {
+        Either::Left(composite) => !composite.elements.is_empty(),
+        Either::Right(data_element) => {
+            !excluded(&data_element.description.label)
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::{self, DecodeOptions};
+
+    #[test]
+    fn test_fingerprint() {
+        let desc = fixtures::aperak();
+        let options = DecodeOptions::default();
+        let fingerprint = |input: &str| {
+            let known = vec![desc.clone()];
+            mig::decode(known, &mut input.as_bytes(), &options)
+                .unwrap()
+                .fingerprint()
+        };
+
+        let resent = fixtures::APERAK
+            .replace("200307:0705+C3AAAAAAAAHKLC", "200308:1200+D4BBBB")
+            .replace("UNH+1", "UNH+7")
+            .replace("UNT+8+1", "UNT+8+7")
+            .replace("UNZ+1+C3AAAAAAAAHKLC", "UNZ+1+D4BBBB")
+            .replace("'", "'\n");
+        assert_eq!(fingerprint(fixtures::APERAK), fingerprint(&resent));
+
+        let changed = fixtures::APERAK.replace("FTX+Z02+++10", "FTX+Z02+++11");
+        assert_ne!(fingerprint(fixtures::APERAK), fingerprint(&changed));
+    }
+}
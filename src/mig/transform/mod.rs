@@ -0,0 +1,521 @@
+//! This module transforms decoded interchanges, e.g. to share them
+//! without the personal data of customers or to cut them down to a
+//! minimal message reproducing an issue.
+//!
+//! [anonymize] replaces the values of personal data elements with
+//! pseudonyms. A pseudonym has the length of its value and keeps digits,
+//! upper and lower case letters as such, so the interchange still
+//! conforms to its description. Equal values get equal pseudonyms, so
+//! references between segments and interchanges are kept.
+//!
+//! [filter] removes segments and segment groups and recomputes the
+//! counters in UNT and UNZ, so the interchange stays valid. The same is
+//! done by the methods editing an [Interchange], like
+//! [Interchange::insert_segment].
+//!
+//! [canonicalize] brings interchanges into a canonical form, so two
+//! interchanges with the same content compare and hash equally, when
+//! encoded. [Interchange::fingerprint] hashes the canonical form without
+//! the envelope, e.g. to detect an interchange sent again.
+mod edit;
+mod fingerprint;
+
+use crate::mig::decode::parser::value::UNA;
+use crate::mig::decode::value::{
+    DataElement, Interchange, Matched, Segment, Segmentgroup, Span,
+};
+use crate::mig::description::Format;
+use crate::mig::either::Either;
+use crate::mig::encode;
+
+pub use crate::mig::transform::edit::EditError;
+
+/// The labels of the data elements containing names, addresses and
+/// contacts of parties, e.g. in NAD or CTA.
+const LABELS: [&str; 10] = [
+    "3036", "3042", "3124", "3148", "3164", "3207", "3229", "3251", "3412",
+    "3413",
+];
+
+/// Parts of the names of data elements containing meter numbers.
+const NAMES: [&str; 3] = ["Zählernummer", "Gerätenummer", "Zählpunkt"];
+
+/// `AnonymizeOptions` define, which data elements are personal and how
+/// pseudonyms are derived.
+///
+/// The default options replace names, addresses, contacts and meter
+/// numbers.
+#[derive(Debug, Clone)]
+pub struct AnonymizeOptions {
+    /// The labels of personal data elements, e.g. `3036`.
+    pub labels: Vec<String>,
+    /// Parts of the names of personal data elements, e.g. `Zählernummer`.
+    pub names: Vec<String>,
+    /// A secret mixed into every pseudonym. Without one, short values
+    /// like postal codes can be restored by trying all of them.
+    pub key: String,
+}
+
+impl Default for AnonymizeOptions {
+    fn default() -> Self {
+        AnonymizeOptions {
+            labels: LABELS.iter().map(|label| label.to_string()).collect(),
+            names: NAMES.iter().map(|name| name.to_string()).collect(),
+            key: String::new(),
+        }
+    }
+}
+
+impl AnonymizeOptions {
+    fn is_personal(&self, element: &DataElement) -> bool {
+        let desc = &element.description;
+        self.labels.contains(&desc.label)
+            || self.names.iter().any(|name| desc.name.contains(name.as_str()))
+    }
+}
+
+/// Replaces the values of all personal data elements of the given
+/// interchange with pseudonyms and returns the number of replaced values.
+///
+/// The raw text of changed segments is replaced as well and segments,
+/// which could not be parsed, are removed, since they may contain
+/// personal data. Spans still refer to the original input.
+pub fn anonymize(
+    interchange: &mut Interchange,
+    options: &AnonymizeOptions,
+) -> usize {
+    interchange.raw_segments.clear();
+    anonymize_segments(&mut interchange.segments, options)
+}
+
+fn anonymize_segments(
+    values: &mut [Either<Segmentgroup, Segment>],
+    options: &AnonymizeOptions,
+) -> usize {
+    let mut count = 0;
+    for value in values {
+        count += match value {
+            Either::Left(group) => {
+                anonymize_segments(&mut group.segments, options)
+            }
+            Either::Right(segment) => anonymize_segment(segment, options),
+        };
+    }
+    count
+}
+
+fn anonymize_segment(
+    segment: &mut Segment,
+    options: &AnonymizeOptions,
+) -> usize {
+    let mut count = 0;
+    for element in &mut segment.elements {
+        let data_elements = match element {
+            Either::Left(composite) => composite.elements.iter_mut().collect(),
+            Either::Right(data_element) => vec![data_element],
+        };
+        for data_element in data_elements {
+            if !options.is_personal(data_element) {
+                continue;
+            }
+            if let Some(Matched::Text(text)) = &mut data_element.value {
+                *text = pseudonym(&options.key, text);
+                count += 1;
+            }
+        }
+    }
+
+    if count > 0 {
+        segment.raw = encode::encode_segment(segment) + "'";
+    }
+    count
+}
+
+/// Returns the given interchange without the segments and segment groups,
+/// for which `keep` returns false. A removed group is removed with all
+/// of its segments.
+///
+/// The service segments UNB, UNH, UNT and UNZ are always kept and the
+/// numbers of segments in UNT and messages in UNZ are recomputed. The
+/// positions of the remaining segments are renumbered.
+pub fn filter(
+    interchange: &Interchange,
+    mut keep: impl FnMut(Either<&Segmentgroup, &Segment>) -> bool,
+) -> Interchange {
+    let mut result = Interchange {
+        segments: filter_segments(&interchange.segments, &mut keep),
+        test: interchange.test,
+        raw_segments: interchange.raw_segments.clone(),
+        una: interchange.una,
+        warnings: interchange.warnings.clone(),
+        security: interchange.security.clone(),
+    };
+    recount(&mut result.segments, &mut Counter::default());
+    result
+}
+
+fn filter_segments(
+    values: &[Either<Segmentgroup, Segment>],
+    keep: &mut impl FnMut(Either<&Segmentgroup, &Segment>) -> bool,
+) -> Vec<Either<Segmentgroup, Segment>> {
+    let mut result = vec![];
+    for value in values {
+        match value {
+            Either::Left(group) if keep(Either::Left(group)) => {
+                let segments = filter_segments(&group.segments, keep);
+                // A group without segments cannot be encoded.
+                if !segments.is_empty() {
+                    result.push(Either::Left(Segmentgroup {
+                        segments,
+                        ..group.clone()
+                    }))
+                }
+            }
+            Either::Right(segment)
+                if is_service_segment(segment)
+                    || keep(Either::Right(segment)) =>
+            {
+                result.push(Either::Right(segment.clone()))
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+fn is_service_segment(segment: &Segment) -> bool {
+    ["UNB", "UNH", "UNT", "UNZ"].contains(&segment.tag.as_str())
+}
+
+/// The `Counter` counts segments and messages while recounting.
+#[derive(Debug, Default)]
+struct Counter {
+    position: usize,
+    segments: usize,
+    messages: usize,
+}
+
+fn recount(
+    values: &mut [Either<Segmentgroup, Segment>],
+    counter: &mut Counter,
+) {
+    for value in values {
+        let segment = match value {
+            Either::Left(group) => {
+                recount(&mut group.segments, counter);
+                continue;
+            }
+            Either::Right(segment) => segment,
+        };
+        segment.index = counter.position;
+        counter.position += 1;
+
+        match segment.tag.as_str() {
+            "UNH" => {
+                counter.segments = 1;
+                counter.messages += 1;
+            }
+            "UNT" => {
+                counter.segments += 1;
+                set_count(segment, counter.segments);
+            }
+            "UNZ" => set_count(segment, counter.messages),
+            _ => counter.segments += 1,
+        }
+    }
+}
+
+/// Sets the count in the first data element of the given segment.
+fn set_count(segment: &mut Segment, count: usize) {
+    let first =
+        segment.elements.iter_mut().find_map(|element| match element {
+            Either::Right(data_element) if data_element.index == 0 => {
+                Some(data_element)
+            }
+            _ => None,
+        });
+    if let Some(data_element) = first {
+        data_element.value = Some(Matched::Text(count.to_string()));
+        segment.raw = encode::encode_segment(segment) + "'";
+    }
+}
+
+/// Returns the given interchange in a canonical form: empty data
+/// elements and composites are removed, numeric values use `.` as the
+/// decimal character and the raw text of every segment is encoded with
+/// the default separators, without any line breaks. The repetition
+/// separator is `*`, if the interchange has one. The spans refer to the
+/// [encoded](crate::mig::encode()) interchange.
+///
+/// Two interchanges with the same content have the same encoding after
+/// being canonicalized, regardless of their separators and layout.
+pub fn canonicalize(interchange: &Interchange) -> Interchange {
+    let mut result = interchange.clone();
+    result.una = UNA::default();
+    if interchange.una.repetition_sep().is_some() {
+        result.una.reserved = '*';
+    }
+    let mut offset = result.una.to_string().len();
+    canonicalize_segments(&mut result.segments, &result.una, &mut offset);
+    result
+}
+
+fn canonicalize_segments(
+    values: &mut [Either<Segmentgroup, Segment>],
+    una: &UNA,
+    offset: &mut usize,
+) {
+    for value in values {
+        match value {
+            Either::Left(group) => {
+                canonicalize_segments(&mut group.segments, una, offset)
+            }
+            Either::Right(segment) => {
+                canonicalize_segment(segment, una, offset)
+            }
+        }
+    }
+}
+
+fn canonicalize_segment(segment: &mut Segment, una: &UNA, offset: &mut usize) {
+    for element in &mut segment.elements {
+        if let Either::Left(composite) = element {
+            composite.elements.retain(|e| !is_empty(e));
+        }
+    }
+    segment.elements.retain(|element| match element {
+        Either::Left(composite) => !composite.elements.is_empty(),
+        Either::Right(data_element) => !is_empty(data_element),
+    });
+    for element in &mut segment.elements {
+        let data_elements = match element {
+            Either::Left(composite) => composite.elements.iter_mut().collect(),
+            Either::Right(data_element) => vec![data_element],
+        };
+        for data_element in data_elements {
+            let numeric =
+                matches!(data_element.description.format, Format::Numeric(_));
+            if let Some(Matched::Text(text)) = &mut data_element.value {
+                if numeric {
+                    *text = text.replace(',', ".");
+                }
+            }
+        }
+    }
+    segment.raw = encode::encode_segment_with(segment, una) + "'";
+
+    // The spans are computed like the segment is encoded, every element
+    // being preceded by a separator for each position skipped. A further
+    // occurrence of a repeated element is preceded by the repetition
+    // separator instead.
+    let start = *offset;
+    *offset += segment.tag.len();
+    let mut next = 0;
+    for element in &mut segment.elements {
+        match element {
+            Either::Left(composite) => {
+                *offset += (composite.index + 1 - next).max(1);
+                next = composite.index + 1;
+                let start = *offset;
+                let mut next = 0;
+                for data_element in &mut composite.elements {
+                    // Components are separated, but not preceded.
+                    *offset +=
+                        data_element.index - next + usize::from(next > 0);
+                    next = data_element.index + 1;
+                    respan(data_element, una, offset);
+                }
+                composite.span = Span { start, end: *offset };
+            }
+            Either::Right(data_element) => {
+                *offset += (data_element.index + 1 - next).max(1);
+                next = data_element.index + 1;
+                respan(data_element, una, offset);
+            }
+        }
+    }
+    *offset += 1;
+    segment.span = Span { start, end: *offset };
+}
+
+fn is_empty(data_element: &DataElement) -> bool {
+    match &data_element.value {
+        None => true,
+        Some(Matched::Text(text)) => text.is_empty(),
+        Some(_) => false,
+    }
+}
+
+/// Sets the span of the given data element starting at the offset and
+/// moves the offset behind its encoded value.
+fn respan(data_element: &mut DataElement, una: &UNA, offset: &mut usize) {
+    let len = match &data_element.value {
+        Some(Matched::Text(text)) => una.escape_value(text).len(),
+        Some(Matched::Int(int)) => int.to_string().len(),
+        Some(Matched::Decimal(decimal)) => decimal.to_string().len(),
+        None => 0,
+    };
+    data_element.span = Span { start: *offset, end: *offset + len };
+    *offset += len;
+}
+
+/// Returns the pseudonym of the given value, which replaces every digit
+/// and letter with another one of the same kind. Other characters, like
+/// spaces or the decimal character, are kept.
+pub fn pseudonym(key: &str, value: &str) -> String {
+    let mut state = fnv1a(key.as_bytes(), fnv1a(value.as_bytes(), OFFSET));
+    let mut next = |n: u8| {
+        // A linear congruential generator, so pseudonyms are the same on
+        // every platform and with every version of Rust.
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        ((state >> 33) % u64::from(n)) as u8
+    };
+    value
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => char::from(b'0' + next(10)),
+            'A'..='Z' => char::from(b'A' + next(26)),
+            'a'..='z' => char::from(b'a' + next(26)),
+            c if c.is_alphabetic() => char::from(b'a' + next(26)),
+            c => c,
+        })
+        .collect()
+}
+
+const OFFSET: u64 = 0xcbf29ce484222325;
+
+fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::parser::value::UNA;
+    use crate::mig::either::Either;
+    use crate::mig::transform::{
+        anonymize, canonicalize, filter, pseudonym, AnonymizeOptions,
+    };
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_pseudonym() {
+        let value = pseudonym("", "Am Markt 12a");
+        assert_eq!(value.len(), "Am Markt 12a".len());
+        assert_eq!(value.chars().nth(2), Some(' '));
+        assert!(value[9..11].chars().all(|c| c.is_ascii_digit()));
+        assert!(value[..2].chars().all(|c| c.is_ascii_alphabetic()));
+        assert_eq!(value, pseudonym("", "Am Markt 12a"));
+        assert_ne!(value, pseudonym("secret", "Am Markt 12a"));
+    }
+
+    #[test]
+    fn test_anonymize() {
+        let desc = fixtures::aperak();
+        let input = &fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'\
+             NAD+MS+9900467000000::293'\
+             CTA+IC+:Erika Mustermann'\
+             COM+erika@example.com:EM'\
+             NAD+MR+9904590000002::293'\
+             ERC+Z29'",
+        );
+        let options = DecodeOptions::default();
+        let mut interchange =
+            mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap();
+
+        let count = anonymize(&mut interchange, &AnonymizeOptions::default());
+        assert_eq!(count, 2);
+        let output = mig::encode(&interchange);
+        assert!(!output.contains("Mustermann"));
+        assert!(!output.contains("erika"));
+        assert!(output.contains("NAD+MS+9900467000000::293'"));
+        assert_eq!(output.len(), input.len());
+
+        let raw: Vec<&str> =
+            interchange.all_segments().iter().map(|s| s.raw()).collect();
+        assert!(!raw.concat().contains("Mustermann"));
+    }
+
+    #[test]
+    fn test_filter() {
+        let desc = fixtures::aperak();
+        let input = &fixtures::aperak_with(
+            "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+             DTM+137:202003070705:203'\
+             NAD+MS+9900467000000::293'\
+             NAD+MR+9904590000002::293'\
+             ERC+Z29'\
+             FTX+ABO+++LOC17251283352734'\
+             RFF+ACW:V1583553607732'\
+             RFF+AGO:9904590000002ORD1583553607706'\
+             FTX+AAO+++LOC Datenelement 3225 ungültiger Wert'\
+             FTX+Z02+++10'",
+        );
+        let options = DecodeOptions::default();
+        let interchange =
+            mig::decode(vec![desc], &mut input.as_bytes(), &options).unwrap();
+
+        let filtered = filter(&interchange, |value| match value {
+            Either::Left(group) => group.label() != "SG5",
+            Either::Right(segment) => segment.tag() != "FTX",
+        });
+        assert_eq!(
+            mig::encode(&filtered),
+            fixtures::aperak_with(
+                "BGM+313+53ff5de4caab4ea18abafab5e6036991'\
+                 DTM+137:202003070705:203'\
+                 NAD+MS+9900467000000::293'\
+                 NAD+MR+9904590000002::293'\
+                 ERC+Z29'",
+            )
+        );
+        let last = filtered.all_segments().last().map(|s| s.index);
+        assert_eq!(last, Some(8));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let desc = fixtures::aperak();
+        let input = fixtures::APERAK_ESCAPED;
+        let other = "UNA*|.? '\r\n\
+                     UNB|UNOC*3|9900467000000*500|9904590000002*500\
+                     |200307*0705|C3AAAAAAAAHKLC'\r\n\
+                     UNH|1|APERAK*D*07B*UN*2.1d'\r\n\
+                     BGM|313|53ff5de4caab4ea18abafab5e6036991'\r\n\
+                     DTM|137*202003070705*203**'\r\n\
+                     NAD|MS|9900467000000**293'\r\n\
+                     NAD|MR|9904590000002**293'\r\n\
+                     ERC|Z29'\r\n\
+                     FTX|Z02|||1?'0'\r\n\
+                     UNT|8|1||'\r\n\
+                     UNZ|1|C3AAAAAAAAHKLC'\r\n";
+        let decode = |input: &str| {
+            let options = DecodeOptions::lenient();
+            let known = vec![desc.clone()];
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap()
+        };
+
+        let first = canonicalize(&decode(input));
+        let second = canonicalize(&decode(other));
+        let output = mig::encode(&second);
+        assert_eq!(mig::encode(&first), output);
+        assert_eq!(output, input);
+        for segment in second.all_segments() {
+            let span = segment.span();
+            assert_eq!(&output[span.start..span.end], segment.raw());
+            for element in segment.data_elements() {
+                let span = element.span();
+                let value =
+                    UNA::default().escape_value(element.text().unwrap());
+                assert_eq!(output[span.start..span.end], value);
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Validation of interchanges in bounded memory, e.g. of an archive with
+//! interchanges of several gigabytes.
+//!
+//! The interchange is decoded with [DecodeEvents] and every segment is
+//! dropped right after it has been checked, so only the open segment
+//! groups and the errors found are kept. The counters and references in
+//! UNT and UNZ are checked like with [decode](crate::mig::decode()).
+use std::io::BufRead;
+
+use crate::mig::decode::compiled::CompiledMig;
+use crate::mig::decode::counters::Counters;
+use crate::mig::decode::events::{DecodeEvents, Event};
+use crate::mig::decode::options::DecodeOptions;
+use crate::mig::decode::Error;
+use crate::mig::error::{
+    InterchangeError, MessageError, SegmentError, ServiceSegmentError,
+};
+
+/// Validates the given input with the given description like
+/// [decode](crate::mig::decode()), but without returning the decoded
+/// interchange. At most [DecodeOptions::max_errors] segment errors are
+/// kept.
+pub fn validate<R: BufRead>(
+    mig: &CompiledMig,
+    input: R,
+    options: &DecodeOptions,
+) -> Result<(), Error> {
+    let mut state = State::default();
+    for event in DecodeEvents::new(mig, input, options) {
+        match event? {
+            Event::Segment(segment) => {
+                let text = |label| segment.text_of(label).map(String::from);
+                match segment.tag() {
+                    "UNB" => state.counters.unb(text("0020")),
+                    "UNH" => {
+                        state.counters.unh(segment.index, text("0062"));
+                        state.unh(segment.index);
+                    }
+                    "UNT" => {
+                        let error = state.counters.unt(
+                            segment.index,
+                            text("0074"),
+                            text("0062"),
+                        );
+                        if let Some(message) = state.messages.last_mut() {
+                            message.service_segment_error = error;
+                        }
+                    }
+                    "UNZ" => {
+                        state.unz =
+                            state.counters.unz(text("0036"), text("0020"))
+                    }
+                    _ => {}
+                }
+            }
+            Event::Error(error)
+                if !options.reached_max_errors(state.errors) =>
+            {
+                state.error(error)
+            }
+            _ => {}
+        }
+    }
+
+    if !state.head.is_empty() {
+        // The UNH follows UNB at 1, if there is none.
+        state.unh(1);
+    }
+    let mut message_errors: Vec<MessageError> = state
+        .messages
+        .into_iter()
+        .filter(|error| {
+            error.service_segment_error.is_some()
+                || !error.segment_errors.is_empty()
+        })
+        .collect();
+    if message_errors.is_empty() && state.unz.is_none() {
+        return Ok(());
+    }
+    if let Some(max) = options.max_identical_errors {
+        for error in &mut message_errors {
+            error.limit_identical(max);
+        }
+    }
+    Err(Error::Mig(InterchangeError {
+        pos: 0,
+        service_segment_error: state.unz,
+        message_errors,
+        una: None,
+    }))
+}
+
+/// The `State` of the validation, which does not depend on the size of
+/// the input, apart from the errors.
+#[derive(Default)]
+struct State {
+    counters: Counters,
+    /// The errors of every message, including the ones without errors.
+    /// The position of a message is the one of its UNH.
+    messages: Vec<MessageError>,
+    /// The errors before the first UNH, e.g. of UNB, which are kept with
+    /// the first message.
+    head: Vec<SegmentError>,
+    /// The number of segment errors kept.
+    errors: usize,
+    unz: Option<ServiceSegmentError>,
+}
+
+impl State {
+    fn unh(&mut self, index: usize) {
+        self.messages.push(MessageError {
+            pos: index,
+            service_segment_error: None,
+            segment_errors: std::mem::take(&mut self.head),
+            repeated: vec![],
+        });
+    }
+
+    /// Keeps the given error with the current message.
+    fn error(&mut self, error: SegmentError) {
+        self.errors += 1;
+        match self.messages.last_mut() {
+            Some(message) => message.segment_errors.push(error),
+            None => self.head.push(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::decode::Error;
+    use crate::mig::validate::bounded::validate;
+    use crate::mig::{self, CompiledMig, DecodeOptions};
+
+    #[test]
+    fn test_validate() {
+        let desc = fixtures::aperak();
+        let mig = CompiledMig::from(desc);
+        let options = DecodeOptions::default();
+        assert!(validate(&mig, fixtures::APERAK_ESCAPED.as_bytes(), &options)
+            .is_ok());
+        // The security segments are counted in UNT.
+        let secured = fixtures::APERAK_ESCAPED
+            .replace("'BGM", "'USH+7+1'BGM")
+            .replace("'UNT+8", "'UST+1+2'UNT+10");
+        assert!(validate(&mig, secured.as_bytes(), &options).is_ok());
+        let messages = fixtures::APERAK_ESCAPED.replace(
+            "UNZ+1+C",
+            "UNH+2+APERAK:D:07B:UN:2.1d'BGM+313+DOC2'UNT+3+2'UNZ+2+C",
+        );
+        assert!(validate(&mig, messages.as_bytes(), &options).is_ok());
+
+        let input = fixtures::APERAK_ESCAPED
+            .replace("UNT+8", "UNT+7")
+            .replace("UNZ+1+C", "UNZ+1+D");
+        let Err(Error::Mig(error)) =
+            validate(&mig, input.as_bytes(), &options)
+        else {
+            panic!("Expected an invalid interchange");
+        };
+        let codes: Vec<u64> = error
+            .syntax_errors()
+            .iter()
+            .map(|error| error.get_code())
+            .collect();
+        assert_eq!(codes, vec![28, 29]);
+    }
+
+    #[test]
+    fn test_validate_like_decode() {
+        let mig = CompiledMig::from(fixtures::aperak());
+        let options = DecodeOptions::default();
+        let codes = |result: Result<_, Error>| match result {
+            Ok(_) => vec![],
+            Err(Error::Mig(error)) => error
+                .syntax_errors()
+                .iter()
+                .map(|error| error.get_code())
+                .collect(),
+            Err(error) => panic!("Unexpected error {:?}", error),
+        };
+        for (from, to, expected) in [
+            ("UNT+8", "UNT+8", vec![]),
+            ("UNT+8", "UNT+5", vec![29]),
+            ("UNT+8+1", "UNT+8+2", vec![28]),
+            ("UNZ+1+C", "UNZ+2+C", vec![29]),
+            ("UNZ+1+C", "UNZ+1+D", vec![28]),
+        ] {
+            let input = fixtures::APERAK_ESCAPED.replace(from, to);
+            let decoded = mig::decode(&mig, &mut input.as_bytes(), &options);
+            let validated = validate(&mig, input.as_bytes(), &options);
+            assert_eq!(codes(decoded.map(|_| ())), expected, "{}", to);
+            assert_eq!(codes(validated), expected, "{}", to);
+        }
+    }
+}
@@ -0,0 +1,547 @@
+//! Validation of date and time values in DTM segments.
+//!
+//! A DTM segment contains a qualifier (2005), which defines the meaning
+//! of the date, the value itself (2380) and a format code (2379), which
+//! defines how the value is structured. The following format codes are
+//! checked, since they are the ones used within edi@energy.
+//!
+//! - `102`: CCYYMMDD
+//! - `203`: CCYYMMDDHHMM
+//! - `303`: CCYYMMDDHHMMZZZ, where ZZZ is the offset to UTC
+//! - `602`: CCYY
+//! - `610`: CCYYMM
+//! - `719`: CCYYMMDDHHMMCCYYMMDDHHMM, an interval
+//!
+//! The offset of a `303` value may be `+00` (UTC), `+01` (MEZ) or `+02`
+//! (MESZ). MEZ and MESZ are only valid while they are in effect in
+//! German civil time, which follows the rules of `Europe/Berlin` in the
+//! tz database of [chrono_tz]. A time of 24:00 is only valid for the end
+//! of a period.
+//!
+//! Timestamps and dates with their offsets are converted to the types of
+//! [chrono] with [Timestamp::to_naive] and [DateTime::to_chrono].
+use std::fmt;
+
+use chrono::{
+    Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset,
+    TimeZone as _,
+};
+use chrono_tz::{Europe::Berlin, Tz};
+
+use crate::mig::decode::value::Interchange;
+
+/// Qualifiers denoting the end of a period, which may use 24:00.
+const PERIOD_END_QUALIFIERS: [&str; 2] = ["93", "164"];
+
+/// A `DateTimeError` describes an invalid value of a DTM segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimeError {
+    /// The index of the DTM segment in the interchange.
+    pub pos: usize,
+    pub qualifier: String,
+    pub value: String,
+    pub format: String,
+    pub reason: Reason,
+}
+
+impl fmt::Display for DateTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: DTM+{}:{}:{} {}",
+            self.pos, self.qualifier, self.value, self.format, self.reason
+        )
+    }
+}
+
+/// The `Reason`, why a date or time value is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The value is not structured as required by the format code.
+    Malformed,
+    /// The date does not exist, e.g. the 30th of February.
+    InvalidDate,
+    /// The time of day does not exist, e.g. 25:61.
+    InvalidTime,
+    /// 24:00 has been used for a qualifier, which is not the end of a
+    /// period.
+    MidnightNotAllowed,
+    /// The offset is neither UTC, MEZ nor MESZ.
+    InvalidOffset,
+    /// The offset of MEZ or MESZ has been used at a time, where the other
+    /// one is in effect.
+    OffsetNotInEffect,
+    /// The start of an interval is after its end.
+    InvalidInterval,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Reason::Malformed => "does not match its format",
+            Reason::InvalidDate => "contains a date, which does not exist",
+            Reason::InvalidTime => "contains a time, which does not exist",
+            Reason::MidnightNotAllowed => {
+                "uses 24:00, but is not the end of a period"
+            }
+            Reason::InvalidOffset => {
+                "uses an offset other than UTC, MEZ or MESZ"
+            }
+            Reason::OffsetNotInEffect => {
+                "uses an offset, which is not in effect at that time"
+            }
+            Reason::InvalidInterval => "starts after it ends",
+        };
+        f.write_str(text)
+    }
+}
+
+/// A date and time of day as contained in DTM segments.
+///
+/// The time of day may be 24:00, denoting the end of a day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+}
+
+impl Timestamp {
+    /// Returns the minutes since 1970-01-01 00:00 of this timestamp.
+    pub fn minutes(&self) -> i64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        (days * 24 + self.hour) * 60 + self.minute
+    }
+
+    /// Returns the timestamp for the given minutes since 1970-01-01 00:00.
+    pub fn from_minutes(minutes: i64) -> Timestamp {
+        from_minutes(minutes)
+    }
+
+    /// Returns this timestamp as a date and time of chrono, where 24:00 is
+    /// the start of the next day, or `None`, if the date does not exist.
+    pub fn to_naive(&self) -> Option<NaiveDateTime> {
+        let date = NaiveDate::from_ymd_opt(
+            self.year.try_into().ok()?,
+            self.month.try_into().ok()?,
+            self.day.try_into().ok()?,
+        )?;
+        let minutes = Duration::minutes(self.hour * 60 + self.minute);
+        date.and_hms_opt(0, 0, 0)?.checked_add_signed(minutes)
+    }
+
+    fn from_naive(naive: &NaiveDateTime) -> Timestamp {
+        from_minutes(naive.and_utc().timestamp().div_euclid(60))
+    }
+}
+
+/// A `Date` of a DTM segment, as structured by its format code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Date {
+    /// A day, format code `102`.
+    Day { year: i64, month: i64, day: i64 },
+    /// A date and time of day without an offset, format code `203`.
+    Timestamp(Timestamp),
+    /// A date and time of day with its offset to UTC, format code `303`.
+    DateTime(DateTime),
+}
+
+/// Parses the given value with the given format code, or returns `None`,
+/// if the format code is not one of `102`, `203` and `303`. A time of day
+/// of 24:00 is accepted, since the qualifier is not known.
+pub fn parse(value: &str, format: &str) -> Option<Result<Date, Reason>> {
+    let result = match format {
+        "102" => parse_date(value)
+            .map(|(year, month, day)| Date::Day { year, month, day }),
+        "203" => parse_timestamp(value, true).map(Date::Timestamp),
+        "303" => parse_timestamp_with_offset(value, true).map(|utc| {
+            // The offset has been checked by parsing.
+            let offset = value[13..].parse().unwrap_or_default();
+            Date::DateTime(DateTime {
+                local: from_minutes(utc.minutes() + offset * 60),
+                offset,
+            })
+        }),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Validates the values of all DTM segments of the given interchange.
+///
+/// Values with a format code not listed in the module documentation are
+/// not checked.
+pub fn validate(interchange: &Interchange) -> Vec<DateTimeError> {
+    let mut errors = vec![];
+    for segment in interchange.all_segments() {
+        if segment.tag != "DTM" {
+            continue;
+        }
+
+        let (qualifier, value, format) = match (
+            segment.text_of("2005"),
+            segment.text_of("2380"),
+            segment.text_of("2379"),
+        ) {
+            (Some(qualifier), Some(value), Some(format)) => {
+                (qualifier, value, format)
+            }
+            _ => continue,
+        };
+
+        if let Err(reason) = check(qualifier, value, format) {
+            errors.push(DateTimeError {
+                pos: segment.index,
+                qualifier: qualifier.to_string(),
+                value: value.to_string(),
+                format: format.to_string(),
+                reason,
+            });
+        }
+    }
+    errors
+}
+
+/// Checks a single value with the given qualifier and format code.
+pub fn check(
+    qualifier: &str,
+    value: &str,
+    format: &str,
+) -> Result<(), Reason> {
+    if !value.is_ascii() {
+        return Err(Reason::Malformed);
+    }
+
+    let is_period_end = PERIOD_END_QUALIFIERS.contains(&qualifier);
+    match format {
+        "102" => parse_date(value).map(|_| ()),
+        "203" => parse_timestamp(value, is_period_end).map(|_| ()),
+        "303" => parse_timestamp_with_offset(value, is_period_end).map(|_| ()),
+        "602" => number(value, 4).map(|_| ()),
+        "610" => {
+            if value.len() != 6 {
+                return Err(Reason::Malformed);
+            }
+            let month = number(&value[4..], 2)?;
+            number(&value[..4], 4)?;
+            if (1..=12).contains(&month) {
+                Ok(())
+            } else {
+                Err(Reason::InvalidDate)
+            }
+        }
+        "719" => {
+            if value.len() != 24 {
+                return Err(Reason::Malformed);
+            }
+            let start = parse_timestamp(&value[..12], false)?;
+            let end = parse_timestamp(&value[12..], true)?;
+            if start.minutes() <= end.minutes() {
+                Ok(())
+            } else {
+                Err(Reason::InvalidInterval)
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parses a date in the format CCYYMMDD.
+pub fn parse_date(value: &str) -> Result<(i64, i64, i64), Reason> {
+    if value.len() != 8 || !value.is_ascii() {
+        return Err(Reason::Malformed);
+    }
+    let year = number(&value[..4], 4)?;
+    let month = number(&value[4..6], 2)?;
+    let day = number(&value[6..], 2)?;
+    if (1..=12).contains(&month)
+        && (1..=days_in_month(year, month)).contains(&day)
+    {
+        Ok((year, month, day))
+    } else {
+        Err(Reason::InvalidDate)
+    }
+}
+
+/// Parses a timestamp in the format CCYYMMDDHHMM. If `allow_midnight` is
+/// set, 24:00 is accepted as time of day.
+pub fn parse_timestamp(
+    value: &str,
+    allow_midnight: bool,
+) -> Result<Timestamp, Reason> {
+    if value.len() != 12 || !value.is_ascii() {
+        return Err(Reason::Malformed);
+    }
+    let (year, month, day) = parse_date(&value[..8])?;
+    let hour = number(&value[8..10], 2)?;
+    let minute = number(&value[10..], 2)?;
+    if hour == 24 && minute == 0 && !allow_midnight {
+        Err(Reason::MidnightNotAllowed)
+    } else if (hour < 24 || (hour == 24 && minute == 0)) && minute < 60 {
+        Ok(Timestamp { year, month, day, hour, minute })
+    } else {
+        Err(Reason::InvalidTime)
+    }
+}
+
+/// Parses a timestamp in the format CCYYMMDDHHMMZZZ and checks, that the
+/// offset is in effect in German civil time. Returns the timestamp in
+/// UTC.
+pub fn parse_timestamp_with_offset(
+    value: &str,
+    allow_midnight: bool,
+) -> Result<Timestamp, Reason> {
+    if value.len() != 15 || !value.is_ascii() {
+        return Err(Reason::Malformed);
+    }
+    let local = parse_timestamp(&value[..12], allow_midnight)?;
+    let offset = match &value[12..] {
+        "+00" => 0,
+        "+01" => 1,
+        "+02" => 2,
+        _ => return Err(Reason::InvalidOffset),
+    };
+
+    let utc = from_minutes(local.minutes() - offset * 60);
+    match offset {
+        1 if is_summer_time(&utc) => Err(Reason::OffsetNotInEffect),
+        2 if !is_summer_time(&utc) => Err(Reason::OffsetNotInEffect),
+        _ => Ok(utc),
+    }
+}
+
+/// Returns, whether MESZ is in effect at the given UTC timestamp.
+pub fn is_summer_time(utc: &Timestamp) -> bool {
+    TimeZone::German.offset_at(utc) == 2
+}
+
+/// The `TimeZone` a date and time without an offset is given in, e.g.
+/// the creation date in UNB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZone {
+    Utc,
+    /// German civil time, i.e. MEZ or MESZ, which is used within
+    /// edi@energy.
+    German,
+}
+
+impl TimeZone {
+    /// Returns the time zone of the tz database, e.g. `Europe/Berlin`.
+    pub fn tz(&self) -> Tz {
+        match self {
+            TimeZone::Utc => Tz::UTC,
+            TimeZone::German => Berlin,
+        }
+    }
+
+    /// Returns the offset in hours in effect at the given UTC timestamp.
+    fn offset_at(&self, utc: &Timestamp) -> i64 {
+        let utc = utc.to_naive().unwrap_or_default();
+        hours(self.tz().offset_from_utc_datetime(&utc))
+    }
+
+    /// Returns the given UTC timestamp in this time zone.
+    pub fn from_utc(&self, utc: &Timestamp) -> DateTime {
+        let offset = self.offset_at(utc);
+        DateTime {
+            local: from_minutes(utc.minutes() + offset * 60),
+            offset,
+        }
+    }
+
+    /// Returns the given local timestamp with the offset in effect in
+    /// this time zone. The hour repeated, when MESZ ends, is taken as
+    /// MESZ and the hour skipped, when it starts, as MEZ.
+    pub fn from_local(&self, local: &Timestamp) -> DateTime {
+        let naive = local.to_naive().unwrap_or_default();
+        let offset = match self.tz().offset_from_local_datetime(&naive) {
+            LocalResult::Single(offset) => hours(offset),
+            LocalResult::Ambiguous(first, second) => {
+                hours(first).max(hours(second))
+            }
+            // The offset before the skipped hour is still in effect.
+            LocalResult::None => {
+                let before = naive - Duration::hours(1);
+                let offset = self.tz().offset_from_local_datetime(&before);
+                offset.earliest().map(hours).unwrap_or_default()
+            }
+        };
+        DateTime { local: *local, offset }
+    }
+
+    /// Returns the given date and time of chrono in this time zone.
+    pub fn from_chrono<Z: chrono::TimeZone>(
+        &self,
+        date_time: &chrono::DateTime<Z>,
+    ) -> DateTime {
+        self.from_utc(&Timestamp::from_naive(&date_time.naive_utc()))
+    }
+}
+
+/// Returns the offset to UTC in hours.
+fn hours(offset: impl Offset) -> i64 {
+    i64::from(offset.fix().local_minus_utc()) / 3600
+}
+
+/// A `DateTime` is a local timestamp with its offset to UTC in hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub local: Timestamp,
+    pub offset: i64,
+}
+
+impl DateTime {
+    /// Returns this date and time in UTC.
+    pub fn utc(&self) -> Timestamp {
+        from_minutes(self.local.minutes() - self.offset * 60)
+    }
+
+    /// Returns this date and time as a date and time of chrono with its
+    /// offset, or `None`, if the date does not exist.
+    pub fn to_chrono(&self) -> Option<chrono::DateTime<FixedOffset>> {
+        let offset =
+            FixedOffset::east_opt((self.offset * 3600).try_into().ok()?)?;
+        self.local.to_naive()?.and_local_timezone(offset).single()
+    }
+}
+
+/// Parses a number with exactly `len` ASCII digits.
+fn number(value: &str, len: usize) -> Result<i64, Reason> {
+    if value.len() == len && value.chars().all(|c| c.is_ascii_digit()) {
+        value.parse().map_err(|_| Reason::Malformed)
+    } else {
+        Err(Reason::Malformed)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days since 1970-01-01 of the given date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Returns the timestamp for the given minutes since 1970-01-01 00:00.
+fn from_minutes(minutes: i64) -> Timestamp {
+    let days = minutes.div_euclid(24 * 60);
+    let minute_of_day = minutes.rem_euclid(24 * 60);
+    let era = (days + 719468).div_euclid(146097);
+    let day_of_era = (days + 719468).rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    Timestamp {
+        year,
+        month,
+        day,
+        hour: minute_of_day / 60,
+        minute: minute_of_day % 60,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::validate::dtm::{check, Reason, TimeZone, Timestamp};
+
+    #[test]
+    fn test_check_formats() {
+        assert_eq!(check("137", "20200307", "102"), Ok(()));
+        assert_eq!(check("137", "20210229", "102"), Err(Reason::InvalidDate));
+        assert_eq!(check("137", "202003070705", "203"), Ok(()));
+        assert_eq!(
+            check("137", "202003072561", "203"),
+            Err(Reason::InvalidTime)
+        );
+        assert_eq!(check("137", "2020030707", "203"), Err(Reason::Malformed));
+        assert_eq!(
+            check("Z01", "202001012400", "203"),
+            Err(Reason::MidnightNotAllowed)
+        );
+        assert_eq!(check("164", "202001012400", "203"), Ok(()));
+        assert_eq!(
+            check("Z01", "201609160400201609090400", "719"),
+            Err(Reason::InvalidInterval)
+        );
+    }
+
+    #[test]
+    fn test_check_offsets() {
+        assert_eq!(check("163", "202001011200+00", "303"), Ok(()));
+        assert_eq!(check("163", "202001011200+01", "303"), Ok(()));
+        assert_eq!(
+            check("163", "202001011200+02", "303"),
+            Err(Reason::OffsetNotInEffect)
+        );
+        assert_eq!(check("163", "202007011200+02", "303"), Ok(()));
+        assert_eq!(
+            check("163", "202007011200+01", "303"),
+            Err(Reason::OffsetNotInEffect)
+        );
+        assert_eq!(
+            check("163", "202007011200+05", "303"),
+            Err(Reason::InvalidOffset)
+        );
+        // The change to MESZ in 2020 happened on 2020-03-29 at 02:00 MEZ.
+        assert_eq!(check("163", "202003290159+01", "303"), Ok(()));
+        assert_eq!(check("163", "202003290300+02", "303"), Ok(()));
+    }
+
+    #[test]
+    fn test_time_zone() {
+        let at = |day, hour, minute| Timestamp {
+            year: 2020,
+            month: 10,
+            day,
+            hour,
+            minute,
+        };
+        let german = TimeZone::German;
+        assert_eq!(german.from_local(&at(24, 12, 0)).utc(), at(24, 10, 0));
+        assert_eq!(german.from_local(&at(25, 12, 0)).utc(), at(25, 11, 0));
+        // MESZ ended on 2020-10-25 at 03:00 MESZ.
+        assert_eq!(german.from_local(&at(25, 2, 30)).offset, 2);
+        assert_eq!(german.from_utc(&at(25, 0, 59)).local, at(25, 2, 59));
+        assert_eq!(german.from_utc(&at(25, 1, 0)).local, at(25, 2, 0));
+        assert_eq!(TimeZone::Utc.from_local(&at(25, 2, 30)).offset, 0);
+    }
+
+    #[test]
+    fn test_chrono() {
+        let german = TimeZone::German;
+        let local =
+            Timestamp { year: 2020, month: 3, day: 7, hour: 7, minute: 5 };
+        let date_time = german.from_local(&local).to_chrono().unwrap();
+        assert_eq!(date_time.to_rfc3339(), "2020-03-07T07:05:00+01:00");
+        assert_eq!(german.from_chrono(&date_time), german.from_local(&local));
+
+        let end = Timestamp { hour: 24, minute: 0, ..local };
+        let naive = end.to_naive().unwrap();
+        assert_eq!(naive.to_string(), "2020-03-08 00:00:00");
+        assert_eq!(german.tz().name(), "Europe/Berlin");
+    }
+}
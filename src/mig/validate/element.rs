@@ -0,0 +1,152 @@
+//! Custom checks of single values, which are run while decoding, e.g.
+//! whether an IBAN in a REMADV is valid.
+//!
+//! An [ElementValidator] is registered in [Validators] by the label or
+//! the name of the data elements it checks and is run after the format
+//! of a value has been checked. It gets the value as it is decoded, e.g.
+//! with the decimals normalized to `.`, see
+//! [DecodeOptions::normalize_decimals](crate::mig::DecodeOptions):
+//!
+//! ```
+//! # use edifact::mig::description::DataElement;
+//! # use edifact::mig::error::SyntaxError;
+//! # use edifact::mig::DecodeOptions;
+//! let mut options = DecodeOptions::default();
+//! options.validators.register("3194", |_: &DataElement, value: &str| {
+//!     if value.starts_with("DE") {
+//!         vec![]
+//!     } else {
+//!         vec![SyntaxError::invalid_value()]
+//!     }
+//! });
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use crate::mig::description::DataElement;
+use crate::mig::error::SyntaxError;
+
+/// An `ElementValidator` checks the values of data elements and returns
+/// the errors found, which fail the segment containing the value.
+///
+/// Empty values are not checked, since the status of the data element
+/// already defines, whether they are allowed.
+pub trait ElementValidator: Send + Sync {
+    fn validate(&self, desc: &DataElement, value: &str) -> Vec<SyntaxError>;
+}
+
+impl<F> ElementValidator for F
+where
+    F: Fn(&DataElement, &str) -> Vec<SyntaxError> + Send + Sync,
+{
+    fn validate(&self, desc: &DataElement, value: &str) -> Vec<SyntaxError> {
+        self(desc, value)
+    }
+}
+
+/// The `Validators` passed with [DecodeOptions](crate::mig::DecodeOptions)
+/// by the label or name of the data elements they check.
+#[derive(Clone, Default)]
+pub struct Validators {
+    validators: HashMap<String, Vec<Arc<dyn ElementValidator>>>,
+}
+
+impl Validators {
+    /// Registers the validator for the data elements with the given label,
+    /// e.g. `3194`, or name. Several validators may be registered for the
+    /// same data element.
+    pub fn register(
+        &mut self,
+        key: impl Into<String>,
+        validator: impl ElementValidator + 'static,
+    ) {
+        self.validators
+            .entry(key.into())
+            .or_default()
+            .push(Arc::new(validator));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    /// Returns the errors of all validators registered for the given data
+    /// element.
+    pub(crate) fn validate(
+        &self,
+        desc: &DataElement,
+        value: &str,
+    ) -> Vec<SyntaxError> {
+        let by_label = self.validators.get(&desc.label);
+        let by_name = self.validators.get(&desc.name);
+        by_label
+            .into_iter()
+            .chain(by_name.filter(|_| desc.name != desc.label))
+            .flatten()
+            .flat_map(|validator| validator.validate(desc, value))
+            .collect()
+    }
+}
+
+impl Debug for Validators {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.validators.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::Error;
+    use crate::mig::description::DataElement;
+    use crate::mig::error::SyntaxError;
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_validators() {
+        let desc = fixtures::aperak();
+        let mut options = DecodeOptions::default();
+        options.validators.register("3039", |_: &DataElement, id: &str| {
+            if id.starts_with("99") {
+                vec![]
+            } else {
+                vec![SyntaxError::invalid_value()]
+            }
+        });
+        let decode = |input: &str| {
+            mig::decode(vec![desc.clone()], &mut input.as_bytes(), &options)
+        };
+        assert!(decode(fixtures::APERAK_ESCAPED).is_ok());
+
+        let input = fixtures::APERAK_ESCAPED.replace("NAD+MR+99", "NAD+MR+40");
+        let Err(Error::Mig(error)) = decode(&input) else {
+            panic!("Expected an invalid interchange");
+        };
+        let codes: Vec<u64> =
+            error.syntax_errors().iter().map(|e| e.get_code()).collect();
+        assert_eq!(codes, vec![SyntaxError::invalid_value().get_code()]);
+    }
+
+    #[test]
+    fn test_validators_normalized() {
+        let mut options = DecodeOptions::default();
+        options.validators.register(
+            "5004",
+            |_: &DataElement, amount: &str| match amount.parse::<f64>() {
+                Ok(_) => vec![],
+                Err(_) => vec![SyntaxError::invalid_value()],
+            },
+        );
+        let message =
+            fixtures::INVOIC.replace(".00'", ",00'").replace(".50'", ",50'");
+        let input = fixtures::interchange(&[&message]).replacen(
+            "UNA:+.? '",
+            "UNA:+,? '",
+            1,
+        );
+        let known = vec![fixtures::invoic()];
+        assert!(mig::decode(known, &mut input.as_bytes(), &options).is_ok());
+    }
+}
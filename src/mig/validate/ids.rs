@@ -0,0 +1,295 @@
+//! Validation of identifiers used in the German energy market.
+//!
+//! The identifiers are recognized by the name of their data element or
+//! by the qualifier or code list given in the same segment.
+//!
+//! - OBIS codes identify a measured value, e.g. `1-1:1.8.0`. They are
+//!   used in PIA segments with code list `SRW`.
+//! - A Marktlokations-ID (MaLo-ID) consists of 10 digits and a check
+//!   digit, e.g. `41373559241`. It is used in LOC+Z16 and LOC+172.
+//! - A Messlokations-ID (MeLo-ID) consists of a country code, the
+//!   6-digit number of the network operator, a 5-digit postal code and
+//!   a 20 character meter point number. It is used in LOC+Z17 and
+//!   LOC+172.
+//! - Market partner IDs are 13 digits. GS1 numbers (code list 9 in NAD,
+//!   14 in UNB) have a check digit, BDEW (293, 500) numbers start with
+//!   `99` and DVGW (332, 502) numbers with `98`.
+use crate::mig::decode::value::{DataElement, Segment};
+use crate::mig::either::Either;
+use crate::mig::error::{CompositeError, DataElementError, SyntaxError};
+
+/// The `Kind` of an identifier, which can be validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Obis,
+    MarktlokationsId,
+    MesslokationsId,
+    MarketPartnerId,
+}
+
+impl Kind {
+    /// Returns all kinds of identifiers.
+    pub fn all() -> Vec<Kind> {
+        vec![
+            Kind::Obis,
+            Kind::MarktlokationsId,
+            Kind::MesslokationsId,
+            Kind::MarketPartnerId,
+        ]
+    }
+}
+
+/// Returns, whether the given value is a valid OBIS code.
+///
+/// An OBIS code has the structure `A-B:C.D.E` with an optional `*F`, where
+/// each group is a number up to 255. C may also be one of the letters C,
+/// F, L or P.
+pub fn is_valid_obis(value: &str) -> bool {
+    let is_group = |group: &str| {
+        !group.is_empty()
+            && group.len() <= 3
+            && group.chars().all(|c| c.is_ascii_digit())
+            && group.parse::<u16>().is_ok_and(|n| n <= 255)
+    };
+
+    let (ab, cdef) = match value.split_once(':') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let (cde, f) = match cdef.split_once('*') {
+        Some((cde, f)) => (cde, Some(f)),
+        None => (cdef, None),
+    };
+
+    let ab: Vec<&str> = ab.split('-').collect();
+    let cde: Vec<&str> = cde.split('.').collect();
+    match (ab.as_slice(), cde.as_slice()) {
+        ([a, b], [c, d, e]) => {
+            is_group(a)
+                && is_group(b)
+                && (is_group(c) || ["C", "F", "L", "P"].contains(c))
+                && is_group(d)
+                && is_group(e)
+                && f.is_none_or(is_group)
+        }
+        _ => false,
+    }
+}
+
+/// Returns, whether the given value is a valid Marktlokations-ID.
+pub fn is_valid_malo_id(value: &str) -> bool {
+    let digits: Vec<u32> =
+        value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if value.len() != 11 || digits.len() != 11 || digits[0] == 0 {
+        return false;
+    }
+
+    // Digits at odd positions are added, digits at even positions are
+    // added and doubled.
+    let sum: u32 = digits[..10]
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { *d } else { d * 2 })
+        .sum();
+    (10 - sum % 10) % 10 == digits[10]
+}
+
+/// Returns, whether the given value is structured like a
+/// Messlokations-ID.
+pub fn is_valid_melo_id(value: &str) -> bool {
+    value.len() == 33
+        && value.is_ascii()
+        && value[..2].chars().all(|c| c.is_ascii_uppercase())
+        && value[2..13].chars().all(|c| c.is_ascii_digit())
+        && value[13..]
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Returns, whether the given value is a valid market partner ID issued
+/// by the organization with the given code. Values of unknown
+/// organizations are considered valid.
+pub fn is_valid_market_partner_id(value: &str, code_list: &str) -> bool {
+    let is_13_digits =
+        value.len() == 13 && value.chars().all(|c| c.is_ascii_digit());
+    match code_list {
+        "9" | "14" => is_13_digits && has_gs1_check_digit(value),
+        "293" | "500" => is_13_digits && value.starts_with("99"),
+        "332" | "502" => is_13_digits && value.starts_with("98"),
+        _ => true,
+    }
+}
+
+/// Returns, whether the last digit of the given digits is a valid GS1
+/// check digit.
+fn has_gs1_check_digit(value: &str) -> bool {
+    let digits: Vec<u32> =
+        value.chars().filter_map(|c| c.to_digit(10)).collect();
+    match digits.split_last() {
+        Some((check, rest)) => {
+            // Weights alternate between 3 and 1, starting from the right.
+            let sum: u32 = rest
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+                .sum();
+            (10 - sum % 10) % 10 == *check
+        }
+        None => false,
+    }
+}
+
+/// Checks all identifiers of the enabled kinds in the given segment and
+/// returns an invalid value error for each invalid one.
+pub(crate) fn check_segment(
+    segment: &Segment,
+    kinds: &[Kind],
+) -> Vec<Either<CompositeError, DataElementError>> {
+    let mut errors = vec![];
+    for element in &segment.elements {
+        match element {
+            Either::Left(composite) => {
+                let invalid: Vec<DataElementError> = composite
+                    .elements
+                    .iter()
+                    .filter(|e| {
+                        !is_valid(segment, &composite.elements, e, kinds)
+                    })
+                    .map(|e| {
+                        DataElementError::new(
+                            e.index,
+                            SyntaxError::invalid_value(),
+                        )
+                    })
+                    .collect();
+                if !invalid.is_empty() {
+                    errors.push(Either::Left(CompositeError {
+                        pos: composite.index,
+                        syntax_error: None,
+                        errors: invalid,
+                    }));
+                }
+            }
+            Either::Right(data_element) => {
+                let siblings = std::slice::from_ref(data_element);
+                if !is_valid(segment, siblings, data_element, kinds) {
+                    errors.push(Either::Right(DataElementError::new(
+                        data_element.index,
+                        SyntaxError::invalid_value(),
+                    )));
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// Returns, whether the given data element is valid, if it contains an
+/// identifier of one of the enabled kinds.
+fn is_valid(
+    segment: &Segment,
+    siblings: &[DataElement],
+    element: &DataElement,
+    kinds: &[Kind],
+) -> bool {
+    let value = match element.text() {
+        Some(value) => value,
+        None => return true,
+    };
+    let sibling = |label: &str| {
+        siblings
+            .iter()
+            .find(|e| e.description.label == label)
+            .and_then(|e| e.text())
+    };
+
+    match kind_of(segment, element, value) {
+        Some(kind) if kinds.contains(&kind) => match kind {
+            Kind::Obis => is_valid_obis(value),
+            Kind::MarktlokationsId => is_valid_malo_id(value),
+            Kind::MesslokationsId => is_valid_melo_id(value),
+            Kind::MarketPartnerId => match sibling("3055").or(sibling("0007"))
+            {
+                Some(code_list) => {
+                    is_valid_market_partner_id(value, code_list)
+                }
+                None => true,
+            },
+        },
+        _ => true,
+    }
+}
+
+/// Returns the kind of identifier the given data element contains, if
+/// any.
+fn kind_of(
+    segment: &Segment,
+    element: &DataElement,
+    value: &str,
+) -> Option<Kind> {
+    let name = element.description.name.as_str();
+    let label = element.description.label.as_str();
+    if name.contains("OBIS") {
+        return Some(Kind::Obis);
+    } else if name.contains("Marktlokation") {
+        return Some(Kind::MarktlokationsId);
+    } else if name.contains("Messlokation") {
+        return Some(Kind::MesslokationsId);
+    }
+
+    match (segment.tag.as_str(), label) {
+        ("PIA", "7140") if segment.text_of("7143") == Some("SRW") => {
+            Some(Kind::Obis)
+        }
+        ("LOC", "3225") => match segment.text_of("3227") {
+            Some("Z16") => Some(Kind::MarktlokationsId),
+            Some("Z17") => Some(Kind::MesslokationsId),
+            Some("172") if value.len() == 11 => Some(Kind::MarktlokationsId),
+            Some("172") => Some(Kind::MesslokationsId),
+            _ => None,
+        },
+        ("NAD", "3039") | ("UNB", "0004") | ("UNB", "0010") => {
+            Some(Kind::MarketPartnerId)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::validate::ids::{
+        is_valid_malo_id, is_valid_market_partner_id, is_valid_melo_id,
+        is_valid_obis,
+    };
+
+    #[test]
+    fn test_is_valid_obis() {
+        assert!(is_valid_obis("1-1:1.8.0"));
+        assert!(is_valid_obis("1-0:1.8.0*255"));
+        assert!(is_valid_obis("7-0:C.0.0"));
+        assert!(!is_valid_obis("1-1:1.8"));
+        assert!(!is_valid_obis("1-1:1.8.256"));
+    }
+
+    #[test]
+    fn test_is_valid_malo_id() {
+        assert!(is_valid_malo_id("41373559241"));
+        assert!(!is_valid_malo_id("41373559242"));
+        assert!(!is_valid_malo_id("4137355924"));
+    }
+
+    #[test]
+    fn test_is_valid_melo_id() {
+        assert!(is_valid_melo_id("DE00056266802AO6G56M11SN51G21M24S"));
+        assert!(!is_valid_melo_id("DE00056266802AO6G56M11SN51G21M24"));
+    }
+
+    #[test]
+    fn test_is_valid_market_partner_id() {
+        assert!(is_valid_market_partner_id("4078901000029", "9"));
+        assert!(!is_valid_market_partner_id("4078901000028", "9"));
+        assert!(is_valid_market_partner_id("9900467000000", "293"));
+        assert!(!is_valid_market_partner_id("9800467000000", "293"));
+    }
+}
@@ -0,0 +1,14 @@
+//! Semantic validation of decoded interchanges.
+//!
+//! Decoding only checks, whether a message conforms to the syntax given
+//! by its description, e.g. the length and characters of a value. The
+//! validators in this module are opt-in and check, whether the values
+//! make sense, e.g. whether a date actually exists. Custom checks can be
+//! added with an [ElementValidator](element::ElementValidator).
+
+pub mod bounded;
+pub mod dtm;
+pub mod element;
+pub mod ids;
+pub mod mscons;
+pub mod summary;
@@ -0,0 +1,241 @@
+//! Plausibility validation of MSCONS time series.
+//!
+//! An MSCONS message transmits series of measured quantities for
+//! metering locations. A location (LOC) may declare the transmitted
+//! period with DTM+163 (start) and DTM+164 (end). Each series starts
+//! with a LIN segment and consists of QTY segments, each followed by a
+//! DTM+163 and DTM+164 defining the interval of the quantity.
+//!
+//! The intervals of a series have to be consecutive without gaps or
+//! overlaps, all have the same length and cover the declared period.
+use std::fmt;
+
+use crate::mig::decode::value::{Interchange, Segment};
+use crate::mig::validate::dtm;
+
+/// An `MsconsError` describes an implausible quantity or interval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsconsError {
+    /// The index of the QTY segment or the DTM segment of the period.
+    pub pos: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for MsconsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.pos, self.kind)
+    }
+}
+
+/// The kinds of implausibilities in a time series. Lengths are given in
+/// minutes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A quantity without the start or end of its interval.
+    MissingInterval,
+    /// The start or end of an interval is not a valid timestamp.
+    InvalidTimestamp(dtm::Reason),
+    /// The start of an interval is not before its end.
+    InvalidInterval,
+    /// There is a gap in minutes between an interval and its predecessor.
+    Gap(i64),
+    /// An interval overlaps with its predecessor by the given minutes.
+    Overlap(i64),
+    /// The interval does not have the same length as the first interval
+    /// of its series.
+    LengthMismatch { expected: i64, actual: i64 },
+    /// The series does not start or end with the declared period.
+    PeriodNotCovered,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::MissingInterval => {
+                write!(f, "quantity without start or end of its interval")
+            }
+            ErrorKind::InvalidTimestamp(reason) => {
+                write!(f, "invalid interval timestamp, which {}", reason)
+            }
+            ErrorKind::InvalidInterval => {
+                write!(f, "interval does not start before it ends")
+            }
+            ErrorKind::Gap(minutes) => {
+                write!(f, "gap of {} minutes before interval", minutes)
+            }
+            ErrorKind::Overlap(minutes) => {
+                write!(
+                    f,
+                    "overlap of {} minutes with previous interval",
+                    minutes
+                )
+            }
+            ErrorKind::LengthMismatch { expected, actual } => write!(
+                f,
+                "interval is {} minutes long, expected {}",
+                actual, expected
+            ),
+            ErrorKind::PeriodNotCovered => {
+                write!(f, "series does not cover the declared period")
+            }
+        }
+    }
+}
+
+/// The interval of a quantity or a declared period, in minutes since
+/// 1970-01-01 00:00 UTC.
+#[derive(Debug, Default)]
+struct Interval {
+    pos: usize,
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+/// A series of intervals, possibly within a declared period.
+#[derive(Debug, Default)]
+struct Series {
+    period: Option<(usize, i64, i64)>,
+    intervals: Vec<Interval>,
+}
+
+/// Validates the time series of the given MSCONS interchange.
+pub fn validate_mscons(interchange: &Interchange) -> Vec<MsconsError> {
+    let mut errors = vec![];
+    let mut period = Interval::default();
+    let mut series: Option<Series> = None;
+
+    for segment in interchange.all_segments() {
+        match segment.tag.as_str() {
+            "LOC" => {
+                if let Some(series) = series.take() {
+                    check_series(&series, &mut errors);
+                }
+                period =
+                    Interval { pos: segment.index, ..Interval::default() };
+            }
+            "LIN" => {
+                if let Some(series) = series.take() {
+                    check_series(&series, &mut errors);
+                }
+                let declared = match (period.start, period.end) {
+                    (Some(start), Some(end)) => Some((period.pos, start, end)),
+                    _ => None,
+                };
+                series = Some(Series { period: declared, intervals: vec![] });
+            }
+            "QTY" => {
+                if let Some(series) = series.as_mut() {
+                    series.intervals.push(Interval {
+                        pos: segment.index,
+                        ..Interval::default()
+                    });
+                }
+            }
+            "DTM" => {
+                let target = match series.as_mut() {
+                    Some(series) => match series.intervals.last_mut() {
+                        Some(interval) => interval,
+                        None => continue,
+                    },
+                    None => &mut period,
+                };
+                if let Err(error) = set_timestamp(segment, target) {
+                    errors.push(error);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(series) = series.take() {
+        check_series(&series, &mut errors);
+    }
+    errors
+}
+
+/// Sets the start or end of the given interval from a DTM+163 or
+/// DTM+164 segment. Other DTM segments are ignored.
+fn set_timestamp(
+    segment: &Segment,
+    interval: &mut Interval,
+) -> Result<(), MsconsError> {
+    let qualifier = segment.text_of("2005");
+    if qualifier != Some("163") && qualifier != Some("164") {
+        return Ok(());
+    }
+
+    let value = segment.text_of("2380").unwrap_or("");
+    let minutes = match segment.text_of("2379") {
+        Some("303") => dtm::parse_timestamp_with_offset(value, true),
+        _ => dtm::parse_timestamp(value, true),
+    }
+    .map(|timestamp| timestamp.minutes())
+    .map_err(|reason| MsconsError {
+        pos: segment.index,
+        kind: ErrorKind::InvalidTimestamp(reason),
+    })?;
+
+    if qualifier == Some("163") {
+        interval.start = Some(minutes);
+    } else {
+        interval.end = Some(minutes);
+    }
+    Ok(())
+}
+
+fn check_series(series: &Series, errors: &mut Vec<MsconsError>) {
+    let mut expected_length: Option<i64> = None;
+    let mut previous: Option<(i64, i64)> = None;
+    let mut first_start: Option<i64> = None;
+
+    for interval in &series.intervals {
+        let error = |kind| MsconsError { pos: interval.pos, kind };
+        let (start, end) = match (interval.start, interval.end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                errors.push(error(ErrorKind::MissingInterval));
+                continue;
+            }
+        };
+
+        if start >= end {
+            errors.push(error(ErrorKind::InvalidInterval));
+            continue;
+        }
+
+        let length = end - start;
+        match expected_length {
+            Some(expected) if expected != length => {
+                errors.push(error(ErrorKind::LengthMismatch {
+                    expected,
+                    actual: length,
+                }))
+            }
+            Some(_) => {}
+            None => expected_length = Some(length),
+        }
+
+        if let Some((_, previous_end)) = previous {
+            if start > previous_end {
+                errors.push(error(ErrorKind::Gap(start - previous_end)));
+            } else if start < previous_end {
+                errors.push(error(ErrorKind::Overlap(previous_end - start)));
+            }
+        }
+
+        first_start.get_or_insert(start);
+        previous = Some((start, end));
+    }
+
+    if let (
+        Some((pos, period_start, period_end)),
+        Some(start),
+        Some((_, end)),
+    ) = (series.period, first_start, previous)
+    {
+        if start != period_start || end != period_end {
+            errors
+                .push(MsconsError { pos, kind: ErrorKind::PeriodNotCovered });
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Aggregation of the results of validating many interchanges, e.g. to
+//! monitor the quality of an archive.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::mig::decode::Error;
+use crate::mig::error::{Language, SyntaxError};
+
+/// A `ValidationSummary` counts the valid and invalid interchanges and
+/// how often each syntax error occurred.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationSummary {
+    valid: usize,
+    invalid: usize,
+    unreadable: usize,
+    /// The names and number of occurrences by error code.
+    codes: BTreeMap<u64, (&'static str, usize)>,
+    language: Language,
+}
+
+impl ValidationSummary {
+    /// Create an empty summary.
+    pub fn new() -> Self {
+        ValidationSummary::default()
+    }
+
+    /// Create an empty summary, which names the errors in the given
+    /// language.
+    pub fn with_language(language: Language) -> Self {
+        ValidationSummary { language, ..ValidationSummary::default() }
+    }
+
+    /// Adds the result of decoding an interchange. Errors other than
+    /// syntax errors, e.g. one reading the input, make the interchange
+    /// unreadable.
+    pub fn add<T>(&mut self, result: &Result<T, Error>) {
+        match result {
+            Ok(_) => self.valid += 1,
+            Err(Error::Mig(error)) => {
+                self.invalid += 1;
+                for syntax_error in error.syntax_errors() {
+                    self.count(syntax_error, 1);
+                }
+                // Identical errors omitted from the error count as well.
+                let repeated =
+                    error.message_errors.iter().flat_map(|e| &e.repeated);
+                for repetition in repeated {
+                    for syntax_error in &repetition.syntax_errors {
+                        self.count(syntax_error, repetition.omitted);
+                    }
+                }
+            }
+            Err(_) => self.unreadable += 1,
+        }
+    }
+
+    fn count(&mut self, syntax_error: &SyntaxError, n: usize) {
+        let name = syntax_error.get_name_in(self.language);
+        let (_, count) =
+            self.codes.entry(syntax_error.get_code()).or_insert((name, 0));
+        *count += n;
+    }
+
+    pub fn valid(&self) -> usize {
+        self.valid
+    }
+
+    pub fn invalid(&self) -> usize {
+        self.invalid
+    }
+
+    pub fn unreadable(&self) -> usize {
+        self.unreadable
+    }
+
+    /// Returns the number of all syntax errors and unreadable
+    /// interchanges.
+    pub fn errors(&self) -> usize {
+        self.codes.values().map(|(_, count)| count).sum::<usize>()
+            + self.unreadable
+    }
+
+    /// Returns the error codes with their names and number of
+    /// occurrences, the most frequent first.
+    pub fn frequencies(&self) -> Vec<(u64, &'static str, usize)> {
+        let mut result: Vec<_> = self
+            .codes
+            .iter()
+            .map(|(code, (name, count))| (*code, *name, *count))
+            .collect();
+        result.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        result
+    }
+}
+
+impl fmt::Display for ValidationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} valid, {} invalid, {} unreadable",
+            self.valid, self.invalid, self.unreadable
+        )?;
+        if !self.codes.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:>5}  {:>6}  Error", "Code", "Count")?;
+            for (code, name, count) in self.frequencies() {
+                writeln!(f, "{:>5}  {:>6}  {}", code, count, name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig;
+    use crate::mig::decode::Error;
+    use crate::mig::error::Language;
+    use crate::mig::validate::summary::ValidationSummary;
+    use crate::mig::DecodeOptions;
+
+    #[test]
+    fn test_summary() {
+        let desc = fixtures::aperak();
+        let mut summary = ValidationSummary::new();
+        for input in ["UNA:+.? 'XYZ+1'ABC+2'", "UNA:+.? 'XYZ+1'", "UNB"] {
+            let known = vec![desc.clone()];
+            let options = DecodeOptions::default();
+            summary.add(&mig::decode(known, &mut input.as_bytes(), &options));
+        }
+        summary.add::<()>(&Err(Error::NoDescription));
+
+        assert_eq!(summary.invalid(), 2);
+        assert_eq!(summary.unreadable(), 2);
+        assert_eq!(summary.errors(), 5);
+        let codes: Vec<(u64, usize)> = summary
+            .frequencies()
+            .into_iter()
+            .map(|(code, _, count)| (code, count))
+            .collect();
+        assert_eq!(codes, vec![(15, 3)]);
+
+        let mut summary = ValidationSummary::with_language(Language::English);
+        let input = "UNA:+.? 'XYZ+1'";
+        let options = DecodeOptions::default();
+        summary.add(&mig::decode(vec![desc], &mut input.as_bytes(), &options));
+        assert_eq!(
+            summary.frequencies(),
+            vec![(15, "Not supported in this position", 1)]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Python bindings to decode, validate and encode messages.
+//!
+//! This module is only available with the `python` feature and builds
+//! the Python module `pyedifact`, e.g. with `maturin build`.
+//! Descriptions are passed as JSON, as generated by `edifact mig parse`.
+//! Decoded interchanges and errors are passed as dicts.
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+
+use crate::mig;
+use crate::mig::decode::{value, Error};
+use crate::mig::{description, DecodeOptions};
+
+create_exception!(
+    pyedifact,
+    DecodeError,
+    PyException,
+    "Raised, if an interchange does not match its description. The first \
+     argument contains the errors as a dict."
+);
+
+/// Decodes the given EDIFACT interchange with the given description and
+/// returns the decoded interchange.
+#[pyfunction]
+fn decode(py: Python<'_>, mig_json: &str, text: &str) -> PyResult<PyObject> {
+    match decode_with(mig_json, text)? {
+        Ok(interchange) => to_python(py, &interchange),
+        Err(error) => Err(DecodeError::new_err(to_python(py, &error)?)),
+    }
+}
+
+/// Validates the given EDIFACT interchange with the given description
+/// and returns the errors, or `None` if the interchange is valid.
+#[pyfunction]
+fn validate(py: Python<'_>, mig_json: &str, text: &str) -> PyResult<PyObject> {
+    match decode_with(mig_json, text)? {
+        Ok(_) => Ok(py.None()),
+        Err(error) => to_python(py, &error),
+    }
+}
+
+/// Encodes a decoded interchange, as returned by `decode`, into EDIFACT.
+#[pyfunction]
+fn encode(py: Python<'_>, interchange: PyObject) -> PyResult<String> {
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (interchange,))?
+        .extract()?;
+    let interchange: value::Interchange = serde_json::from_str(&json)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(mig::encode(&interchange))
+}
+
+#[pymodule]
+fn pyedifact(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("DecodeError", m.py().get_type::<DecodeError>())?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    Ok(())
+}
+
+/// Decodes the given interchange. Errors of the interchange itself are
+/// returned in the inner result, all others in the outer one.
+fn decode_with(
+    mig_json: &str,
+    text: &str,
+) -> PyResult<Result<value::Interchange, mig::error::InterchangeError>> {
+    let description: description::Interchange = serde_json::from_str(mig_json)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let options = DecodeOptions::default();
+    match mig::decode(vec![description], &mut text.as_bytes(), &options) {
+        Ok(interchange) => Ok(Ok(interchange)),
+        Err(Error::Mig(error)) => Ok(Err(error)),
+        Err(error) => Err(PyValueError::new_err(error.to_string())),
+    }
+}
+
+/// Converts the given value into Python objects by way of JSON.
+fn to_python<T: serde::Serialize>(
+    py: Python<'_>,
+    value: &T,
+) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
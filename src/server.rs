@@ -0,0 +1,295 @@
+//! A small HTTP server for `edifact serve`, so teams can validate,
+//! decode and encode interchanges centrally instead of writing their own
+//! wrapper around the library.
+//!
+//! The connections are served by hyper with one request per connection.
+//! Every request must be received and answered within [TIMEOUT] in
+//! total and its head and body are limited in size, so a client cannot
+//! exhaust the memory of the server or keep it busy by sending slowly.
+//! At most [WORKERS] requests are handled at once, since decoding blocks
+//! a thread.
+use std::convert::Infallible;
+use std::io;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Body, Bytes, Incoming};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::StatusCode;
+use hyper_util::rt::TokioIo;
+use tokio::sync::Semaphore;
+
+/// The largest body accepted, which is far larger than any interchange
+/// exchanged in practice.
+pub const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// The size of the buffer for the request line and the headers in bytes,
+/// which limits their length.
+const MAX_HEAD: usize = 64 * 1024;
+
+/// The most headers accepted.
+const MAX_HEADERS: usize = 100;
+
+/// The number of requests handled at once. Further ones wait until one
+/// of them is done.
+const WORKERS: usize = 16;
+
+/// The time receiving a request and sending its response may take in
+/// total.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A `Request` received by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// The path without the query, e.g. `/validate`.
+    pub path: String,
+    /// The body as received, which is decoded with the character set of
+    /// the interchange.
+    pub body: Vec<u8>,
+}
+
+/// A `Response` to a [Request].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, value: &serde_json::Value) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: value.to_string(),
+        }
+    }
+
+    pub fn text(status: u16, body: String) -> Self {
+        Response { status, content_type: "text/plain; charset=utf-8", body }
+    }
+
+    /// A response with the given message as a JSON object, e.g. for a
+    /// request, which could not be handled.
+    pub fn error(status: u16, message: &str) -> Self {
+        Response::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+/// The handler of the requests, which may block.
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+/// Handles the connections to the given listener with the given handler.
+/// A connection, which cannot be accepted, is reported and the server
+/// goes on with the next one.
+pub fn serve(
+    listener: TcpListener,
+    handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+) -> io::Result<()> {
+    serve_within(listener, Arc::new(handler), TIMEOUT)
+}
+
+/// Serves like [serve], but with the given deadline of every request.
+fn serve_within(
+    listener: TcpListener,
+    handler: Arc<Handler>,
+    deadline: Duration,
+) -> io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let workers = Arc::new(Semaphore::new(WORKERS));
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    eprintln!("error: {}", error);
+                    // The error, e.g. too many open files, may last a
+                    // while, so the server does not spin on it.
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let handler = Arc::clone(&handler);
+            let workers = Arc::clone(&workers);
+            tokio::spawn(async move {
+                let service = service_fn(|request| {
+                    respond(request, Arc::clone(&handler), &workers)
+                });
+                let connection = http1::Builder::new()
+                    .keep_alive(false)
+                    .max_buf_size(MAX_HEAD)
+                    .max_headers(MAX_HEADERS)
+                    .serve_connection(TokioIo::new(stream), service);
+                // The connection is closed, when it is dropped.
+                match tokio::time::timeout(deadline, connection).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => eprintln!("error: {}", error),
+                    Err(_) => eprintln!("error: The request timed out"),
+                }
+            });
+        }
+    })
+}
+
+/// Receives the body of the given request and handles it on a thread,
+/// which may block.
+async fn respond(
+    request: hyper::Request<Incoming>,
+    handler: Arc<Handler>,
+    workers: &Semaphore,
+) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let response = match receive(request.into_body()).await {
+        Ok(body) => {
+            let request = Request { method, path, body };
+            let _permit = workers.acquire().await;
+            tokio::task::spawn_blocking(move || handler(&request))
+                .await
+                .unwrap_or_else(|error| {
+                    Response::error(500, &error.to_string())
+                })
+        }
+        Err(response) => response,
+    };
+
+    let mut result = hyper::Response::new(Full::new(response.body.into()));
+    *result.status_mut() = StatusCode::from_u16(response.status)
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    result
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(response.content_type));
+    Ok(result)
+}
+
+/// Receives the given body, which is an error, if it is too large or
+/// incomplete.
+async fn receive(body: Incoming) -> Result<Vec<u8>, Response> {
+    let too_large = || Response::error(413, "The body is too large");
+    if body.size_hint().lower() > MAX_BODY as u64 {
+        return Err(too_large());
+    }
+    // The body grows with the bytes received instead of the length the
+    // client claims.
+    match Limited::new(body, MAX_BODY).collect().await {
+        Ok(body) => Ok(body.to_bytes().to_vec()),
+        Err(error) if error.is::<LengthLimitError>() => Err(too_large()),
+        Err(_) => {
+            Err(Response::error(400, "The request ended within its body"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crate::server::{
+        serve_within, Request, Response, MAX_BODY, MAX_HEAD, WORKERS,
+    };
+
+    /// Serves the path and the upper-cased body of every request within
+    /// the given deadline and returns the address of the server.
+    fn start(deadline: Duration) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handler = |request: &Request| {
+            let body = String::from_utf8_lossy(&request.body).to_uppercase();
+            Response::text(200, format!("{} {}", request.path, body))
+        };
+        thread::spawn(move || {
+            serve_within(listener, Arc::new(handler), deadline)
+        });
+        address
+    }
+
+    fn send(address: std::net::SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        let address = start(Duration::from_secs(30));
+
+        // Idle connections do not keep the others from being handled.
+        let idle: Vec<_> = (0..2 * WORKERS)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        let response = send(
+            address,
+            b"POST /validate?strict HTTP/1.1\r\nHost: localhost\r\n\
+              content-length: 3\r\n\r\nunb",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n/validate UNB"));
+        drop(idle);
+
+        let response =
+            send(address, b"POST / HTTP/1.1\r\nHost localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_limits() {
+        let address = start(Duration::from_secs(30));
+        let long = "a".repeat(2 * MAX_HEAD);
+        let response = send(
+            address,
+            format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", long).as_bytes(),
+        );
+        assert!(response.starts_with("HTTP/1.1 431"));
+        let headers = "A: b\r\n".repeat(101);
+        let response = send(
+            address,
+            format!("GET / HTTP/1.1\r\n{}\r\n", headers).as_bytes(),
+        );
+        assert!(response.starts_with("HTTP/1.1 431"));
+        // A body longer than the limit is not waited for.
+        let response = send(
+            address,
+            format!(
+                "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\nUNA",
+                MAX_BODY + 1
+            )
+            .as_bytes(),
+        );
+        assert!(response.starts_with("HTTP/1.1 413"));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let address = start(Duration::from_millis(200));
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let start = Instant::now();
+        // A client sending a header line every 50 ms never stalls a
+        // single read, but the request still times out.
+        thread::spawn(move || {
+            writer.write_all(b"POST / HTTP/1.1\r\n").unwrap();
+            while start.elapsed() < Duration::from_secs(5) {
+                if writer.write_all(b"A: b\r\n").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let mut response = vec![];
+        let _ = stream.read_to_end(&mut response);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,311 @@
+//! Conformance test suites, so market partners can keep regression tests
+//! for their descriptions.
+//!
+//! A suite is a directory of EDIFACT files, each of which is a case. The
+//! expected outcome is given by a file with the same name next to it:
+//!
+//! - `case.toml` expects the file to be accepted or rejected, optionally
+//!   with exactly the given error codes, e.g.
+//!
+//!   ```toml
+//!   outcome = "reject"
+//!   codes = [28, 29]
+//!   ```
+//!
+//! - `case.json` expects the file to be accepted and decoded into the
+//!   given JSON.
+//!
+//! A file without either is expected to be accepted. The results can be
+//! written in the JUnit XML format, which most CI servers display.
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use edifact::mig::decode::{self, value};
+
+/// The extensions of the files in a suite, which are no cases.
+const EXPECTATIONS: [&str; 2] = ["toml", "json"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Accept,
+    Reject,
+}
+
+/// The `Expected` outcome of a case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    Outcome {
+        outcome: Outcome,
+        /// The error codes of a rejection, which are not checked, if
+        /// empty.
+        codes: Vec<u64>,
+    },
+    Json(serde_json::Value),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expectation {
+    outcome: Outcome,
+    #[serde(default)]
+    codes: Vec<u64>,
+}
+
+/// A `Case` of a suite.
+#[derive(Debug, Clone)]
+pub struct Case {
+    /// The name of the file without its extension.
+    pub name: String,
+    pub path: PathBuf,
+    pub expected: Expected,
+}
+
+/// The `CaseResult` of running a case, which passed, if it has no
+/// failure.
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub name: String,
+    pub failure: Option<String>,
+    pub time: Duration,
+}
+
+/// The error returned, if a suite cannot be read.
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    InvalidToml(PathBuf, toml::de::Error),
+    InvalidJson(PathBuf, serde_json::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, error) => {
+                write!(f, "{} could not be read: {}", path.display(), error)
+            }
+            Error::InvalidToml(path, error) => {
+                write!(
+                    f,
+                    "{} is not a valid expectation: {}",
+                    path.display(),
+                    error
+                )
+            }
+            Error::InvalidJson(path, error) => {
+                write!(
+                    f,
+                    "{} is not a valid expectation: {}",
+                    path.display(),
+                    error
+                )
+            }
+        }
+    }
+}
+
+/// Reads the cases of the suite in the given directory in the order of
+/// their names.
+pub fn read(dir: &Path) -> Result<Vec<Case>, Error> {
+    let io = |path: &Path| {
+        let path = path.to_path_buf();
+        move |error| Error::Io(path, error)
+    };
+    let mut paths = fs::read_dir(dir)
+        .map_err(io(dir))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io(dir))?;
+    paths.sort();
+
+    let mut cases = vec![];
+    for path in paths {
+        let is_expectation = path
+            .extension()
+            .is_some_and(|e| EXPECTATIONS.iter().any(|other| e == *other));
+        if path.is_dir() || is_expectation {
+            continue;
+        }
+        let toml = path.with_extension("toml");
+        let json = path.with_extension("json");
+        let expected = if toml.is_file() {
+            let input = fs::read_to_string(&toml).map_err(io(&toml))?;
+            let expectation: Expectation = toml::from_str(&input)
+                .map_err(|error| Error::InvalidToml(toml, error))?;
+            Expected::Outcome {
+                outcome: expectation.outcome,
+                codes: expectation.codes,
+            }
+        } else if json.is_file() {
+            let input = fs::read_to_string(&json).map_err(io(&json))?;
+            let value = serde_json::from_str(&input)
+                .map_err(|error| Error::InvalidJson(json, error))?;
+            Expected::Json(value)
+        } else {
+            Expected::Outcome { outcome: Outcome::Accept, codes: vec![] }
+        };
+        let name = path.file_stem().unwrap_or_default();
+        cases.push(Case {
+            name: name.to_string_lossy().to_string(),
+            path,
+            expected,
+        });
+    }
+    Ok(cases)
+}
+
+/// Runs the given case by decoding its file with the given function.
+pub fn run<F>(case: &Case, decode: F) -> CaseResult
+where
+    F: FnOnce(&[u8]) -> Result<value::Interchange, decode::Error>,
+{
+    let start = Instant::now();
+    let failure = match fs::read(&case.path) {
+        Ok(input) => check(&case.expected, decode(&input)),
+        Err(error) => Some(format!("The file could not be read: {}", error)),
+    };
+    CaseResult { name: case.name.clone(), failure, time: start.elapsed() }
+}
+
+/// Returns the failure of the given result, if it is not the expected
+/// one.
+fn check(
+    expected: &Expected,
+    result: Result<value::Interchange, decode::Error>,
+) -> Option<String> {
+    match (expected, result) {
+        (Expected::Outcome { outcome: Outcome::Accept, .. }, Ok(_)) => None,
+        (Expected::Outcome { outcome: Outcome::Reject, .. }, Ok(_)) => {
+            Some("Expected a rejection, but it was accepted".to_string())
+        }
+        (Expected::Outcome { outcome: Outcome::Accept, .. }, Err(error))
+        | (Expected::Json(_), Err(error)) => {
+            Some(format!("Expected an acceptance, but got: {}", error))
+        }
+        (
+            Expected::Outcome { outcome: Outcome::Reject, codes },
+            Err(error),
+        ) => {
+            let mut actual = match &error {
+                decode::Error::Mig(error) => error
+                    .syntax_errors()
+                    .iter()
+                    .map(|error| error.get_code())
+                    .collect(),
+                _ => vec![],
+            };
+            actual.sort();
+            actual.dedup();
+            let mut codes = codes.clone();
+            codes.sort();
+            codes.dedup();
+            if codes.is_empty() || codes == actual {
+                None
+            } else {
+                Some(format!(
+                    "Expected the error codes {:?}, but got {:?}",
+                    codes, actual
+                ))
+            }
+        }
+        (Expected::Json(expected), Ok(interchange)) => {
+            // Decoded interchanges consist of plain data, so they always
+            // serialize.
+            let actual = serde_json::to_value(&interchange).unwrap();
+            if &actual == expected {
+                None
+            } else {
+                Some(
+                    "The decoded interchange differs from the JSON"
+                        .to_string(),
+                )
+            }
+        }
+    }
+}
+
+/// Returns the results of the suite with the given name in the JUnit XML
+/// format.
+pub fn junit(name: &str, results: &[CaseResult]) -> String {
+    let failures = results.iter().filter(|r| r.failure.is_some()).count();
+    let time: Duration = results.iter().map(|result| result.time).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        escape(name),
+        results.len(),
+        failures,
+        time.as_secs_f64()
+    ));
+    for result in results {
+        let attributes = format!(
+            "name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape(&result.name),
+            escape(name),
+            result.time.as_secs_f64()
+        );
+        match &result.failure {
+            Some(failure) => xml.push_str(&format!(
+                "  <testcase {}>\n    <failure message=\"{}\"/>\n  </testcase>\n",
+                attributes,
+                escape(failure)
+            )),
+            None => xml.push_str(&format!("  <testcase {}/>\n", attributes)),
+        }
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+/// Escapes the characters with a special meaning in XML attributes.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use edifact::mig::decode;
+
+    use crate::suite::{check, junit, CaseResult, Expected, Outcome};
+
+    #[test]
+    fn test_check() {
+        let reject = |codes: Vec<u64>| Expected::Outcome {
+            outcome: Outcome::Reject,
+            codes,
+        };
+        let error = || Err(decode::Error::NoDescription);
+        assert!(check(&reject(vec![]), error()).is_none());
+        assert!(check(&reject(vec![28]), error()).is_some());
+        let accept =
+            Expected::Outcome { outcome: Outcome::Accept, codes: vec![] };
+        assert!(check(&accept, error()).is_some());
+
+        let results = [
+            CaseResult {
+                name: "accepted".to_string(),
+                failure: None,
+                time: Duration::from_millis(2),
+            },
+            CaseResult {
+                name: "rejected".to_string(),
+                failure: Some("Expected \"28\"".to_string()),
+                time: Duration::from_millis(1),
+            },
+        ];
+        let xml = junit("APERAK", &results);
+        assert!(xml.contains("tests=\"2\" failures=\"1\" time=\"0.003\""));
+        assert!(xml.contains("<failure message=\"Expected &quot;28&quot;\"/>"));
+    }
+}
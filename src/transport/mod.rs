@@ -0,0 +1,318 @@
+//! Unwrapping of interchanges from their transport containers, e.g. the
+//! MIME message of an AS2 or email transmission with a base64 encoded
+//! or gzip compressed body.
+//!
+//! [reader] detects the wrappers by their content and removes them one
+//! after the other until the interchange remains. Every unwrapped
+//! payload is limited to [Options::max_size], so a small compressed
+//! payload cannot expand to gigabytes. Multipart messages,
+//! e.g. signed ones, are searched for the first part containing an
+//! interchange. The text is decoded with the charset of its MIME part,
+//! otherwise as UTF-8, if it is valid, and as Latin-1, which most
+//! converters use for UNOC, if not.
+//!
+//! ```
+//! # use edifact::transport::{self, Options};
+//! let payload = b"Content-Type: application/EDIFACT\r\n\
+//!                 Content-Transfer-Encoding: base64\r\n\r\n\
+//!                 VU5CK1VOT0M6MysxKzIrMjAwMzA3OjA3MDUrMSdVTlorMCsxJw==";
+//! let input = transport::reader(&payload[..], &Options::default()).unwrap();
+//! assert!(input.get_ref().starts_with("UNB+UNOC:3+1+2"));
+//! ```
+use std::fmt;
+use std::io::{self, Read};
+
+use base64::alphabet;
+use base64::engine::{
+    DecodePaddingMode, Engine as _, GeneralPurpose, GeneralPurposeConfig,
+};
+use encoding::all::ISO_8859_1;
+use encoding::{DecoderTrap, Encoding};
+use flate2::read::MultiGzDecoder;
+use mailparse::ParsedMail;
+
+/// The number of wrappers removed at most, so a malicious payload
+/// cannot nest them endlessly.
+const MAX_DEPTH: usize = 8;
+
+/// The magic bytes every gzip member starts with.
+const GZIP: [u8; 2] = [0x1f, 0x8b];
+
+/// The `Options` for unwrapping a payload.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The size in bytes the payload and every payload unwrapped from it
+    /// may have at most, by default 64 MiB.
+    pub max_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { max_size: 64 * 1024 * 1024 }
+    }
+}
+
+/// The error returned, if a payload cannot be unwrapped.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The payload is no interchange and no known wrapper of one.
+    NoInterchange,
+    /// The gzip compressed payload is broken.
+    InvalidGzip(io::Error),
+    /// The payload or a payload unwrapped from it is larger than the
+    /// given maximum size.
+    TooLarge(usize),
+    InvalidBase64,
+    /// The MIME message has no boundary or a broken header.
+    InvalidMime(String),
+    /// The charset of a MIME part is not known.
+    UnknownCharset(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => error.fmt(f),
+            Error::NoInterchange => {
+                write!(f, "The payload does not contain an interchange")
+            }
+            Error::InvalidGzip(error) => {
+                write!(f, "The gzip payload is invalid: {}", error)
+            }
+            Error::TooLarge(max_size) => {
+                write!(f, "The payload exceeds {} bytes", max_size)
+            }
+            Error::InvalidBase64 => {
+                write!(f, "The payload is no valid base64")
+            }
+            Error::InvalidMime(reason) => {
+                write!(f, "The MIME message is invalid: {}", reason)
+            }
+            Error::UnknownCharset(charset) => {
+                write!(f, "The charset {} is not supported", charset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Reads the given input and returns a reader of the interchange in it
+/// without its wrappers, which can be handed to
+/// [decode](crate::mig::decode()), see the [module](self).
+pub fn reader<R: Read>(
+    input: R,
+    options: &Options,
+) -> Result<io::Cursor<String>, Error> {
+    let payload = read_limited(input, options.max_size, Error::Io)?;
+    Ok(io::Cursor::new(unwrap_help(&payload, None, 0, options)?))
+}
+
+/// Reads the given input, which may have the given size at most, and
+/// maps the errors of reading it with the given function.
+fn read_limited<R: Read>(
+    input: R,
+    max_size: usize,
+    error: fn(io::Error) -> Error,
+) -> Result<Vec<u8>, Error> {
+    let mut payload = vec![];
+    // One byte more tells a payload of the maximum size from a larger one.
+    let limit = u64::try_from(max_size).unwrap_or(u64::MAX).saturating_add(1);
+    input.take(limit).read_to_end(&mut payload).map_err(error)?;
+    if payload.len() > max_size {
+        return Err(Error::TooLarge(max_size));
+    }
+    Ok(payload)
+}
+
+fn unwrap_help(
+    payload: &[u8],
+    charset: Option<&str>,
+    depth: usize,
+    options: &Options,
+) -> Result<String, Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::NoInterchange);
+    }
+    // A byte order mark is left by some editors.
+    let payload = payload.strip_prefix(b"\xef\xbb\xbf").unwrap_or(payload);
+    let trimmed = payload.trim_ascii_start();
+    if trimmed.starts_with(b"UNA") || trimmed.starts_with(b"UNB") {
+        return decode_text(trimmed, charset);
+    }
+    if payload.starts_with(&GZIP) {
+        let decoder = MultiGzDecoder::new(payload);
+        let payload =
+            read_limited(decoder, options.max_size, Error::InvalidGzip)?;
+        return unwrap_help(&payload, charset, depth + 1, options);
+    }
+    if let Some(mail) = parse_mime(payload)? {
+        return unwrap_mime(&mail, depth, options);
+    }
+    // A base64 encoded payload without a MIME header, e.g. from a JSON
+    // message of a broker.
+    match decode_base64(payload) {
+        Ok(decoded) => unwrap_help(&decoded, charset, depth + 1, options),
+        Err(_) => Err(Error::NoInterchange),
+    }
+}
+
+/// Decodes the text of an interchange with the given charset, by default
+/// UTF-8 or Latin-1.
+fn decode_text(text: &[u8], charset: Option<&str>) -> Result<String, Error> {
+    let latin1 = || {
+        // Every byte is a character in Latin-1.
+        ISO_8859_1
+            .decode(text, DecoderTrap::Replace)
+            .map_err(|error| Error::UnknownCharset(error.to_string()))
+    };
+    match charset.map(str::to_ascii_lowercase).as_deref() {
+        None => match std::str::from_utf8(text) {
+            Ok(text) => Ok(text.to_string()),
+            Err(_) => latin1(),
+        },
+        Some("utf-8" | "utf8" | "us-ascii") => {
+            String::from_utf8(text.to_vec()).map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidData, error).into()
+            })
+        }
+        Some("iso-8859-1" | "latin1" | "latin-1") => latin1(),
+        Some(charset) => Err(Error::UnknownCharset(charset.to_string())),
+    }
+}
+
+/// Returns the MIME message, if the payload starts with a MIME header.
+fn parse_mime(payload: &[u8]) -> Result<Option<ParsedMail<'_>>, Error> {
+    let Ok((headers, _)) = mailparse::parse_headers(payload) else {
+        return Ok(None);
+    };
+    let is_mime = headers.iter().any(|header| {
+        let name = header.get_key_ref().to_ascii_lowercase();
+        name == "mime-version" || name.starts_with("content-")
+    });
+    if !is_mime {
+        return Ok(None);
+    }
+    let mail = mailparse::parse_mail(payload)
+        .map_err(|error| Error::InvalidMime(error.to_string()))?;
+    Ok(Some(mail))
+}
+
+/// Unwraps the given MIME message, which is the first part containing an
+/// interchange, if it is a multipart.
+fn unwrap_mime(
+    mail: &ParsedMail,
+    depth: usize,
+    options: &Options,
+) -> Result<String, Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::NoInterchange);
+    }
+    if !mail.ctype.mimetype.starts_with("multipart/") {
+        let body = mail
+            .get_body_raw()
+            .map_err(|error| Error::InvalidMime(error.to_string()))?;
+        // The charset of mailparse defaults to US-ASCII.
+        let charset = mail.ctype.params.get("charset").map(String::as_str);
+        return unwrap_help(&body, charset, depth + 1, options);
+    }
+
+    if !mail.ctype.params.contains_key("boundary") {
+        let reason = "The multipart has no boundary".to_string();
+        return Err(Error::InvalidMime(reason));
+    }
+    mail.subparts
+        .iter()
+        .find_map(|part| unwrap_mime(part, depth + 1, options).ok())
+        .ok_or(Error::NoInterchange)
+}
+
+/// Decodes the given base64 text, ignoring whitespace and missing
+/// padding.
+fn decode_base64(text: &[u8]) -> Result<Vec<u8>, Error> {
+    let engine = GeneralPurpose::new(
+        &alphabet::STANDARD,
+        GeneralPurposeConfig::new()
+            .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+    let text: Vec<u8> =
+        text.iter().copied().filter(|c| !c.is_ascii_whitespace()).collect();
+    if text.is_empty() {
+        return Err(Error::InvalidBase64);
+    }
+    engine.decode(text).map_err(|_| Error::InvalidBase64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::transport::{self, Error, Options};
+
+    fn unwrap(payload: &[u8], options: &Options) -> Result<String, Error> {
+        transport::reader(payload, options).map(|input| input.into_inner())
+    }
+
+    /// An APERAK in Latin-1 compressed with gzip.
+    const GZIP: &str = "\
+        H4sIAAAAAAACA12PQY7CMAxFrzK7LL6EnLhpiTcobWGQmGZGpZVQdym0B0Dcl6tMKKzq\
+        jeX/Lf/nPnjBZvel+lCiD7+VMJwjyvKClhJL9FIy696CWRRDxFQIFWRRsf/U8fRTpUtH\
+        aPi/fetPUqeVUvogZqNvqvxuwJpheZ7tbcquMY7ZFPU2jnGOo51y4tw5requgeZCDL2D\
+        XjlpYBV8jea8QhTjPk67Ql2cfVthME4dugsGMgDOj3t8Tom0wxY69SERr974B5eziC8a\
+        AQAA";
+
+    #[test]
+    fn test_unwrap() {
+        let message = format!(
+            "MIME-Version: 1.0\r\n\
+             Content-Type: multipart/signed; protocol=\"application/\
+             pkcs7-signature\";\r\n\tboundary=\"----=_Part_1\"\r\n\r\n\
+             ------=_Part_1\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             {}\r\n\
+             ------=_Part_1\r\n\
+             Content-Type: application/pkcs7-signature\r\n\r\n\
+             MIAGCSqGSIb3DQEHAqCAMIACAQExDzANBglghkgBZQMEAgEFADCABgkqhkiG\r\n\
+             ------=_Part_1--\r\n",
+            GZIP
+        );
+        let options = Options::default();
+        let interchange = unwrap(message.as_bytes(), &options).unwrap();
+        assert!(interchange.starts_with("UNA:+.? 'UNB+UNOC:3"));
+        assert!(interchange.contains("FTX+Z02+++Straße'"));
+
+        assert!(unwrap(b"Hello, World!", &options).is_err());
+    }
+
+    #[test]
+    fn test_max_size() {
+        let options = Options { max_size: 1024 };
+        // A megabyte of spaces compresses to about a kilobyte.
+        let mut encoder = GzEncoder::new(vec![], Compression::best());
+        encoder.write_all(b"UNB+UNOC:3+1+2").unwrap();
+        encoder.write_all(&[b' '; 1024 * 1024]).unwrap();
+        let payload = encoder.finish().unwrap();
+        assert!(payload.len() < 2 * 1024);
+        assert!(matches!(
+            unwrap(&payload, &options),
+            Err(Error::TooLarge(1024))
+        ));
+        assert!(matches!(
+            unwrap(&[b' '; 1025], &options),
+            Err(Error::TooLarge(1024))
+        ));
+        assert!(unwrap(&payload[..payload.len() - 8], &Options::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Evaluated conditions of a use case, which decide whether segments and
+//! data elements with the status D or C of a MIG are required.
+//!
+//! The conditions are numbered like in the application handbook, e.g.
+//! `[492]`, and evaluated by the caller for an interchange. They are
+//! passed to [decode](crate::mig::decode()) with the
+//! [DecodeOptions](crate::mig::DecodeOptions).
+use std::collections::HashMap;
+
+use crate::usecase::description::{Condition, Op};
+
+/// The `Conditions`, which have been evaluated, by their number.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conditions {
+    fulfilled: HashMap<u16, bool>,
+}
+
+impl Conditions {
+    pub fn new() -> Self {
+        Conditions::default()
+    }
+
+    /// Sets, whether the condition with the given number is fulfilled.
+    pub fn set(&mut self, number: u16, fulfilled: bool) {
+        self.fulfilled.insert(number, fulfilled);
+    }
+
+    /// Returns, whether the condition with the given number is fulfilled,
+    /// or `None`, if it has not been evaluated.
+    pub fn get(&self, number: u16) -> Option<bool> {
+        self.fulfilled.get(&number).copied()
+    }
+
+    /// Evaluates the given condition, or returns `None`, if one of the
+    /// conditions it refers to has not been evaluated.
+    pub fn evaluate(&self, condition: &Condition) -> Option<bool> {
+        match condition {
+            Condition::Ref(number) => self.get(*number),
+            Condition::Cond(op, left, right) => {
+                let (left, right) =
+                    (self.evaluate(left)?, self.evaluate(right)?);
+                Some(match op {
+                    Op::U => left && right,
+                    Op::O => left || right,
+                    Op::X => left != right,
+                })
+            }
+        }
+    }
+}
+
+impl FromIterator<(u16, bool)> for Conditions {
+    fn from_iter<T: IntoIterator<Item = (u16, bool)>>(iter: T) -> Self {
+        Conditions { fulfilled: iter.into_iter().collect() }
+    }
+}
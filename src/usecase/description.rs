@@ -1,14 +1,21 @@
+use serde::{Deserialize, Serialize};
+
 use crate::mig::either::Either;
 
 /// A manual defines multiple use cases for a given message
 /// (e.g. UTILMD) and version (e.g. 5.1a).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Manual {
-    message: String,
-    version: String,
-    use_cases: Vec<UseCase>,
+    pub message: String,
+    pub version: String,
+    pub use_cases: Vec<UseCase>,
 }
 
-///
+/// A `UseCase` of a message, e.g. the registration at a new supplier,
+/// with the segments and values it requires.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct UseCase {
     pub ident: Option<Identifier>,
     pub data: Vec<Either<Segmentgroup, Segment>>,
@@ -16,10 +23,14 @@ pub struct UseCase {
 
 /// An `Identifier` is used to identify a use case, contained in
 /// one or more EDIFACT messages of certain types.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Identifier {
     pub value: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Segment {
     pub order: u16,
     pub name: String,
@@ -27,6 +38,8 @@ pub struct Segment {
     pub elements: Vec<DataElement>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Segmentgroup {
     pub order: u16,
     pub name: String,
@@ -34,6 +47,8 @@ pub struct Segmentgroup {
     pub segments: Vec<Either<Segmentgroup, Segment>>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct DataElement {
     pub label: String,
     pub op: Option<Op>,
@@ -41,6 +56,8 @@ pub struct DataElement {
     pub values: Vec<Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Value {
     pub code: String,
     pub op: Option<(Op, Option<Condition>)>,
@@ -48,6 +65,7 @@ pub struct Value {
 
 /// The `Necessity` describes, whether a `Segment` or `Segmentgroup`
 /// *Must*, *Should* or *Can* occur.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Necessity {
     Must(Option<Condition>),
     Should(Option<Condition>),
@@ -56,13 +74,62 @@ pub enum Necessity {
 
 /// An `Op` defines logical operations, *xor*, *and* and *or*,
 /// which, depending on the context have different meanings.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum Op {
     X,
     U,
     O,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Condition {
     Ref(u16),
     Cond(Op, Box<Condition>, Box<Condition>),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::mig::either::Either;
+    use crate::usecase::description::{
+        Condition, DataElement, Identifier, Manual, Necessity, Op, Segment,
+        Segmentgroup, UseCase, Value,
+    };
+
+    #[test]
+    fn test_json() {
+        let rff = Segment {
+            order: 1,
+            name: "Prüfidentifikator".to_string(),
+            necessities: vec![Necessity::Must(None)],
+            elements: vec![DataElement {
+                label: "1153".to_string(),
+                op: None,
+                cond: None,
+                values: vec![Value { code: "Z13".to_string(), op: None }],
+            }],
+        };
+        let condition = Condition::Cond(
+            Op::U,
+            Box::new(Condition::Ref(492)),
+            Box::new(Condition::Ref(493)),
+        );
+        let manual = Manual {
+            message: "UTILMD".to_string(),
+            version: "5.2a".to_string(),
+            use_cases: vec![UseCase {
+                ident: Some(Identifier { value: "55001".to_string() }),
+                data: vec![Either::Left(Segmentgroup {
+                    order: 1,
+                    name: "Referenz".to_string(),
+                    necessities: vec![Necessity::Can(Some(condition))],
+                    segments: vec![Either::Right(rff)],
+                })],
+            }],
+        };
+
+        let json = serde_json::to_value(&manual).unwrap();
+        assert_eq!(json["useCases"][0]["ident"]["value"], "55001");
+        let parsed: Manual = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+    }
+}
@@ -1 +1,89 @@
-mod description;
+//! Descriptions of the use cases of a message as defined in the
+//! application handbooks (AHB) of the edi@energy subset, which restrict
+//! a MIG further.
+pub mod conditions;
+pub mod description;
+
+use crate::domain;
+use crate::mig::decode::value::Interchange;
+
+/// Returns the Pruefidentifikator of the first message of the given
+/// interchange, which identifies its use case, e.g. `55001`.
+///
+/// It is given in RFF+Z13, which is part of every transaction (SG4) in
+/// UTILMD and UTILTS and part of the header (SG1) in all other message
+/// types, e.g. MSCONS or INVOIC. Returns `None`, if the message does not
+/// contain one, like CONTRL.
+pub fn detect_pruefidentifikator(interchange: &Interchange) -> Option<String> {
+    let transactions =
+        matches!(domain::message_type(interchange), Some("UTILMD" | "UTILTS"));
+    let mut in_transaction = false;
+    for segment in interchange.all_segments() {
+        match (segment.tag(), domain::qualifier(segment)) {
+            ("UNT", _) => return None,
+            ("IDE", _) => in_transaction = true,
+            ("RFF", Some("Z13")) if in_transaction || !transactions => {
+                return segment.text_of("1154").map(|v| v.to_string())
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::mig::{self, DecodeOptions};
+    use crate::usecase::detect_pruefidentifikator;
+
+    #[test]
+    fn test_detect_pruefidentifikator() {
+        let decode = |qualifier: &str| {
+            // The reference is turned into a Pruefidentifikator.
+            let desc = fixtures::APERAK_JSON
+                .replace("\"ACE\"", &format!("\"{}\"", qualifier));
+            let known = vec![serde_json::from_str(&desc).unwrap()];
+            let input = fixtures::APERAK_FULL.replace(
+                "ACE:O1583553607732",
+                &format!("{}:55001", qualifier),
+            );
+            let options = DecodeOptions::default();
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap()
+        };
+
+        let interchange = decode("Z13");
+        assert_eq!(
+            detect_pruefidentifikator(&interchange).as_deref(),
+            Some("55001")
+        );
+        let interchange = decode("ACE");
+        assert_eq!(detect_pruefidentifikator(&interchange), None);
+    }
+
+    #[test]
+    fn test_detect_pruefidentifikator_transaction() {
+        // Only the RFF+Z13 of the transaction after IDE is the
+        // Pruefidentifikator in UTILMD, not one in the header.
+        let input = fixtures::interchange(&["UNH+1+UTILMD:D:11A:UN:5.2a'\
+            BGM+E01+DOC1'\
+            DTM+137:202003070705?+00:303'\
+            RFF+Z13:99999'\
+            NAD+MS+9900467000000::293'\
+            IDE+24+TX1'\
+            DTM+92:202004010000?+00:303'\
+            DTM+93:20201231:102'\
+            STS+7++E01'\
+            LOC+Z16+51238696781'\
+            RFF+Z13:11001'\
+            UNT+12+1'"]);
+        let options = DecodeOptions::default();
+        let known = vec![fixtures::utilmd()];
+        let interchange =
+            mig::decode(known, &mut input.as_bytes(), &options).unwrap();
+        assert_eq!(
+            detect_pruefidentifikator(&interchange).as_deref(),
+            Some("11001")
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! JavaScript bindings to decode and validate messages in the browser.
+//!
+//! This module is only available with the `wasm` feature. Descriptions
+//! are passed as JSON, as generated by `edifact mig parse`.
+use wasm_bindgen::prelude::*;
+
+use crate::mig;
+use crate::mig::decode::{value, Error};
+use crate::mig::{description, DecodeOptions};
+
+/// Decodes the given EDIFACT interchange with the given description and
+/// returns the decoded interchange.
+///
+/// Throws the errors, if the interchange does not match the description.
+#[wasm_bindgen]
+pub fn decode(json_mig: &str, edifact: &str) -> Result<JsValue, JsValue> {
+    match decode_with(json_mig, edifact)? {
+        Ok(interchange) => to_value(&interchange),
+        Err(error) => Err(to_value(&error)?),
+    }
+}
+
+/// Validates the given EDIFACT interchange with the given description
+/// and returns the errors, or `null` if the interchange is valid.
+#[wasm_bindgen]
+pub fn validate(json_mig: &str, edifact: &str) -> Result<JsValue, JsValue> {
+    match decode_with(json_mig, edifact)? {
+        Ok(_) => Ok(JsValue::NULL),
+        Err(error) => to_value(&error),
+    }
+}
+
+/// Decodes the given interchange. Errors of the interchange itself are
+/// returned in the inner result, all others in the outer one.
+fn decode_with(
+    json_mig: &str,
+    edifact: &str,
+) -> Result<Result<value::Interchange, mig::error::InterchangeError>, JsValue>
+{
+    let description: description::Interchange = serde_json::from_str(json_mig)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let options = DecodeOptions::default();
+    match mig::decode(vec![description], &mut edifact.as_bytes(), &options) {
+        Ok(interchange) => Ok(Ok(interchange)),
+        Err(Error::Mig(error)) => Ok(Err(error)),
+        Err(error) => Err(JsError::new(&error.to_string()).into()),
+    }
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(value).map_err(JsValue::from)
+}
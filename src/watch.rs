@@ -0,0 +1,239 @@
+//! Watching a directory for arriving interchanges, e.g. as the intake of
+//! files received by SFTP or AS4:
+//!
+//! ```text
+//! edifact watch inbox --on-valid mv-to ok/ --on-invalid mv-to err/ \
+//!     --report reports/
+//! ```
+//!
+//! The directory is scanned whenever [notify] reports a change in it and
+//! after an interval at the latest, which covers file systems without
+//! notifications, e.g. network shares. A file is processed once its size
+//! has not changed between two scans, so files still being written are
+//! not picked up. Hidden files, e.g. temporary ones of an upload, are
+//! ignored.
+//!
+//! A processed file never replaces another one of the same name in the
+//! directory of its action, but gets a number appended, e.g. `a-1.edi`.
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher as _};
+
+/// The time without changes, after which a change of the directory is
+/// considered complete, e.g. the creation of a file and its writes.
+const QUIET: Duration = Duration::from_millis(200);
+
+/// The `Action` applied to a processed file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Moves the file to the directory, given as `mv-to <dir>`.
+    MoveTo(PathBuf),
+    /// Copies the file to the directory, given as `cp-to <dir>`.
+    CopyTo(PathBuf),
+}
+
+impl Action {
+    /// Parses the action and directory given on the command line, if
+    /// any.
+    pub fn parse(args: &[String]) -> Result<Option<Action>, String> {
+        match args {
+            [] => Ok(None),
+            [action, dir] if action == "mv-to" => {
+                Ok(Some(Action::MoveTo(dir.into())))
+            }
+            [action, dir] if action == "cp-to" => {
+                Ok(Some(Action::CopyTo(dir.into())))
+            }
+            [action, ..] => Err(format!(
+                "The action {} is unknown, expected mv-to or cp-to",
+                action
+            )),
+        }
+    }
+
+    /// Applies this action to the given file and returns its new path,
+    /// which is not taken by another file yet. The directory is created,
+    /// if it does not exist.
+    pub fn apply(&self, file: &Path) -> io::Result<PathBuf> {
+        let (Action::MoveTo(dir) | Action::CopyTo(dir)) = self;
+        fs::create_dir_all(dir)?;
+        let (target, mut output) = create_target(dir, file)?;
+        match self {
+            Action::MoveTo(_) => {
+                // The rename replaces the file just created only. It fails
+                // across file systems, so copy instead.
+                if fs::rename(file, &target).is_err() {
+                    io::copy(&mut File::open(file)?, &mut output)?;
+                    fs::remove_file(file)?;
+                }
+            }
+            Action::CopyTo(_) => {
+                io::copy(&mut File::open(file)?, &mut output)?;
+            }
+        }
+        Ok(target)
+    }
+}
+
+/// Creates a new, empty file in the given directory named like the given
+/// file or, if that is taken, with a number appended to its stem.
+fn create_target(dir: &Path, file: &Path) -> io::Result<(PathBuf, File)> {
+    let name = Path::new(file.file_name().unwrap_or_default());
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut number = 0;
+    loop {
+        let target = match number {
+            0 => dir.join(name),
+            _ => dir.join(format!("{}-{}{}", stem, number, extension)),
+        };
+        // Creating the file fails, if it exists, even if another process
+        // creates it at the same time.
+        match OpenOptions::new().write(true).create_new(true).open(&target) {
+            Ok(output) => return Ok((target, output)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                number += 1
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// A `Watcher` of a directory, see the [module](self).
+pub struct Watcher {
+    dir: PathBuf,
+    /// Whether a file has to keep its size between two polls to be
+    /// processed. If not, every file is processed at once.
+    settle: bool,
+    /// The sizes of the files not processed yet at the last poll.
+    sizes: HashMap<PathBuf, u64>,
+    /// The files processed, which are still in the directory.
+    processed: HashSet<PathBuf>,
+    /// The changes of the directory reported by `notifier`.
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    /// The watcher of the directory, which stops, when it is dropped.
+    _notifier: notify::RecommendedWatcher,
+}
+
+impl Watcher {
+    pub fn new(dir: &Path, settle: bool) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut notifier = notify::recommended_watcher(sender)?;
+        notifier.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Watcher {
+            dir: dir.to_path_buf(),
+            settle,
+            sizes: HashMap::new(),
+            processed: HashSet::new(),
+            events,
+            _notifier: notifier,
+        })
+    }
+
+    /// Waits until the directory has changed or the given time has passed
+    /// and returns, whether it has changed. A change is waited for until
+    /// it is complete, see [QUIET].
+    pub fn wait(&self, timeout: Duration) -> bool {
+        if self.events.recv_timeout(timeout).is_err() {
+            return false;
+        }
+        while self.events.recv_timeout(QUIET).is_ok() {}
+        true
+    }
+
+    /// Returns the files, which arrived since the last poll and are
+    /// complete, in a stable order. They are not returned again.
+    pub fn poll(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut sizes = HashMap::new();
+        let mut present = HashSet::new();
+        let mut ready = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let metadata = entry.metadata()?;
+            if hidden || !metadata.is_file() {
+                continue;
+            }
+            present.insert(path.clone());
+            if self.processed.contains(&path) {
+                continue;
+            }
+            let size = metadata.len();
+            if !self.settle || self.sizes.get(&path) == Some(&size) {
+                ready.push(path);
+            } else {
+                sizes.insert(path, size);
+            }
+        }
+        // Files removed from the directory may arrive again.
+        self.processed.retain(|path| present.contains(path));
+        self.processed.extend(ready.iter().cloned());
+        self.sizes = sizes;
+        ready.sort();
+        Ok(ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::watch::{Action, Watcher};
+
+    #[test]
+    fn test_watcher() {
+        let dir = std::env::temp_dir()
+            .join(format!("edifact-watch-{}", std::process::id()));
+        let inbox = dir.join("inbox");
+        fs::create_dir_all(&inbox).unwrap();
+        let file = inbox.join("a.edi");
+        fs::write(&file, "UNA:+.? '").unwrap();
+        fs::write(inbox.join(".upload"), "UNB").unwrap();
+
+        let mut watcher = Watcher::new(&inbox, true).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(watcher.poll().unwrap(), vec![file.clone()]);
+        assert!(watcher.poll().unwrap().is_empty());
+
+        let args = ["mv-to".to_string(), dir.join("ok").display().to_string()];
+        let action = Action::parse(&args).unwrap().unwrap();
+        let target = action.apply(&file).unwrap();
+        assert_eq!(target, dir.join("ok").join("a.edi"));
+        assert!(target.exists() && !file.exists());
+        assert!(Action::parse(&["rm".to_string()]).is_err());
+
+        // A file of the same name is not replaced.
+        fs::write(&file, "UNB").unwrap();
+        let moved = action.apply(&file).unwrap();
+        assert_eq!(moved, dir.join("ok").join("a-1.edi"));
+        assert_eq!(fs::read_to_string(target).unwrap(), "UNA:+.? '");
+        assert_eq!(fs::read_to_string(moved).unwrap(), "UNB");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_wait() {
+        let dir = std::env::temp_dir()
+            .join(format!("edifact-watch-wait-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let watcher = Watcher::new(&dir, true).unwrap();
+        assert!(!watcher.wait(Duration::from_millis(10)));
+
+        let file = dir.join("a.edi");
+        let writer = thread::spawn(move || fs::write(file, "UNB").unwrap());
+        assert!(watcher.wait(Duration::from_secs(10)));
+        writer.join().unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}